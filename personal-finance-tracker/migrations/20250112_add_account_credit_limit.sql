@@ -0,0 +1,2 @@
+-- Optional credit limit (positive amount) used by the balance forecast
+ALTER TABLE accounts ADD COLUMN credit_limit TEXT;
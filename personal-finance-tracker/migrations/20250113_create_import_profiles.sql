@@ -0,0 +1,8 @@
+-- Saved column mappings for statement imports, one per bank
+CREATE TABLE IF NOT EXISTS import_profiles (
+    profile_id INTEGER PRIMARY KEY,
+    profile_name TEXT NOT NULL UNIQUE,
+    format TEXT NOT NULL DEFAULT 'csv',   -- 'csv'
+    mapping TEXT NOT NULL,                -- JSON, see import::csv::CsvMapping
+    profile_created_at TEXT NOT NULL
+);
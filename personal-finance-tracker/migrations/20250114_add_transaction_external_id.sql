@@ -0,0 +1,6 @@
+-- Bank-assigned id of an imported row (OFX FITID), used to skip rows already imported
+ALTER TABLE transactions ADD COLUMN external_id TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_external_id
+    ON transactions(account_id, external_id)
+    WHERE external_id IS NOT NULL;
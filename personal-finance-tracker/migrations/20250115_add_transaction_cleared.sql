@@ -0,0 +1,3 @@
+-- Cleared = seen on a bank statement, reconciled = locked in by a reconciliation
+ALTER TABLE transactions ADD COLUMN cleared INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE transactions ADD COLUMN reconciled INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Value date of an imported row (camt.053 / MT940), when it differs from the booking date
+ALTER TABLE transactions ADD COLUMN value_date TEXT;
@@ -0,0 +1,16 @@
+-- tags.tag_id referenced transactions(transaction_id) by mistake, so a new tag could only
+-- be created with the id of an existing transaction. Rebuild the table without that key.
+-- Dropping tags cascades into transaction_tags, so the links are copied out and back.
+CREATE TEMP TABLE transaction_tags_copy AS SELECT transaction_id, tag_id FROM transaction_tags;
+
+CREATE TABLE tags_new (
+    tag_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tag TEXT NOT NULL
+);
+INSERT INTO tags_new (tag_id, tag) SELECT tag_id, tag FROM tags;
+DROP TABLE tags;
+ALTER TABLE tags_new RENAME TO tags;
+
+INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id)
+    SELECT transaction_id, tag_id FROM transaction_tags_copy;
+DROP TABLE transaction_tags_copy;
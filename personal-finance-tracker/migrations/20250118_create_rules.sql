@@ -0,0 +1,21 @@
+-- User rules that clean up and categorise transactions on import or on demand.
+-- Rules run in `position` order; for each field the first matching rule that sets it wins.
+CREATE TABLE IF NOT EXISTS rules (
+    rule_id INTEGER PRIMARY KEY,
+    rule_name TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    match_field TEXT NOT NULL DEFAULT 'payee',      -- 'payee' / 'memo' / 'any'
+    match_kind TEXT NOT NULL DEFAULT 'contains',    -- 'contains' (case-insensitive) / 'regex'
+    pattern TEXT NOT NULL DEFAULT '',
+    min_amount TEXT,                                -- signed, inclusive; NULL = no bound
+    max_amount TEXT,
+    account_id INTEGER,                             -- NULL = any account
+    set_category_id INTEGER,
+    set_payee TEXT,
+    set_memo TEXT,
+    add_tags TEXT,                                  -- comma separated
+
+    FOREIGN KEY(account_id) REFERENCES accounts(account_id) ON DELETE CASCADE,
+    FOREIGN KEY(set_category_id) REFERENCES categories(category_id) ON DELETE SET NULL
+);
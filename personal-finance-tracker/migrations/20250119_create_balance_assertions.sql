@@ -0,0 +1,12 @@
+-- "On this date this account held exactly this much", checked by the ledger checker
+CREATE TABLE IF NOT EXISTS balance_assertions (
+    assertion_id INTEGER PRIMARY KEY,
+    account_id INTEGER NOT NULL,
+    assert_date TEXT NOT NULL,      -- YYYY-MM-DD, balance at the end of that day
+    balance TEXT NOT NULL,
+    assertion_created_at TEXT NOT NULL,
+
+    FOREIGN KEY(account_id) REFERENCES accounts(account_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_balance_assertions_account ON balance_assertions(account_id, assert_date);
@@ -0,0 +1,167 @@
+-- Append-only edit history of accounts, transactions, savings goals and budgets.
+-- Triggers record every insert, update and delete with the row as JSON before and after,
+-- so changes made by any code path (or by cascading deletes) are captured.
+CREATE TABLE IF NOT EXISTS audit_log (
+    audit_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    audited_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+    origin TEXT NOT NULL,           -- 'tui' / 'server' / 'import' / 'recurring' / 'cli'
+    table_name TEXT NOT NULL,
+    row_id INTEGER NOT NULL,        -- no foreign key: the history outlives the row
+    action TEXT NOT NULL,           -- 'insert' / 'update' / 'delete'
+    before TEXT,                    -- JSON object, NULL for inserts
+    after TEXT                      -- JSON object, NULL for deletes
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_row ON audit_log(table_name, row_id);
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+-- Who is writing: every write transaction of the app sets this first. SQLite lets only
+-- one write transaction run at a time, so the triggers below always read the origin of
+-- the transaction they run in.
+CREATE TABLE IF NOT EXISTS audit_origin (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    origin TEXT NOT NULL
+);
+INSERT OR IGNORE INTO audit_origin (id, origin) VALUES (1, 'unknown');
+
+/*========== accounts ==========*/
+CREATE TRIGGER IF NOT EXISTS audit_accounts_insert AFTER INSERT ON accounts
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'accounts', NEW.account_id, 'insert', NULL,
+        json_object('account_id', NEW.account_id, 'account_name', NEW.account_name, 'account_type', NEW.account_type,
+            'balance', NEW.balance, 'currency', NEW.currency, 'account_created_at', NEW.account_created_at,
+            'credit_limit', NEW.credit_limit));
+END;
+
+-- Balance recomputes that do not change anything are not recorded
+CREATE TRIGGER IF NOT EXISTS audit_accounts_update AFTER UPDATE ON accounts
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    SELECT (SELECT origin FROM audit_origin), 'accounts', NEW.account_id, 'update', b, a
+    FROM (SELECT
+        json_object('account_id', OLD.account_id, 'account_name', OLD.account_name, 'account_type', OLD.account_type,
+            'balance', OLD.balance, 'currency', OLD.currency, 'account_created_at', OLD.account_created_at,
+            'credit_limit', OLD.credit_limit) AS b,
+        json_object('account_id', NEW.account_id, 'account_name', NEW.account_name, 'account_type', NEW.account_type,
+            'balance', NEW.balance, 'currency', NEW.currency, 'account_created_at', NEW.account_created_at,
+            'credit_limit', NEW.credit_limit) AS a)
+    WHERE b IS NOT a;
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_accounts_delete AFTER DELETE ON accounts
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'accounts', OLD.account_id, 'delete',
+        json_object('account_id', OLD.account_id, 'account_name', OLD.account_name, 'account_type', OLD.account_type,
+            'balance', OLD.balance, 'currency', OLD.currency, 'account_created_at', OLD.account_created_at,
+            'credit_limit', OLD.credit_limit), NULL);
+END;
+
+/*========== transactions ==========*/
+CREATE TRIGGER IF NOT EXISTS audit_transactions_insert AFTER INSERT ON transactions
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'transactions', NEW.transaction_id, 'insert', NULL,
+        json_object('transaction_id', NEW.transaction_id, 'account_id', NEW.account_id, 'category_id', NEW.category_id,
+            'amount', NEW.amount, 'base_amount', NEW.base_amount, 'is_expense', NEW.is_expense,
+            'description', NEW.description, 'payee', NEW.payee, 'currency', NEW.currency,
+            'transacted_at', NEW.transacted_at, 'value_date', NEW.value_date, 'external_id', NEW.external_id,
+            'cleared', NEW.cleared, 'reconciled', NEW.reconciled));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_transactions_update AFTER UPDATE ON transactions
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    SELECT (SELECT origin FROM audit_origin), 'transactions', NEW.transaction_id, 'update', b, a
+    FROM (SELECT
+        json_object('transaction_id', OLD.transaction_id, 'account_id', OLD.account_id, 'category_id', OLD.category_id,
+            'amount', OLD.amount, 'base_amount', OLD.base_amount, 'is_expense', OLD.is_expense,
+            'description', OLD.description, 'payee', OLD.payee, 'currency', OLD.currency,
+            'transacted_at', OLD.transacted_at, 'value_date', OLD.value_date, 'external_id', OLD.external_id,
+            'cleared', OLD.cleared, 'reconciled', OLD.reconciled) AS b,
+        json_object('transaction_id', NEW.transaction_id, 'account_id', NEW.account_id, 'category_id', NEW.category_id,
+            'amount', NEW.amount, 'base_amount', NEW.base_amount, 'is_expense', NEW.is_expense,
+            'description', NEW.description, 'payee', NEW.payee, 'currency', NEW.currency,
+            'transacted_at', NEW.transacted_at, 'value_date', NEW.value_date, 'external_id', NEW.external_id,
+            'cleared', NEW.cleared, 'reconciled', NEW.reconciled) AS a)
+    WHERE b IS NOT a;
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_transactions_delete AFTER DELETE ON transactions
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'transactions', OLD.transaction_id, 'delete',
+        json_object('transaction_id', OLD.transaction_id, 'account_id', OLD.account_id, 'category_id', OLD.category_id,
+            'amount', OLD.amount, 'base_amount', OLD.base_amount, 'is_expense', OLD.is_expense,
+            'description', OLD.description, 'payee', OLD.payee, 'currency', OLD.currency,
+            'transacted_at', OLD.transacted_at, 'value_date', OLD.value_date, 'external_id', OLD.external_id,
+            'cleared', OLD.cleared, 'reconciled', OLD.reconciled), NULL);
+END;
+
+/*========== savings_goals ==========*/
+CREATE TRIGGER IF NOT EXISTS audit_savings_goals_insert AFTER INSERT ON savings_goals
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'savings_goals', NEW.goal_id, 'insert', NULL,
+        json_object('goal_id', NEW.goal_id, 'account_id', NEW.account_id, 'goal_name', NEW.goal_name,
+            'target_amount', NEW.target_amount, 'current_amount', NEW.current_amount, 'deadline', NEW.deadline));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_savings_goals_update AFTER UPDATE ON savings_goals
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    SELECT (SELECT origin FROM audit_origin), 'savings_goals', NEW.goal_id, 'update', b, a
+    FROM (SELECT
+        json_object('goal_id', OLD.goal_id, 'account_id', OLD.account_id, 'goal_name', OLD.goal_name,
+            'target_amount', OLD.target_amount, 'current_amount', OLD.current_amount, 'deadline', OLD.deadline) AS b,
+        json_object('goal_id', NEW.goal_id, 'account_id', NEW.account_id, 'goal_name', NEW.goal_name,
+            'target_amount', NEW.target_amount, 'current_amount', NEW.current_amount, 'deadline', NEW.deadline) AS a)
+    WHERE b IS NOT a;
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_savings_goals_delete AFTER DELETE ON savings_goals
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'savings_goals', OLD.goal_id, 'delete',
+        json_object('goal_id', OLD.goal_id, 'account_id', OLD.account_id, 'goal_name', OLD.goal_name,
+            'target_amount', OLD.target_amount, 'current_amount', OLD.current_amount, 'deadline', OLD.deadline), NULL);
+END;
+
+/*========== budgets ==========*/
+CREATE TRIGGER IF NOT EXISTS audit_budgets_insert AFTER INSERT ON budgets
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'budgets', NEW.budget_id, 'insert', NULL,
+        json_object('budget_id', NEW.budget_id, 'account_id', NEW.account_id, 'category_id', NEW.category_id,
+            'period', NEW.period, 'amount', NEW.amount, 'currency', NEW.currency, 'start_date', NEW.start_date));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_budgets_update AFTER UPDATE ON budgets
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    SELECT (SELECT origin FROM audit_origin), 'budgets', NEW.budget_id, 'update', b, a
+    FROM (SELECT
+        json_object('budget_id', OLD.budget_id, 'account_id', OLD.account_id, 'category_id', OLD.category_id,
+            'period', OLD.period, 'amount', OLD.amount, 'currency', OLD.currency, 'start_date', OLD.start_date) AS b,
+        json_object('budget_id', NEW.budget_id, 'account_id', NEW.account_id, 'category_id', NEW.category_id,
+            'period', NEW.period, 'amount', NEW.amount, 'currency', NEW.currency, 'start_date', NEW.start_date) AS a)
+    WHERE b IS NOT a;
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_budgets_delete AFTER DELETE ON budgets
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'budgets', OLD.budget_id, 'delete',
+        json_object('budget_id', OLD.budget_id, 'account_id', OLD.account_id, 'category_id', OLD.category_id,
+            'period', OLD.period, 'amount', OLD.amount, 'currency', OLD.currency, 'start_date', OLD.start_date), NULL);
+END;
@@ -0,0 +1,91 @@
+-- Deleted accounts and transactions go to the trash first: the row stays, marked with the
+-- time it was deleted (RFC 3339 with milliseconds, UTC), until it is restored or purged.
+-- Deleting an account marks its transactions with the same time, so they come back with it.
+ALTER TABLE accounts ADD COLUMN deleted_at TEXT;
+ALTER TABLE transactions ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_accounts_deleted_at ON accounts(deleted_at);
+CREATE INDEX IF NOT EXISTS idx_transactions_deleted_at ON transactions(deleted_at);
+
+-- The audit triggers record the new column too
+DROP TRIGGER IF EXISTS audit_accounts_insert;
+DROP TRIGGER IF EXISTS audit_accounts_update;
+DROP TRIGGER IF EXISTS audit_accounts_delete;
+DROP TRIGGER IF EXISTS audit_transactions_insert;
+DROP TRIGGER IF EXISTS audit_transactions_update;
+DROP TRIGGER IF EXISTS audit_transactions_delete;
+
+/*========== accounts ==========*/
+CREATE TRIGGER IF NOT EXISTS audit_accounts_insert AFTER INSERT ON accounts
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'accounts', NEW.account_id, 'insert', NULL,
+        json_object('account_id', NEW.account_id, 'account_name', NEW.account_name, 'account_type', NEW.account_type,
+            'balance', NEW.balance, 'currency', NEW.currency, 'account_created_at', NEW.account_created_at,
+            'credit_limit', NEW.credit_limit, 'deleted_at', NEW.deleted_at));
+END;
+
+-- Balance recomputes that do not change anything are not recorded
+CREATE TRIGGER IF NOT EXISTS audit_accounts_update AFTER UPDATE ON accounts
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    SELECT (SELECT origin FROM audit_origin), 'accounts', NEW.account_id, 'update', b, a
+    FROM (SELECT
+        json_object('account_id', OLD.account_id, 'account_name', OLD.account_name, 'account_type', OLD.account_type,
+            'balance', OLD.balance, 'currency', OLD.currency, 'account_created_at', OLD.account_created_at,
+            'credit_limit', OLD.credit_limit, 'deleted_at', OLD.deleted_at) AS b,
+        json_object('account_id', NEW.account_id, 'account_name', NEW.account_name, 'account_type', NEW.account_type,
+            'balance', NEW.balance, 'currency', NEW.currency, 'account_created_at', NEW.account_created_at,
+            'credit_limit', NEW.credit_limit, 'deleted_at', NEW.deleted_at) AS a)
+    WHERE b IS NOT a;
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_accounts_delete AFTER DELETE ON accounts
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'accounts', OLD.account_id, 'delete',
+        json_object('account_id', OLD.account_id, 'account_name', OLD.account_name, 'account_type', OLD.account_type,
+            'balance', OLD.balance, 'currency', OLD.currency, 'account_created_at', OLD.account_created_at,
+            'credit_limit', OLD.credit_limit, 'deleted_at', OLD.deleted_at), NULL);
+END;
+
+/*========== transactions ==========*/
+CREATE TRIGGER IF NOT EXISTS audit_transactions_insert AFTER INSERT ON transactions
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'transactions', NEW.transaction_id, 'insert', NULL,
+        json_object('transaction_id', NEW.transaction_id, 'account_id', NEW.account_id, 'category_id', NEW.category_id,
+            'amount', NEW.amount, 'base_amount', NEW.base_amount, 'is_expense', NEW.is_expense,
+            'description', NEW.description, 'payee', NEW.payee, 'currency', NEW.currency,
+            'transacted_at', NEW.transacted_at, 'value_date', NEW.value_date, 'external_id', NEW.external_id,
+            'cleared', NEW.cleared, 'reconciled', NEW.reconciled, 'deleted_at', NEW.deleted_at));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_transactions_update AFTER UPDATE ON transactions
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    SELECT (SELECT origin FROM audit_origin), 'transactions', NEW.transaction_id, 'update', b, a
+    FROM (SELECT
+        json_object('transaction_id', OLD.transaction_id, 'account_id', OLD.account_id, 'category_id', OLD.category_id,
+            'amount', OLD.amount, 'base_amount', OLD.base_amount, 'is_expense', OLD.is_expense,
+            'description', OLD.description, 'payee', OLD.payee, 'currency', OLD.currency,
+            'transacted_at', OLD.transacted_at, 'value_date', OLD.value_date, 'external_id', OLD.external_id,
+            'cleared', OLD.cleared, 'reconciled', OLD.reconciled, 'deleted_at', OLD.deleted_at) AS b,
+        json_object('transaction_id', NEW.transaction_id, 'account_id', NEW.account_id, 'category_id', NEW.category_id,
+            'amount', NEW.amount, 'base_amount', NEW.base_amount, 'is_expense', NEW.is_expense,
+            'description', NEW.description, 'payee', NEW.payee, 'currency', NEW.currency,
+            'transacted_at', NEW.transacted_at, 'value_date', NEW.value_date, 'external_id', NEW.external_id,
+            'cleared', NEW.cleared, 'reconciled', NEW.reconciled, 'deleted_at', NEW.deleted_at) AS a)
+    WHERE b IS NOT a;
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_transactions_delete AFTER DELETE ON transactions
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'transactions', OLD.transaction_id, 'delete',
+        json_object('transaction_id', OLD.transaction_id, 'account_id', OLD.account_id, 'category_id', OLD.category_id,
+            'amount', OLD.amount, 'base_amount', OLD.base_amount, 'is_expense', OLD.is_expense,
+            'description', OLD.description, 'payee', OLD.payee, 'currency', OLD.currency,
+            'transacted_at', OLD.transacted_at, 'value_date', OLD.value_date, 'external_id', OLD.external_id,
+            'cleared', OLD.cleared, 'reconciled', OLD.reconciled, 'deleted_at', OLD.deleted_at), NULL);
+END;
@@ -0,0 +1,26 @@
+-- Lock dates: transactions dated on or before `lock_date` can't be added, edited or
+-- deleted without an explicit override. A row without an account locks every account;
+-- an account's effective lock is the later of the two.
+CREATE TABLE IF NOT EXISTS period_locks (
+    lock_id INTEGER PRIMARY KEY,
+    account_id INTEGER,             -- NULL: all accounts
+    lock_date TEXT NOT NULL,        -- YYYY-MM-DD, the last locked day
+    lock_created_at TEXT NOT NULL,
+
+    FOREIGN KEY(account_id) REFERENCES accounts(account_id) ON DELETE CASCADE
+);
+
+-- One lock per account, and one for all of them
+CREATE UNIQUE INDEX IF NOT EXISTS idx_period_locks_account ON period_locks(IFNULL(account_id, 0));
+
+-- Balances at the end of a closed year, as they were when it was closed
+CREATE TABLE IF NOT EXISTS year_end_closes (
+    close_id INTEGER PRIMARY KEY,
+    account_id INTEGER NOT NULL,
+    close_year INTEGER NOT NULL,
+    balance TEXT NOT NULL,          -- at the end of December 31
+    closed_at TEXT NOT NULL,
+
+    UNIQUE(account_id, close_year),
+    FOREIGN KEY(account_id) REFERENCES accounts(account_id) ON DELETE CASCADE
+);
@@ -0,0 +1,63 @@
+-- Full-text index for search: one row per transaction (rowid = transaction_id) with the
+-- words of its payee, memo, category name and tags. Kept current by the triggers below;
+-- rows in the trash stay indexed and are filtered out by the query.
+CREATE VIRTUAL TABLE IF NOT EXISTS transaction_search USING fts5(
+    payee, memo, category, tags,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO transaction_search (rowid, payee, memo, category, tags)
+SELECT t.transaction_id, t.payee, t.description,
+    (SELECT category_name FROM categories WHERE category_id = t.category_id),
+    (SELECT group_concat(g.tag, ' ') FROM transaction_tags tt JOIN tags g ON g.tag_id = tt.tag_id WHERE tt.transaction_id = t.transaction_id)
+FROM transactions t;
+
+-- Restores write rows with their old ids, so clear the id first
+CREATE TRIGGER IF NOT EXISTS search_transactions_insert AFTER INSERT ON transactions
+BEGIN
+    DELETE FROM transaction_search WHERE rowid = NEW.transaction_id;
+    INSERT INTO transaction_search (rowid, payee, memo, category, tags)
+    VALUES (NEW.transaction_id, NEW.payee, NEW.description,
+        (SELECT category_name FROM categories WHERE category_id = NEW.category_id),
+        (SELECT group_concat(g.tag, ' ') FROM transaction_tags tt JOIN tags g ON g.tag_id = tt.tag_id WHERE tt.transaction_id = NEW.transaction_id));
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_transactions_update AFTER UPDATE OF payee, description, category_id ON transactions
+BEGIN
+    UPDATE transaction_search
+    SET payee = NEW.payee, memo = NEW.description,
+        category = (SELECT category_name FROM categories WHERE category_id = NEW.category_id)
+    WHERE rowid = NEW.transaction_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_transactions_delete AFTER DELETE ON transactions
+BEGIN
+    DELETE FROM transaction_search WHERE rowid = OLD.transaction_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_categories_update AFTER UPDATE OF category_name ON categories
+BEGIN
+    UPDATE transaction_search SET category = NEW.category_name
+    WHERE rowid IN (SELECT transaction_id FROM transactions WHERE category_id = NEW.category_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_transaction_tags_insert AFTER INSERT ON transaction_tags
+BEGIN
+    UPDATE transaction_search
+    SET tags = (SELECT group_concat(g.tag, ' ') FROM transaction_tags tt JOIN tags g ON g.tag_id = tt.tag_id WHERE tt.transaction_id = NEW.transaction_id)
+    WHERE rowid = NEW.transaction_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_transaction_tags_delete AFTER DELETE ON transaction_tags
+BEGIN
+    UPDATE transaction_search
+    SET tags = (SELECT group_concat(g.tag, ' ') FROM transaction_tags tt JOIN tags g ON g.tag_id = tt.tag_id WHERE tt.transaction_id = OLD.transaction_id)
+    WHERE rowid = OLD.transaction_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS search_tags_update AFTER UPDATE OF tag ON tags
+BEGIN
+    UPDATE transaction_search
+    SET tags = (SELECT group_concat(g.tag, ' ') FROM transaction_tags tt JOIN tags g ON g.tag_id = tt.tag_id WHERE tt.transaction_id = transaction_search.rowid)
+    WHERE rowid IN (SELECT transaction_id FROM transaction_tags WHERE tag_id = NEW.tag_id);
+END;
@@ -0,0 +1,50 @@
+-- Rows imported from one split record (QIF `S` / `E` / `$` lines) share a split group: the
+-- transaction_id of the group's first row. `export qif` writes a group back as one record.
+ALTER TABLE transactions ADD COLUMN split_group INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_transactions_split_group ON transactions(split_group);
+
+-- The audit triggers record the new column too
+DROP TRIGGER IF EXISTS audit_transactions_insert;
+DROP TRIGGER IF EXISTS audit_transactions_update;
+DROP TRIGGER IF EXISTS audit_transactions_delete;
+
+CREATE TRIGGER IF NOT EXISTS audit_transactions_insert AFTER INSERT ON transactions
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'transactions', NEW.transaction_id, 'insert', NULL,
+        json_object('transaction_id', NEW.transaction_id, 'account_id', NEW.account_id, 'category_id', NEW.category_id,
+            'amount', NEW.amount, 'base_amount', NEW.base_amount, 'is_expense', NEW.is_expense,
+            'description', NEW.description, 'payee', NEW.payee, 'currency', NEW.currency,
+            'transacted_at', NEW.transacted_at, 'value_date', NEW.value_date, 'external_id', NEW.external_id,
+            'cleared', NEW.cleared, 'reconciled', NEW.reconciled, 'deleted_at', NEW.deleted_at, 'split_group', NEW.split_group));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_transactions_update AFTER UPDATE ON transactions
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    SELECT (SELECT origin FROM audit_origin), 'transactions', NEW.transaction_id, 'update', b, a
+    FROM (SELECT
+        json_object('transaction_id', OLD.transaction_id, 'account_id', OLD.account_id, 'category_id', OLD.category_id,
+            'amount', OLD.amount, 'base_amount', OLD.base_amount, 'is_expense', OLD.is_expense,
+            'description', OLD.description, 'payee', OLD.payee, 'currency', OLD.currency,
+            'transacted_at', OLD.transacted_at, 'value_date', OLD.value_date, 'external_id', OLD.external_id,
+            'cleared', OLD.cleared, 'reconciled', OLD.reconciled, 'deleted_at', OLD.deleted_at, 'split_group', OLD.split_group) AS b,
+        json_object('transaction_id', NEW.transaction_id, 'account_id', NEW.account_id, 'category_id', NEW.category_id,
+            'amount', NEW.amount, 'base_amount', NEW.base_amount, 'is_expense', NEW.is_expense,
+            'description', NEW.description, 'payee', NEW.payee, 'currency', NEW.currency,
+            'transacted_at', NEW.transacted_at, 'value_date', NEW.value_date, 'external_id', NEW.external_id,
+            'cleared', NEW.cleared, 'reconciled', NEW.reconciled, 'deleted_at', NEW.deleted_at, 'split_group', NEW.split_group) AS a)
+    WHERE b IS NOT a;
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_transactions_delete AFTER DELETE ON transactions
+BEGIN
+    INSERT INTO audit_log (origin, table_name, row_id, action, before, after)
+    VALUES ((SELECT origin FROM audit_origin), 'transactions', OLD.transaction_id, 'delete',
+        json_object('transaction_id', OLD.transaction_id, 'account_id', OLD.account_id, 'category_id', OLD.category_id,
+            'amount', OLD.amount, 'base_amount', OLD.base_amount, 'is_expense', OLD.is_expense,
+            'description', OLD.description, 'payee', OLD.payee, 'currency', OLD.currency,
+            'transacted_at', OLD.transacted_at, 'value_date', OLD.value_date, 'external_id', OLD.external_id,
+            'cleared', OLD.cleared, 'reconciled', OLD.reconciled, 'deleted_at', OLD.deleted_at, 'split_group', OLD.split_group), NULL);
+END;
@@ -0,0 +1,156 @@
+//! Edit history of accounts, transactions, savings goals and budgets
+//! - Triggers in the database append a row to `audit_log` on every insert, update and
+//!   delete, with the row as JSON before and after (see the 20250120 migration)
+//! - The origin of a change is whatever the writing transaction put in `audit_origin`:
+//!   every write of the app starts with `begin`, and SQLite runs one writer at a time.
+//!   `commit` puts back `unknown`, so writes from outside the app are not logged under the
+//!   last origin that wrote.
+//! - The log is append-only; the database refuses updates and deletes on it
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::{Pool, Sqlite, Transaction};
+
+// Tables with audit triggers
+pub const TABLES: [&str; 4] = ["accounts", "transactions", "savings_goals", "budgets"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Tui,
+    Server,
+    Import,
+    Recurring,  // schedules turned into transactions
+    Cli,        // subcommands other than import (restore, check --fix, rules apply ...)
+}
+
+impl Origin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tui => "tui",
+            Self::Server => "server",
+            Self::Import => "import",
+            Self::Recurring => "recurring",
+            Self::Cli => "cli",
+        }
+    }
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "tui" => Some(Self::Tui),
+            "server" => Some(Self::Server),
+            "import" => Some(Self::Import),
+            "recurring" => Some(Self::Recurring),
+            "cli" => Some(Self::Cli),
+            _ => None,
+        }
+    }
+}
+
+// Start a write transaction whose changes are logged under `origin`
+pub async fn begin(pool: &Pool<Sqlite>, origin: Origin) -> Result<Transaction<'static, Sqlite>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE audit_origin SET origin = ? WHERE id = 1")
+        .bind(origin.as_str())
+        .execute(&mut *tx)
+        .await?;
+    Ok(tx)
+}
+
+// Commit a transaction started with `begin`, resetting the origin in the same transaction
+pub async fn commit(mut tx: Transaction<'static, Sqlite>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE audit_origin SET origin = 'unknown' WHERE id = 1")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub at: String,                 // RFC 3339, UTC
+    pub origin: String,             // an `Origin`, or "unknown" for writes from outside the app
+    pub table: String,
+    pub row_id: i64,
+    pub action: String,             // insert / update / delete
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+// One field of a change; None where the row did not exist
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl AuditEntry {
+    // The fields that differ; every field for inserts and deletes
+    pub fn changes(&self) -> Vec<FieldChange> {
+        let empty = Map::new();
+        let before = self.before.as_ref().and_then(Value::as_object);
+        let after = self.after.as_ref().and_then(Value::as_object);
+        let fields = after.or(before).unwrap_or(&empty);
+        fields.keys()
+            .filter_map(|field| {
+                let b = before.and_then(|m| m.get(field));
+                let a = after.and_then(|m| m.get(field));
+                (b != a).then(|| FieldChange {
+                    field: field.clone(),
+                    before: b.map(show),
+                    after: a.map(show),
+                })
+            })
+            .collect()
+    }
+}
+
+fn show(v: &Value) -> String {
+    match v {
+        Value::Null => "-".into(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// GET /api/audit and the TUI history view; all conditions are optional
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub table: Option<String>,
+    pub row_id: Option<i64>,
+    pub origin: Option<Origin>,
+    pub since: Option<String>,      // entries at or after this RFC 3339 time or date
+    pub limit: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use crate::database::db::migrate;
+
+    const INSERT: &str = "INSERT INTO accounts (account_name, account_type, balance, currency, account_created_at) VALUES ('Cash', 'CASH', '0', 'CAD', '2025-01-01T00:00:00Z')";
+
+    async fn origins(pool: &Pool<Sqlite>) -> Vec<String> {
+        sqlx::query_scalar("SELECT origin FROM audit_log WHERE table_name = 'accounts' ORDER BY audit_id").fetch_all(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn writes_after_a_commit_are_not_logged_under_its_origin() {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        migrate::run_migrations(&pool).await.unwrap();
+
+        let mut tx = begin(&pool, Origin::Tui).await.unwrap();
+        sqlx::query(INSERT).execute(&mut *tx).await.unwrap();
+        commit(tx).await.unwrap();
+        // A write that does not go through `begin`, e.g. the sqlite3 shell
+        sqlx::query(INSERT).execute(&pool).await.unwrap();
+        // Rolled back, origin and all
+        let mut tx = begin(&pool, Origin::Server).await.unwrap();
+        sqlx::query(INSERT).execute(&mut *tx).await.unwrap();
+        drop(tx);
+        sqlx::query(INSERT).execute(&pool).await.unwrap();
+
+        assert_eq!(origins(&pool).await, ["tui", "unknown", "unknown"]);
+    }
+}
@@ -1,15 +1,21 @@
 // src/backend/handlers.rs
 use axum::{
-    extract::State,
+    extract::{Query, State},
     Json,
-    response::IntoResponse,
+    response::{Html, IntoResponse},
     http::StatusCode,
 };
 use serde::{Deserialize, Serialize};
+use crate::audit::{self, AuditFilter, Origin};
 use crate::backend::AppState;
 use rust_decimal::Decimal;
-use chrono::NaiveDateTime;
-use rust_decimal::prelude::ToPrimitive; //converting Decimal to f64
+use chrono::{NaiveDate, NaiveDateTime};
+use crate::cli::api::Client;
+use crate::database::db::queries;
+use crate::lock::PeriodLocked;
+use crate::search;
+use crate::cli::state::{ReportGrouping, ReportPeriod};
+use crate::export::statement;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CreateTransaction {
@@ -26,6 +32,8 @@ pub struct CreateTransaction {
 pub struct SyncRequest {
     pub last_synced_at: Option<String>,
     pub transactions: Vec<CreateTransaction>,
+    #[serde(default)]
+    pub override_lock: bool,    // also write rows dated in a locked period
 }
 
 pub async fn sync_handler(
@@ -35,36 +43,31 @@ pub async fn sync_handler(
     println!(" Received sync request, processing {} transactions...", payload.transactions.len());
 
     let mut success_count = 0;
+    let mut locked_count = 0;
 
     for txn in payload.transactions {
-        let amount_f64 = txn.amount.to_f64().unwrap_or(0.0);
-        let base_amount_f64 = txn.base_amount.to_f64().unwrap_or(0.0);
-
-        let now = chrono::Local::now().naive_local();
-
-        let result = sqlx::query!(
-            r#"
-            INSERT INTO transactions (
-                account_id, category_id, amount, base_amount, is_expense, 
-                description, currency, transacted_at, trans_create_at
-            )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
+        // Stored as exact decimal text; the account balance is recomputed from the rows
+        let result = queries::create_transaction(
+            &state.db,
             txn.account_id,
             txn.category_id,
-            amount_f64,        
-            base_amount_f64,  
-            txn.is_expense,
-            txn.description,
+            txn.amount.abs(),
+            txn.base_amount.abs(),
             txn.currency,
+            txn.is_expense,
+            txn.description.as_deref(),
             txn.transacted_at,
-            now
+            Origin::Server,
+            payload.override_lock,
         )
-        .execute(&state.db)
         .await;
 
         match result {
             Ok(_) => success_count += 1,
+            Err(sqlx::Error::Database(e)) if e.try_downcast_ref::<PeriodLocked>().is_some() => {
+                println!("Refused: {}", e);
+                locked_count += 1;
+            }
             Err(e) => {
                 println!("Insert failed: {:?}", e);
             }
@@ -73,5 +76,222 @@ pub async fn sync_handler(
 
     println!("Sync complete! Successfully inserted {} records", success_count);
 
+    if locked_count > 0 {
+        return (StatusCode::OK, Json(format!(
+            "Synced {} transactions successfully, {} refused: dated in a locked period (send override_lock to write them)",
+            success_count, locked_count
+        )));
+    }
     (StatusCode::OK, Json(format!("Synced {} transactions successfully", success_count)))
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpendingQuery {
+    pub start: Option<NaiveDate>,   // YYYY-MM-DD, defaults to the start of the current month
+    pub end: Option<NaiveDate>,     // YYYY-MM-DD inclusive, defaults to the end of that month
+    pub group: Option<String>,      // day/week/month/quarter/year
+}
+
+// GET /api/reports/spending?start=2025-01-01&end=2025-03-31&group=month
+pub async fn spending_report_handler(
+    State(state): State<AppState>,
+    Query(q): Query<SpendingQuery>,
+) -> impl IntoResponse {
+    let grouping = match q.group.as_deref() {
+        None => ReportGrouping::Month,
+        Some(g) => match ReportGrouping::parse(g) {
+            Some(g) => g,
+            None => return (StatusCode::BAD_REQUEST, format!("Unknown grouping: {}", g)).into_response(),
+        },
+    };
+
+    let period = ReportPeriod::default();
+    let start = q.start.unwrap_or(period.start);
+    let end = q.end.unwrap_or(period.end());
+    if end < start {
+        return (StatusCode::BAD_REQUEST, "end must not be before start".to_string()).into_response();
+    }
+
+    let client = Client::from_pool(state.db.clone());
+    match client.get_spending_by_period(start, end, grouping).await {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(e) => {
+            println!("Spending report failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CashFlowQuery {
+    pub months: Option<u32>,        // defaults to 6, capped at 120
+}
+
+// GET /api/reports/cashflow?months=12
+pub async fn cash_flow_handler(
+    State(state): State<AppState>,
+    Query(q): Query<CashFlowQuery>,
+) -> impl IntoResponse {
+    let months = q.months.unwrap_or(6).clamp(1, 120);
+
+    let client = Client::from_pool(state.db.clone());
+    match client.get_cash_flow(months).await {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(e) => {
+            println!("Cash flow report failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForecastQuery {
+    pub days: Option<u32>,          // defaults to 30, capped at 366
+}
+
+// GET /api/reports/forecast?days=90
+pub async fn forecast_handler(
+    State(state): State<AppState>,
+    Query(q): Query<ForecastQuery>,
+) -> impl IntoResponse {
+    let days = q.days.unwrap_or(30).clamp(1, 366);
+
+    let client = Client::from_pool(state.db.clone());
+    match client.get_forecast(days).await {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(e) => {
+            println!("Forecast failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatementQuery {
+    pub month: Option<String>,      // YYYY-MM, defaults to the current month
+}
+
+// GET /api/reports/statement?month=2025-03 -> self-contained HTML page
+pub async fn statement_handler(
+    State(state): State<AppState>,
+    Query(q): Query<StatementQuery>,
+) -> impl IntoResponse {
+    let month = match q.month.as_deref() {
+        Some(m) => match statement::parse_month(m) {
+            Some(d) => d,
+            None => return (StatusCode::BAD_REQUEST, "month must be YYYY-MM").into_response(),
+        },
+        None => chrono::Utc::now().date_naive(),
+    };
+
+    let client = Client::from_pool(state.db.clone());
+    match client.get_statement(month).await {
+        Ok(doc) => {
+            let generated = chrono::Utc::now().format("%Y-%m-%d %H:%M UTC").to_string();
+            (StatusCode::OK, Html(statement::render(&doc, &generated))).into_response()
+        }
+        Err(e) => {
+            println!("Statement failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PayeeQuery {
+    pub start: Option<NaiveDate>,   // YYYY-MM-DD, defaults to the start of the current month
+    pub end: Option<NaiveDate>,     // YYYY-MM-DD inclusive, defaults to the end of that month
+    pub limit: Option<usize>,       // top N payees, all when omitted
+}
+
+// GET /api/reports/payees?start=2025-01-01&end=2025-03-31&limit=10
+pub async fn payee_report_handler(
+    State(state): State<AppState>,
+    Query(q): Query<PayeeQuery>,
+) -> impl IntoResponse {
+    let period = ReportPeriod::default();
+    let start = q.start.unwrap_or(period.start);
+    let end = q.end.unwrap_or(period.end());
+    if end < start {
+        return (StatusCode::BAD_REQUEST, "end must not be before start".to_string()).into_response();
+    }
+
+    let client = Client::from_pool(state.db.clone());
+    match client.get_payee_report(start, end, q.limit).await {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(e) => {
+            println!("Payee report failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub table: Option<String>,      // accounts / transactions / savings_goals / budgets
+    pub row_id: Option<i64>,
+    pub origin: Option<String>,     // tui / server / import / recurring / cli
+    pub since: Option<String>,      // YYYY-MM-DD or RFC 3339 (UTC)
+    pub limit: Option<i64>,         // defaults to 100, capped at 1000
+}
+
+// GET /api/audit?table=transactions&row_id=42 -> edit history, newest first
+pub async fn audit_handler(
+    State(state): State<AppState>,
+    Query(q): Query<AuditQuery>,
+) -> impl IntoResponse {
+    if let Some(t) = q.table.as_deref()
+        && !audit::TABLES.contains(&t) {
+        return (StatusCode::BAD_REQUEST, format!("Unknown table: {}", t)).into_response();
+    }
+    let origin = match q.origin.as_deref() {
+        None => None,
+        Some(o) => match Origin::parse(o) {
+            Some(o) => Some(o),
+            None => return (StatusCode::BAD_REQUEST, format!("Unknown origin: {}", o)).into_response(),
+        },
+    };
+    let filter = AuditFilter {
+        table: q.table,
+        row_id: q.row_id,
+        origin,
+        since: q.since,
+        limit: Some(q.limit.unwrap_or(100).clamp(1, 1000)),
+    };
+
+    let client = Client::from_pool(state.db.clone());
+    match client.list_audit(&filter).await {
+        Ok(entries) => (StatusCode::OK, Json(entries)).into_response(),
+        Err(e) => {
+            println!("Audit log failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    pub q: String,                  // same syntax as the TUI: payee:amazon amount:>50 date:2025-01..2025-03
+    pub limit: Option<usize>,       // defaults to 100, capped at 1000
+}
+
+// GET /api/search?q=payee:amazon%20amount:>50 -> matching transactions of every account, newest first
+pub async fn search_handler(
+    State(state): State<AppState>,
+    Query(p): Query<SearchParams>,
+) -> impl IntoResponse {
+    let query = match search::parse(&p.q) {
+        Ok(q) if !q.is_empty() => q,
+        Ok(_) => return (StatusCode::BAD_REQUEST, "q is empty".to_string()).into_response(),
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let client = Client::from_pool(state.db.clone());
+    match client.search_transactions(&query, p.limit.unwrap_or(100).clamp(1, 1000)).await {
+        Ok(hits) => (StatusCode::OK, Json(hits)).into_response(),
+        Err(e) => {
+            println!("Search failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
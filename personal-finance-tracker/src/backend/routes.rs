@@ -1,5 +1,5 @@
 use axum::{
-    routing::post,
+    routing::{get, post},
     Router,
 };
 use crate::backend::{handlers, AppState};
@@ -7,4 +7,11 @@ use crate::backend::{handlers, AppState};
 pub fn api_routes() -> Router<AppState> {
     Router::new()
         .route("/api/sync", post(handlers::sync_handler))
+        .route("/api/reports/spending", get(handlers::spending_report_handler))
+        .route("/api/reports/cashflow", get(handlers::cash_flow_handler))
+        .route("/api/reports/payees", get(handlers::payee_report_handler))
+        .route("/api/reports/forecast", get(handlers::forecast_handler))
+        .route("/api/reports/statement", get(handlers::statement_handler))
+        .route("/api/audit", get(handlers::audit_handler))
+        .route("/api/search", get(handlers::search_handler))
 }
\ No newline at end of file
@@ -0,0 +1,295 @@
+//! Full JSON backup of the ledger, and the checks a restore runs first
+//! - One document holds every table, tagged with the schema version it was taken at
+//!   (the newest applied migration, e.g. 20250115)
+//! - Documents from older schemas are upgraded before they are read: `SCHEMA_STEPS` fills
+//!   in what each migration since added, `FORMAT_STEPS` what each layout change of the
+//!   document added. Documents from a newer build are refused.
+//! - Records reuse the `cli::state` DTOs and the serde models in `database::models`
+
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::cli::state::{AccountDto, CategoryDto, ImportProfileDto, Money, TransactionDto};
+use crate::database::models::{Budget, CurrencyRate, RecurringTransaction, SavingsGoal};
+use crate::check::BalanceAssertion;
+use crate::lock::{PeriodLock, YearEndClose};
+use crate::rules::Rule;
+
+pub const FORMAT: &str = "personal-finance-tracker-backup";
+// Layout of the document, bumped when a record changes shape without a migration
+// 1: transactions carry their date only
+// 2: transactions carry `transacted_at` as stored and `is_expense`
+pub const FORMAT_VERSION: i64 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Backup {
+    pub format: String,
+    pub format_version: i64,
+    pub schema_version: i64,
+    pub created_at: String,
+    pub accounts: Vec<AccountDto>,
+    pub categories: Vec<CategoryDto>,
+    pub transactions: Vec<TransactionRecord>,
+    #[serde(default)]
+    pub tags: Vec<TagRecord>,
+    #[serde(default)]
+    pub transaction_tags: Vec<TransactionTag>,
+    #[serde(default)]
+    pub recurring: Vec<RecurringTransaction>,
+    #[serde(default)]
+    pub budgets: Vec<Budget>,
+    #[serde(default)]
+    pub savings_goals: Vec<SavingsGoal>,
+    #[serde(default)]
+    pub currency_rates: Vec<CurrencyRate>,
+    pub import_profiles: Vec<ImportProfileDto>,     // since 20250113
+    pub rules: Vec<Rule>,                           // since 20250118
+    pub balance_assertions: Vec<BalanceAssertion>,  // since 20250119
+    pub period_locks: Vec<PeriodLock>,              // since 20250122
+    pub year_end_closes: Vec<YearEndClose>,         // since 20250122
+}
+
+// A transaction plus the columns the DTO leaves out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionRecord {
+    #[serde(flatten)]
+    pub txn: TransactionDto,
+    pub transacted_at: String,                      // as stored, with the time of day (format 2)
+    pub is_expense: bool,                           // as stored; a zero amount keeps its side (format 2)
+    pub base_amount: Option<Money>,
+    pub external_id: Option<String>,                // since 20250114
+    pub value_date: Option<NaiveDate>,              // since 20250116
+    pub created_at: Option<String>,
+    pub deleted_at: Option<String>,                 // since 20250121, in the trash
+    pub split_group: Option<i64>,                   // since 20250124, id of the first row of its split
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagRecord {
+    pub id: i64,
+    pub tag: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionTag {
+    pub transaction_id: i64,
+    pub tag_id: i64,
+}
+
+// Rows written by a restore
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreSummary {
+    pub accounts: usize,
+    pub categories: usize,
+    pub transactions: usize,
+    pub other: usize,       // tags, rules, budgets, goals, rates, profiles, assertions
+}
+
+// Read a document written by this or an older build; `current` is this build's schema version
+pub fn parse(text: &str, current: i64) -> Result<Backup> {
+    let mut doc: Value = serde_json::from_str(text)?;
+    let Some(d) = doc.as_object_mut().filter(|d| d.get("format").and_then(|f| f.as_str()) == Some(FORMAT)) else {
+        bail!("not a personal-finance-tracker backup");
+    };
+    let Some(version) = d.get("schema_version").and_then(|v| v.as_i64()) else {
+        bail!("backup has no schema_version");
+    };
+    if version > current {
+        bail!("backup is from a newer version (schema {}, this build knows {}); upgrade first", version, current);
+    }
+    // Documents from before the field was written are format 1
+    let format = d.get("format_version").map_or(Some(1), |v| v.as_i64()).unwrap_or(0);
+    if !(1..=FORMAT_VERSION).contains(&format) {
+        bail!("backup has an unknown format version {} (this build knows 1 to {}); upgrade first", format, FORMAT_VERSION);
+    }
+    upgrade(d, version, format);
+    Ok(serde_json::from_value(doc)?)
+}
+
+type Step = fn(&mut Map<String, Value>);
+
+// What each migration added to the document, oldest first. A document goes through every
+// step newer than the schema it was taken at; a step only adds what is missing.
+const SCHEMA_STEPS: &[(i64, Step)] = &[
+    (20250113, |d| add_list(d, "import_profiles")),
+    (20250114, |d| add_to_each(d, "transactions", "external_id", Value::Null)),
+    (20250115, |d| {
+        add_to_each(d, "transactions", "cleared", Value::Bool(false));
+        add_to_each(d, "transactions", "reconciled", Value::Bool(false));
+    }),
+    (20250116, |d| add_to_each(d, "transactions", "value_date", Value::Null)),
+    (20250118, |d| add_list(d, "rules")),
+    (20250119, |d| add_list(d, "balance_assertions")),
+    (20250121, |d| {
+        add_to_each(d, "transactions", "deleted_at", Value::Null);
+        add_to_each(d, "accounts", "deleted_at", Value::Null);
+    }),
+    (20250122, |d| {
+        add_list(d, "period_locks");
+        add_list(d, "year_end_closes");
+    }),
+    (20250124, |d| add_to_each(d, "transactions", "split_group", Value::Null)),
+];
+
+// What each format version added, by the version that added it
+const FORMAT_STEPS: &[(i64, Step)] = &[
+    // The time of day was not kept: midnight, and the side taken from the amount's sign
+    (2, |d| {
+        for t in d.get_mut("transactions").and_then(|t| t.as_array_mut()).into_iter().flatten().filter_map(|t| t.as_object_mut()) {
+            let date = t.get("txn_date").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            let negative = t.get("amount").and_then(|v| v.as_str()).is_some_and(|a| a.trim_start().starts_with('-'));
+            t.entry("transacted_at").or_insert_with(|| Value::String(format!("{} 00:00:00", date)));
+            t.entry("is_expense").or_insert(Value::Bool(negative));
+        }
+    }),
+];
+
+// Bring a document taken at `schema` and `format` up to this build's layout
+fn upgrade(d: &mut Map<String, Value>, schema: i64, format: i64) {
+    for (_, step) in SCHEMA_STEPS.iter().filter(|(v, _)| *v > schema) {
+        step(d);
+    }
+    for (_, step) in FORMAT_STEPS.iter().filter(|(v, _)| *v > format) {
+        step(d);
+    }
+    d.insert("format_version".into(), Value::from(FORMAT_VERSION));
+}
+
+fn add_list(d: &mut Map<String, Value>, key: &str) {
+    d.entry(key).or_insert_with(|| Value::Array(Vec::new()));
+}
+
+fn add_to_each(d: &mut Map<String, Value>, list: &str, key: &str, value: Value) {
+    for row in d.get_mut(list).and_then(|l| l.as_array_mut()).into_iter().flatten().filter_map(|r| r.as_object_mut()) {
+        row.entry(key).or_insert_with(|| value.clone());
+    }
+}
+
+// Everything that would make the restore fail half way: duplicate ids and rows that point
+// at missing accounts, categories or tags
+pub fn validate(b: &Backup) -> Result<()> {
+    let mut errors = Vec::new();
+
+    let accounts = unique_ids("account", b.accounts.iter().map(|a| a.id), &mut errors);
+    let categories = unique_ids("category", b.categories.iter().map(|c| c.id), &mut errors);
+    let txns = unique_ids("transaction", b.transactions.iter().map(|t| t.txn.id), &mut errors);
+    let tags = unique_ids("tag", b.tags.iter().map(|t| t.id), &mut errors);
+    unique_ids("recurring rule", b.recurring.iter().map(|r| r.recurring_id), &mut errors);
+    unique_ids("budget", b.budgets.iter().map(|r| r.budget_id), &mut errors);
+    unique_ids("goal", b.savings_goals.iter().map(|r| r.goal_id), &mut errors);
+    unique_ids("rule", b.rules.iter().map(|r| r.id), &mut errors);
+    unique_ids("balance assertion", b.balance_assertions.iter().map(|a| a.id), &mut errors);
+
+    for t in &b.transactions {
+        if !accounts.contains(&t.txn.account_id) {
+            errors.push(format!("transaction {}: no account {}", t.txn.id, t.txn.account_id));
+        }
+        match t.txn.category_id {
+            Some(c) if !categories.contains(&c) => errors.push(format!("transaction {}: no category {}", t.txn.id, c)),
+            None => errors.push(format!("transaction {}: no category", t.txn.id)),
+            _ => {}
+        }
+    }
+    for l in &b.transaction_tags {
+        if !txns.contains(&l.transaction_id) || !tags.contains(&l.tag_id) {
+            errors.push(format!("tag link {} -> {}: missing transaction or tag", l.transaction_id, l.tag_id));
+        }
+    }
+    let owned = b.recurring.iter().map(|r| ("recurring rule", r.recurring_id, Some(r.account_id), r.category_id))
+        .chain(b.budgets.iter().map(|r| ("budget", r.budget_id, Some(r.account_id), r.category_id)))
+        .chain(b.savings_goals.iter().map(|r| ("goal", r.goal_id, Some(r.account_id), None)))
+        .chain(b.rules.iter().map(|r| ("rule", r.id, r.account_id, r.set_category_id)))
+        .chain(b.balance_assertions.iter().map(|a| ("balance assertion", a.id, Some(a.account_id), None)));
+    for (kind, id, account, category) in owned {
+        if let Some(account) = account.filter(|a| !accounts.contains(a)) {
+            errors.push(format!("{} {}: no account {}", kind, id, account));
+        }
+        if let Some(c) = category.filter(|c| !categories.contains(c)) {
+            errors.push(format!("{} {}: no category {}", kind, id, c));
+        }
+    }
+    for a in b.period_locks.iter().filter_map(|l| l.account_id).filter(|a| !accounts.contains(a)) {
+        errors.push(format!("lock date: no account {}", a));
+    }
+    for c in b.year_end_closes.iter().filter(|c| !accounts.contains(&c.account_id)) {
+        errors.push(format!("{} close: no account {}", c.year, c.account_id));
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let more = if errors.len() > 10 { format!("\n  ... and {} more", errors.len() - 10) } else { String::new() };
+    errors.truncate(10);
+    bail!("backup is inconsistent:\n  {}{}", errors.join("\n  "), more)
+}
+
+fn unique_ids(kind: &str, ids: impl Iterator<Item = i64>, errors: &mut Vec<String>) -> HashSet<i64> {
+    let mut seen = HashSet::new();
+    for id in ids {
+        if !seen.insert(id) {
+            errors.push(format!("duplicate {} id {}", kind, id));
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = include_str!("../../tests/fixtures/backup/schema-20250112.json");
+    const CURRENT: i64 = 20250124;
+
+    fn with(text: &str, key: &str, value: Value) -> String {
+        let mut doc: Value = serde_json::from_str(text).unwrap();
+        doc[key] = value;
+        doc.to_string()
+    }
+
+    #[test]
+    fn upgrades_an_old_document_step_by_step() {
+        let b = parse(OLD, CURRENT).unwrap();
+        assert_eq!(b.format_version, FORMAT_VERSION);
+        assert!(b.import_profiles.is_empty() && b.rules.is_empty() && b.period_locks.is_empty());
+        assert_eq!(b.accounts[0].deleted_at, None);
+
+        let (pay, shop) = (&b.transactions[0], &b.transactions[1]);
+        assert_eq!(pay.transacted_at, "2025-01-15 00:00:00");
+        assert!(!pay.is_expense);
+        assert_eq!(shop.transacted_at, "2025-01-18 00:00:00");
+        assert!(shop.is_expense);
+        assert_eq!((shop.external_id.as_deref(), shop.value_date, shop.deleted_at.as_deref()), (None, None, None));
+        assert!(!shop.txn.cleared && !shop.txn.reconciled);
+    }
+
+    #[test]
+    fn keeps_what_a_current_document_stored() {
+        let mut doc: Value = serde_json::from_str(OLD).unwrap();
+        upgrade(doc.as_object_mut().unwrap(), 20250112, 1);
+        doc["schema_version"] = Value::from(CURRENT);
+        doc["transactions"][1]["transacted_at"] = Value::from("2025-01-18T17:12:44Z");
+        doc["transactions"][1]["amount"] = Value::from("0.00");
+
+        let b = parse(&doc.to_string(), CURRENT).unwrap();
+        assert_eq!(b.transactions[1].transacted_at, "2025-01-18T17:12:44Z");
+        assert!(b.transactions[1].is_expense);
+    }
+
+    #[test]
+    fn refuses_newer_and_unknown_versions() {
+        let newer = with(OLD, "schema_version", Value::from(CURRENT + 1));
+        assert!(parse(&newer, CURRENT).unwrap_err().to_string().contains("newer version"));
+
+        for format in [Value::from(FORMAT_VERSION + 1), Value::from(0), Value::from("2")] {
+            let doc = with(OLD, "format_version", format);
+            assert!(parse(&doc, CURRENT).unwrap_err().to_string().contains("unknown format version"));
+        }
+
+        assert!(parse(&with(OLD, "format", Value::from("other")), CURRENT).is_err());
+        assert!(parse("[]", CURRENT).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 
 use dotenvy::dotenv;
 use personal_finance_tracker::database::db::connection::get_db_pool;
+use personal_finance_tracker::audit::Origin;
 use personal_finance_tracker::database::db::queries;
 use personal_finance_tracker::database::models::{Budget};
 
@@ -38,7 +39,7 @@ async fn main() -> anyhow::Result<()> {
     // TEST：CREATE ACCOUNT
     // ----------------------------------------------------
     println!("\n--- Testing: create_account ---");
-    let account_id = queries::create_account(&pool, initial_acc_name, initial_acc_type, initial_currency).await?;
+    let account_id = queries::create_account(&pool, initial_acc_name, initial_acc_type, initial_currency, Origin::Cli).await?;
     println!("   > Account created successfully {}", account_id);
     assert!(account_id > 0, "Failed to create Account, ID invalid.");
 
@@ -66,7 +67,7 @@ async fn main() -> anyhow::Result<()> {
     let new_acc_name = "Name changed - Savings Account";
     let new_acc_type = "Savings Account";
     println!("\n--- Testing: update_account ---");
-    let update_success = queries::update_account(&pool, account_id, new_acc_name.to_string(), new_acc_type.to_string()).await?;
+    let update_success = queries::update_account(&pool, account_id, new_acc_name.to_string(), new_acc_type.to_string(), Origin::Cli).await?;
     println!("   > Updated successfully: {}", update_success);
     assert!(update_success, "Failed to update account!");
 
@@ -114,7 +115,9 @@ async fn main() -> anyhow::Result<()> {
         currency,
         true, // is_expense
         Some("Metro purchasing"), 
-        trans_time
+        trans_time,
+        Origin::Cli,
+        false,
     ).await?;
     println!("   > Transaction created successfully, ID: {}", transaction_id);
     assert!(transaction_id > 0, "Failed to create Transaction!");
@@ -178,7 +181,7 @@ async fn main() -> anyhow::Result<()> {
         start_date: NaiveDateTime::parse_from_str("2025-11-22 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
     };
 
-    let new_id = queries::create_budget(&pool, &budget).await?;
+    let new_id = queries::create_budget(&pool, &budget, Origin::Cli).await?;
     println!("New budget inserted with id {}", new_id);
 
     println!("Listing budgets for account 1...");
@@ -202,11 +205,11 @@ async fn main() -> anyhow::Result<()> {
     //     deadline: NaiveDateTime::parse_from_str("2025-12-31 23:59:59", "%Y-%m-%d %H:%M:%S").unwrap(),
     // };
 
-    // let new_goal_id = queries::create_saving_goal(&pool, &saving).await?;
+    // let new_goal_id = queries::create_saving_goal(&pool, &saving, Origin::Cli).await?;
     // println!("New savings goal id = {}", new_goal_id);
 
     // println!("Updating current amount...");
-    // queries::update_goal_amount(&pool, new_goal_id, rust_decimal::Decimal::new(300, 0)).await?;
+    // queries::update_goal_amount(&pool, new_goal_id, rust_decimal::Decimal::new(300, 0), Origin::Cli).await?;
     // println!("   > update_goal_amount successfully");
 
        // ----------------------------------------------------
@@ -232,7 +235,7 @@ async fn main() -> anyhow::Result<()> {
     // TEST：DELETE ACCOUNT
     // ----------------------------------------------------
     // println!("\n--- Testing: delete_account ---");
-    // let delete_success = queries::delete_account(&pool, account_id).await?;
+    // let delete_success = queries::delete_account(&pool, account_id, Origin::Cli).await?;
     // println!("   > Account deleted successfully: {}", delete_success);
     // assert!(delete_success, "Failed to delete account!");
 
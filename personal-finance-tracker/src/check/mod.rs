@@ -0,0 +1,539 @@
+//! Ledger integrity checks
+//! - Every account balance is recomputed exactly in `Decimal` from its transactions and
+//!   compared with the stored `accounts.balance`
+//! - Rows that point at a missing account, category, transaction or tag
+//! - Amounts that are not plain unsigned decimals, dates SQLite's `date()` can't read,
+//!   and dates in the future
+//! - Money out filed under an income category, and money in under an expense category
+//! - Balance assertions ("on 2025-01-31 Chequing = 1234.56") against the transactions
+//!
+//! `check` only reads a snapshot of the raw rows. Each issue carries the repair
+//! `Client::repair_ledger` would make, or None when a person has to decide.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// Where the fixed fallback categories are looked up by name
+const UNCATEGORIZED: &str = "Uncategorized";
+const OTHER_INCOME: &str = "Other Income";
+// Opening balances are income-type rows of either sign
+const INITIAL_BALANCE: &str = "Initial Balance";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceAssertion {
+    pub id: i64,
+    pub account_id: i64,
+    pub date: NaiveDate,            // balance at the end of that day
+    #[serde(with = "rust_decimal::serde::str")]
+    pub balance: Decimal,
+}
+
+// The rows as stored, before the parsing that elsewhere reads bad values as zero or today
+#[derive(Debug, Default)]
+pub struct Ledger {
+    pub accounts: Vec<AccountRow>,
+    pub categories: Vec<CategoryRow>,
+    pub transactions: Vec<TxnRow>,
+    pub tag_ids: HashSet<i64>,
+    pub tag_links: Vec<(i64, i64)>,     // (transaction id, tag id)
+    pub owned: Vec<OwnedRow>,           // rows of other tables that belong to an account
+    pub assertions: Vec<BalanceAssertion>,
+}
+
+#[derive(Debug)]
+pub struct AccountRow {
+    pub id: i64,
+    pub name: String,
+    pub balance: String,
+}
+
+#[derive(Debug)]
+pub struct CategoryRow {
+    pub id: i64,
+    pub name: String,
+    pub kind: String,               // category_type as stored: Income / Expense / Transfer
+}
+
+#[derive(Debug)]
+pub struct TxnRow {
+    pub id: i64,
+    pub account_id: i64,
+    pub category_id: Option<i64>,
+    pub amount: String,
+    pub is_expense: bool,
+    pub transacted_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Owned {
+    Budget,
+    Goal,
+    Recurring,
+    Rule,
+    Assertion,
+}
+
+impl Owned {
+    // (table, primary key column)
+    pub fn table(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Budget => ("budgets", "budget_id"),
+            Self::Goal => ("savings_goals", "goal_id"),
+            Self::Recurring => ("recurring_transactions", "recurring_id"),
+            Self::Rule => ("rules", "rule_id"),
+            Self::Assertion => ("balance_assertions", "assertion_id"),
+        }
+    }
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Budget => "budget",
+            Self::Goal => "saving goal",
+            Self::Recurring => "recurring rule",
+            Self::Rule => "rule",
+            Self::Assertion => "balance assertion",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OwnedRow {
+    pub kind: Owned,
+    pub id: i64,
+    pub account_id: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,    // probably fine, worth a look
+    Error,      // some total or report is wrong because of it
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repair {
+    SetBalance { account_id: i64, balance: Decimal },
+    SetAmount { transaction_id: i64, amount: Decimal, is_expense: bool },
+    SetDate { transaction_id: i64, date: String },
+    SetCategory { transaction_id: i64, category_id: i64, category: String },
+    DeleteTransaction(i64),
+    DeleteTagLink { transaction_id: i64, tag_id: i64 },
+    DeleteOwned(Owned, i64),
+}
+
+impl Repair {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::SetBalance { balance, .. } => format!("store the balance as {}", balance),
+            Self::SetAmount { amount, is_expense, .. } => {
+                format!("store {} as money {}", amount, if *is_expense { "out" } else { "in" })
+            }
+            Self::SetDate { date, .. } => format!("rewrite the date as {}", date),
+            Self::SetCategory { category, .. } => format!("move it to '{}'", category),
+            Self::DeleteTransaction(_) => "move the transaction to the Trash".into(),
+            Self::DeleteTagLink { .. } => "drop the tag link".into(),
+            Self::DeleteOwned(kind, _) => format!("delete the {}", kind.label()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+    pub repair: Option<Repair>,
+}
+
+impl Issue {
+    fn error(message: String, repair: Option<Repair>) -> Self {
+        Self { severity: Severity::Error, message, repair }
+    }
+    fn warning(message: String, repair: Option<Repair>) -> Self {
+        Self { severity: Severity::Warning, message, repair }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub accounts: usize,
+    pub transactions: usize,
+    pub assertions: usize,
+    pub issues: Vec<Issue>,     // errors first
+}
+
+impl Report {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues.iter().filter(|i| i.severity == severity).count()
+    }
+    pub fn repairable(&self) -> usize {
+        self.issues.iter().filter(|i| i.repair.is_some()).count()
+    }
+    // "3 accounts, 120 transactions, 2 balance assertions: 1 error, 2 warnings (2 repairable)"
+    pub fn summary(&self) -> String {
+        let checked = format!(
+            "{} accounts, {} transactions, {} balance assertions",
+            self.accounts, self.transactions, self.assertions
+        );
+        if self.issues.is_empty() {
+            return format!("{}: no problems found", checked);
+        }
+        format!(
+            "{}: {} errors, {} warnings ({} repairable)",
+            checked, self.count(Severity::Error), self.count(Severity::Warning), self.repairable()
+        )
+    }
+}
+
+pub fn check(l: &Ledger, today: NaiveDate) -> Report {
+    let mut issues = Vec::new();
+    let accounts: HashMap<i64, &AccountRow> = l.accounts.iter().map(|a| (a.id, a)).collect();
+    let categories: HashMap<i64, &CategoryRow> = l.categories.iter().map(|c| (c.id, c)).collect();
+    let fallback = |expense: bool| {
+        let name = if expense { UNCATEGORIZED } else { OTHER_INCOME };
+        l.categories.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    };
+    let move_to = |transaction_id: i64, c: &CategoryRow| Repair::SetCategory {
+        transaction_id,
+        category_id: c.id,
+        category: c.name.clone(),
+    };
+
+    // (date, signed amount) of every row whose amount can be read, per account; dates and
+    // amounts the repairs would rewrite count as rewritten
+    let mut entries: HashMap<i64, Vec<(Option<NaiveDate>, Decimal)>> = HashMap::new();
+
+    for t in &l.transactions {
+        let Some(account) = accounts.get(&t.account_id) else {
+            issues.push(Issue::error(
+                format!("transaction #{}: account {} does not exist", t.id, t.account_id),
+                Some(Repair::DeleteTransaction(t.id)),
+            ));
+            continue;
+        };
+        let at = format!("transaction #{} ({}, {})", t.id, account.name, t.transacted_at);
+
+        let sign = |d: Decimal| if t.is_expense { -d } else { d };
+        let strict = Decimal::from_str_exact(&t.amount).ok();
+        let value = match strict {
+            Some(d) if !t.amount.starts_with(['-', '+']) => Some(sign(d)),
+            _ => match strict.or_else(|| loose_amount(&t.amount)) {
+                Some(d) => {
+                    let v = sign(d);
+                    let what = if strict.is_some() { "carries a sign (money out is the expense flag)" } else { "is not a plain decimal" };
+                    issues.push(Issue::error(
+                        format!("{}: amount '{}' {}", at, t.amount, what),
+                        Some(Repair::SetAmount { transaction_id: t.id, amount: v.abs(), is_expense: v < Decimal::ZERO }),
+                    ));
+                    Some(v)
+                }
+                None => {
+                    issues.push(Issue::error(format!("{}: amount '{}' is not a number and counts as 0", at, t.amount), None));
+                    None
+                }
+            },
+        };
+        let expense = value.map(|v| v < Decimal::ZERO).unwrap_or(t.is_expense);
+
+        let date = match read_date(&t.transacted_at) {
+            Some(d) => Some(d),
+            None => {
+                let fixed = loose_date(&t.transacted_at);
+                issues.push(Issue::error(
+                    format!("{}: date '{}' can't be read, so the row drops out of dated reports", at, t.transacted_at),
+                    fixed.map(|d| Repair::SetDate { transaction_id: t.id, date: date_text(d) }),
+                ));
+                fixed
+            }
+        };
+        if let Some(d) = date.filter(|d| d.date() > today) {
+            issues.push(Issue::warning(format!("{}: dated {}, in the future", at, d.date()), None));
+        }
+
+        match t.category_id.and_then(|c| categories.get(&c)) {
+            None => issues.push(Issue::error(
+                match t.category_id {
+                    Some(c) => format!("{}: category {} does not exist", at, c),
+                    None => format!("{}: no category", at),
+                },
+                fallback(expense).map(|c| move_to(t.id, c)),
+            )),
+            Some(c) if c.name.eq_ignore_ascii_case(INITIAL_BALANCE) => {}
+            Some(c) if expense && c.kind.eq_ignore_ascii_case("income") => issues.push(Issue::warning(
+                format!("{}: money out filed under the income category '{}'", at, c.name),
+                fallback(true).map(|c| move_to(t.id, c)),
+            )),
+            // Usually a refund, so only worth a look
+            Some(c) if !expense && value.is_some_and(|v| !v.is_zero()) && c.kind.eq_ignore_ascii_case("expense") => {
+                issues.push(Issue::warning(format!("{}: money in filed under the expense category '{}' (a refund?)", at, c.name), None));
+            }
+            Some(_) => {}
+        }
+
+        if let Some(v) = value {
+            entries.entry(t.account_id).or_default().push((date.map(|d| d.date()), v));
+        }
+    }
+
+    let txn_ids: HashSet<i64> = l.transactions.iter().map(|t| t.id).collect();
+    for &(transaction_id, tag_id) in &l.tag_links {
+        let missing = if !txn_ids.contains(&transaction_id) {
+            format!("transaction #{}", transaction_id)
+        } else if !l.tag_ids.contains(&tag_id) {
+            format!("tag {}", tag_id)
+        } else {
+            continue;
+        };
+        issues.push(Issue::error(
+            format!("tag link #{} -> {}: {} does not exist", transaction_id, tag_id, missing),
+            Some(Repair::DeleteTagLink { transaction_id, tag_id }),
+        ));
+    }
+
+    for o in l.owned.iter().filter(|o| !accounts.contains_key(&o.account_id)) {
+        issues.push(Issue::error(
+            format!("{} #{}: account {} does not exist", o.kind.label(), o.id, o.account_id),
+            Some(Repair::DeleteOwned(o.kind, o.id)),
+        ));
+    }
+
+    for a in &l.accounts {
+        let computed: Decimal = entries.get(&a.id).map(|e| e.iter().map(|(_, v)| *v).sum()).unwrap_or_default();
+        match a.balance.trim().parse::<Decimal>() {
+            Ok(stored) if stored == computed => {}
+            Ok(stored) => issues.push(Issue::error(
+                format!("{}: stored balance {}, transactions add up to {}", a.name, stored, computed),
+                Some(Repair::SetBalance { account_id: a.id, balance: computed }),
+            )),
+            Err(_) => issues.push(Issue::error(
+                format!("{}: stored balance '{}' is not a number, transactions add up to {}", a.name, a.balance, computed),
+                Some(Repair::SetBalance { account_id: a.id, balance: computed }),
+            )),
+        }
+    }
+
+    let mut assertions = 0;
+    for b in &l.assertions {
+        let Some(account) = accounts.get(&b.account_id) else { continue };
+        assertions += 1;
+        let actual: Decimal = entries.get(&b.account_id)
+            .map(|e| e.iter().filter(|(d, _)| d.is_some_and(|d| d <= b.date)).map(|(_, v)| *v).sum())
+            .unwrap_or_default();
+        if actual != b.balance {
+            issues.push(Issue::error(
+                format!(
+                    "{} on {}: asserted {}, transactions give {} (off by {})",
+                    account.name, b.date, b.balance, actual, actual - b.balance
+                ),
+                None,
+            ));
+        }
+    }
+
+    issues.sort_by_key(|i| std::cmp::Reverse(i.severity));
+    Report { accounts: l.accounts.len(), transactions: l.transactions.len(), assertions, issues }
+}
+
+// Signed value of a stored amount the way balances count it; None when the text is not a
+// decimal
+pub fn signed_amount(amount: &str, is_expense: bool) -> Option<Decimal> {
+    let d = Decimal::from_str_exact(amount).ok()?;
+    Some(if is_expense { -d } else { d })
+}
+
+// "1,234.50", " $12 ", "(12.50)" -> the number they mean
+fn loose_amount(s: &str) -> Option<Decimal> {
+    let s = s.trim();
+    let (negative, s) = match s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        Some(inner) => (true, inner),
+        None => (false, s),
+    };
+    let cleaned: String = s.chars().filter(|c| !matches!(c, ' ' | ',' | '_' | '\'' | '$' | '€' | '£')).collect();
+    let d = Decimal::from_str_exact(&cleaned).ok()?;
+    Some(if negative { -d } else { d })
+}
+
+// What SQLite's date functions accept: YYYY-MM-DD, optionally followed by a time
+// (space or T), seconds, fractions and Z, or an RFC 3339 offset (read as UTC)
+fn read_date(s: &str) -> Option<NaiveDateTime> {
+    let b = s.as_bytes();
+    let shaped = b.len() >= 10
+        && b[4] == b'-' && b[7] == b'-'
+        && [0, 1, 2, 3, 5, 6, 8, 9].iter().all(|&i| b[i].is_ascii_digit());
+    if !shaped {
+        return None;
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Some(dt.naive_utc());
+    }
+    let s = s.strip_suffix('Z').unwrap_or(s);
+    if s.len() == 10 {
+        return NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0);
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+}
+
+// Dates that are clearly meant but unreadable to SQLite: "2025/03/01", "2025-3-1", "20250301"
+fn loose_date(s: &str) -> Option<NaiveDateTime> {
+    let s = s.trim();
+    for f in ["%Y/%m/%d %H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y.%m.%d %H:%M:%S"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, f) {
+            return Some(dt);
+        }
+    }
+    ["%Y/%m/%d", "%Y-%m-%d", "%Y.%m.%d", "%Y%m%d"]
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(s, f).ok())
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+}
+
+// Dates at midnight are written as plain dates, the way the app writes them
+fn date_text(dt: NaiveDateTime) -> String {
+    if dt.time() == chrono::NaiveTime::MIN {
+        dt.format("%Y-%m-%d").to_string()
+    } else {
+        dt.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn txn(id: i64, account_id: i64, category_id: Option<i64>, amount: &str, is_expense: bool, transacted_at: &str) -> TxnRow {
+        TxnRow { id, account_id, category_id, amount: amount.into(), is_expense, transacted_at: transacted_at.into() }
+    }
+
+    fn ledger() -> Ledger {
+        let category = |id, name: &str, kind: &str| CategoryRow { id, name: name.into(), kind: kind.into() };
+        Ledger {
+            accounts: vec![
+                AccountRow { id: 1, name: "Chequing".into(), balance: "90.00".into() },
+                AccountRow { id: 2, name: "Savings".into(), balance: "abc".into() },
+            ],
+            categories: vec![
+                category(1, "Groceries", "Expense"),
+                category(2, "Uncategorized", "Expense"),
+                category(3, "Other Income", "Income"),
+                category(4, "Salary", "Income"),
+                category(5, "Initial Balance", "Income"),
+            ],
+            transactions: vec![
+                txn(1, 1, Some(5), "100.00", false, "2025-01-01T00:00:00Z"),
+                txn(2, 1, Some(1), "-5.00", false, "2025-01-05 10:00:00"),
+                txn(3, 1, Some(1), "$1,200.50", true, "2025/01/06"),
+                txn(4, 1, Some(4), "20", true, "2025-01-07"),
+                txn(5, 1, None, "3", false, "2025-01-08"),
+                txn(6, 9, Some(1), "1", true, "2025-01-08"),
+                txn(7, 1, Some(1), "abc", true, "2025-01-08"),
+                txn(8, 1, Some(1), "10", false, "2025-01-09"),
+                txn(9, 2, Some(1), "1", true, "2099-01-01"),
+                txn(10, 1, Some(99), "2", true, "2025-01-10"),
+            ],
+            tag_ids: HashSet::from([1]),
+            tag_links: vec![(1, 1), (1, 2), (42, 1)],
+            owned: vec![
+                OwnedRow { kind: Owned::Budget, id: 3, account_id: 9 },
+                OwnedRow { kind: Owned::Goal, id: 1, account_id: 1 },
+            ],
+            assertions: vec![
+                BalanceAssertion { id: 1, account_id: 1, date: day(2025, 1, 5), balance: "95".parse().unwrap() },
+                BalanceAssertion { id: 2, account_id: 1, date: day(2025, 1, 6), balance: Decimal::ZERO },
+            ],
+        }
+    }
+
+    #[test]
+    fn a_clean_ledger_has_no_issues() {
+        let l = Ledger {
+            accounts: vec![AccountRow { id: 1, name: "Chequing".into(), balance: "95.00".into() }],
+            categories: vec![
+                CategoryRow { id: 1, name: "Groceries".into(), kind: "Expense".into() },
+                CategoryRow { id: 2, name: "Initial Balance".into(), kind: "Income".into() },
+            ],
+            transactions: vec![txn(1, 1, Some(2), "100", false, "2025-01-01T00:00:00Z"), txn(2, 1, Some(1), "5.00", true, "2025-01-05 10:00:00")],
+            assertions: vec![BalanceAssertion { id: 1, account_id: 1, date: day(2025, 1, 5), balance: "95".parse().unwrap() }],
+            ..Default::default()
+        };
+        let report = check(&l, day(2025, 6, 1));
+        assert!(report.issues.is_empty());
+        assert_eq!(report.summary(), "1 accounts, 2 transactions, 1 balance assertions: no problems found");
+    }
+
+    #[test]
+    fn every_problem_comes_with_its_repair() {
+        let report = check(&ledger(), day(2025, 6, 1));
+        let found: Vec<_> = report.issues.iter().map(|i| (i.severity, i.repair.clone())).collect();
+        let uncategorized = |transaction_id| Some(Repair::SetCategory { transaction_id, category_id: 2, category: "Uncategorized".into() });
+        use Severity::{Error, Warning};
+        assert_eq!(found, [
+            (Error, Some(Repair::SetAmount { transaction_id: 2, amount: "5.00".parse().unwrap(), is_expense: true })),
+            (Error, Some(Repair::SetAmount { transaction_id: 3, amount: "1200.50".parse().unwrap(), is_expense: true })),
+            (Error, Some(Repair::SetDate { transaction_id: 3, date: "2025-01-06".into() })),
+            (Error, Some(Repair::SetCategory { transaction_id: 5, category_id: 3, category: "Other Income".into() })),
+            (Error, Some(Repair::DeleteTransaction(6))),
+            (Error, None),
+            (Error, uncategorized(10)),
+            (Error, Some(Repair::DeleteTagLink { transaction_id: 1, tag_id: 2 })),
+            (Error, Some(Repair::DeleteTagLink { transaction_id: 42, tag_id: 1 })),
+            (Error, Some(Repair::DeleteOwned(Owned::Budget, 3))),
+            (Error, Some(Repair::SetBalance { account_id: 1, balance: "-1114.50".parse().unwrap() })),
+            (Error, Some(Repair::SetBalance { account_id: 2, balance: "-1".parse().unwrap() })),
+            (Error, None),
+            (Warning, uncategorized(4)),
+            (Warning, None),
+            (Warning, None),
+        ]);
+
+        let messages: Vec<_> = report.issues.iter().filter(|i| i.repair.is_none()).map(|i| i.message.as_str()).collect();
+        assert_eq!(messages, [
+            "transaction #7 (Chequing, 2025-01-08): amount 'abc' is not a number and counts as 0",
+            "Chequing on 2025-01-06: asserted 0, transactions give -1105.50 (off by -1105.50)",
+            "transaction #8 (Chequing, 2025-01-09): money in filed under the expense category 'Groceries' (a refund?)",
+            "transaction #9 (Savings, 2099-01-01): dated 2099-01-01, in the future",
+        ]);
+        assert_eq!(report.summary(), "2 accounts, 10 transactions, 2 balance assertions: 13 errors, 3 warnings (12 repairable)");
+        assert_eq!(report.issues[1].repair.as_ref().unwrap().describe(), "store 1200.50 as money out");
+        assert_eq!(Repair::DeleteOwned(Owned::Budget, 3).describe(), "delete the budget");
+    }
+
+    #[test]
+    fn dates_read_the_way_sqlite_reads_them() {
+        let at = |h, m, s| day(2025, 3, 1).and_hms_opt(h, m, s).unwrap();
+        assert_eq!(read_date("2025-03-01"), Some(at(0, 0, 0)));
+        assert_eq!(read_date("2025-03-01T10:20:30Z"), Some(at(10, 20, 30)));
+        assert_eq!(read_date("2025-03-01 10:20"), Some(at(10, 20, 0)));
+        assert_eq!(read_date("2025-03-01T12:20:30+02:00"), Some(at(10, 20, 30)));
+        assert_eq!(read_date("2025/03/01"), None);
+        assert_eq!(read_date("2025-3-1"), None);
+
+        assert_eq!(loose_date("2025-3-1").map(date_text).as_deref(), Some("2025-03-01"));
+        assert_eq!(loose_date("20250301").map(date_text).as_deref(), Some("2025-03-01"));
+        assert_eq!(loose_date("2025/03/01 10:20:30").map(date_text).as_deref(), Some("2025-03-01 10:20:30"));
+        assert_eq!(loose_date("March 1"), None);
+    }
+
+    #[test]
+    fn loose_amounts_keep_their_meaning() {
+        assert_eq!(loose_amount(" $1,234.50 "), Some("1234.50".parse().unwrap()));
+        assert_eq!(loose_amount("(12.50)"), Some("-12.50".parse().unwrap()));
+        assert_eq!(loose_amount("12 abc"), None);
+        assert_eq!(signed_amount("12.50", true), Some("-12.50".parse().unwrap()));
+        assert_eq!(signed_amount("1e3", false), None);
+    }
+}
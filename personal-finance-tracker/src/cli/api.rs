@@ -1,16 +1,34 @@
+use std::collections::{BTreeMap, HashMap};
+
 use anyhow::Result;
 use chrono::{NaiveDate, NaiveDateTime};
 use rust_decimal::Decimal;
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Row, Sqlite};
+use sqlx::{sqlite::{SqlitePoolOptions, SqliteRow}, Pool, Row, Sqlite, SqliteConnection};
 
 use super::state::{
     AccountDto, AccountType, CategoryDto, CategoryType,
     CreateAccountReq, CreateTxnReq, Money, TransactionDto, SavingGoalDto, CategorySpendingDto,
+    PeriodSpendingDto, ReportGrouping, ReportPeriod, CashFlowDto, CategoryComparisonDto,
+    PayeeSpendingDto, AccountForecastDto, BalancePointDto, ForecastAlertDto, ImportProfileDto,
+    StatementDto, AccountStatementDto, BudgetStatusDto, TrashItemDto, TrashKind,
+    LedgerRow, PageFrom, TxnCursor,
 };
+use super::undo::{Rows, Target};
+use crate::audit::{self, AuditEntry, AuditFilter, Origin};
+use crate::backup::{self, Backup, RestoreSummary, TagRecord, TransactionRecord, TransactionTag};
+use crate::check::{self, BalanceAssertion};
+use crate::database::db::{migrate, queries};
+use crate::lock::{self, PeriodLock, YearEndClose};
+use crate::database::models::{Budget, CurrencyRate, RecurringTransaction, SavingsGoal};
+use crate::import::{csv::CsvMapping, duplicates::{self, DuplicateMatch}, ImportSummary, ImportedTxn};
+use crate::rules::{self, suggest::CategoryModel, MatchField, MatchKind, Rule, RuleChange, RuleSet};
+use crate::search::{self, SearchHit, SearchQuery};
 
 #[derive(Clone)]
 pub struct Client {
     pool: Pool<Sqlite>,
+    origin: Origin,     // recorded in the audit log for every change made through this client
+    allow_locked: bool, // write transactions dated in a locked period too
 }
 
 pub struct CreateGoalReq {
@@ -34,25 +52,55 @@ impl Client {
             .await?;
 
 
-        Ok(Self { pool })
+        Ok(Self { pool, origin: Origin::Tui, allow_locked: false })
     }
     pub async fn delete_goal(&self, id: i64) -> anyhow::Result<()> {
+        let mut tx = self.begin().await?;
         sqlx::query("DELETE FROM savings_goals WHERE goal_id = ?")
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
+        audit::commit(tx).await?;
         Ok(())
     }
 
+    // Wrap an existing pool (used by the backend server)
+    pub fn from_pool(pool: Pool<Sqlite>) -> Self {
+        Self { pool, origin: Origin::Server, allow_locked: false }
+    }
+
+    pub fn with_origin(self, origin: Origin) -> Self {
+        Self { origin, ..self }
+    }
+
+    // Override the lock dates for changes made through this client
+    pub fn with_lock_override(self, allow_locked: bool) -> Self {
+        Self { allow_locked, ..self }
+    }
+
     pub fn pool(&self) -> &Pool<Sqlite> {
         &self.pool
     }
 
+    // Every write to an audited table goes through one of these
+    async fn begin(&self) -> Result<sqlx::Transaction<'static, Sqlite>> {
+        Ok(audit::begin(&self.pool, self.origin).await?)
+    }
+
+    // Refuse a change to a transaction dated in a locked period, unless overridden
+    async fn check_lock(&self, conn: &mut SqliteConnection, account_id: i64, date: NaiveDate) -> Result<()> {
+        if !self.allow_locked && let Some(locked) = lock::find(conn, account_id, date).await? {
+            return Err(locked.into());
+        }
+        Ok(())
+    }
+
     pub async fn update_goal(&self, id: i64, req: &CreateGoalReq) -> anyhow::Result<()> {
         let amount_str = req.target_amount.0.to_string();
         let current_str = req.current_amount.0.to_string();
         let deadline_str = req.deadline.map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string());
 
+        let mut tx = self.begin().await?;
         sqlx::query(
             r#"
             UPDATE savings_goals 
@@ -65,19 +113,21 @@ impl Client {
         .bind(&current_str)
         .bind(deadline_str)
         .bind(id)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
+        audit::commit(tx).await?;
         Ok(())
     }
 
-    pub async fn create_goal(&self, req: &CreateGoalReq) -> anyhow::Result<()> {
+    pub async fn create_goal(&self, req: &CreateGoalReq) -> anyhow::Result<i64> {
         let amount_str = req.target_amount.0.to_string();
         let current_str = req.current_amount.0.to_string();
         
         // Handle deadline: if None, insert NULL, otherwise insert string
         let deadline_str = req.deadline.map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string());
 
-        sqlx::query(
+        let mut tx = self.begin().await?;
+        let id = sqlx::query(
             r#"
             INSERT INTO savings_goals (account_id, goal_name, target_amount, current_amount, deadline)
             VALUES (?, ?, ?, ?, ?)
@@ -88,70 +138,42 @@ impl Client {
         .bind(&amount_str)
         .bind(&current_str)
         .bind(deadline_str)
-        .execute(&self.pool)
-        .await?;
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        audit::commit(tx).await?;
 
-        Ok(())
+        Ok(id)
     }
    
     // Accounts
     pub async fn list_accounts(&self) -> Result<Vec<AccountDto>> {
-        let rows = sqlx::query("SELECT account_id, account_name, account_type, currency, balance, account_created_at FROM accounts ORDER BY account_id")
+        let rows = sqlx::query(&format!("{} WHERE deleted_at IS NULL ORDER BY account_id", ACCOUNT_RECORD_SQL))
             .fetch_all(&self.pool).await?;
 
-        let mut list = Vec::new();
-        for r in rows {
-             list.push(AccountDto {
-                id: r.try_get("account_id")?,
-                name: r.try_get("account_name")?,
-                r#type: map_account_type(&r.try_get::<String, _>("account_type")?),
-                currency: r.try_get("currency")?,
-                opening_balance: Money(Decimal::from_str_exact(&r.try_get::<String, _>("balance")?).unwrap_or(Decimal::ZERO)),
-                created_at: r.try_get("account_created_at")?,
-            });
-        }
-        Ok(list)
+        rows.iter().map(map_account_row).collect()
     }
 
-    async fn recompute_balance_exec<'e, E>(&self, executor: E, account_id: i64) -> anyhow::Result<()>
-    where
-        E: sqlx::Executor<'e, Database = Sqlite>,
-    {
-        sqlx::query(
-            r#"
-            UPDATE accounts
-            SET balance = IFNULL((
-                SELECT ROUND(SUM(
-                    CASE WHEN t.is_expense = 1 THEN -CAST(t.amount AS NUMERIC)
-                        ELSE CAST(t.amount AS NUMERIC)
-                    END
-                ), 2)
-                FROM transactions t
-                WHERE t.account_id = ?
-            ), 0.00)
-            WHERE account_id = ?
-            "#
-        )
-        .bind(account_id)
-        .bind(account_id)
-        .execute(executor)
-        .await?;
+    async fn recompute_balance_exec(&self, conn: &mut SqliteConnection, account_id: i64) -> anyhow::Result<()> {
+        queries::recompute_balance(conn, account_id).await?;
         Ok(())
     }
 
     pub async fn create_account(&self, req: &CreateAccountReq) -> Result<AccountDto> {
-        let mut tx = self.pool.begin().await?;
+        let mut tx = self.begin().await?;
         
-        let row = sqlx::query("INSERT INTO accounts (account_name, account_type, balance, currency, account_created_at) VALUES (?, ?, '0', ?, strftime('%Y-%m-%dT%H:%M:%SZ','now')) RETURNING account_id, account_name, account_type, currency, account_created_at")
+        let row = sqlx::query("INSERT INTO accounts (account_name, account_type, balance, currency, account_created_at, credit_limit) VALUES (?, ?, '0', ?, strftime('%Y-%m-%dT%H:%M:%SZ','now'), ?) RETURNING account_id, account_name, account_type, currency, account_created_at")
             .bind(&req.name)
             .bind(req.r#type.as_str())
             .bind(&req.currency)
+            .bind(req.credit_limit.map(|l| l.0.to_string()))
             .fetch_one(&mut *tx).await?;
             
         let aid: i64 = row.try_get("account_id")?;
         
         if !req.opening_balance.0.is_zero() {
-             let amount_str = req.opening_balance.0.to_string();
+             // Stored unsigned like every other row; a negative opening balance is money out
+             let amount_str = req.opening_balance.0.abs().to_string();
              
              let cat_id_row = sqlx::query("SELECT category_id FROM categories WHERE category_name = 'Initial Balance'")
                 .fetch_optional(&mut *tx).await?;
@@ -165,40 +187,49 @@ impl Client {
                  }
              };
 
-             sqlx::query("INSERT INTO transactions (account_id, category_id, amount, base_amount, is_expense, description, currency, transacted_at, trans_create_at) VALUES (?, ?, ?, ?, 0, 'Opening Balance', ?, strftime('%Y-%m-%dT%H:%M:%SZ','now'), strftime('%Y-%m-%dT%H:%M:%SZ','now'))")
+             sqlx::query("INSERT INTO transactions (account_id, category_id, amount, base_amount, is_expense, description, currency, transacted_at, trans_create_at) VALUES (?, ?, ?, ?, ?, 'Opening Balance', ?, strftime('%Y-%m-%dT%H:%M:%SZ','now'), strftime('%Y-%m-%dT%H:%M:%SZ','now'))")
                 .bind(aid)
                 .bind(cat_id)
                 .bind(&amount_str)
                 .bind(&amount_str)
+                .bind(req.opening_balance.0.is_sign_negative())
                 .bind(&req.currency)
                 .execute(&mut *tx).await.ok(); 
         }
         
-        self.recompute_balance_exec(&mut *tx, aid).await?;
-        tx.commit().await?;
+        self.recompute_balance_exec(&mut tx, aid).await?;
+        audit::commit(tx).await?;
 
         Ok(AccountDto {
             id: aid,
             name: row.try_get("account_name")?,
             r#type: map_account_type(&row.try_get::<String, _>("account_type")?),
             currency: row.try_get("currency")?,
-            opening_balance: req.opening_balance.clone(),
+            opening_balance: req.opening_balance,
             created_at: row.try_get("account_created_at")?,
+            credit_limit: req.credit_limit,
+            deleted_at: None,
         })
     }
 
-    pub async fn update_account(&self, id: i64, name: &str, atype: &str, currency: &str) -> Result<()> {
-        sqlx::query("UPDATE accounts SET account_name = ?, account_type = ?, currency = ? WHERE account_id = ?")
-            .bind(name).bind(atype).bind(currency).bind(id)
-            .execute(&self.pool).await?;
+    pub async fn update_account(&self, id: i64, name: &str, atype: &str, currency: &str, credit_limit: Option<Money>) -> Result<()> {
+        let mut tx = self.begin().await?;
+        sqlx::query("UPDATE accounts SET account_name = ?, account_type = ?, currency = ?, credit_limit = ? WHERE account_id = ? AND deleted_at IS NULL")
+            .bind(name).bind(atype).bind(currency).bind(credit_limit.map(|l| l.0.to_string())).bind(id)
+            .execute(&mut *tx).await?;
+        audit::commit(tx).await?;
         Ok(())
     }
 
+    // Moves the account to the trash, with its transactions marked at the same time
     pub async fn delete_account(&self, id: i64) -> Result<()> {
-        let mut tx = self.pool.begin().await?;
-        sqlx::query("DELETE FROM transactions WHERE account_id = ?").bind(id).execute(&mut *tx).await?;
-        sqlx::query("DELETE FROM accounts WHERE account_id = ?").bind(id).execute(&mut *tx).await?;
-        tx.commit().await?;
+        let mut tx = self.begin().await?;
+        sqlx::query("UPDATE accounts SET deleted_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE account_id = ? AND deleted_at IS NULL")
+            .bind(id).execute(&mut *tx).await?;
+        sqlx::query("UPDATE transactions SET deleted_at = (SELECT deleted_at FROM accounts WHERE account_id = ?1) WHERE account_id = ?1 AND deleted_at IS NULL")
+            .bind(id).execute(&mut *tx).await?;
+        self.recompute_balance_exec(&mut tx, id).await?;
+        audit::commit(tx).await?;
         Ok(())
     }
 
@@ -212,7 +243,7 @@ impl Client {
              out.push(CategoryDto {
                  id: r.try_get("category_id")?,
                  name: r.try_get("category_name")?,
-                 r#type: if r.try_get::<String, _>("category_type")?.eq_ignore_ascii_case("INCOME") { CategoryType::Income } else { CategoryType::Expense },
+                 r#type: map_category_type(&r.try_get::<String, _>("category_type")?),
                  icon: r.try_get("icon")?,
              });
          }
@@ -232,9 +263,11 @@ impl Client {
               t.description,
               t.payee,
               t.currency,
-              t.transacted_at
+              t.transacted_at,
+              t.cleared,
+              t.reconciled
             FROM transactions t
-            WHERE t.account_id = ?
+            WHERE t.account_id = ? AND t.deleted_at IS NULL
             ORDER BY t.transacted_at DESC, t.transaction_id DESC
             LIMIT ? OFFSET ?
             "#
@@ -245,41 +278,260 @@ impl Client {
         .fetch_all(&self.pool)
         .await?;
 
+        rows.iter().map(map_transaction_row).collect()
+    }
+
+    // Up to `limit` rows of an account's ledger, newest first, each with the running balance.
+    // Below and Above carry the balance on from the row given, so a window loaded page by
+    // page adds up the same as the whole ledger; the others sum what lies below once.
+    pub async fn ledger_page(&self, account_id: i64, from: PageFrom<'_>, limit: usize) -> Result<Vec<LedgerRow>> {
+        let (filter, newest_first) = match from {
+            PageFrom::Newest | PageFrom::Oldest => ("", !matches!(from, PageFrom::Oldest)),
+            PageFrom::Date(_) => ("AND t.transacted_at < ?", true),
+            PageFrom::At(_) => ("AND (t.transacted_at, t.transaction_id) <= (?, ?)", true),
+            PageFrom::Below(_) => ("AND (t.transacted_at, t.transaction_id) < (?, ?)", true),
+            PageFrom::Above(_) => ("AND (t.transacted_at, t.transaction_id) > (?, ?)", false),
+        };
+        let order = if newest_first { "DESC" } else { "ASC" };
+        let sql = format!(
+            "SELECT t.transaction_id, t.account_id, t.category_id, t.amount, t.is_expense,
+                    t.description, t.payee, t.currency, t.transacted_at, t.cleared, t.reconciled
+             FROM transactions t
+             WHERE t.account_id = ? AND t.deleted_at IS NULL {}
+             ORDER BY t.transacted_at {order}, t.transaction_id {order}
+             LIMIT ?",
+            filter
+        );
+        let mut q = sqlx::query(&sql).bind(account_id);
+        match from {
+            PageFrom::Newest | PageFrom::Oldest => {}
+            // The stored text starts with the date, so everything before the next day
+            PageFrom::Date(d) => q = q.bind(d.succ_opt().unwrap_or(d).format("%Y-%m-%d").to_string()),
+            PageFrom::At(c) => q = q.bind(&c.transacted_at).bind(c.id),
+            PageFrom::Below(r) | PageFrom::Above(r) => q = q.bind(&r.cursor.transacted_at).bind(r.cursor.id),
+        }
+        let rows = q.bind(limit as i64).fetch_all(&self.pool).await?;
+
         let mut out = Vec::with_capacity(rows.len());
+        for r in &rows {
+            let cursor = TxnCursor { transacted_at: r.try_get("transacted_at")?, id: r.try_get("transaction_id")? };
+            out.push(LedgerRow { txn: map_transaction_row(r)?, balance: Decimal::ZERO, cursor });
+        }
+        if !newest_first {
+            out.reverse();
+        }
+
+        // The balance after the newest row of the page, then each older row takes its amount off
+        let Some(top) = out.first() else { return Ok(out) };
+        let mut balance = match from {
+            PageFrom::Below(r) => r.balance - r.txn.amount.0,
+            PageFrom::Above(r) => r.balance + out.iter().map(|row| row.txn.amount.0).sum::<Decimal>(),
+            _ => self.balance_through(account_id, &top.cursor).await?,
+        };
+        for row in &mut out {
+            row.balance = balance;
+            balance -= row.txn.amount.0;
+        }
+        Ok(out)
+    }
+
+    // The balance of an account once the row at the cursor and everything older is counted
+    pub async fn balance_through(&self, account_id: i64, cursor: &TxnCursor) -> Result<Decimal> {
+        let rows = sqlx::query("SELECT amount, is_expense FROM transactions WHERE account_id = ? AND deleted_at IS NULL AND (transacted_at, transaction_id) <= (?, ?)")
+            .bind(account_id)
+            .bind(&cursor.transacted_at)
+            .bind(cursor.id)
+            .fetch_all(&self.pool).await?;
+        let mut total = Decimal::ZERO;
         for r in rows {
-            let id: i64 = r.try_get("transaction_id")?;
-            let amount_s: String = r.try_get("amount")?;
-            let is_expense_i: i64 = r.try_get("is_expense")?;
-            let txn_date_s: String = r.try_get("transacted_at")?;
+            total += check::signed_amount(&r.try_get::<String, _>("amount")?, r.try_get("is_expense")?).unwrap_or_default();
+        }
+        Ok(total)
+    }
+
+    // Every transaction of every account, oldest first
+    pub async fn list_all_transactions(&self) -> Result<Vec<TransactionDto>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT t.transaction_id, t.account_id, t.category_id, t.amount, t.is_expense,
+                   t.description, t.payee, t.currency, t.transacted_at, t.cleared, t.reconciled
+            FROM transactions t
+            WHERE t.deleted_at IS NULL
+            ORDER BY t.transacted_at, t.transaction_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
 
-            let mut amt = Decimal::from_str_exact(&amount_s).unwrap_or(Decimal::ZERO);
-            if is_expense_i != 0 { amt = -amt; }
+        rows.iter().map(map_transaction_row).collect()
+    }
 
-            out.push(TransactionDto {
-                id,
-                account_id: r.try_get("account_id")?,
-                category_id: r.try_get("category_id")?,
-                amount: Money(amt),
-                memo: r.try_get("description")?,
-                payee: r.try_get("payee")?,
-                currency: r.try_get::<Option<String>, _>("currency")?.unwrap_or("CAD".into()),
-                txn_date: parse_date_any(&txn_date_s),
-                cleared: false,
-                reconciled: false,
-            });
+    // Transactions of every live account that match the query, newest first, at most `limit`
+    pub async fn search_transactions(&self, q: &SearchQuery, limit: usize) -> Result<Vec<SearchHit>> {
+        if q.is_empty() {
+            anyhow::bail!("nothing to search for");
+        }
+        // Amounts are compared here, exactly, rather than as floats in SQL; with an amount
+        // filter the rows are read a page at a time until enough of them match
+        let page = if q.amounts.is_empty() { limit.max(1) } else { limit.max(search::PAGE_ROWS) };
+        let mut after: Option<TxnCursor> = None;
+        let mut out = Vec::new();
+        loop {
+            let rows = sqlx::query(
+                r#"
+                SELECT t.transaction_id, t.account_id, t.category_id, t.amount, t.is_expense,
+                       t.description, t.payee, t.currency, t.transacted_at, t.cleared, t.reconciled,
+                       a.account_name, c.category_name,
+                       (SELECT group_concat(g.tag, char(31)) FROM transaction_tags tt JOIN tags g ON g.tag_id = tt.tag_id
+                        WHERE tt.transaction_id = t.transaction_id) AS tags
+                FROM transactions t
+                JOIN accounts a ON a.account_id = t.account_id
+                LEFT JOIN categories c ON c.category_id = t.category_id
+                WHERE t.deleted_at IS NULL AND a.deleted_at IS NULL
+                  AND (?1 IS NULL OR t.transaction_id IN (SELECT rowid FROM transaction_search WHERE transaction_search MATCH ?1))
+                  AND (?2 IS NULL OR a.account_name LIKE ?2 ESCAPE '\')
+                  AND (?3 IS NULL OR date(t.transacted_at) >= ?3)
+                  AND (?4 IS NULL OR date(t.transacted_at) <= ?4)
+                  AND (?5 IS NULL OR (t.transacted_at, t.transaction_id) < (?5, ?6))
+                ORDER BY t.transacted_at DESC, t.transaction_id DESC
+                LIMIT ?7
+                "#
+            )
+            .bind(q.fts_match())
+            .bind(q.account_like())
+            .bind(q.from.map(|d| d.format("%Y-%m-%d").to_string()))
+            .bind(q.to.map(|d| d.format("%Y-%m-%d").to_string()))
+            .bind(after.as_ref().map(|c| c.transacted_at.clone()))
+            .bind(after.as_ref().map(|c| c.id))
+            .bind(page as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+            for r in &rows {
+                let txn = map_transaction_row(r)?;
+                if out.len() == limit || !q.amount_matches(txn.amount.0) {
+                    continue;
+                }
+                out.push(SearchHit {
+                    txn,
+                    account: r.try_get("account_name")?,
+                    category: r.try_get("category_name")?,
+                    tags: r.try_get::<Option<String>, _>("tags")?
+                        .map(|t| t.split('\u{1f}').map(String::from).collect())
+                        .unwrap_or_default(),
+                });
+            }
+            let Some(last) = rows.last().filter(|_| rows.len() == page && out.len() < limit) else { break };
+            after = Some(TxnCursor { transacted_at: last.try_get("transacted_at")?, id: last.try_get("transaction_id")? });
+        }
+        Ok(out)
+    }
+
+    // Tags of every tagged transaction, by transaction id
+    pub async fn list_transaction_tags(&self) -> Result<HashMap<i64, Vec<String>>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT tt.transaction_id, tg.tag
+            FROM transaction_tags tt
+            JOIN tags tg ON tg.tag_id = tt.tag_id
+            ORDER BY tt.transaction_id, tg.tag
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut out: HashMap<i64, Vec<String>> = HashMap::new();
+        for r in rows {
+            out.entry(r.try_get("transaction_id")?).or_default().push(r.try_get("tag")?);
+        }
+        Ok(out)
+    }
+
+    // (currency, rate_to_base) pairs; unparsable rates are left out
+    pub async fn list_currency_rates(&self) -> Result<Vec<(String, Decimal)>> {
+        let rows = sqlx::query("SELECT currency, CAST(rate_to_base AS TEXT) AS rate_to_base FROM currency_rates ORDER BY currency")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut out = Vec::new();
+        for r in rows {
+            let rate: String = r.try_get("rate_to_base")?;
+            if let Ok(rate) = rate.trim().parse::<Decimal>() {
+                out.push((r.try_get("currency")?, rate));
+            }
         }
         Ok(out)
     }
 
+    // Every transaction with this payee (case-insensitive) across all accounts, newest first
+    pub async fn list_payee_transactions(&self, payee: &str, start: NaiveDate, end: NaiveDate) -> Result<Vec<TransactionDto>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+              t.transaction_id,
+              t.account_id,
+              t.category_id,
+              t.amount,
+              t.is_expense,
+              t.description,
+              t.payee,
+              t.currency,
+              t.transacted_at,
+              t.cleared,
+              t.reconciled
+            FROM transactions t
+            WHERE TRIM(t.payee) = TRIM(?1) COLLATE NOCASE
+              AND t.deleted_at IS NULL
+              AND date(t.transacted_at) >= date(?2)
+              AND date(t.transacted_at) <= date(?3)
+            ORDER BY t.transacted_at DESC, t.transaction_id DESC
+            "#
+        )
+        .bind(payee)
+        .bind(start.format("%Y-%m-%d").to_string())
+        .bind(end.format("%Y-%m-%d").to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(map_transaction_row).collect()
+    }
+
     pub async fn create_transaction(&self, req: &CreateTxnReq) -> Result<TransactionDto> {
+        let mut tx = self.begin().await?;
+        self.check_lock(&mut tx, req.account_id, req.transacted_at.date()).await?;
+
+        let new_id = self.insert_transaction_exec(&mut *tx, req).await?;
+
+        // 4. 更新余额并提交事务
+        self.recompute_balance_exec(&mut tx, req.account_id).await?;
+        audit::commit(tx).await?;
+
+        // 5. 构造一个返回对象 (虽然 App 端其实丢弃了这个返回值，但为了满足函数签名，我们构造一个)
+        Ok(TransactionDto {
+            id: new_id,
+            account_id: req.account_id,
+            category_id: Some(req.category_id),
+            amount: req.amount,
+            memo: req.description.clone(),
+            payee: req.payee.clone(),
+            currency: req.currency.clone(),
+            txn_date: req.transacted_at.date(),
+            cleared: false,
+            reconciled: false,
+        })
+    }
+
+    // Insert one row without touching the balance; callers recompute it once they are done
+    async fn insert_transaction_exec<'e, E>(&self, executor: E, req: &CreateTxnReq) -> Result<i64>
+    where
+        E: sqlx::Executor<'e, Database = Sqlite>,
+    {
         let is_expense = if req.amount.0.is_sign_negative() { 1 } else { 0 };
         let amount_abs = req.amount.0.abs().to_string();
-        
+
         // 1. 不需要手动 format date_str 了，直接用 req.transacted_at，
         // 这样能保证和 update_transaction 存入的格式完全一致 (YYYY-MM-DD HH:MM:SS)
 
-        let mut tx = self.pool.begin().await?;
-
         // 2. 修改 SQL：去掉 RETURNING，改用 execute，这是最稳妥的写入方式
         let res = sqlx::query(
             r#"
@@ -297,132 +549,2352 @@ impl Client {
         .bind(req.payee.as_deref())
         .bind(&req.currency)
         .bind(req.transacted_at) // <--- 关键修改：直接绑定对象，不要传 String
-        .execute(&mut *tx)       // <--- 关键修改：使用 execute 而不是 fetch_one
+        .execute(executor)       // <--- 关键修改：使用 execute 而不是 fetch_one
         .await?;
 
         // 3. 手动获取新插入的 ID
-        let new_id = res.last_insert_rowid();
+        Ok(res.last_insert_rowid())
+    }
 
-        // 4. 更新余额并提交事务
-        self.recompute_balance_exec(&mut *tx, req.account_id).await?;
-        tx.commit().await?;
+    // Write a parsed statement to one account: a single DB transaction for the whole file,
+    // balance recomputed once at the end. The rules clean up payee and memo and add tags first.
+    // Rows without `category_id` go to the rule's category, then the category named in the file
+    // (created if missing), else 'Uncategorized' / 'Other Income' by sign.
+    // Rows whose external id is already on the account are skipped, and so are rows dated
+    // in a locked period unless the lock is overridden.
+    pub async fn import_transactions(&self, account_id: i64, category_id: Option<i64>, txns: &[ImportedTxn]) -> Result<ImportSummary> {
+        let rules = self.rule_set().await?;
+        let mut tx = audit::begin(&self.pool, Origin::Import).await?;
 
-        // 5. 构造一个返回对象 (虽然 App 端其实丢弃了这个返回值，但为了满足函数签名，我们构造一个)
-        Ok(TransactionDto {
-            id: new_id,
-            account_id: req.account_id,
-            category_id: Some(req.category_id),
-            amount: req.amount.clone(),
-            memo: req.description.clone(),
-            payee: req.payee.clone(),
-            currency: req.currency.clone(),
-            txn_date: req.transacted_at.date(),
-            cleared: false,
-            reconciled: false,
-        })
-    }
-    
-    pub async fn update_transaction(&self, id: i64, req: &CreateTxnReq) -> Result<()> {
-        let is_expense = if req.amount.0.is_sign_negative() { 1 } else { 0 };
-        let amount_abs = req.amount.0.abs().to_string();
-        let mut tx = self.pool.begin().await?;
-        
-        sqlx::query("UPDATE transactions SET category_id = ?, amount = ?, base_amount = ?, is_expense = ?, description = ?, payee = ?, currency = ?, transacted_at = ? WHERE transaction_id = ?")
-            .bind(req.category_id)
-            .bind(&amount_abs)
-            .bind(&amount_abs)
-            .bind(is_expense)
-            .bind(&req.description)
-            .bind(&req.payee)
-            .bind(&req.currency)
-            .bind(&req.transacted_at)
-            .bind(id)
-            .execute(&mut *tx).await?;
-            
-        self.recompute_balance_exec(&mut *tx, req.account_id).await?;
-        tx.commit().await?;
-        Ok(())
+        let currency: String = sqlx::query("SELECT currency FROM accounts WHERE account_id = ? AND deleted_at IS NULL")
+            .bind(account_id)
+            .fetch_optional(&mut *tx).await?
+            .ok_or_else(|| anyhow::anyhow!("account {} not found (or in the Trash)", account_id))?
+            .try_get("currency")?;
+
+        let fallback = |name: &'static str| {
+            sqlx::query("SELECT category_id FROM categories WHERE category_name = ?").bind(name)
+        };
+        let expense_cat: i64 = fallback("Uncategorized").fetch_one(&mut *tx).await?.try_get("category_id")?;
+        let income_cat: i64 = fallback("Other Income").fetch_one(&mut *tx).await?.try_get("category_id")?;
+
+        let mut by_name: HashMap<String, i64> = HashMap::new();
+        for r in sqlx::query("SELECT category_id, category_name FROM categories").fetch_all(&mut *tx).await? {
+            by_name.insert(r.try_get::<String, _>("category_name")?.to_lowercase(), r.try_get("category_id")?);
+        }
+
+        let locked_until = if self.allow_locked { None } else { lock::lock_date(&mut tx, account_id).await? };
+        // Split number in the file -> split group (id of the group's first row)
+        let mut groups: HashMap<usize, i64> = HashMap::new();
+
+        let mut summary = ImportSummary::default();
+        for t in txns {
+            if locked_until.is_some_and(|d| t.date <= d) {
+                summary.locked += 1;
+                continue;
+            }
+            let mut t = t.clone();
+            let effect = rules.apply_to_import(account_id, &mut t);
+            // Rows in the trash count too: they still hold their external id
+            if let Some(ext) = &t.external_id {
+                let exists = sqlx::query("SELECT 1 FROM transactions WHERE account_id = ? AND external_id = ?")
+                    .bind(account_id)
+                    .bind(ext)
+                    .fetch_optional(&mut *tx).await?;
+                if exists.is_some() {
+                    summary.duplicates += 1;
+                    continue;
+                }
+            }
+
+            let ruled = effect.as_ref().and_then(|e| e.category_id);
+            let named = match &t.category {
+                Some(name) if category_id.is_none() && ruled.is_none() => match by_name.get(&name.to_lowercase()) {
+                    Some(id) => Some(*id),
+                    None => {
+                        let cat_type = if t.amount.is_sign_negative() { "Expense" } else { "Income" };
+                        let id: i64 = sqlx::query("INSERT INTO categories (category_name, category_type, icon) VALUES (?, ?, '') RETURNING category_id")
+                            .bind(name)
+                            .bind(cat_type)
+                            .fetch_one(&mut *tx).await?
+                            .try_get("category_id")?;
+                        by_name.insert(name.to_lowercase(), id);
+                        Some(id)
+                    }
+                },
+                _ => None,
+            };
+
+            let req = CreateTxnReq {
+                account_id,
+                category_id: category_id.or(ruled).or(named).unwrap_or(if t.amount.is_sign_negative() { expense_cat } else { income_cat }),
+                amount: Money(t.amount),
+                base_amount: Money(t.amount.abs()),
+                is_expense: t.amount.is_sign_negative(),
+                description: t.memo.clone(),
+                payee: t.payee.clone(),
+                currency: currency.clone(),
+                transacted_at: t.date.and_hms_opt(0, 0, 0).unwrap_or_default(),
+            };
+            let id = self.insert_transaction_exec(&mut *tx, &req).await?;
+            if t.external_id.is_some() || t.value_date.is_some() || t.cleared || t.reconciled {
+                sqlx::query("UPDATE transactions SET external_id = ?, value_date = ?, cleared = ?, reconciled = ? WHERE transaction_id = ?")
+                    .bind(&t.external_id)
+                    .bind(t.value_date.map(|d| d.format("%Y-%m-%d").to_string()))
+                    .bind(t.cleared)
+                    .bind(t.reconciled)
+                    .bind(id)
+                    .execute(&mut *tx).await?;
+            }
+            if let Some(n) = t.split {
+                sqlx::query("UPDATE transactions SET split_group = ? WHERE transaction_id = ?")
+                    .bind(*groups.entry(n).or_insert(id))
+                    .bind(id)
+                    .execute(&mut *tx).await?;
+            }
+            if let Some(e) = &effect {
+                add_tags_exec(&mut tx, id, &e.tags).await?;
+                summary.ruled += 1;
+            }
+            summary.imported += 1;
+        }
+
+        self.recompute_balance_exec(&mut tx, account_id).await?;
+        audit::commit(tx).await?;
+        Ok(summary)
     }
-    
-    pub async fn delete_transaction(&self, transaction_id: i64) -> anyhow::Result<()> {
-        let mut tx = self.pool.begin().await?;
-        
-        let row = sqlx::query("SELECT account_id FROM transactions WHERE transaction_id = ?")
+
+    // Clearing marks a row as seen on the bank statement; un-clearing also drops the
+    // reconciled lock
+    pub async fn set_cleared(&self, transaction_id: i64, cleared: bool) -> Result<()> {
+        let mut tx = self.begin().await?;
+        let row = sqlx::query("SELECT account_id, date(transacted_at) AS day FROM transactions WHERE transaction_id = ? AND deleted_at IS NULL")
             .bind(transaction_id)
-            .fetch_one(&mut *tx).await?;
-        let aid: i64 = row.try_get("account_id")?;
-        
-        sqlx::query("DELETE FROM transactions WHERE transaction_id = ?")
+            .fetch_optional(&mut *tx).await?;
+        if let Some(row) = row {
+            self.check_lock(&mut tx, row.try_get("account_id")?, row.try_get("day")?).await?;
+        }
+        sqlx::query("UPDATE transactions SET cleared = ?, reconciled = reconciled AND ? WHERE transaction_id = ? AND deleted_at IS NULL")
+            .bind(cleared)
+            .bind(cleared)
             .bind(transaction_id)
             .execute(&mut *tx).await?;
-            
-        self.recompute_balance_exec(&mut *tx, aid).await?;
-        tx.commit().await?;
+        audit::commit(tx).await?;
         Ok(())
     }
 
-    pub async fn list_goals(&self) -> Result<Vec<SavingGoalDto>> {
-        let rows = sqlx::query("SELECT goal_id, goal_name, target_amount, current_amount, deadline FROM savings_goals ORDER BY deadline ASC").fetch_all(&self.pool).await?;
-        let mut out = Vec::new();
+    // Rows still to reconcile against a statement ending on `end`, oldest first
+    pub async fn list_unreconciled(&self, account_id: i64, end: NaiveDate) -> Result<Vec<TransactionDto>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT t.transaction_id, t.account_id, t.category_id, t.amount, t.is_expense,
+                   t.description, t.payee, t.currency, t.transacted_at, t.cleared, t.reconciled
+            FROM transactions t
+            WHERE t.account_id = ? AND t.deleted_at IS NULL AND t.reconciled = 0 AND date(t.transacted_at) <= date(?)
+            ORDER BY t.transacted_at, t.transaction_id
+            "#
+        )
+        .bind(account_id)
+        .bind(end.format("%Y-%m-%d").to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(map_transaction_row).collect()
+    }
+
+    // Sum of the rows already reconciled up to `end`: where the next reconciliation starts from
+    pub async fn reconciled_balance(&self, account_id: i64, end: NaiveDate) -> Result<Decimal> {
+        let rows = sqlx::query("SELECT amount, is_expense FROM transactions WHERE account_id = ? AND deleted_at IS NULL AND reconciled = 1 AND date(transacted_at) <= date(?)")
+            .bind(account_id)
+            .bind(end.format("%Y-%m-%d").to_string())
+            .fetch_all(&self.pool).await?;
+        let mut total = Decimal::ZERO;
         for r in rows {
-            out.push(SavingGoalDto {
-                id: r.try_get("goal_id")?,
-                name: r.try_get("goal_name")?,
-                target_amount: Money(Decimal::from_str_exact(&r.try_get::<String, _>("target_amount")?).unwrap_or(Decimal::ZERO)),          
-                current_amount: Money(Decimal::from_str_exact(&r.try_get::<String, _>("current_amount")?).unwrap_or(Decimal::ZERO)),
-                deadline: r.try_get("deadline")?,
-            });
+            total += check::signed_amount(&r.try_get::<String, _>("amount")?, r.try_get("is_expense")?).unwrap_or_default();
         }
-        Ok(out)
+        Ok(total)
+    }
+
+    // Lock every cleared row up to the statement date; returns how many were locked
+    pub async fn finish_reconciliation(&self, account_id: i64, end: NaiveDate) -> Result<u64> {
+        let mut tx = self.begin().await?;
+        let res = sqlx::query("UPDATE transactions SET reconciled = 1 WHERE account_id = ? AND deleted_at IS NULL AND cleared = 1 AND reconciled = 0 AND date(transacted_at) <= date(?)")
+            .bind(account_id)
+            .bind(end.format("%Y-%m-%d").to_string())
+            .execute(&mut *tx).await?;
+        audit::commit(tx).await?;
+        Ok(res.rows_affected())
     }
 
-    pub async fn get_monthly_report(&self) -> Result<Vec<CategorySpendingDto>> {
-        let now = chrono::Utc::now();
-        let start_str = format!("{}-01 00:00:00", now.format("%Y-%m")); 
-        let end_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
+    // Transactions of one account dated in [start, end], oldest first
+    async fn account_transactions_between(&self, account_id: i64, start: NaiveDate, end: NaiveDate) -> Result<Vec<TransactionDto>> {
         let rows = sqlx::query(
             r#"
-            SELECT 
-                c.category_name, 
-                SUM(ABS(CAST(t.amount AS REAL))) as total
-            FROM transactions t 
-            JOIN categories c ON t.category_id = c.category_id 
-            WHERE t.is_expense = 1 
-                AND t.transacted_at >= ?1
-                AND t.transacted_at <= ?2
-            GROUP BY c.category_name 
-            ORDER BY total DESC
+            SELECT t.transaction_id, t.account_id, t.category_id, t.amount, t.is_expense,
+                   t.description, t.payee, t.currency, t.transacted_at, t.cleared, t.reconciled
+            FROM transactions t
+            WHERE t.account_id = ?
+                AND t.deleted_at IS NULL
+                AND date(t.transacted_at) >= date(?)
+                AND date(t.transacted_at) <= date(?)
+            ORDER BY t.transacted_at, t.transaction_id
             "#
-        ).bind(start_str)
-        .bind(end_str)
+        )
+        .bind(account_id)
+        .bind(start.format("%Y-%m-%d").to_string())
+        .bind(end.format("%Y-%m-%d").to_string())
         .fetch_all(&self.pool)
         .await?;
+
+        rows.iter().map(map_transaction_row).collect()
+    }
+
+    // Recorded transactions that look like the one about to be saved; `exclude` is the row
+    // being edited, if any
+    pub async fn find_duplicates(
+        &self,
+        account_id: i64,
+        date: NaiveDate,
+        amount: Decimal,
+        payee: Option<&str>,
+        exclude: Option<i64>,
+    ) -> Result<Vec<DuplicateMatch>> {
+        let window = chrono::Duration::days(duplicates::DATE_WINDOW_DAYS);
+        let mut existing = self.account_transactions_between(account_id, date - window, date + window).await?;
+        existing.retain(|t| Some(t.id) != exclude);
+        Ok(duplicates::find(date, amount, payee, &existing))
+    }
+
+    // Likely duplicate of each statement row, for review before importing. Rows whose
+    // external id is already recorded are left out: the import skips them anyway.
+    pub async fn find_import_duplicates(&self, account_id: i64, txns: &[ImportedTxn]) -> Result<Vec<Option<DuplicateMatch>>> {
+        let (Some(first), Some(last)) = (txns.iter().map(|t| t.date).min(), txns.iter().map(|t| t.date).max()) else {
+            return Ok(Vec::new());
+        };
+        let window = chrono::Duration::days(duplicates::DATE_WINDOW_DAYS);
+        let existing = self.account_transactions_between(account_id, first - window, last + window).await?;
+
+        let known: std::collections::HashSet<String> = sqlx::query("SELECT external_id FROM transactions WHERE account_id = ? AND external_id IS NOT NULL")
+            .bind(account_id)
+            .fetch_all(&self.pool).await?
+            .iter()
+            .filter_map(|r| r.try_get("external_id").ok())
+            .collect();
+
+        // Compare with the payees the import would write
+        let rules = self.rule_set().await?;
+        let cleaned: Vec<ImportedTxn> = txns.iter().cloned()
+            .map(|mut t| {
+                rules.apply_to_import(account_id, &mut t);
+                t
+            })
+            .collect();
+        let mut matches = duplicates::match_batch(&cleaned, &existing);
+        for (m, t) in matches.iter_mut().zip(txns) {
+            if t.external_id.as_ref().is_some_and(|id| known.contains(id)) {
+                *m = None;
+            }
+        }
+        Ok(matches)
+    }
+
+    // Transaction id -> split group, for the account's rows imported as part of a split
+    pub async fn split_groups(&self, account_id: i64) -> Result<HashMap<i64, i64>> {
+        let rows: Vec<(i64, i64)> = sqlx::query_as("SELECT transaction_id, split_group FROM transactions WHERE account_id = ? AND split_group IS NOT NULL AND deleted_at IS NULL")
+            .bind(account_id)
+            .fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    // Account balance at the end of `date`, from its transactions
+    pub async fn balance_as_of(&self, account_id: i64, date: NaiveDate) -> Result<Decimal> {
+        let mut conn = self.pool.acquire().await?;
+        balance_as_of_exec(&mut conn, account_id, date).await
+    }
+
+    // ================= Import profiles =================
+    pub async fn list_import_profiles(&self) -> Result<Vec<ImportProfileDto>> {
+        let rows = sqlx::query("SELECT profile_id, profile_name, mapping FROM import_profiles WHERE format = 'csv' ORDER BY profile_name")
+            .fetch_all(&self.pool).await?;
         let mut out = Vec::new();
         for r in rows {
-            out.push(CategorySpendingDto {
-                category: r.try_get("category_name")?,
-                total_amount: Money(Decimal::from_f64_retain(r.try_get("total")?).unwrap_or(Decimal::ZERO)),
+            out.push(ImportProfileDto {
+                id: r.try_get("profile_id")?,
+                name: r.try_get("profile_name")?,
+                mapping: serde_json::from_str(&r.try_get::<String, _>("mapping")?)?,
             });
         }
         Ok(out)
     }
 
-} 
+    pub async fn get_import_profile(&self, name: &str) -> Result<Option<CsvMapping>> {
+        let row = sqlx::query("SELECT mapping FROM import_profiles WHERE profile_name = ? COLLATE NOCASE AND format = 'csv'")
+            .bind(name.trim())
+            .fetch_optional(&self.pool).await?;
+        match row {
+            Some(r) => Ok(Some(serde_json::from_str(&r.try_get::<String, _>("mapping")?)?)),
+            None => Ok(None),
+        }
+    }
 
-// Helpers
-fn map_account_type(s: &str) -> AccountType {
-    if s.eq_ignore_ascii_case("checking") { AccountType::Checking } 
-    else if s.eq_ignore_ascii_case("credit") { AccountType::Credit }
-    else if s.eq_ignore_ascii_case("savings") { AccountType::Savings }
-    else if s.eq_ignore_ascii_case("cash") { AccountType::Cash }
-    else { AccountType::Other }
-}
+    // Create or overwrite the named profile
+    pub async fn save_import_profile(&self, name: &str, mapping: &CsvMapping) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO import_profiles (profile_name, format, mapping, profile_created_at)
+            VALUES (?, 'csv', ?, strftime('%Y-%m-%dT%H:%M:%SZ','now'))
+            ON CONFLICT(profile_name) DO UPDATE SET mapping = excluded.mapping
+            "#
+        )
+        .bind(name.trim())
+        .bind(serde_json::to_string(mapping)?)
+        .execute(&self.pool).await?;
+        Ok(())
+    }
+    
+    // ================= Rules =================
+    // Every rule, in the order they run
+    pub async fn list_rules(&self) -> Result<Vec<Rule>> {
+        let rows = sqlx::query("SELECT * FROM rules ORDER BY position, rule_id").fetch_all(&self.pool).await?;
+        rows.iter().map(map_rule_row).collect()
+    }
 
-fn parse_date_any(s: &str) -> NaiveDate {
-    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") { return d; }
-    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S") { return dt.date(); }
-    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%SZ") { return dt.date(); }
-    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") { return dt.date(); }
-    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y/%m/%d") { return d; }
-    chrono::Utc::now().date_naive()
-}
\ No newline at end of file
+    // Insert when `id` is 0 (the rule goes last), else update; returns the rule id
+    pub async fn save_rule(&self, rule: &Rule) -> Result<i64> {
+        if rule.kind == MatchKind::Regex {
+            rules::compile(&rule.pattern).map_err(|e| anyhow::anyhow!("invalid regex: {}", e))?;
+        }
+        let query = if rule.id == 0 {
+            r#"
+            INSERT INTO rules (rule_name, enabled, match_field, match_kind, pattern, min_amount, max_amount,
+                               account_id, set_category_id, set_payee, set_memo, add_tags, position)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, (SELECT COALESCE(MAX(position), -1) + 1 FROM rules))
+            "#
+        } else {
+            r#"
+            UPDATE rules SET rule_name = ?, enabled = ?, match_field = ?, match_kind = ?, pattern = ?,
+                             min_amount = ?, max_amount = ?, account_id = ?, set_category_id = ?,
+                             set_payee = ?, set_memo = ?, add_tags = ?
+            WHERE rule_id = ?
+            "#
+        };
+        let tags = rule.add_tags.join(", ");
+        let res = sqlx::query(query)
+            .bind(rule.name.trim())
+            .bind(rule.enabled)
+            .bind(rule.field.as_str())
+            .bind(rule.kind.as_str())
+            .bind(&rule.pattern)
+            .bind(rule.min_amount.map(|d| d.to_string()))
+            .bind(rule.max_amount.map(|d| d.to_string()))
+            .bind(rule.account_id)
+            .bind(rule.set_category_id)
+            .bind(&rule.set_payee)
+            .bind(&rule.set_memo)
+            .bind(if tags.is_empty() { None } else { Some(tags) })
+            .bind(rule.id)
+            .execute(&self.pool).await?;
+        Ok(if rule.id == 0 { res.last_insert_rowid() } else { rule.id })
+    }
+
+    pub async fn delete_rule(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM rules WHERE rule_id = ?").bind(id).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    // Renumber the rules in the given order
+    pub async fn reorder_rules(&self, ids: &[i64]) -> Result<()> {
+        let mut tx = self.begin().await?;
+        for (i, id) in ids.iter().enumerate() {
+            sqlx::query("UPDATE rules SET position = ? WHERE rule_id = ?").bind(i as i64).bind(id).execute(&mut *tx).await?;
+        }
+        audit::commit(tx).await?;
+        Ok(())
+    }
+
+    async fn rule_set(&self) -> Result<RuleSet> {
+        RuleSet::new(self.list_rules().await?)
+    }
+
+    // Category model trained on every categorised transaction. The fallback categories
+    // ('Uncategorized', 'Other Income') and opening balances say nothing about the payee.
+    pub async fn train_category_model(&self) -> Result<CategoryModel> {
+        let rows = sqlx::query(
+            r#"
+            SELECT t.category_id, t.payee, t.description
+            FROM transactions t
+            JOIN categories c ON c.category_id = t.category_id
+            WHERE c.category_name NOT IN ('Uncategorized', 'Other Income', 'Initial Balance')
+              AND t.deleted_at IS NULL
+            "#
+        )
+        .fetch_all(&self.pool).await?;
+        let mut examples = Vec::with_capacity(rows.len());
+        for r in &rows {
+            examples.push((r.try_get::<i64, _>("category_id")?, r.try_get::<Option<String>, _>("payee")?, r.try_get::<Option<String>, _>("description")?));
+        }
+        Ok(CategoryModel::train(examples.iter().map(|(c, p, m)| (*c, p.as_deref(), m.as_deref()))))
+    }
+
+    // What running the rules over recorded transactions would change, oldest first.
+    // Opening balances are left alone.
+    pub async fn preview_rules(&self, account_id: Option<i64>) -> Result<Vec<RuleChange>> {
+        let rules = self.rule_set().await?;
+        if rules.is_empty() {
+            return Ok(Vec::new());
+        }
+        let opening: Option<i64> = sqlx::query("SELECT category_id FROM categories WHERE category_name = 'Initial Balance'")
+            .fetch_optional(&self.pool).await?
+            .map(|r| r.try_get("category_id")).transpose()?;
+        let tags = self.list_transaction_tags().await?;
+        // Transactions in a locked period are left alone too, unless the lock is overridden
+        let locks = if self.allow_locked { Vec::new() } else { self.list_period_locks().await? };
+        let none = Vec::new();
+        Ok(self.list_all_transactions().await?
+            .iter()
+            .filter(|t| account_id.is_none_or(|a| t.account_id == a) && (opening.is_none() || t.category_id != opening))
+            .filter(|t| lock::lock_date_for(&locks, t.account_id).is_none_or(|d| t.txn_date > d))
+            .filter_map(|t| rules.change_for(t, tags.get(&t.id).unwrap_or(&none)))
+            .collect())
+    }
+
+    // Write previewed changes in one DB transaction; returns how many transactions changed.
+    // A period locked since the preview refuses the whole batch.
+    pub async fn apply_rule_changes(&self, changes: &[RuleChange]) -> Result<usize> {
+        let mut tx = self.begin().await?;
+        for c in changes {
+            self.check_lock(&mut tx, c.txn.account_id, c.txn.txn_date).await?;
+            sqlx::query(
+                r#"
+                UPDATE transactions
+                SET category_id = COALESCE(?, category_id), payee = COALESCE(?, payee), description = COALESCE(?, description)
+                WHERE transaction_id = ?
+                "#
+            )
+            .bind(c.category_id)
+            .bind(&c.payee)
+            .bind(&c.memo)
+            .bind(c.txn.id)
+            .execute(&mut *tx).await?;
+            add_tags_exec(&mut tx, c.txn.id, &c.tags).await?;
+        }
+        audit::commit(tx).await?;
+        Ok(changes.len())
+    }
+
+    // ================= Integrity check =================
+    // Every row the checker looks at, read as text so nothing is lost in parsing
+    async fn load_ledger(&self) -> Result<check::Ledger> {
+        let mut ledger = check::Ledger::default();
+        // Accounts in the trash still own their rows; their balance is zero like the sum
+        // of their (trashed) transactions
+        let mut trashed = std::collections::HashSet::new();
+        for r in sqlx::query("SELECT account_id, account_name, IFNULL(CAST(balance AS TEXT), '') AS balance, deleted_at FROM accounts ORDER BY account_id")
+            .fetch_all(&self.pool).await? {
+            if r.try_get::<Option<String>, _>("deleted_at")?.is_some() {
+                trashed.insert(r.try_get::<i64, _>("account_id")?);
+            }
+            ledger.accounts.push(check::AccountRow {
+                id: r.try_get("account_id")?,
+                name: r.try_get("account_name")?,
+                balance: r.try_get("balance")?,
+            });
+        }
+        for r in sqlx::query("SELECT category_id, category_name, category_type FROM categories").fetch_all(&self.pool).await? {
+            ledger.categories.push(check::CategoryRow {
+                id: r.try_get("category_id")?,
+                name: r.try_get("category_name")?,
+                kind: r.try_get("category_type")?,
+            });
+        }
+        let rows = sqlx::query(
+            r#"
+            SELECT transaction_id, account_id, category_id, IFNULL(CAST(amount AS TEXT), '') AS amount,
+                   is_expense, IFNULL(CAST(transacted_at AS TEXT), '') AS transacted_at
+            FROM transactions
+            WHERE deleted_at IS NULL
+            ORDER BY transaction_id
+            "#
+        )
+        .fetch_all(&self.pool).await?;
+        for r in rows {
+            ledger.transactions.push(check::TxnRow {
+                id: r.try_get("transaction_id")?,
+                account_id: r.try_get("account_id")?,
+                category_id: r.try_get("category_id")?,
+                amount: r.try_get("amount")?,
+                is_expense: r.try_get("is_expense")?,
+                transacted_at: r.try_get("transacted_at")?,
+            });
+        }
+        for r in sqlx::query("SELECT tag_id FROM tags").fetch_all(&self.pool).await? {
+            ledger.tag_ids.insert(r.try_get("tag_id")?);
+        }
+        // Links of rows in the Trash wait there with them
+        let links = "SELECT tt.transaction_id, tt.tag_id FROM transaction_tags tt
+                     WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.transaction_id = tt.transaction_id AND t.deleted_at IS NOT NULL)";
+        for r in sqlx::query(links).fetch_all(&self.pool).await? {
+            ledger.tag_links.push((r.try_get("transaction_id")?, r.try_get("tag_id")?));
+        }
+        for kind in [check::Owned::Budget, check::Owned::Goal, check::Owned::Recurring, check::Owned::Rule, check::Owned::Assertion] {
+            let (table, key) = kind.table();
+            let sql = format!("SELECT {} AS id, account_id FROM {} WHERE account_id IS NOT NULL", key, table);
+            for r in sqlx::query(&sql).fetch_all(&self.pool).await? {
+                ledger.owned.push(check::OwnedRow { kind, id: r.try_get("id")?, account_id: r.try_get("account_id")? });
+            }
+        }
+        ledger.assertions = self.list_balance_assertions().await?;
+        ledger.assertions.retain(|a| !trashed.contains(&a.account_id));
+        Ok(ledger)
+    }
+
+    pub async fn check_ledger(&self) -> Result<check::Report> {
+        let ledger = self.load_ledger().await?;
+        Ok(check::check(&ledger, chrono::Local::now().date_naive()))
+    }
+
+    // Make the repairs of the given issues in one DB transaction; returns how many were made.
+    // Any repair to a transaction in a locked period refuses them all, unless overridden.
+    pub async fn repair_ledger(&self, issues: &[check::Issue]) -> Result<usize> {
+        use check::Repair;
+
+        let mut tx = self.begin().await?;
+        let mut n = 0;
+        for repair in issues.iter().filter_map(|i| i.repair.as_ref()) {
+            // Rewriting or removing a transaction is a write to its period like any other
+            let target = match repair {
+                Repair::SetAmount { transaction_id, .. }
+                | Repair::SetDate { transaction_id, .. }
+                | Repair::SetCategory { transaction_id, .. }
+                | Repair::DeleteTransaction(transaction_id) => Some(*transaction_id),
+                _ => None,
+            };
+            if let Some(id) = target {
+                let row = sqlx::query("SELECT account_id, date(transacted_at) AS day FROM transactions WHERE transaction_id = ?")
+                    .bind(id)
+                    .fetch_one(&mut *tx).await?;
+                let account_id: i64 = row.try_get("account_id")?;
+                // An unreadable date has no day to check; the date it is rewritten to does
+                if let Some(day) = row.try_get::<Option<NaiveDate>, _>("day")? {
+                    self.check_lock(&mut tx, account_id, day).await?;
+                }
+                if let Repair::SetDate { date, .. } = repair
+                    && let Some(day) = date.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                {
+                    self.check_lock(&mut tx, account_id, day).await?;
+                }
+            }
+            match repair {
+                Repair::SetBalance { account_id, balance } => {
+                    sqlx::query("UPDATE accounts SET balance = ? WHERE account_id = ?")
+                        .bind(balance.to_string()).bind(account_id)
+                        .execute(&mut *tx).await?;
+                }
+                Repair::SetAmount { transaction_id, amount, is_expense } => {
+                    sqlx::query("UPDATE transactions SET amount = ?, is_expense = ? WHERE transaction_id = ?")
+                        .bind(amount.to_string()).bind(is_expense).bind(transaction_id)
+                        .execute(&mut *tx).await?;
+                }
+                Repair::SetDate { transaction_id, date } => {
+                    sqlx::query("UPDATE transactions SET transacted_at = ? WHERE transaction_id = ?")
+                        .bind(date).bind(transaction_id)
+                        .execute(&mut *tx).await?;
+                }
+                Repair::SetCategory { transaction_id, category_id, .. } => {
+                    sqlx::query("UPDATE transactions SET category_id = ? WHERE transaction_id = ?")
+                        .bind(category_id).bind(transaction_id)
+                        .execute(&mut *tx).await?;
+                }
+                // To the Trash like any other delete; its tags stay for a restore
+                Repair::DeleteTransaction(id) => {
+                    sqlx::query("UPDATE transactions SET deleted_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE transaction_id = ? AND deleted_at IS NULL")
+                        .bind(id)
+                        .execute(&mut *tx).await?;
+                }
+                Repair::DeleteTagLink { transaction_id, tag_id } => {
+                    sqlx::query("DELETE FROM transaction_tags WHERE transaction_id = ? AND tag_id = ?")
+                        .bind(transaction_id).bind(tag_id)
+                        .execute(&mut *tx).await?;
+                }
+                Repair::DeleteOwned(kind, id) => {
+                    let (table, key) = kind.table();
+                    sqlx::query(&format!("DELETE FROM {} WHERE {} = ?", table, key)).bind(id).execute(&mut *tx).await?;
+                }
+            }
+            n += 1;
+        }
+        audit::commit(tx).await?;
+        Ok(n)
+    }
+
+    pub async fn list_balance_assertions(&self) -> Result<Vec<BalanceAssertion>> {
+        let rows = sqlx::query("SELECT assertion_id, account_id, assert_date, balance FROM balance_assertions ORDER BY account_id, assert_date, assertion_id")
+            .fetch_all(&self.pool).await?;
+        rows.iter().map(map_assertion_row).collect()
+    }
+
+    pub async fn add_balance_assertion(&self, account_id: i64, date: NaiveDate, balance: Decimal) -> Result<i64> {
+        let res = sqlx::query("INSERT INTO balance_assertions (account_id, assert_date, balance, assertion_created_at) VALUES (?, ?, ?, strftime('%Y-%m-%dT%H:%M:%SZ','now'))")
+            .bind(account_id)
+            .bind(date.format("%Y-%m-%d").to_string())
+            .bind(balance.to_string())
+            .execute(&self.pool).await?;
+        Ok(res.last_insert_rowid())
+    }
+
+    pub async fn delete_balance_assertion(&self, id: i64) -> Result<bool> {
+        let res = sqlx::query("DELETE FROM balance_assertions WHERE assertion_id = ?").bind(id).execute(&self.pool).await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    // ================= Lock dates =================
+    // The lock for every account first
+    pub async fn list_period_locks(&self) -> Result<Vec<PeriodLock>> {
+        let rows = sqlx::query("SELECT account_id, lock_date FROM period_locks ORDER BY account_id")
+            .fetch_all(&self.pool).await?;
+        rows.iter().map(|r| Ok(PeriodLock {
+            account_id: r.try_get("account_id")?,
+            date: NaiveDate::parse_from_str(&r.try_get::<String, _>("lock_date")?, "%Y-%m-%d")?,
+        })).collect()
+    }
+
+    // Lock an account (every account for None) up to and including `date`; None unlocks it
+    pub async fn set_period_lock(&self, account_id: Option<i64>, date: Option<NaiveDate>) -> Result<()> {
+        let mut tx = self.begin().await?;
+        set_lock_exec(&mut tx, account_id, date).await?;
+        audit::commit(tx).await?;
+        Ok(())
+    }
+
+    pub async fn list_year_end_closes(&self) -> Result<Vec<YearEndClose>> {
+        let rows = sqlx::query("SELECT account_id, close_year, balance, closed_at FROM year_end_closes ORDER BY close_year, account_id")
+            .fetch_all(&self.pool).await?;
+        rows.iter().map(map_close_row).collect()
+    }
+
+    // Store every account's balance at the end of `year` and lock the year for all
+    // accounts. Closing a year again takes a new snapshot; the lock never moves back.
+    pub async fn close_year(&self, year: i32) -> Result<Vec<YearEndClose>> {
+        let end = NaiveDate::from_ymd_opt(year, 12, 31).ok_or_else(|| anyhow::anyhow!("no year {}", year))?;
+        if end >= chrono::Local::now().date_naive() {
+            anyhow::bail!("{} is not over yet", year);
+        }
+        // Read in the write transaction, so no change can land between the balances and the lock
+        let mut tx = self.begin().await?;
+        let accounts: Vec<i64> = sqlx::query_scalar("SELECT account_id FROM accounts WHERE deleted_at IS NULL ORDER BY account_id")
+            .fetch_all(&mut *tx).await?;
+        let mut balances = Vec::new();
+        for id in accounts {
+            balances.push((id, balance_as_of_exec(&mut tx, id, end).await?));
+        }
+        let closed_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        for (account_id, balance) in &balances {
+            sqlx::query("INSERT OR REPLACE INTO year_end_closes (account_id, close_year, balance, closed_at) VALUES (?, ?, ?, ?)")
+                .bind(account_id).bind(year).bind(balance.to_string()).bind(&closed_at)
+                .execute(&mut *tx).await?;
+        }
+        let current: Option<String> = sqlx::query_scalar("SELECT lock_date FROM period_locks WHERE account_id IS NULL")
+            .fetch_optional(&mut *tx).await?;
+        if current.is_none_or(|d| d < end.format("%Y-%m-%d").to_string()) {
+            set_lock_exec(&mut tx, None, Some(end)).await?;
+        }
+        audit::commit(tx).await?;
+
+        Ok(balances.into_iter()
+            .map(|(account_id, balance)| YearEndClose { account_id, year, balance, closed_at: closed_at.clone() })
+            .collect())
+    }
+
+    // ================= Audit log =================
+    // Newest first
+    pub async fn list_audit(&self, f: &AuditFilter) -> Result<Vec<AuditEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT audit_id, audited_at, origin, table_name, row_id, action, before, after
+            FROM audit_log
+            WHERE (?1 IS NULL OR table_name = ?1)
+              AND (?2 IS NULL OR row_id = ?2)
+              AND (?3 IS NULL OR origin = ?3)
+              AND (?4 IS NULL OR audited_at >= ?4)
+            ORDER BY audit_id DESC
+            LIMIT ?5
+            "#
+        )
+        .bind(f.table.as_deref())
+        .bind(f.row_id)
+        .bind(f.origin.map(|o| o.as_str()))
+        .bind(f.since.as_deref())
+        .bind(f.limit.unwrap_or(-1))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let json = |r: &SqliteRow, col: &str| -> Result<Option<serde_json::Value>> {
+            Ok(match r.try_get::<Option<String>, _>(col)? {
+                Some(text) => Some(serde_json::from_str(&text)?),
+                None => None,
+            })
+        };
+        let mut out = Vec::new();
+        for r in &rows {
+            out.push(AuditEntry {
+                id: r.try_get("audit_id")?,
+                at: r.try_get("audited_at")?,
+                origin: r.try_get("origin")?,
+                table: r.try_get("table_name")?,
+                row_id: r.try_get("row_id")?,
+                action: r.try_get("action")?,
+                before: json(r, "before")?,
+                after: json(r, "after")?,
+            });
+        }
+        Ok(out)
+    }
+
+    // ================= Undo =================
+    // The rows an undoable edit touches, as they are now
+    pub async fn snapshot(&self, target: Target) -> Result<Rows> {
+        let mut conn = self.pool.acquire().await?;
+        load_rows(&mut conn, target).await
+    }
+
+    // Put the target's rows back the way `to` has them, provided they still look like
+    // `expected` (what the edit being undone or redone left behind)
+    pub async fn revert(&self, target: Target, expected: &Rows, to: &Rows) -> Result<()> {
+        let mut tx = self.begin().await?;
+        let current = load_rows(&mut tx, target).await?;
+        if serde_json::to_value(&current)? != serde_json::to_value(expected)? {
+            anyhow::bail!("it has been changed since");
+        }
+        // Transactions that change either way must be outside the locked period
+        let same = |a: &TransactionRecord, rows: &Rows| rows.transactions.iter().any(|b| serde_json::to_value(a).ok() == serde_json::to_value(b).ok());
+        for (r, other) in current.transactions.iter().map(|r| (r, to)).chain(to.transactions.iter().map(|r| (r, &current))) {
+            if !same(r, other) {
+                self.check_lock(&mut tx, r.txn.account_id, r.txn.txn_date).await?;
+            }
+        }
+        delete_missing_rows(&mut tx, &current, to).await?;
+        write_rows(&mut tx, to).await?;
+
+        let mut accounts: Vec<i64> = current.transactions.iter().chain(&to.transactions).map(|r| r.txn.account_id)
+            .chain(current.accounts.iter().chain(&to.accounts).map(|a| a.id))
+            .collect();
+        accounts.sort();
+        accounts.dedup();
+        for id in accounts {
+            self.recompute_balance_exec(&mut tx, id).await?;
+        }
+        audit::commit(tx).await?;
+        Ok(())
+    }
+
+    // ================= Trash =================
+    // Accounts in the trash, and transactions deleted on their own from live accounts
+    // (the ones deleted with their account come back with it). Newest first.
+    pub async fn list_trash(&self) -> Result<Vec<TrashItemDto>> {
+        let mut items = Vec::new();
+        let rows = sqlx::query(
+            r#"
+            SELECT a.account_id, a.account_name, a.deleted_at,
+                   (SELECT COUNT(*) FROM transactions t WHERE t.account_id = a.account_id AND t.deleted_at = a.deleted_at) AS n
+            FROM accounts a
+            WHERE a.deleted_at IS NOT NULL
+            "#
+        )
+        .fetch_all(&self.pool).await?;
+        for r in rows {
+            items.push(TrashItemDto {
+                kind: TrashKind::Account,
+                id: r.try_get("account_id")?,
+                account: r.try_get("account_name")?,
+                transaction: None,
+                transactions: r.try_get("n")?,
+                deleted_at: r.try_get("deleted_at")?,
+            });
+        }
+        let rows = sqlx::query(
+            r#"
+            SELECT t.transaction_id, t.account_id, t.category_id, t.amount, t.is_expense,
+                   t.description, t.payee, t.currency, t.transacted_at, t.cleared, t.reconciled,
+                   t.deleted_at, IFNULL(a.account_name, 'missing account ' || t.account_id) AS account_name
+            FROM transactions t
+            LEFT JOIN accounts a ON a.account_id = t.account_id
+            WHERE t.deleted_at IS NOT NULL AND a.deleted_at IS NULL
+            "#
+        )
+        .fetch_all(&self.pool).await?;
+        for r in rows {
+            let txn = map_transaction_row(&r)?;
+            items.push(TrashItemDto {
+                kind: TrashKind::Transaction,
+                id: txn.id,
+                account: r.try_get("account_name")?,
+                transaction: Some(txn),
+                transactions: 1,
+                deleted_at: r.try_get("deleted_at")?,
+            });
+        }
+        items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then(b.id.cmp(&a.id)));
+        Ok(items)
+    }
+
+    // Take an item out of the trash; an account brings back the transactions deleted with it
+    pub async fn restore_from_trash(&self, kind: TrashKind, id: i64) -> Result<()> {
+        let mut tx = self.begin().await?;
+        let account_id = match kind {
+            TrashKind::Account => {
+                sqlx::query("UPDATE transactions SET deleted_at = NULL WHERE account_id = ?1 AND deleted_at = (SELECT deleted_at FROM accounts WHERE account_id = ?1)")
+                    .bind(id).execute(&mut *tx).await?;
+                let res = sqlx::query("UPDATE accounts SET deleted_at = NULL WHERE account_id = ? AND deleted_at IS NOT NULL")
+                    .bind(id).execute(&mut *tx).await?;
+                if res.rows_affected() == 0 {
+                    anyhow::bail!("account {} is not in the trash", id);
+                }
+                id
+            }
+            TrashKind::Transaction => {
+                let row = sqlx::query("SELECT t.account_id, date(t.transacted_at) AS day, a.account_id AS live, a.deleted_at FROM transactions t LEFT JOIN accounts a ON a.account_id = t.account_id WHERE t.transaction_id = ? AND t.deleted_at IS NOT NULL")
+                    .bind(id).fetch_optional(&mut *tx).await?;
+                let Some(row) = row else { anyhow::bail!("transaction {} is not in the trash", id) };
+                // Put there by `check --fix`; it has nowhere to go back to
+                if row.try_get::<Option<i64>, _>("live")?.is_none() {
+                    anyhow::bail!("its account no longer exists; purge it instead");
+                }
+                if row.try_get::<Option<String>, _>("deleted_at")?.is_some() {
+                    anyhow::bail!("its account is in the trash; restore the account first");
+                }
+                self.check_lock(&mut tx, row.try_get("account_id")?, row.try_get("day")?).await?;
+                sqlx::query("UPDATE transactions SET deleted_at = NULL WHERE transaction_id = ?")
+                    .bind(id).execute(&mut *tx).await?;
+                row.try_get("account_id")?
+            }
+        };
+        self.recompute_balance_exec(&mut tx, account_id).await?;
+        audit::commit(tx).await?;
+        Ok(())
+    }
+
+    // Delete an item in the trash for good; an account takes everything that belongs to it
+    pub async fn purge_from_trash(&self, kind: TrashKind, id: i64) -> Result<()> {
+        let mut tx = self.begin().await?;
+        let res = match kind {
+            TrashKind::Account => {
+                sqlx::query("DELETE FROM transactions WHERE account_id = ?1 AND EXISTS (SELECT 1 FROM accounts WHERE account_id = ?1 AND deleted_at IS NOT NULL)")
+                    .bind(id).execute(&mut *tx).await?;
+                sqlx::query("DELETE FROM accounts WHERE account_id = ? AND deleted_at IS NOT NULL")
+                    .bind(id).execute(&mut *tx).await?
+            }
+            TrashKind::Transaction => {
+                sqlx::query("DELETE FROM transactions WHERE transaction_id = ? AND deleted_at IS NOT NULL")
+                    .bind(id).execute(&mut *tx).await?
+            }
+        };
+        if res.rows_affected() == 0 {
+            anyhow::bail!("it is not in the trash");
+        }
+        audit::commit(tx).await?;
+        Ok(())
+    }
+
+    // Purge everything deleted before `cutoff`; returns how many accounts and transactions went
+    pub async fn purge_trash_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+        let cutoff = cutoff.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        let mut tx = self.begin().await?;
+        let txns = sqlx::query("DELETE FROM transactions WHERE deleted_at < ?1 OR account_id IN (SELECT account_id FROM accounts WHERE deleted_at < ?1)")
+            .bind(&cutoff).execute(&mut *tx).await?;
+        let accounts = sqlx::query("DELETE FROM accounts WHERE deleted_at < ?")
+            .bind(&cutoff).execute(&mut *tx).await?;
+        audit::commit(tx).await?;
+        Ok(txns.rows_affected() + accounts.rows_affected())
+    }
+
+    // ================= Backup / restore =================
+    // Every table, as one document
+    pub async fn backup(&self) -> Result<Backup> {
+        // The trash is part of the backup
+        let accounts = sqlx::query(&format!("{} ORDER BY account_id", ACCOUNT_RECORD_SQL))
+            .fetch_all(&self.pool).await?
+            .iter()
+            .map(map_account_row)
+            .collect::<Result<Vec<_>>>()?;
+        let transactions = sqlx::query(&format!("{} ORDER BY transaction_id", TRANSACTION_RECORD_SQL))
+            .fetch_all(&self.pool).await?
+            .iter()
+            .map(map_transaction_record)
+            .collect::<Result<Vec<_>>>()?;
+
+        let tags = sqlx::query("SELECT tag_id, tag FROM tags ORDER BY tag_id")
+            .fetch_all(&self.pool).await?
+            .iter()
+            .map(|r| Ok(TagRecord { id: r.try_get("tag_id")?, tag: r.try_get("tag")? }))
+            .collect::<Result<Vec<_>>>()?;
+        let transaction_tags = sqlx::query("SELECT transaction_id, tag_id FROM transaction_tags ORDER BY transaction_id, tag_id")
+            .fetch_all(&self.pool).await?
+            .iter()
+            .map(|r| Ok(TransactionTag { transaction_id: r.try_get("transaction_id")?, tag_id: r.try_get("tag_id")? }))
+            .collect::<Result<Vec<_>>>()?;
+
+        let recurring = sqlx::query("SELECT * FROM recurring_transactions ORDER BY recurring_id")
+            .fetch_all(&self.pool).await?
+            .iter()
+            .map(map_recurring_row)
+            .collect::<Result<Vec<_>>>()?;
+        let budgets = self.list_budgets().await?;
+        let savings_goals = sqlx::query(&format!("{} ORDER BY goal_id", GOAL_RECORD_SQL))
+            .fetch_all(&self.pool).await?
+            .iter()
+            .map(map_goal_row)
+            .collect::<Result<Vec<_>>>()?;
+        let currency_rates = self.list_currency_rates().await?
+            .into_iter()
+            .map(|(currency, rate_to_base)| CurrencyRate { currency, rate_to_base })
+            .collect();
+
+        Ok(Backup {
+            format: backup::FORMAT.into(),
+            format_version: backup::FORMAT_VERSION,
+            schema_version: migrate::latest_version(),
+            created_at: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            accounts,
+            categories: self.list_categories().await?,
+            transactions,
+            tags,
+            transaction_tags,
+            recurring,
+            budgets,
+            savings_goals,
+            currency_rates,
+            import_profiles: self.list_import_profiles().await?,
+            rules: self.list_rules().await?,
+            balance_assertions: self.list_balance_assertions().await?,
+            period_locks: self.list_period_locks().await?,
+            year_end_closes: self.list_year_end_closes().await?,
+        })
+    }
+
+    // Load a backup into an empty database (the fixed categories seeded at startup are
+    // replaced), keeping every id. One DB transaction: on any error nothing is written.
+    pub async fn restore(&self, b: &Backup) -> Result<RestoreSummary> {
+        backup::validate(b)?;
+
+        let mut tx = self.begin().await?;
+        let used: i64 = sqlx::query(
+            r#"
+            SELECT (SELECT COUNT(*) FROM accounts) + (SELECT COUNT(*) FROM transactions)
+                 + (SELECT COUNT(*) FROM recurring_transactions) + (SELECT COUNT(*) FROM budgets)
+                 + (SELECT COUNT(*) FROM savings_goals) + (SELECT COUNT(*) FROM tags)
+                 + (SELECT COUNT(*) FROM currency_rates) + (SELECT COUNT(*) FROM import_profiles)
+                 + (SELECT COUNT(*) FROM rules) + (SELECT COUNT(*) FROM balance_assertions)
+                 + (SELECT COUNT(*) FROM period_locks) + (SELECT COUNT(*) FROM year_end_closes) AS n
+            "#
+        )
+        .fetch_one(&mut *tx).await?
+        .try_get("n")?;
+        if used > 0 {
+            anyhow::bail!("the database is not empty; restore into a new database file");
+        }
+        sqlx::query("DELETE FROM categories").execute(&mut *tx).await?;
+
+        for c in &b.categories {
+            sqlx::query("INSERT INTO categories (category_id, category_name, category_type, icon) VALUES (?, ?, ?, ?)")
+                .bind(c.id).bind(&c.name).bind(format!("{:?}", c.r#type)).bind(&c.icon)
+                .execute(&mut *tx).await?;
+        }
+        for t in &b.tags {
+            sqlx::query("INSERT INTO tags (tag_id, tag) VALUES (?, ?)").bind(t.id).bind(&t.tag).execute(&mut *tx).await?;
+        }
+        write_rows(&mut tx, &Rows::from(b)).await?;
+        for r in &b.currency_rates {
+            sqlx::query("INSERT INTO currency_rates (currency, rate_to_base) VALUES (?, ?)")
+                .bind(&r.currency).bind(r.rate_to_base.to_string())
+                .execute(&mut *tx).await?;
+        }
+        for p in &b.import_profiles {
+            sqlx::query("INSERT INTO import_profiles (profile_id, profile_name, format, mapping, profile_created_at) VALUES (?, ?, 'csv', ?, strftime('%Y-%m-%dT%H:%M:%SZ','now'))")
+                .bind(p.id).bind(&p.name).bind(serde_json::to_string(&p.mapping)?)
+                .execute(&mut *tx).await?;
+        }
+        for l in &b.period_locks {
+            set_lock_exec(&mut tx, l.account_id, Some(l.date)).await?;
+        }
+        for c in &b.year_end_closes {
+            sqlx::query("INSERT INTO year_end_closes (account_id, close_year, balance, closed_at) VALUES (?, ?, ?, ?)")
+                .bind(c.account_id).bind(c.year).bind(c.balance.to_string()).bind(&c.closed_at)
+                .execute(&mut *tx).await?;
+        }
+        for a in &b.accounts {
+            self.recompute_balance_exec(&mut tx, a.id).await?;
+        }
+        audit::commit(tx).await?;
+
+        Ok(RestoreSummary {
+            accounts: b.accounts.len(),
+            categories: b.categories.len(),
+            transactions: b.transactions.len(),
+            other: b.tags.len() + b.transaction_tags.len() + b.recurring.len() + b.budgets.len()
+                + b.savings_goals.len() + b.currency_rates.len() + b.import_profiles.len() + b.rules.len()
+                + b.balance_assertions.len() + b.period_locks.len() + b.year_end_closes.len(),
+        })
+    }
+
+    pub async fn update_transaction(&self, id: i64, req: &CreateTxnReq) -> Result<()> {
+        let is_expense = if req.amount.0.is_sign_negative() { 1 } else { 0 };
+        let amount_abs = req.amount.0.abs().to_string();
+        let mut tx = self.begin().await?;
+
+        // Moving a row out of a locked period is as much a change as moving it in
+        let row = sqlx::query("SELECT account_id, date(transacted_at) AS day FROM transactions WHERE transaction_id = ? AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_one(&mut *tx).await?;
+        self.check_lock(&mut tx, row.try_get("account_id")?, row.try_get("day")?).await?;
+        self.check_lock(&mut tx, req.account_id, req.transacted_at.date()).await?;
+
+        sqlx::query("UPDATE transactions SET category_id = ?, amount = ?, base_amount = ?, is_expense = ?, description = ?, payee = ?, currency = ?, transacted_at = ? WHERE transaction_id = ? AND deleted_at IS NULL")
+            .bind(req.category_id)
+            .bind(&amount_abs)
+            .bind(&amount_abs)
+            .bind(is_expense)
+            .bind(&req.description)
+            .bind(&req.payee)
+            .bind(&req.currency)
+            .bind(req.transacted_at)
+            .bind(id)
+            .execute(&mut *tx).await?;
+            
+        self.recompute_balance_exec(&mut tx, req.account_id).await?;
+        audit::commit(tx).await?;
+        Ok(())
+    }
+    
+    // Moves the transaction to the trash
+    pub async fn delete_transaction(&self, transaction_id: i64) -> anyhow::Result<()> {
+        let mut tx = self.begin().await?;
+        
+        let row = sqlx::query("SELECT account_id, date(transacted_at) AS day FROM transactions WHERE transaction_id = ?")
+            .bind(transaction_id)
+            .fetch_one(&mut *tx).await?;
+        let aid: i64 = row.try_get("account_id")?;
+        self.check_lock(&mut tx, aid, row.try_get("day")?).await?;
+
+        sqlx::query("UPDATE transactions SET deleted_at = strftime('%Y-%m-%dT%H:%M:%fZ','now') WHERE transaction_id = ? AND deleted_at IS NULL")
+            .bind(transaction_id)
+            .execute(&mut *tx).await?;
+            
+        self.recompute_balance_exec(&mut tx, aid).await?;
+        audit::commit(tx).await?;
+        Ok(())
+    }
+
+    pub async fn list_budgets(&self) -> Result<Vec<Budget>> {
+        let rows = sqlx::query("SELECT * FROM budgets ORDER BY budget_id").fetch_all(&self.pool).await?;
+        rows.iter().map(map_budget_row).collect()
+    }
+
+    pub async fn list_goals(&self) -> Result<Vec<SavingGoalDto>> {
+        let rows = sqlx::query("SELECT goal_id, goal_name, target_amount, current_amount, deadline FROM savings_goals g WHERE NOT EXISTS (SELECT 1 FROM accounts a WHERE a.account_id = g.account_id AND a.deleted_at IS NOT NULL) ORDER BY deadline ASC").fetch_all(&self.pool).await?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(SavingGoalDto {
+                id: r.try_get("goal_id")?,
+                name: r.try_get("goal_name")?,
+                target_amount: Money(Decimal::from_str_exact(&r.try_get::<String, _>("target_amount")?).unwrap_or(Decimal::ZERO)),          
+                current_amount: Money(Decimal::from_str_exact(&r.try_get::<String, _>("current_amount")?).unwrap_or(Decimal::ZERO)),
+                deadline: r.try_get("deadline")?,
+            });
+        }
+        Ok(out)
+    }
+
+    // ================= Reports =================
+    // All report queries filter on `transacted_at` (the date the money moved),
+    // never on `trans_create_at` (the time the row was inserted).
+
+    // Expense rows in [start, end] (both inclusive) as (date, category, amount)
+    async fn expense_rows(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<(NaiveDate, String, Decimal)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                c.category_name,
+                t.amount,
+                t.transacted_at
+            FROM transactions t
+            JOIN categories c ON t.category_id = c.category_id
+            WHERE t.is_expense = 1
+                AND t.deleted_at IS NULL
+                AND UPPER(c.category_type) <> 'TRANSFER'
+                AND date(t.transacted_at) >= date(?1)
+                AND date(t.transacted_at) <= date(?2)
+            "#
+        )
+        .bind(start.format("%Y-%m-%d").to_string())
+        .bind(end.format("%Y-%m-%d").to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut out = Vec::with_capacity(rows.len());
+        for r in rows {
+            out.push((
+                parse_date_any(&r.try_get::<String, _>("transacted_at")?),
+                r.try_get("category_name")?,
+                check::signed_amount(&r.try_get::<String, _>("amount")?, true).unwrap_or_default().abs(),
+            ));
+        }
+        Ok(out)
+    }
+
+    // Income, expenses and savings rate for the last `months` months (current month included),
+    // in the base currency. Transfers and opening balances move money around but are not cash flow.
+    pub async fn get_cash_flow(&self, months: u32) -> Result<Vec<CashFlowDto>> {
+        let months = months.max(1);
+        let current = ReportPeriod::containing(ReportGrouping::Month, chrono::Utc::now().date_naive());
+        let first = ReportGrouping::Month.shift(current.start, 1 - months as i32);
+
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                CAST(t.base_amount AS TEXT) AS base_amount,
+                t.is_expense,
+                t.transacted_at
+            FROM transactions t
+            JOIN categories c ON t.category_id = c.category_id
+            WHERE UPPER(c.category_type) <> 'TRANSFER'
+                AND t.deleted_at IS NULL
+                AND c.category_name <> 'Initial Balance'
+                AND date(t.transacted_at) >= date(?1)
+                AND date(t.transacted_at) <= date(?2)
+            "#
+        )
+        .bind(first.format("%Y-%m-%d").to_string())
+        .bind(current.end().format("%Y-%m-%d").to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        // (income, expenses) per month, pre-filled so empty months still show up
+        let mut buckets: BTreeMap<NaiveDate, (Decimal, Decimal)> = (0..months as i32)
+            .map(|i| (ReportGrouping::Month.shift(first, i), (Decimal::ZERO, Decimal::ZERO)))
+            .collect();
+
+        for r in rows {
+            let amount = r.try_get::<Option<String>, _>("base_amount")?.as_deref().and_then(queries::stored_decimal).unwrap_or_default().abs();
+            let date = parse_date_any(&r.try_get::<String, _>("transacted_at")?);
+            let is_expense: i64 = r.try_get("is_expense")?;
+
+            let entry = buckets.entry(ReportGrouping::Month.period_start(date)).or_default();
+            if is_expense != 0 { entry.1 += amount; } else { entry.0 += amount; }
+        }
+
+        Ok(buckets
+            .into_iter()
+            .map(|(period_start, (income, expenses))| {
+                let net = income - expenses;
+                let savings_rate = if income.is_zero() {
+                    None
+                } else {
+                    Some((net * Decimal::ONE_HUNDRED / income).round_dp(1))
+                };
+                CashFlowDto {
+                    month: ReportGrouping::Month.label(period_start),
+                    period_start,
+                    income: Money(income),
+                    expenses: Money(expenses),
+                    net: Money(net),
+                    savings_rate,
+                }
+            })
+            .collect())
+    }
+
+    // Category totals for `current` next to `previous` (inclusive date ranges), largest increase first
+    pub async fn get_category_comparison(
+        &self,
+        current: (NaiveDate, NaiveDate),
+        previous: (NaiveDate, NaiveDate),
+    ) -> Result<Vec<CategoryComparisonDto>> {
+        let day_start = |d: NaiveDate| d.and_hms_opt(0, 0, 0).unwrap();
+        let day_end = |d: NaiveDate| d.and_hms_opt(23, 59, 59).unwrap();
+
+        let rows = queries::compare_category_spending(
+            &self.pool,
+            day_start(current.0),
+            day_end(current.1),
+            day_start(previous.0),
+            day_end(previous.1),
+        )
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| CategoryComparisonDto {
+                category: r.category,
+                previous_amount: Money(r.previous_amount),
+                current_amount: Money(r.current_amount),
+                change: Money(r.change),
+                change_pct: r.change_pct,
+            })
+            .collect())
+    }
+
+    // Payees ranked by total spend in [start, end] (inclusive) in the base currency,
+    // optionally only the top `limit`
+    pub async fn get_payee_report(&self, start: NaiveDate, end: NaiveDate, limit: Option<usize>) -> Result<Vec<PayeeSpendingDto>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                TRIM(t.payee) AS payee,
+                CAST(t.base_amount AS TEXT) AS base_amount
+            FROM transactions t
+            JOIN categories c ON t.category_id = c.category_id
+            WHERE t.is_expense = 1
+                AND t.deleted_at IS NULL
+                AND UPPER(c.category_type) <> 'TRANSFER'
+                AND t.payee IS NOT NULL
+                AND TRIM(t.payee) <> ''
+                AND date(t.transacted_at) >= date(?1)
+                AND date(t.transacted_at) <= date(?2)
+            ORDER BY t.transacted_at ASC
+            "#
+        )
+        .bind(start.format("%Y-%m-%d").to_string())
+        .bind(end.format("%Y-%m-%d").to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Group case-insensitively, keep the first spelling seen for display
+        let mut totals: BTreeMap<String, (String, Decimal, i64)> = BTreeMap::new();
+        for r in rows {
+            let payee: String = r.try_get("payee")?;
+            let amount = r.try_get::<Option<String>, _>("base_amount")?.as_deref().and_then(queries::stored_decimal).unwrap_or_default().abs();
+
+            let entry = totals.entry(payee.to_lowercase()).or_insert_with(|| (payee, Decimal::ZERO, 0));
+            entry.1 += amount;
+            entry.2 += 1;
+        }
+
+        let mut out: Vec<PayeeSpendingDto> = totals
+            .into_values()
+            .map(|(payee, total, count)| PayeeSpendingDto {
+                payee,
+                total_amount: Money(total),
+                txn_count: count,
+                average_amount: Money((total / Decimal::from(count)).round_dp(2)),
+            })
+            .collect();
+        out.sort_by(|a, b| b.total_amount.0.cmp(&a.total_amount.0).then(b.txn_count.cmp(&a.txn_count)));
+        if let Some(n) = limit {
+            out.truncate(n);
+        }
+        Ok(out)
+    }
+
+    // Project every account's balance `days` ahead from its current balance, future-dated
+    // transactions and the recurring_transactions schedules. Also returns the last `days`
+    // of actual history so the projection can be drawn as a continuation of it.
+    pub async fn get_forecast(&self, days: u32) -> Result<Vec<AccountForecastDto>> {
+        let today = chrono::Utc::now().date_naive();
+        let days = days.max(1) as i64;
+        let history_start = today - chrono::Duration::days(days);
+        let horizon = today + chrono::Duration::days(days);
+
+        let accounts = self.list_accounts().await?;
+
+        // Signed amounts of everything after the first history day, including future-dated rows,
+        // read the way the stored balance adds them up
+        let txn_rows = sqlx::query(
+            r#"
+            SELECT account_id, amount, is_expense, transacted_at
+            FROM transactions
+            WHERE date(transacted_at) > date(?1) AND deleted_at IS NULL
+            "#
+        )
+        .bind(history_start.format("%Y-%m-%d").to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut changes: BTreeMap<(i64, NaiveDate), Decimal> = BTreeMap::new();
+        for r in txn_rows {
+            let amount = check::signed_amount(&r.try_get::<String, _>("amount")?, r.try_get("is_expense")?).unwrap_or_default();
+            let date = parse_date_any(&r.try_get::<String, _>("transacted_at")?);
+            *changes.entry((r.try_get("account_id")?, date)).or_default() += amount;
+        }
+
+        // Recurring rules: the sign comes from the category type when there is one,
+        // otherwise from the stored amount (negative = money out).
+        let rule_rows = sqlx::query(
+            r#"
+            SELECT r.account_id, r.amount, r.recurrence_rule, r.next_run_date, c.category_type
+            FROM recurring_transactions r
+            LEFT JOIN categories c ON r.category_id = c.category_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut scheduled: BTreeMap<(i64, NaiveDate), Decimal> = BTreeMap::new();
+        for r in rule_rows {
+            let amount_s: String = r.try_get("amount")?;
+            let amount = Decimal::from_str_exact(amount_s.trim()).unwrap_or(Decimal::ZERO);
+            let amount = match r.try_get::<Option<String>, _>("category_type")?.map(|t| map_category_type(&t)) {
+                Some(CategoryType::Expense) => -amount.abs(),
+                Some(CategoryType::Income) => amount.abs(),
+                _ => amount,
+            };
+            let account_id: i64 = r.try_get("account_id")?;
+            let rule: String = r.try_get("recurrence_rule")?;
+            let anchor = parse_date_any(&r.try_get::<String, _>("next_run_date")?);
+
+            // Occurrences up to today are either already posted or missed; only project the future
+            let mut n = 0;
+            while let Some(date) = recurrence_date(&rule, anchor, n) {
+                if date > horizon { break; }
+                if date > today {
+                    *scheduled.entry((account_id, date)).or_default() += amount;
+                }
+                n += 1;
+            }
+        }
+
+        let mut out = Vec::with_capacity(accounts.len());
+        for acc in accounts {
+            let current = acc.opening_balance.0;
+
+            // Stored balance includes future-dated rows, so back them out to get today's balance
+            let after = |d: NaiveDate| -> Decimal {
+                changes.range((acc.id, d + chrono::Duration::days(1))..=(acc.id, NaiveDate::MAX))
+                    .map(|(_, v)| *v)
+                    .sum()
+            };
+
+            let history: Vec<BalancePointDto> = (0..=days)
+                .map(|i| {
+                    let date = history_start + chrono::Duration::days(i);
+                    BalancePointDto { date, balance: Money(current - after(date)) }
+                })
+                .collect();
+
+            let mut balance = current - after(today);
+            let mut projection = vec![BalancePointDto { date: today, balance: Money(balance) }];
+            for i in 1..=days {
+                let date = today + chrono::Duration::days(i);
+                balance += changes.get(&(acc.id, date)).copied().unwrap_or_default();
+                balance += scheduled.get(&(acc.id, date)).copied().unwrap_or_default();
+                projection.push(BalancePointDto { date, balance: Money(balance) });
+            }
+
+            let limit = acc.credit_limit.map(|l| l.0);
+            let alert = projection.iter().find_map(|p| {
+                let reason = match (acc.r#type, limit) {
+                    (_, Some(l)) if p.balance.0 < -l => "exceeds credit limit",
+                    (AccountType::Credit, _) => return None,
+                    _ if p.balance.0.is_sign_negative() && !p.balance.0.is_zero() => "goes negative",
+                    _ => return None,
+                };
+                Some(ForecastAlertDto { date: p.date, balance: p.balance, reason: reason.into() })
+            });
+
+            out.push(AccountForecastDto {
+                account_id: acc.id,
+                name: acc.name,
+                credit_limit: acc.credit_limit,
+                history,
+                projection,
+                alert,
+            });
+        }
+        Ok(out)
+    }
+
+    // Everything the monthly statement shows for the month starting at `start`: each account's
+    // opening/closing balance and transactions, category totals, budget status and goals
+    pub async fn get_statement(&self, start: NaiveDate) -> Result<StatementDto> {
+        let period = ReportPeriod::containing(ReportGrouping::Month, start);
+        let (start, end) = (period.start, period.end());
+        let categories = self.list_categories().await?;
+        let cats: HashMap<i64, &CategoryDto> = categories.iter().map(|c| (c.id, c)).collect();
+        // Transfers and opening balances move money between our own books
+        let counts = |t: &TransactionDto| match t.category_id.and_then(|id| cats.get(&id)) {
+            Some(c) => c.r#type != CategoryType::Transfer && !c.name.eq_ignore_ascii_case("Initial Balance"),
+            None => true,
+        };
+
+        let accounts = self.list_accounts().await?;
+        let mut statements = Vec::with_capacity(accounts.len());
+        let mut income = Decimal::ZERO;
+        for a in &accounts {
+            let transactions = self.account_transactions_between(a.id, start, end).await?;
+            let money_in: Decimal = transactions.iter().map(|t| t.amount.0).filter(|d| d.is_sign_positive()).sum();
+            let money_out: Decimal = transactions.iter().map(|t| t.amount.0).filter(|d| d.is_sign_negative()).map(|d| -d).sum();
+            income += transactions.iter().filter(|t| counts(t)).map(|t| t.amount.0).filter(|d| d.is_sign_positive()).sum::<Decimal>();
+            statements.push(AccountStatementDto {
+                account_id: a.id,
+                name: a.name.clone(),
+                currency: a.currency.clone(),
+                opening_balance: Money(self.balance_as_of(a.id, start.pred_opt().unwrap_or(start)).await?),
+                closing_balance: Money(self.balance_as_of(a.id, end).await?),
+                money_in: Money(money_in),
+                money_out: Money(money_out),
+                transactions,
+            });
+        }
+
+        // Weekly limits are scaled to the length of the month
+        let days = Decimal::from((end - start).num_days() + 1);
+        let mut budgets = Vec::new();
+        for b in self.list_budgets().await? {
+            let Some(account) = statements.iter().find(|s| s.account_id == b.account_id) else { continue };
+            let limit = if b.period.eq_ignore_ascii_case("weekly") { (b.amount * days / Decimal::from(7)).round_dp(2) } else { b.amount };
+            let spent: Decimal = account.transactions.iter()
+                .filter(|t| t.amount.0.is_sign_negative() && counts(t))
+                .filter(|t| b.category_id.is_none() || t.category_id == b.category_id)
+                .map(|t| -t.amount.0)
+                .sum();
+            budgets.push(BudgetStatusDto {
+                account: account.name.clone(),
+                category: b.category_id.map(|id| cats.get(&id).map(|c| c.name.clone()).unwrap_or_else(|| format!("#{}", id))),
+                period: b.period.clone(),
+                limit: Money(limit),
+                spent: Money(spent),
+            });
+        }
+
+        Ok(StatementDto {
+            month: ReportGrouping::Month.label(start),
+            start,
+            end,
+            accounts: statements,
+            income: Money(income),
+            spending: self.get_spending_report(start, end).await?,
+            budgets,
+            goals: self.list_goals().await?,
+            categories,
+        })
+    }
+
+    // Spending per category between two dates (both inclusive), largest first
+    pub async fn get_spending_report(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<CategorySpendingDto>> {
+        let rows = self.expense_rows(start, end).await?;
+        Ok(sum_by_category(rows.into_iter().map(|(_, c, a)| (c, a))))
+    }
+
+    // Spending per category, bucketed by day/week/month/quarter/year
+    pub async fn get_spending_by_period(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        grouping: ReportGrouping,
+    ) -> Result<Vec<PeriodSpendingDto>> {
+        let rows = self.expense_rows(start, end).await?;
+
+        let mut buckets: BTreeMap<NaiveDate, Vec<(String, Decimal)>> = BTreeMap::new();
+        for (date, category, amount) in rows {
+            buckets.entry(grouping.period_start(date)).or_default().push((category, amount));
+        }
+
+        Ok(buckets
+            .into_iter()
+            .map(|(period_start, items)| {
+                let categories = sum_by_category(items.into_iter());
+                let total = categories.iter().map(|c| c.total_amount.0).sum();
+                PeriodSpendingDto {
+                    period: grouping.label(period_start),
+                    period_start,
+                    categories,
+                    total_amount: Money(total),
+                }
+            })
+            .collect())
+    }
+
+} 
+
+// Helpers
+fn map_account_type(s: &str) -> AccountType {
+    if s.eq_ignore_ascii_case("checking") { AccountType::Checking } 
+    else if s.eq_ignore_ascii_case("credit") { AccountType::Credit }
+    else if s.eq_ignore_ascii_case("savings") { AccountType::Savings }
+    else if s.eq_ignore_ascii_case("cash") { AccountType::Cash }
+    else { AccountType::Other }
+}
+
+// The n-th occurrence (0 = anchor) of a recurrence rule, None for unknown rules
+fn recurrence_date(rule: &str, anchor: NaiveDate, n: u32) -> Option<NaiveDate> {
+    match rule.trim().to_ascii_lowercase().as_str() {
+        "daily" => anchor.checked_add_days(chrono::Days::new(n as u64)),
+        "weekly" => anchor.checked_add_days(chrono::Days::new(7 * n as u64)),
+        "biweekly" => anchor.checked_add_days(chrono::Days::new(14 * n as u64)),
+        "monthly" => anchor.checked_add_months(chrono::Months::new(n)),
+        "quarterly" => anchor.checked_add_months(chrono::Months::new(3 * n)),
+        "yearly" | "annually" => anchor.checked_add_months(chrono::Months::new(12 * n)),
+        _ => None,
+    }
+}
+
+fn map_transaction_row(r: &SqliteRow) -> Result<TransactionDto> {
+    let amount_s: String = r.try_get("amount")?;
+    let is_expense_i: i64 = r.try_get("is_expense")?;
+    let txn_date_s: String = r.try_get("transacted_at")?;
+
+    let mut amt = Decimal::from_str_exact(&amount_s).unwrap_or(Decimal::ZERO);
+    if is_expense_i != 0 { amt = -amt; }
+
+    Ok(TransactionDto {
+        id: r.try_get("transaction_id")?,
+        account_id: r.try_get("account_id")?,
+        category_id: r.try_get("category_id")?,
+        amount: Money(amt),
+        memo: r.try_get("description")?,
+        payee: r.try_get("payee")?,
+        currency: r.try_get::<Option<String>, _>("currency")?.unwrap_or("CAD".into()),
+        txn_date: parse_date_any(&txn_date_s),
+        cleared: r.try_get::<i64, _>("cleared")? != 0,
+        reconciled: r.try_get::<i64, _>("reconciled")? != 0,
+    })
+}
+
+// Every column of an account, for lists, backups and undo
+const ACCOUNT_RECORD_SQL: &str = "SELECT account_id, account_name, account_type, currency, balance, account_created_at, credit_limit, deleted_at FROM accounts";
+
+fn map_account_row(r: &SqliteRow) -> Result<AccountDto> {
+    Ok(AccountDto {
+        id: r.try_get("account_id")?,
+        name: r.try_get("account_name")?,
+        r#type: map_account_type(&r.try_get::<String, _>("account_type")?),
+        currency: r.try_get("currency")?,
+        opening_balance: Money(Decimal::from_str_exact(&r.try_get::<String, _>("balance")?).unwrap_or(Decimal::ZERO)),
+        created_at: r.try_get("account_created_at")?,
+        credit_limit: r.try_get::<Option<String>, _>("credit_limit")?
+            .and_then(|s| Decimal::from_str_exact(&s).ok())
+            .map(Money),
+        deleted_at: r.try_get("deleted_at")?,
+    })
+}
+
+// Every column of a transaction, for backups and undo
+const TRANSACTION_RECORD_SQL: &str = r#"
+    SELECT transaction_id, account_id, category_id, amount, is_expense, description, payee, currency,
+           transacted_at, cleared, reconciled, CAST(base_amount AS TEXT) AS base_amount, external_id,
+           value_date, trans_create_at, deleted_at, split_group
+    FROM transactions"#;
+
+fn map_transaction_record(r: &SqliteRow) -> Result<TransactionRecord> {
+    Ok(TransactionRecord {
+        txn: map_transaction_row(r)?,
+        transacted_at: r.try_get("transacted_at")?,
+        is_expense: r.try_get::<i64, _>("is_expense")? != 0,
+        base_amount: r.try_get::<Option<String>, _>("base_amount")?
+            .and_then(|s| s.trim().parse::<Decimal>().ok())
+            .map(Money),
+        external_id: r.try_get("external_id")?,
+        value_date: r.try_get::<Option<String>, _>("value_date")?.map(|d| parse_date_any(&d)),
+        created_at: r.try_get("trans_create_at")?,
+        deleted_at: r.try_get("deleted_at")?,
+        split_group: r.try_get("split_group")?,
+    })
+}
+
+fn map_recurring_row(r: &SqliteRow) -> Result<RecurringTransaction> {
+    Ok(RecurringTransaction {
+        recurring_id: r.try_get("recurring_id")?,
+        account_id: r.try_get("account_id")?,
+        amount: parse_decimal(&r.try_get::<String, _>("amount")?),
+        currency: r.try_get("currency")?,
+        category_id: r.try_get("category_id")?,
+        description: r.try_get("description")?,
+        recurrence_rule: r.try_get("recurrence_rule")?,
+        next_run_date: parse_datetime_any(&r.try_get::<String, _>("next_run_date")?),
+    })
+}
+
+fn map_budget_row(r: &SqliteRow) -> Result<Budget> {
+    Ok(Budget {
+        budget_id: r.try_get("budget_id")?,
+        account_id: r.try_get("account_id")?,
+        category_id: r.try_get("category_id")?,
+        period: r.try_get("period")?,
+        amount: parse_decimal(&r.try_get::<String, _>("amount")?),
+        currency: r.try_get("currency")?,
+        start_date: parse_datetime_any(&r.try_get::<String, _>("start_date")?),
+    })
+}
+
+// Amounts may have been stored as numbers by older versions
+const GOAL_RECORD_SQL: &str = "SELECT goal_id, account_id, goal_name, CAST(target_amount AS TEXT) AS target_amount, CAST(current_amount AS TEXT) AS current_amount, deadline FROM savings_goals";
+
+fn map_goal_row(r: &SqliteRow) -> Result<SavingsGoal> {
+    Ok(SavingsGoal {
+        goal_id: r.try_get("goal_id")?,
+        account_id: r.try_get("account_id")?,
+        goal_name: r.try_get("goal_name")?,
+        target_amount: parse_decimal(&r.try_get::<String, _>("target_amount")?),
+        current_amount: parse_decimal(&r.try_get::<String, _>("current_amount")?),
+        deadline: r.try_get("deadline")?,
+    })
+}
+
+fn map_rule_row(r: &SqliteRow) -> Result<Rule> {
+    let amount = |s: Option<String>| s.and_then(|s| s.trim().parse::<Decimal>().ok());
+    Ok(Rule {
+        id: r.try_get("rule_id")?,
+        name: r.try_get("rule_name")?,
+        position: r.try_get("position")?,
+        enabled: r.try_get::<i64, _>("enabled")? != 0,
+        field: MatchField::parse(&r.try_get::<String, _>("match_field")?),
+        kind: MatchKind::parse(&r.try_get::<String, _>("match_kind")?),
+        pattern: r.try_get("pattern")?,
+        min_amount: amount(r.try_get("min_amount")?),
+        max_amount: amount(r.try_get("max_amount")?),
+        account_id: r.try_get("account_id")?,
+        set_category_id: r.try_get("set_category_id")?,
+        set_payee: r.try_get("set_payee")?,
+        set_memo: r.try_get("set_memo")?,
+        add_tags: rules::split_tags(&r.try_get::<Option<String>, _>("add_tags")?.unwrap_or_default()),
+    })
+}
+
+fn map_close_row(r: &SqliteRow) -> Result<YearEndClose> {
+    Ok(YearEndClose {
+        account_id: r.try_get("account_id")?,
+        year: r.try_get("close_year")?,
+        balance: r.try_get::<String, _>("balance")?.parse()?,
+        closed_at: r.try_get("closed_at")?,
+    })
+}
+
+// `Client::balance_as_of` on a connection the caller holds
+async fn balance_as_of_exec(conn: &mut SqliteConnection, account_id: i64, date: NaiveDate) -> Result<Decimal> {
+    let rows = sqlx::query("SELECT amount, is_expense FROM transactions WHERE account_id = ? AND deleted_at IS NULL AND date(transacted_at) <= date(?)")
+        .bind(account_id)
+        .bind(date.format("%Y-%m-%d").to_string())
+        .fetch_all(&mut *conn).await?;
+    let mut total = Decimal::ZERO;
+    for r in rows {
+        total += check::signed_amount(&r.try_get::<String, _>("amount")?, r.try_get("is_expense")?).unwrap_or_default();
+    }
+    Ok(total)
+}
+
+// Replace the lock of an account (every account for None)
+async fn set_lock_exec(conn: &mut SqliteConnection, account_id: Option<i64>, date: Option<NaiveDate>) -> Result<()> {
+    sqlx::query("DELETE FROM period_locks WHERE account_id IS ?").bind(account_id).execute(&mut *conn).await?;
+    if let Some(date) = date {
+        sqlx::query("INSERT INTO period_locks (account_id, lock_date, lock_created_at) VALUES (?, ?, strftime('%Y-%m-%dT%H:%M:%SZ','now'))")
+            .bind(account_id).bind(date.format("%Y-%m-%d").to_string())
+            .execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+fn map_assertion_row(r: &SqliteRow) -> Result<BalanceAssertion> {
+    Ok(BalanceAssertion {
+        id: r.try_get("assertion_id")?,
+        account_id: r.try_get("account_id")?,
+        date: parse_date_any(&r.try_get::<String, _>("assert_date")?),
+        balance: parse_decimal(&r.try_get::<String, _>("balance")?),
+    })
+}
+
+fn map_category_type(s: &str) -> CategoryType {
+    if s.eq_ignore_ascii_case("income") { CategoryType::Income }
+    else if s.eq_ignore_ascii_case("transfer") { CategoryType::Transfer }
+    else { CategoryType::Expense }
+}
+
+fn sum_by_category(items: impl Iterator<Item = (String, Decimal)>) -> Vec<CategorySpendingDto> {
+    let mut totals: BTreeMap<String, Decimal> = BTreeMap::new();
+    for (category, amount) in items {
+        *totals.entry(category).or_default() += amount;
+    }
+    let mut out: Vec<CategorySpendingDto> = totals
+        .into_iter()
+        .map(|(category, total)| CategorySpendingDto { category, total_amount: Money(total) })
+        .collect();
+    out.sort_by_key(|c| std::cmp::Reverse(c.total_amount.0));
+    out
+}
+
+fn parse_datetime_any(s: &str) -> NaiveDateTime {
+    for fmt in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M:%SZ"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) { return dt; }
+    }
+    parse_date_any(s).and_hms_opt(0, 0, 0).unwrap()
+}
+
+// Dates at midnight go back as plain dates, the way the app writes them
+fn db_datetime(dt: NaiveDateTime) -> String {
+    if dt.time() == chrono::NaiveTime::MIN {
+        dt.format("%Y-%m-%d").to_string()
+    } else {
+        dt.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+fn parse_decimal(s: &str) -> Decimal {
+    s.trim().parse().unwrap_or(Decimal::ZERO)
+}
+
+fn parse_date_any(s: &str) -> NaiveDate {
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") { return d; }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S") { return dt.date(); }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%SZ") { return dt.date(); }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") { return dt.date(); }
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y/%m/%d") { return d; }
+    chrono::Utc::now().date_naive()
+}
+// Link tags to a transaction by name (case-insensitive), creating the missing ones
+async fn add_tags_exec(tx: &mut sqlx::Transaction<'_, Sqlite>, transaction_id: i64, tags: &[String]) -> Result<()> {
+    for name in tags {
+        let existing: Option<i64> = sqlx::query("SELECT tag_id FROM tags WHERE tag = ? COLLATE NOCASE ORDER BY tag_id LIMIT 1")
+            .bind(name)
+            .fetch_optional(&mut **tx).await?
+            .map(|r| r.try_get("tag_id")).transpose()?;
+        let tag_id = match existing {
+            Some(id) => id,
+            None => sqlx::query("INSERT INTO tags (tag) VALUES (?)").bind(name).execute(&mut **tx).await?.last_insert_rowid(),
+        };
+        sqlx::query("INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id) VALUES (?, ?)")
+            .bind(transaction_id)
+            .bind(tag_id)
+            .execute(&mut **tx).await?;
+    }
+    Ok(())
+}
+
+// The rows of `table` that belong to an undo target, as a WHERE condition
+fn target_filter(target: Target, table: &str) -> String {
+    match (target, table) {
+        (Target::Account(id), "transaction_tags") => format!("transaction_id IN (SELECT transaction_id FROM transactions WHERE account_id = {})", id),
+        (Target::Account(id), _) => format!("account_id = {}", id),
+        (Target::Transaction(id), "transactions" | "transaction_tags") => format!("transaction_id = {}", id),
+        (Target::Goal(id), "savings_goals") => format!("goal_id = {}", id),
+        _ => "0".into(),
+    }
+}
+
+async fn load_rows(conn: &mut SqliteConnection, target: Target) -> Result<Rows> {
+    let f = |table: &str| target_filter(target, table);
+    let accounts = sqlx::query(&format!("{} WHERE {}", ACCOUNT_RECORD_SQL, f("accounts")))
+        .fetch_all(&mut *conn).await?;
+    let transactions = sqlx::query(&format!("{} WHERE {} ORDER BY transaction_id", TRANSACTION_RECORD_SQL, f("transactions")))
+        .fetch_all(&mut *conn).await?;
+    let tags = sqlx::query(&format!("SELECT transaction_id, tag_id FROM transaction_tags WHERE {} ORDER BY transaction_id, tag_id", f("transaction_tags")))
+        .fetch_all(&mut *conn).await?;
+    let recurring = sqlx::query(&format!("SELECT * FROM recurring_transactions WHERE {} ORDER BY recurring_id", f("recurring_transactions")))
+        .fetch_all(&mut *conn).await?;
+    let budgets = sqlx::query(&format!("SELECT * FROM budgets WHERE {} ORDER BY budget_id", f("budgets")))
+        .fetch_all(&mut *conn).await?;
+    let goals = sqlx::query(&format!("{} WHERE {} ORDER BY goal_id", GOAL_RECORD_SQL, f("savings_goals")))
+        .fetch_all(&mut *conn).await?;
+    let rules = sqlx::query(&format!("SELECT * FROM rules WHERE {} ORDER BY rule_id", f("rules")))
+        .fetch_all(&mut *conn).await?;
+    let assertions = sqlx::query(&format!("SELECT assertion_id, account_id, assert_date, balance FROM balance_assertions WHERE {} ORDER BY assertion_id", f("balance_assertions")))
+        .fetch_all(&mut *conn).await?;
+
+    Ok(Rows {
+        accounts: accounts.iter().map(map_account_row).collect::<Result<_>>()?,
+        transactions: transactions.iter().map(map_transaction_record).collect::<Result<_>>()?,
+        transaction_tags: tags.iter()
+            .map(|r| Ok(TransactionTag { transaction_id: r.try_get("transaction_id")?, tag_id: r.try_get("tag_id")? }))
+            .collect::<Result<_>>()?,
+        recurring: recurring.iter().map(map_recurring_row).collect::<Result<_>>()?,
+        budgets: budgets.iter().map(map_budget_row).collect::<Result<_>>()?,
+        savings_goals: goals.iter().map(map_goal_row).collect::<Result<_>>()?,
+        rules: rules.iter().map(map_rule_row).collect::<Result<_>>()?,
+        balance_assertions: assertions.iter().map(map_assertion_row).collect::<Result<_>>()?,
+    })
+}
+
+// Write rows with their ids, overwriting the ones that exist. Balances are left to the
+// caller to recompute.
+async fn write_rows(conn: &mut SqliteConnection, rows: &Rows) -> Result<()> {
+    for a in &rows.accounts {
+        sqlx::query(
+            r#"
+            INSERT INTO accounts (account_id, account_name, account_type, balance, currency, account_created_at, credit_limit, deleted_at)
+            VALUES (?, ?, ?, '0', ?, ?, ?, ?)
+            ON CONFLICT(account_id) DO UPDATE SET account_name = excluded.account_name, account_type = excluded.account_type,
+                currency = excluded.currency, account_created_at = excluded.account_created_at, credit_limit = excluded.credit_limit,
+                deleted_at = excluded.deleted_at
+            "#
+        )
+        .bind(a.id).bind(&a.name).bind(a.r#type.as_str()).bind(&a.currency).bind(&a.created_at)
+        .bind(a.credit_limit.map(|l| l.0.to_string())).bind(&a.deleted_at)
+        .execute(&mut *conn).await?;
+    }
+    for r in &rows.transactions {
+        let t = &r.txn;
+        let amount_abs = t.amount.0.abs().to_string();
+        sqlx::query(
+            r#"
+            INSERT INTO transactions
+              (transaction_id, account_id, category_id, amount, base_amount, is_expense, description, payee,
+               currency, transacted_at, trans_create_at, external_id, cleared, reconciled, value_date, deleted_at, split_group)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(transaction_id) DO UPDATE SET account_id = excluded.account_id, category_id = excluded.category_id,
+                amount = excluded.amount, base_amount = excluded.base_amount, is_expense = excluded.is_expense,
+                description = excluded.description, payee = excluded.payee, currency = excluded.currency,
+                transacted_at = excluded.transacted_at, trans_create_at = excluded.trans_create_at,
+                external_id = excluded.external_id, cleared = excluded.cleared, reconciled = excluded.reconciled,
+                value_date = excluded.value_date, deleted_at = excluded.deleted_at, split_group = excluded.split_group
+            "#
+        )
+        .bind(t.id).bind(t.account_id).bind(t.category_id)
+        .bind(&amount_abs)
+        .bind(r.base_amount.map(|m| m.0.to_string()).unwrap_or_else(|| amount_abs.clone()))
+        .bind(r.is_expense as i64)
+        .bind(&t.memo).bind(&t.payee).bind(&t.currency)
+        .bind(&r.transacted_at)
+        .bind(&r.created_at).bind(&r.external_id)
+        .bind(t.cleared as i64).bind(t.reconciled as i64)
+        .bind(r.value_date.map(|d| d.format("%Y-%m-%d").to_string()))
+        .bind(&r.deleted_at).bind(r.split_group)
+        .execute(&mut *conn).await?;
+    }
+    // The tables below have no rows that depend on them, so REPLACE is safe
+    for l in &rows.transaction_tags {
+        sqlx::query("INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id) VALUES (?, ?)")
+            .bind(l.transaction_id).bind(l.tag_id).execute(&mut *conn).await?;
+    }
+    for r in &rows.recurring {
+        sqlx::query("INSERT OR REPLACE INTO recurring_transactions (recurring_id, account_id, amount, currency, category_id, description, recurrence_rule, next_run_date) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(r.recurring_id).bind(r.account_id).bind(r.amount.to_string()).bind(&r.currency)
+            .bind(r.category_id).bind(&r.description).bind(&r.recurrence_rule).bind(db_datetime(r.next_run_date))
+            .execute(&mut *conn).await?;
+    }
+    for r in &rows.budgets {
+        sqlx::query("INSERT OR REPLACE INTO budgets (budget_id, account_id, category_id, period, amount, currency, start_date) VALUES (?, ?, ?, ?, ?, ?, ?)")
+            .bind(r.budget_id).bind(r.account_id).bind(r.category_id).bind(&r.period)
+            .bind(r.amount.to_string()).bind(&r.currency).bind(db_datetime(r.start_date))
+            .execute(&mut *conn).await?;
+    }
+    for g in &rows.savings_goals {
+        sqlx::query("INSERT OR REPLACE INTO savings_goals (goal_id, account_id, goal_name, target_amount, current_amount, deadline) VALUES (?, ?, ?, ?, ?, ?)")
+            .bind(g.goal_id).bind(g.account_id).bind(&g.goal_name)
+            .bind(g.target_amount.to_string()).bind(g.current_amount.to_string()).bind(&g.deadline)
+            .execute(&mut *conn).await?;
+    }
+    for r in &rows.rules {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO rules (rule_id, rule_name, position, enabled, match_field, match_kind, pattern, min_amount,
+                               max_amount, account_id, set_category_id, set_payee, set_memo, add_tags)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(r.id).bind(&r.name).bind(r.position).bind(r.enabled)
+        .bind(r.field.as_str()).bind(r.kind.as_str()).bind(&r.pattern)
+        .bind(r.min_amount.map(|d| d.to_string())).bind(r.max_amount.map(|d| d.to_string()))
+        .bind(r.account_id).bind(r.set_category_id).bind(&r.set_payee).bind(&r.set_memo)
+        .bind(if r.add_tags.is_empty() { None } else { Some(r.add_tags.join(", ")) })
+        .execute(&mut *conn).await?;
+    }
+    for a in &rows.balance_assertions {
+        sqlx::query("INSERT OR REPLACE INTO balance_assertions (assertion_id, account_id, assert_date, balance, assertion_created_at) VALUES (?, ?, ?, ?, strftime('%Y-%m-%dT%H:%M:%SZ','now'))")
+            .bind(a.id).bind(a.account_id).bind(a.date.format("%Y-%m-%d").to_string()).bind(a.balance.to_string())
+            .execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+// Delete the rows of `old` that `new` does not have; dependants go first
+async fn delete_missing_rows(conn: &mut SqliteConnection, old: &Rows, new: &Rows) -> Result<()> {
+    for l in &old.transaction_tags {
+        if !new.transaction_tags.iter().any(|n| n.transaction_id == l.transaction_id && n.tag_id == l.tag_id) {
+            sqlx::query("DELETE FROM transaction_tags WHERE transaction_id = ? AND tag_id = ?")
+                .bind(l.transaction_id).bind(l.tag_id).execute(&mut *conn).await?;
+        }
+    }
+    let mut gone: Vec<(&str, &str, i64)> = Vec::new();
+    gone.extend(old.transactions.iter().map(|r| r.txn.id).filter(|id| !new.transactions.iter().any(|n| n.txn.id == *id)).map(|id| ("transactions", "transaction_id", id)));
+    gone.extend(old.recurring.iter().map(|r| r.recurring_id).filter(|id| !new.recurring.iter().any(|n| n.recurring_id == *id)).map(|id| ("recurring_transactions", "recurring_id", id)));
+    gone.extend(old.budgets.iter().map(|r| r.budget_id).filter(|id| !new.budgets.iter().any(|n| n.budget_id == *id)).map(|id| ("budgets", "budget_id", id)));
+    gone.extend(old.savings_goals.iter().map(|r| r.goal_id).filter(|id| !new.savings_goals.iter().any(|n| n.goal_id == *id)).map(|id| ("savings_goals", "goal_id", id)));
+    gone.extend(old.rules.iter().map(|r| r.id).filter(|id| !new.rules.iter().any(|n| n.id == *id)).map(|id| ("rules", "rule_id", id)));
+    gone.extend(old.balance_assertions.iter().map(|r| r.id).filter(|id| !new.balance_assertions.iter().any(|n| n.id == *id)).map(|id| ("balance_assertions", "assertion_id", id)));
+    gone.extend(old.accounts.iter().map(|r| r.id).filter(|id| !new.accounts.iter().any(|n| n.id == *id)).map(|id| ("accounts", "account_id", id)));
+    for (table, key, id) in gone {
+        sqlx::query(&format!("DELETE FROM {} WHERE {} = ?", table, key)).bind(id).execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A migrated in-memory database with the categories imports fall back to; one connection,
+    // since each holds its own copy
+    async fn client() -> Client {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        migrate::run_migrations(&pool).await.unwrap();
+        sqlx::query("INSERT INTO categories (category_id, category_name, category_type, icon) VALUES (1, 'Groceries', 'EXPENSE', ''), (2, 'Uncategorized', 'EXPENSE', ''), (3, 'Other Income', 'INCOME', '')")
+            .execute(&pool).await.unwrap();
+        Client::from_pool(pool)
+    }
+
+    async fn account(c: &Client, opening: &str) -> i64 {
+        let req = CreateAccountReq {
+            name: "Chequing".into(),
+            r#type: AccountType::Checking,
+            currency: "CAD".into(),
+            opening_balance: Money(opening.parse().unwrap()),
+            credit_limit: None,
+        };
+        c.create_account(&req).await.unwrap().id
+    }
+
+    fn txn(account_id: i64, amount: &str, at: &str) -> CreateTxnReq {
+        let amount: Decimal = amount.parse().unwrap();
+        CreateTxnReq {
+            account_id,
+            category_id: 1,
+            amount: Money(amount),
+            base_amount: Money(amount.abs()),
+            is_expense: amount.is_sign_negative(),
+            description: None,
+            payee: Some("Corner Grocer".into()),
+            currency: "CAD".into(),
+            transacted_at: NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M:%S").unwrap(),
+        }
+    }
+
+    // The columns a snapshot has to bring back as they were
+    async fn stored(c: &Client, id: i64) -> (String, i64, String, Option<String>, Option<String>) {
+        sqlx::query_as("SELECT transacted_at, is_expense, amount, payee, deleted_at FROM transactions WHERE transaction_id = ?")
+            .bind(id)
+            .fetch_one(&c.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn undo_and_redo_restore_the_stored_row() {
+        let c = client().await;
+        let aid = account(&c, "100").await;
+        let id = c.create_transaction(&txn(aid, "-12.50", "2025-03-03 14:05:09")).await.unwrap().id;
+        let target = Target::Transaction(id);
+
+        let before = c.snapshot(target).await.unwrap();
+        let original = stored(&c, id).await;
+        c.update_transaction(id, &txn(aid, "-15", "2025-03-04 00:00:00")).await.unwrap();
+        let after = c.snapshot(target).await.unwrap();
+        let edited = stored(&c, id).await;
+
+        c.revert(target, &after, &before).await.unwrap();
+        assert_eq!(stored(&c, id).await, original);
+        c.revert(target, &before, &after).await.unwrap();
+        assert_eq!(stored(&c, id).await, edited);
+    }
+
+    #[tokio::test]
+    async fn undoing_a_delete_keeps_the_time_and_side() {
+        let c = client().await;
+        let aid = account(&c, "100").await;
+        // Zero is neither sign, so the side can only come from the stored flag
+        let id = c.create_transaction(&txn(aid, "-1", "2025-03-03 14:05:09")).await.unwrap().id;
+        sqlx::query("UPDATE transactions SET amount = '0' WHERE transaction_id = ?").bind(id).execute(&c.pool).await.unwrap();
+        let opening: i64 = sqlx::query_scalar("SELECT transaction_id FROM transactions WHERE account_id = ? AND description = 'Opening Balance'")
+            .bind(aid)
+            .fetch_one(&c.pool).await.unwrap();
+
+        for id in [id, opening] {
+            let target = Target::Transaction(id);
+            let before = c.snapshot(target).await.unwrap();
+            let original = stored(&c, id).await;
+            c.delete_transaction(id).await.unwrap();
+            let after = c.snapshot(target).await.unwrap();
+            c.revert(target, &after, &before).await.unwrap();
+            assert_eq!(stored(&c, id).await, original);
+        }
+        assert!(stored(&c, opening).await.0.ends_with('Z'));
+        assert_eq!(stored(&c, id).await.1, 1);
+        let balance: String = sqlx::query_scalar("SELECT balance FROM accounts WHERE account_id = ?").bind(aid).fetch_one(&c.pool).await.unwrap();
+        assert_eq!(balance.parse::<Decimal>().unwrap(), Decimal::from(100));
+    }
+
+    #[tokio::test]
+    async fn clearing_a_locked_row_is_refused_unless_overridden() {
+        let c = client().await;
+        let aid = account(&c, "0").await;
+        let id = c.create_transaction(&txn(aid, "-20", "2024-06-01 09:00:00")).await.unwrap().id;
+        c.set_cleared(id, true).await.unwrap();
+        sqlx::query("UPDATE transactions SET reconciled = 1 WHERE transaction_id = ?").bind(id).execute(&c.pool).await.unwrap();
+        c.set_period_lock(Some(aid), Some(NaiveDate::from_ymd_opt(2024, 12, 31).unwrap())).await.unwrap();
+
+        let err = c.set_cleared(id, false).await.unwrap_err();
+        assert!(lock::is_locked(&err));
+        let flags: (bool, bool) = sqlx::query_as("SELECT cleared, reconciled FROM transactions WHERE transaction_id = ?").bind(id).fetch_one(&c.pool).await.unwrap();
+        assert_eq!(flags, (true, true));
+
+        c.clone().with_lock_override(true).set_cleared(id, false).await.unwrap();
+        let flags: (bool, bool) = sqlx::query_as("SELECT cleared, reconciled FROM transactions WHERE transaction_id = ?").bind(id).fetch_one(&c.pool).await.unwrap();
+        assert_eq!(flags, (false, false));
+    }
+
+    #[tokio::test]
+    async fn closing_a_year_snapshots_the_balance_at_its_end() {
+        let c = client().await;
+        let aid = account(&c, "0").await;
+        c.create_transaction(&txn(aid, "250", "2024-03-01 09:00:00")).await.unwrap();
+        c.create_transaction(&txn(aid, "-40.50", "2024-12-31 23:00:00")).await.unwrap();
+        c.create_transaction(&txn(aid, "-99", "2025-01-01 00:00:00")).await.unwrap();
+
+        let closes = c.close_year(2024).await.unwrap();
+        assert_eq!(closes.len(), 1);
+        assert_eq!((closes[0].account_id, closes[0].balance), (aid, "209.50".parse().unwrap()));
+        assert_eq!(c.list_year_end_closes().await.unwrap()[0].balance, closes[0].balance);
+        assert!(c.create_transaction(&txn(aid, "-1", "2024-12-31 12:00:00")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn importing_into_a_trashed_account_is_refused() {
+        let c = client().await;
+        let aid = account(&c, "0").await;
+        c.delete_account(aid).await.unwrap();
+        let row = ImportedTxn {
+            date: NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+            value_date: None,
+            amount: "-12.50".parse().unwrap(),
+            payee: Some("Corner Grocer".into()),
+            memo: None,
+            external_id: None,
+            category: None,
+            cleared: false,
+            reconciled: false,
+            split: None,
+        };
+
+        let err = c.import_transactions(aid, None, &[row]).await.unwrap_err();
+        assert!(err.to_string().contains("not found"));
+        let n: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM transactions WHERE account_id = ?").bind(aid).fetch_one(&c.pool).await.unwrap();
+        assert_eq!(n, 0);
+    }
+
+    #[tokio::test]
+    async fn repairs_leave_nothing_for_the_next_check() {
+        let c = client().await;
+        let acc = account(&c, "100").await;
+        let a = c.create_transaction(&txn(acc, "-5.00", "2025-01-05 10:00:00")).await.unwrap().id;
+        let b = c.create_transaction(&txn(acc, "-20.00", "2025-01-06 09:00:00")).await.unwrap().id;
+        let d = c.create_transaction(&txn(acc, "-1.00", "2025-01-07 09:00:00")).await.unwrap().id;
+
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&c.pool).await.unwrap();
+        for sql in [
+            format!("UPDATE transactions SET amount = '-5.00', is_expense = 0 WHERE transaction_id = {}", a),
+            format!("UPDATE transactions SET transacted_at = '2025/01/06' WHERE transaction_id = {}", b),
+            format!("UPDATE transactions SET category_id = 3 WHERE transaction_id = {}", d),
+            format!("INSERT INTO transaction_tags (transaction_id, tag_id) VALUES ({}, 77)", a),
+            format!("UPDATE accounts SET balance = '0' WHERE account_id = {}", acc),
+        ] {
+            sqlx::query(&sql).execute(&c.pool).await.unwrap();
+        }
+
+        let report = c.check_ledger().await.unwrap();
+        assert_eq!((report.count(check::Severity::Error), report.repairable()), (4, 5));
+        assert_eq!(c.repair_ledger(&report.issues).await.unwrap(), 5);
+
+        let report = c.check_ledger().await.unwrap();
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+        assert_eq!(stored(&c, a).await.1, 1);
+        assert_eq!(stored(&c, b).await.0, "2025-01-06");
+        let balance: String = sqlx::query_scalar("SELECT balance FROM accounts WHERE account_id = ?").bind(acc).fetch_one(&c.pool).await.unwrap();
+        assert_eq!(balance.parse::<Decimal>().unwrap(), Decimal::from(74));
+    }
+
+    #[tokio::test]
+    async fn a_row_without_its_account_goes_to_the_trash() {
+        let c = client().await;
+        let aid = account(&c, "0").await;
+        let id = c.create_transaction(&txn(aid, "-20", "2025-01-05 09:00:00")).await.unwrap().id;
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&c.pool).await.unwrap();
+        sqlx::query("INSERT INTO tags (tag_id, tag) VALUES (1, 'trip')").execute(&c.pool).await.unwrap();
+        sqlx::query("INSERT INTO transaction_tags (transaction_id, tag_id) VALUES (?, 1)").bind(id).execute(&c.pool).await.unwrap();
+        sqlx::query("UPDATE transactions SET account_id = 99 WHERE transaction_id = ?").bind(id).execute(&c.pool).await.unwrap();
+
+        let report = c.check_ledger().await.unwrap();
+        assert!(report.issues.iter().any(|i| i.repair == Some(check::Repair::DeleteTransaction(id))));
+        c.repair_ledger(&report.issues).await.unwrap();
+
+        // Its tag link went with it rather than being reported as dangling
+        assert!(c.check_ledger().await.unwrap().issues.is_empty());
+        assert!(stored(&c, id).await.4.is_some());
+        let trash = c.list_trash().await.unwrap();
+        assert_eq!((trash[0].id, trash[0].account.as_str()), (id, "missing account 99"));
+        let err = c.restore_from_trash(TrashKind::Transaction, id).await.unwrap_err();
+        assert_eq!(err.to_string(), "its account no longer exists; purge it instead");
+        c.purge_from_trash(TrashKind::Transaction, id).await.unwrap();
+        assert!(c.list_trash().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn repairs_in_a_locked_period_need_the_override() {
+        let c = client().await;
+        let aid = account(&c, "0").await;
+        let id = c.create_transaction(&txn(aid, "-20", "2024-06-01 09:00:00")).await.unwrap().id;
+        let later = c.create_transaction(&txn(aid, "-5", "2025-02-01 09:00:00")).await.unwrap().id;
+        c.set_period_lock(None, Some(NaiveDate::from_ymd_opt(2024, 12, 31).unwrap())).await.unwrap();
+        sqlx::query("UPDATE transactions SET amount = '-20', is_expense = 0 WHERE transaction_id = ?").bind(id).execute(&c.pool).await.unwrap();
+        // Moving an open row into the locked year is refused as well
+        sqlx::query("UPDATE transactions SET transacted_at = '2024/12/30' WHERE transaction_id = ?").bind(later).execute(&c.pool).await.unwrap();
+
+        let report = c.check_ledger().await.unwrap();
+        let dated = check::Issue {
+            repair: report.issues.iter().find_map(|i| i.repair.clone().filter(|r| matches!(r, check::Repair::SetDate { .. }))),
+            ..report.issues[0].clone()
+        };
+        assert!(lock::is_locked(&c.repair_ledger(&report.issues).await.unwrap_err()));
+        assert!(lock::is_locked(&c.repair_ledger(std::slice::from_ref(&dated)).await.unwrap_err()));
+        assert_eq!(stored(&c, id).await.2, "-20");
+
+        c.clone().with_lock_override(true).repair_ledger(&report.issues).await.unwrap();
+        assert_eq!(stored(&c, id).await.2, "20");
+        assert_eq!(stored(&c, later).await.0, "2024-12-30");
+    }
+
+    #[tokio::test]
+    async fn rule_changes_in_a_locked_period_need_the_override() {
+        let c = client().await;
+        let aid = account(&c, "0").await;
+        let id = c.create_transaction(&txn(aid, "-20", "2024-06-01 09:00:00")).await.unwrap().id;
+        c.save_rule(&Rule { name: "grocer".into(), enabled: true, pattern: "grocer".into(), set_payee: Some("Grocer".into()), ..Default::default() }).await.unwrap();
+        let changes = c.preview_rules(Some(aid)).await.unwrap();
+        assert_eq!(changes.len(), 1);
+
+        // Locked after the preview was taken
+        c.set_period_lock(None, Some(NaiveDate::from_ymd_opt(2024, 12, 31).unwrap())).await.unwrap();
+        assert!(c.preview_rules(Some(aid)).await.unwrap().is_empty());
+        assert!(lock::is_locked(&c.apply_rule_changes(&changes).await.unwrap_err()));
+        assert_eq!(stored(&c, id).await.3.as_deref(), Some("Corner Grocer"));
+
+        assert_eq!(c.clone().with_lock_override(true).apply_rule_changes(&changes).await.unwrap(), 1);
+        assert_eq!(stored(&c, id).await.3.as_deref(), Some("Grocer"));
+    }
+
+    // Every row's balance is what the account holds once it and everything older is counted
+    async fn assert_balances(c: &Client, account_id: i64, rows: &[LedgerRow]) {
+        for r in rows {
+            assert_eq!(r.balance, c.balance_through(account_id, &r.cursor).await.unwrap(), "row {}", r.txn.id);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_ledger_pages_the_same_in_both_directions() {
+        use crate::cli::state::LEDGER_PAGE;
+        let c = client().await;
+        let acc = account(&c, "100").await;
+        // Three rows a day, all at the same minute so the id breaks the tie
+        sqlx::query(
+            "WITH RECURSIVE n(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM n WHERE i < ? - 1)
+             INSERT INTO transactions (account_id, category_id, amount, base_amount, is_expense, payee, currency, transacted_at)
+             SELECT ?, 1, printf('%d.%02d', i % 40, i % 100), 0, i % 3 <> 0, 'Corner Grocer', 'CAD',
+                    date('2025-01-01', '+' || (i / 3) || ' days') || ' 09:00:00' FROM n"
+        )
+        .bind(LEDGER_PAGE as i64 * 2 + 50)
+        .bind(acc)
+        .execute(&c.pool).await.unwrap();
+        let total = LEDGER_PAGE * 2 + 51;
+
+        // Newest to oldest, a page below the last row at a time
+        let mut down = c.ledger_page(acc, PageFrom::Newest, LEDGER_PAGE).await.unwrap();
+        loop {
+            let page = c.ledger_page(acc, PageFrom::Below(down.last().unwrap()), LEDGER_PAGE).await.unwrap();
+            let short = page.len() < LEDGER_PAGE;
+            down.extend(page);
+            if short { break; }
+        }
+        assert_eq!(down.len(), total);
+        assert!(down.windows(2).all(|w| (&w[0].cursor.transacted_at, w[0].cursor.id) > (&w[1].cursor.transacted_at, w[1].cursor.id)));
+        assert_balances(&c, acc, &down).await;
+
+        // Oldest to newest, a page above the first row at a time, lands on the same rows
+        let mut up = c.ledger_page(acc, PageFrom::Oldest, LEDGER_PAGE).await.unwrap();
+        assert_eq!(up.len(), LEDGER_PAGE);
+        assert_balances(&c, acc, &up).await;
+        loop {
+            let page = c.ledger_page(acc, PageFrom::Above(&up[0]), LEDGER_PAGE).await.unwrap();
+            assert_balances(&c, acc, &page).await;
+            let short = page.len() < LEDGER_PAGE;
+            up.splice(0..0, page);
+            if short { break; }
+        }
+        let ids = |rows: &[LedgerRow]| rows.iter().map(|r| (r.txn.id, r.balance)).collect::<Vec<_>>();
+        assert_eq!(ids(&up), ids(&down));
+
+        // Jumping to a day with three rows starts at the newest of them
+        let day = NaiveDate::from_ymd_opt(2025, 2, 10).unwrap();
+        let at = c.ledger_page(acc, PageFrom::Date(day), LEDGER_PAGE).await.unwrap();
+        assert_eq!(at.len(), LEDGER_PAGE);
+        assert_balances(&c, acc, &at).await;
+        assert!(at[..3].iter().all(|r| r.txn.txn_date == day));
+        assert!(at[0].txn.id > at[1].txn.id && at[1].txn.id > at[2].txn.id);
+        assert!(at[3].txn.txn_date < day);
+        let start = down.iter().position(|r| r.txn.id == at[0].txn.id).unwrap();
+        assert_eq!(ids(&at), ids(&down[start..start + LEDGER_PAGE]));
+        let above = c.ledger_page(acc, PageFrom::Above(&at[0]), LEDGER_PAGE).await.unwrap();
+        assert_eq!(ids(&above), ids(&down[start.saturating_sub(LEDGER_PAGE)..start]));
+    }
+
+    #[tokio::test]
+    async fn the_forecast_warns_before_an_account_runs_short() {
+        let c = client().await;
+        let today = chrono::Utc::now().date_naive();
+        let day = |n: i64| today + chrono::Duration::days(n);
+        let chequing = account(&c, "100").await;
+        let card = c.create_account(&CreateAccountReq {
+            name: "Visa".into(),
+            r#type: AccountType::Credit,
+            currency: "CAD".into(),
+            opening_balance: Money(Decimal::ZERO),
+            credit_limit: Some(Money(Decimal::from(500))),
+        }).await.unwrap().id;
+        // Already recorded, but dated in two days: today's balance doesn't have it yet
+        let at = format!("{} 09:00:00", day(2).format("%Y-%m-%d"));
+        c.create_transaction(&txn(chequing, "-30", &at)).await.unwrap();
+        sqlx::query("INSERT INTO recurring_transactions (account_id, amount, category_id, recurrence_rule, next_run_date) VALUES (?, '150', 1, 'monthly', ?), (?, '200', 1, 'weekly', ?)")
+            .bind(chequing).bind(day(5).format("%Y-%m-%d").to_string())
+            .bind(card).bind(day(1).format("%Y-%m-%d").to_string())
+            .execute(&c.pool).await.unwrap();
+
+        let forecast = c.get_forecast(30).await.unwrap();
+        let f = forecast.iter().find(|f| f.account_id == chequing).unwrap();
+        let on = |n: i64| f.projection.iter().find(|p| p.date == day(n)).unwrap().balance.0;
+        assert_eq!((on(0), on(1), on(2), on(4), on(5)), (Decimal::from(100), Decimal::from(100), Decimal::from(70), Decimal::from(70), Decimal::from(-80)));
+        assert_eq!(f.history.last().map(|p| (p.date, p.balance.0)), Some((today, Decimal::from(100))));
+        let alert = f.alert.as_ref().unwrap();
+        assert_eq!((alert.date, alert.balance.0, alert.reason.as_str()), (day(5), Decimal::from(-80), "goes negative"));
+
+        // A card owing money is normal; passing the limit is not
+        let f = forecast.iter().find(|f| f.account_id == card).unwrap();
+        let alert = f.alert.as_ref().unwrap();
+        assert_eq!((alert.date, alert.balance.0, alert.reason.as_str()), (day(15), Decimal::from(-600), "exceeds credit limit"));
+    }
+
+    #[tokio::test]
+    async fn the_forecast_backs_out_future_rows_the_way_the_balance_counts_them() {
+        let c = client().await;
+        let aid = account(&c, "100").await;
+        let tomorrow = chrono::Utc::now().date_naive() + chrono::Duration::days(1);
+        let id = c.create_transaction(&txn(aid, "20", &format!("{} 09:00:00", tomorrow.format("%Y-%m-%d")))).await.unwrap().id;
+        // An older row that kept its sign in the amount: the balance counts it as money out
+        sqlx::query("UPDATE transactions SET amount = '-20' WHERE transaction_id = ?").bind(id).execute(&c.pool).await.unwrap();
+        c.recompute_balance_exec(&mut c.pool.acquire().await.unwrap(), aid).await.unwrap();
+
+        let forecast = c.get_forecast(30).await.unwrap();
+        let points: Vec<_> = forecast[0].projection.iter().take(2).map(|p| p.balance.0).collect();
+        assert_eq!(points, [Decimal::from(100), Decimal::from(80)]);
+    }
+
+    #[tokio::test]
+    async fn cash_flow_counts_income_and_spending_in_the_base_currency() {
+        let c = client().await;
+        sqlx::query("INSERT INTO categories (category_id, category_name, category_type, icon) VALUES (4, 'Transfer', 'TRANSFER', '')")
+            .execute(&c.pool).await.unwrap();
+        let aid = account(&c, "1000").await;
+        let at = format!("{} 09:00:00", chrono::Utc::now().date_naive().format("%Y-%m-%d"));
+        c.create_transaction(&CreateTxnReq { category_id: 3, ..txn(aid, "500", &at) }).await.unwrap();
+        c.create_transaction(&txn(aid, "-120", &at)).await.unwrap();
+        let usd = c.create_transaction(&CreateTxnReq { currency: "USD".into(), ..txn(aid, "-100", &at) }).await.unwrap().id;
+        sqlx::query("UPDATE transactions SET base_amount = 135.0 WHERE transaction_id = ?").bind(usd).execute(&c.pool).await.unwrap();
+        c.create_transaction(&CreateTxnReq { category_id: 4, ..txn(aid, "-300", &at) }).await.unwrap();
+
+        let flow = c.get_cash_flow(2).await.unwrap();
+        assert_eq!(flow.len(), 2);
+        assert!(flow[0].income.0.is_zero() && flow[0].expenses.0.is_zero());
+        // The opening balance and the transfer are left out; the USD row counts at its base amount
+        let month = &flow[1];
+        assert_eq!((month.income.0, month.expenses.0, month.net.0), (Decimal::from(500), Decimal::from(255), Decimal::from(245)));
+        assert_eq!(month.savings_rate, Some(Decimal::new(490, 1)));
+    }
+
+    #[tokio::test]
+    async fn payees_are_grouped_ignoring_case() {
+        let c = client().await;
+        let aid = account(&c, "1000").await;
+        let pay = |payee: &str, amount: &str, at: &str| CreateTxnReq { payee: Some(payee.into()), ..txn(aid, amount, at) };
+        c.create_transaction(&pay("Corner Grocer", "-40", "2025-03-02 09:00:00")).await.unwrap();
+        c.create_transaction(&pay("CORNER GROCER ", "-20", "2025-03-09 09:00:00")).await.unwrap();
+        let usd = c.create_transaction(&CreateTxnReq { currency: "USD".into(), ..pay("corner grocer", "-10", "2025-03-16 09:00:00") }).await.unwrap().id;
+        sqlx::query("UPDATE transactions SET base_amount = 13.5 WHERE transaction_id = ?").bind(usd).execute(&c.pool).await.unwrap();
+        c.create_transaction(&pay("Bakery", "-60", "2025-03-20 09:00:00")).await.unwrap();
+        c.create_transaction(&pay("Bakery", "25", "2025-03-21 09:00:00")).await.unwrap();
+        c.create_transaction(&pay("Bakery", "-99", "2025-04-01 09:00:00")).await.unwrap();
+
+        let start = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let report = c.get_payee_report(start, NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(), None).await.unwrap();
+        let rows: Vec<_> = report.iter().map(|p| (p.payee.as_str(), p.total_amount.0, p.txn_count, p.average_amount.0)).collect();
+        assert_eq!(rows, [
+            ("Corner Grocer", Decimal::new(735, 1), 3, Decimal::new(2450, 2)),
+            ("Bakery", Decimal::from(60), 1, Decimal::from(60)),
+        ]);
+        assert_eq!(c.get_payee_report(start, NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(), Some(1)).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn an_amount_search_reads_past_the_first_page() {
+        let c = client().await;
+        let acc = account(&c, "0").await;
+        let old = c.create_transaction(&txn(acc, "-7.00", "2024-01-01 09:00:00")).await.unwrap().id;
+        sqlx::query(
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < ?)
+             INSERT INTO transactions (account_id, category_id, amount, base_amount, is_expense, payee, currency, transacted_at)
+             SELECT ?, 1, '1.00', 1.0, 1, 'Corner Grocer', 'CAD', printf('2025-01-01 %02d:%02d:00', i / 60, i % 60) FROM n"
+        )
+        .bind(search::PAGE_ROWS as i64 + 100)
+        .bind(acc)
+        .execute(&c.pool).await.unwrap();
+
+        let hits = c.search_transactions(&search::parse("amount:7").unwrap(), 50).await.unwrap();
+        assert_eq!(hits.iter().map(|h| h.txn.id).collect::<Vec<_>>(), [old]);
+        assert_eq!(c.search_transactions(&search::parse("amount:1").unwrap(), 10).await.unwrap().len(), 10);
+        assert_eq!(c.search_transactions(&search::parse("grocer").unwrap(), 5).await.unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn an_account_search_takes_wildcards_literally() {
+        let c = client().await;
+        for name in ["Cash_2", "Cash 2", "100% Savings"] {
+            let req = CreateAccountReq {
+                name: name.into(),
+                r#type: AccountType::Checking,
+                currency: "CAD".into(),
+                opening_balance: Money(Decimal::ONE),
+                credit_limit: None,
+            };
+            c.create_account(&req).await.unwrap();
+        }
+        let accounts = |text: &str| {
+            let q = search::parse(text).unwrap();
+            let c = c.clone();
+            async move { c.search_transactions(&q, 10).await.unwrap().into_iter().map(|h| h.account).collect::<Vec<_>>() }
+        };
+        assert_eq!(accounts("account:cash_").await, ["Cash_2"]);
+        assert_eq!(accounts("account:100%").await, ["100% Savings"]);
+        assert_eq!(accounts("account:%").await, Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn qif_splits_survive_an_export_and_import() {
+        let c = client().await;
+        let (from, to) = (account(&c, "0").await, account(&c, "0").await);
+        let parsed = crate::import::qif::parse(include_str!("../../tests/fixtures/import/quicken.qif")).unwrap();
+        c.import_transactions(from, None, &parsed.txns).await.unwrap();
+
+        let categories = c.list_categories().await.unwrap();
+        let accounts = c.list_accounts().await.unwrap();
+        let qif = crate::export::qif::write_account(&accounts[0], &c.list_transactions(from, Some(-1), None).await.unwrap(), &categories, &c.split_groups(from).await.unwrap());
+        assert!(qif.contains("T-120.00\nPSuperstore\nSGroceries\nEWeekly shop\n$-80.00\nSHousehold\nEBulbs\n$-25.00\n"), "{}", qif);
+        c.import_transactions(to, None, &crate::import::qif::parse(&qif).unwrap().txns).await.unwrap();
+
+        // Same rows, and the same rows grouped together
+        let rows = |id: i64| {
+            let c = c.clone();
+            async move {
+                let groups = c.split_groups(id).await.unwrap();
+                let mut txns = c.list_transactions(id, Some(-1), None).await.unwrap();
+                txns.sort_by_key(|t| t.id);
+                let first = |t: &TransactionDto| txns.iter().position(|o| groups.get(&o.id).is_some_and(|g| Some(g) == groups.get(&t.id)));
+                txns.iter()
+                    .map(|t| (t.txn_date, t.amount.0, t.category_id, t.memo.clone(), t.payee.clone(), t.cleared, t.reconciled, first(t)))
+                    .collect::<Vec<_>>()
+            }
+        };
+        let imported = rows(from).await;
+        assert_eq!(imported.iter().filter(|r| r.7.is_some()).count(), 5);
+        assert_eq!(rows(to).await, imported);
+    }
+}
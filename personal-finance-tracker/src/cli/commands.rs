@@ -0,0 +1,812 @@
+//! Non-interactive subcommands
+//! - `import csv <file> --account <id|name> [options]`
+//! - `import ofx <file> --account <id|name>` (also `qfx`)
+//! - `import qif <file> --account <id|name>`
+//! - `import camt <file> --account <id|name>` / `import mt940 <file> --account <id|name>`
+//! - `export qif --account <id|name> [--output <file>]`
+//! - `export ledger|hledger|beancount [--output <file>]`
+//! - `export transactions|spending|payees|comparison|cashflow|goals|forecast [--format csv|md] [--output <file>]`
+//! - `backup [--output <file>]` / `restore <file>`
+//! - `statement [--month YYYY-MM] [--output <file>]`
+//! - `rules list` / `rules apply [--account <id|name>] [--dry-run]`
+//! - `check [--fix]`
+//! - `assert list` / `assert add <account> <YYYY-MM-DD> <balance>` / `assert delete <id>`
+//! - `lock list` / `lock set <YYYY-MM-DD> [--account <id|name>]` / `lock clear [--account <id|name>]`
+//! - `close <year>`
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+
+use crate::audit::Origin;
+use crate::backup;
+use crate::cli::{api::Client, init_client};
+use crate::database::db::migrate;
+use crate::import::csv::{self as csv_import, AmountColumns, CsvMapping};
+use crate::cli::state::{CompareMode, ReportGrouping, ReportPeriod};
+use crate::export::{self, journal, table::{self, TableFormat}};
+use crate::import::{camt, mt940, ofx, qif, ParsedImport};
+
+const IMPORT_CSV_USAGE: &str = "\
+usage: personal-finance-tracker import csv <file> --account <id|name> [options]
+
+  --profile <name>        start from a saved mapping (e.g. the bank's name)
+  --save-profile <name>   save the mapping used for this import
+  --date <col>            date column (1-based number or header name)
+  --date-format <fmt>     chrono format, default %Y-%m-%d (e.g. %d/%m/%Y)
+  --amount <col>          signed amount column, negative = money out
+  --debit <col>           money-out column (use with --credit instead of --amount)
+  --credit <col>          money-in column
+  --payee <col>           payee column
+  --memo <col>            memo column
+  --decimal <. or ,>      decimal separator, default .
+  --delimiter <char>      field separator, default , (use 'tab' for tabs)
+  --skip <n>              lines to skip before the header
+  --no-header             the first row is data
+  --category <id>         category for every row (default Uncategorized / Other Income)
+  --allow-duplicates      also import rows that look already recorded
+  --accept <n,n,...>      import these held rows anyway (numbers from the held list)
+  --allow-locked          also import rows dated in a locked period
+  --dry-run               parse and report without writing
+
+Rows that look already recorded (same amount, within a few days, similar payee)
+are held back and listed, each with its row number, unless --allow-duplicates is
+given. Run the import again with --accept and the numbers of the rows to keep.";
+
+const IMPORT_OFX_USAGE: &str = "\
+usage: personal-finance-tracker import ofx <file> --account <id|name> [options]
+
+  --category <id>         category for every row (default Uncategorized / Other Income)
+  --allow-duplicates      also import rows that look already recorded
+  --accept <n,n,...>      import these held rows anyway (numbers from the held list)
+  --allow-locked          also import rows dated in a locked period
+  --dry-run               parse and report without writing
+
+Rows whose FITID was already imported into the account are skipped. When the
+statement has a LEDGERBAL, the account balance on that date is checked against it.";
+
+const IMPORT_QIF_USAGE: &str = "\
+usage: personal-finance-tracker import qif <file> --account <id|name> [options]
+
+  --category <id>         category for every row (default: the QIF category, created if missing)
+  --allow-duplicates      also import rows that look already recorded
+  --accept <n,n,...>      import these held rows anyway (numbers from the held list)
+  --allow-locked          also import rows dated in a locked period
+  --dry-run               parse and report without writing
+
+Reads !Type:Bank, !Type:Cash and !Type:CCard sections. Each split line becomes its
+own transaction.";
+
+const IMPORT_BANK_USAGE: &str = "\
+usage: personal-finance-tracker import camt|mt940 <file> --account <id|name> [options]
+
+  --category <id>         category for every row (default Uncategorized / Other Income)
+  --allow-duplicates      also import rows that look already recorded
+  --accept <n,n,...>      import these held rows anyway (numbers from the held list)
+  --allow-locked          also import rows dated in a locked period
+  --dry-run               parse and report without writing
+
+camt reads ISO 20022 camt.053 XML, mt940 reads SWIFT MT940. Each booked entry becomes a
+transaction dated by its booking date, with the value date kept, the counterparty as
+payee and the remittance info as memo. Rows whose bank reference was already imported
+are skipped. The statement's opening and closing balances are checked against the
+lines and against the account.";
+
+const EXPORT_QIF_USAGE: &str = "\
+usage: personal-finance-tracker export qif --account <id|name> [--output <file>]
+
+Writes every transaction of the account; prints to stdout without --output.";
+
+const EXPORT_JOURNAL_USAGE: &str = "\
+usage: personal-finance-tracker export ledger|hledger|beancount [--output <file>]
+
+Writes every account and transaction as a plain-text accounting journal, ending
+with a balance assertion for each account; prints to stdout without --output.
+Check it with `hledger -f FILE check`, `ledger -f FILE balance` or `bean-check FILE`.";
+
+const EXPORT_TABLE_USAGE: &str = "\
+usage: personal-finance-tracker export <view> [--format csv|md] [--output <file>] [options]
+
+  transactions --account <id|name>   every transaction of the account
+  spending     [--period <p>] [--date YYYY-MM-DD]   spending by category
+  payees       [--period <p>] [--date YYYY-MM-DD]   spending by payee
+  comparison   [--period <p>] [--date YYYY-MM-DD] [--baseline previous|year]
+  cashflow     [--months <n>]        income, expenses and savings rate (default 6)
+  goals                              saving goal progress
+  forecast     [--days <n>]          projected balances (default 30)
+
+<p> is day, week, month (default), quarter or year; --date picks the period containing
+that day (default today). The format follows the --output extension (.md is Markdown)
+unless --format is given; CSV otherwise.";
+
+const TABLE_VIEWS: &[&str] = &["transactions", "spending", "payees", "comparison", "cashflow", "goals", "forecast"];
+
+const BACKUP_USAGE: &str = "\
+usage: personal-finance-tracker backup [--output <file>]
+       personal-finance-tracker restore <file>
+
+backup writes every table as one JSON document; prints to stdout without --output.
+restore loads a backup into an empty database (point DATABASE_URL at a new file),
+in one transaction, after checking the file. Backups from older versions are upgraded.";
+
+const STATEMENT_USAGE: &str = "\
+usage: personal-finance-tracker statement [--month YYYY-MM] [--output <file>]
+
+Writes the month's statement (default: this month) as one HTML file with no external
+assets: opening and closing balance per account, every transaction, spending by
+category, budgets and goals. Prints to stdout without --output.";
+
+const RULES_USAGE: &str = "\
+usage: personal-finance-tracker rules list
+       personal-finance-tracker rules apply [--account <id|name>] [--dry-run] [--allow-locked]
+
+list prints the rules in the order they run. apply runs them over the transactions
+already recorded (every account unless --account is given) and writes what they change;
+--dry-run only prints the changes. Transactions in a locked period are left alone
+unless --allow-locked is given. Rules are edited in the TUI (R on the Accounts tab)
+and also run on every import.";
+
+const CHECK_USAGE: &str = "\
+usage: personal-finance-tracker check [--fix] [--allow-locked]
+
+Recomputes every account balance exactly from its transactions and compares it with
+the stored one; reports rows pointing at missing accounts, categories or tags, amounts
+and dates that can't be read, future-dated rows, expenses under income categories (and
+the other way round) and failing balance assertions. --fix makes the repairs listed
+after each issue, in one transaction; issues without one need a look by hand. Repairs
+to transactions in a locked period are refused unless --allow-locked is given.";
+
+const ASSERT_USAGE: &str = "\
+usage: personal-finance-tracker assert list
+       personal-finance-tracker assert add <account> <YYYY-MM-DD> <balance>
+       personal-finance-tracker assert delete <id>
+
+A balance assertion says what the account held at the end of a day, e.g. from a paper
+statement: `assert add chequing 2025-01-31 1234.56`. `check` verifies every assertion.";
+
+const LOCK_USAGE: &str = "\
+usage: personal-finance-tracker lock list
+       personal-finance-tracker lock set <YYYY-MM-DD> [--account <id|name>]
+       personal-finance-tracker lock clear [--account <id|name>]
+       personal-finance-tracker close <year>
+
+Transactions dated on or before the lock date can't be added, edited or deleted (the
+TUI asks to confirm, imports need --allow-locked). Without --account the lock covers
+every account; an account's own lock can only reach further. close stores every
+account's balance at December 31 of the year and locks the year; lock list shows
+those balances next to what the transactions give now.";
+
+// Flags that don't take a value
+const SWITCHES: &[&str] = &["--no-header", "--dry-run", "--allow-duplicates", "--allow-locked", "--fix"];
+
+// `args[0]` is the subcommand ("import" / "export" / "backup" / "restore" / "statement" / "rules" /
+// "check" / "assert" / "lock" / "close")
+pub async fn run(args: &[String]) -> Result<()> {
+    let rest = args.get(2..).unwrap_or_default();
+    match (args[0].as_str(), args.get(1).map(|s| s.as_str())) {
+        ("backup", _) => backup(&args[1..]).await,
+        ("restore", _) => restore(&args[1..]).await,
+        ("statement", _) => statement(&args[1..]).await,
+        ("rules", Some("list")) => list_rules().await,
+        ("rules", Some("apply")) => apply_rules(rest).await,
+        ("rules", _) => bail!("unknown rules command\n\n{}", RULES_USAGE),
+        ("check", _) => check(&args[1..]).await,
+        ("assert", Some("list")) => list_assertions().await,
+        ("assert", Some("add")) => add_assertion(rest).await,
+        ("assert", Some("delete")) => delete_assertion(rest).await,
+        ("assert", _) => bail!("unknown assert command\n\n{}", ASSERT_USAGE),
+        ("lock", Some("list")) => list_locks().await,
+        ("lock", Some("set")) => set_lock(rest).await,
+        ("lock", Some("clear")) => clear_lock(rest).await,
+        ("lock", _) => bail!("unknown lock command\n\n{}", LOCK_USAGE),
+        ("close", _) => close_year(&args[1..]).await,
+        ("import", Some("csv")) => import_csv(rest).await,
+        ("import", Some("ofx")) | ("import", Some("qfx")) => import_file(rest, IMPORT_OFX_USAGE, ofx::parse).await,
+        ("import", Some("qif")) => import_file(rest, IMPORT_QIF_USAGE, qif::parse).await,
+        ("import", Some("camt")) | ("import", Some("camt053")) => import_file(rest, IMPORT_BANK_USAGE, camt::parse).await,
+        ("import", Some("mt940")) | ("import", Some("sta")) => import_file(rest, IMPORT_BANK_USAGE, mt940::parse).await,
+        ("export", Some("qif")) => export_qif(rest).await,
+        ("export", Some(f)) if journal::Flavor::parse(f).is_some() => export_journal(f, rest).await,
+        ("export", Some(v)) if TABLE_VIEWS.contains(&v) => export_table(v, rest).await,
+        ("export", _) => bail!("unknown export format\n\n{}\n\n{}\n\n{}", EXPORT_QIF_USAGE, EXPORT_JOURNAL_USAGE, EXPORT_TABLE_USAGE),
+        _ => bail!("unknown import format\n\n{}\n\n{}\n\n{}\n\n{}", IMPORT_CSV_USAGE, IMPORT_OFX_USAGE, IMPORT_QIF_USAGE, IMPORT_BANK_USAGE),
+    }
+}
+
+// Split `args` into positional arguments and --flag values
+fn parse_flags(args: &[String]) -> Result<(Vec<String>, HashMap<String, String>)> {
+    let mut positional = Vec::new();
+    let mut flags = HashMap::new();
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        if SWITCHES.contains(&a.as_str()) {
+            flags.insert(a.clone(), String::new());
+        } else if a.starts_with("--") {
+            let v = it.next().ok_or_else(|| anyhow!("{} needs a value", a))?;
+            flags.insert(a.clone(), v.clone());
+        } else {
+            positional.push(a.clone());
+        }
+    }
+    Ok((positional, flags))
+}
+
+async fn import_csv(args: &[String]) -> Result<()> {
+    let (positional, flags) = parse_flags(args)?;
+    let Some(path) = positional.first() else {
+        bail!("missing file\n\n{}", IMPORT_CSV_USAGE);
+    };
+    let account = flags.get("--account").ok_or_else(|| anyhow!("missing --account\n\n{}", IMPORT_CSV_USAGE))?;
+
+    let client = init_client().await?.with_origin(Origin::Cli).with_lock_override(flags.contains_key("--allow-locked"));
+    let account_id = resolve_account(&client, account).await?;
+
+    let mut mapping = match flags.get("--profile") {
+        Some(name) => client.get_import_profile(name).await?
+            .ok_or_else(|| anyhow!("no import profile named '{}'", name))?,
+        None => CsvMapping::default(),
+    };
+
+    // Layout options first: they decide how the header is read
+    if let Some(d) = flags.get("--delimiter") {
+        mapping.delimiter = match d.as_str() {
+            "tab" | "\\t" => '\t',
+            d => single_char(d, "--delimiter")?,
+        };
+    }
+    if let Some(d) = flags.get("--decimal") {
+        mapping.decimal_separator = single_char(d, "--decimal")?;
+    }
+    if let Some(n) = flags.get("--skip") {
+        mapping.skip_rows = n.parse().map_err(|_| anyhow!("--skip expects a number"))?;
+    }
+    if flags.contains_key("--no-header") {
+        mapping.has_header = false;
+    }
+    if let Some(f) = flags.get("--date-format") {
+        mapping.date_format = f.clone();
+    }
+
+    let content = std::fs::read_to_string(path).map_err(|e| anyhow!("cannot read {}: {}", path, e))?;
+    let sheet = csv_import::read_sheet(&content, &mapping)?;
+    let col = |flag: &str| -> Result<Option<usize>> {
+        match flags.get(flag) {
+            Some(spec) => sheet.find_column(spec).map(Some).ok_or_else(|| anyhow!("{}: no column '{}'", flag, spec)),
+            None => Ok(None),
+        }
+    };
+
+    if let Some(c) = col("--date")? { mapping.date_col = c; }
+    if let Some(c) = col("--payee")? { mapping.payee_col = Some(c); }
+    if let Some(c) = col("--memo")? { mapping.memo_col = Some(c); }
+    match (col("--amount")?, col("--debit")?, col("--credit")?) {
+        (Some(column), None, None) => mapping.amount = AmountColumns::Single { column },
+        (None, Some(debit), Some(credit)) => mapping.amount = AmountColumns::DebitCredit { debit, credit },
+        (None, None, None) => {}
+        _ => bail!("use either --amount or both --debit and --credit"),
+    }
+
+    let parsed = csv_import::parse_rows(&sheet, &mapping);
+    if !write_import(&client, account_id, &flags, &parsed).await? {
+        return Ok(());
+    }
+
+    if let Some(name) = flags.get("--save-profile") {
+        client.save_import_profile(name, &mapping).await?;
+        println!("Saved import profile '{}'", name);
+    }
+    Ok(())
+}
+
+// Formats that need no mapping: read the file, parse, import
+async fn import_file(args: &[String], usage: &str, parse: fn(&str) -> Result<ParsedImport>) -> Result<()> {
+    let (positional, flags) = parse_flags(args)?;
+    let Some(path) = positional.first() else {
+        bail!("missing file\n\n{}", usage);
+    };
+    let account = flags.get("--account").ok_or_else(|| anyhow!("missing --account\n\n{}", usage))?;
+
+    let client = init_client().await?.with_origin(Origin::Cli).with_lock_override(flags.contains_key("--allow-locked"));
+    let account_id = resolve_account(&client, account).await?;
+
+    // QFX / QIF files are often Windows-1252; keep going with replacement characters
+    let bytes = std::fs::read(path).map_err(|e| anyhow!("cannot read {}: {}", path, e))?;
+    let parsed = parse(&String::from_utf8_lossy(&bytes))?;
+    write_import(&client, account_id, &flags, &parsed).await?;
+    Ok(())
+}
+
+async fn export_qif(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_flags(args)?;
+    let account = flags.get("--account").ok_or_else(|| anyhow!("missing --account\n\n{}", EXPORT_QIF_USAGE))?;
+
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let account_id = resolve_account(&client, account).await?;
+    let acc = client.list_accounts().await?.into_iter().find(|a| a.id == account_id)
+        .ok_or_else(|| anyhow!("no account '{}'", account))?;
+    let txns = client.list_transactions(account_id, Some(-1), None).await?;
+    let categories = client.list_categories().await?;
+
+    let splits = client.split_groups(account_id).await?;
+
+    let qif = export::qif::write_account(&acc, &txns, &categories, &splits);
+    match flags.get("--output") {
+        Some(path) => {
+            std::fs::write(path, qif)?;
+            eprintln!("Wrote {} transactions to {}", txns.len(), path);
+        }
+        None => print!("{}", qif),
+    }
+    Ok(())
+}
+
+async fn export_journal(format: &str, args: &[String]) -> Result<()> {
+    let flavor = journal::Flavor::parse(format).ok_or_else(|| anyhow!("unknown export format\n\n{}", EXPORT_JOURNAL_USAGE))?;
+    let (_, flags) = parse_flags(args)?;
+
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let accounts = client.list_accounts().await?;
+    let categories = client.list_categories().await?;
+    let txns = client.list_all_transactions().await?;
+    let tags = client.list_transaction_tags().await?;
+    let rates = client.list_currency_rates().await?;
+    let mut splits = HashMap::new();
+    for a in &accounts {
+        splits.extend(client.split_groups(a.id).await?);
+    }
+
+    let book = journal::Book { accounts: &accounts, categories: &categories, txns: &txns, tags: &tags, rates: &rates, splits: &splits };
+    let text = journal::write(flavor, &book, chrono::Local::now().date_naive());
+    match flags.get("--output") {
+        Some(path) => {
+            std::fs::write(path, text)?;
+            eprintln!("Wrote {} accounts and {} transactions to {}", accounts.len(), txns.len(), path);
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+async fn export_table(view: &str, args: &[String]) -> Result<()> {
+    let (_, flags) = parse_flags(args)?;
+    let format = match flags.get("--format") {
+        Some(f) => TableFormat::parse(f).ok_or_else(|| anyhow!("--format expects csv or md"))?,
+        None => flags.get("--output").map(|p| TableFormat::from_path(p)).unwrap_or(TableFormat::Csv),
+    };
+    let grouping = match flags.get("--period") {
+        Some(p) => ReportGrouping::parse(p).ok_or_else(|| anyhow!("unknown --period '{}'\n\n{}", p, EXPORT_TABLE_USAGE))?,
+        None => ReportGrouping::Month,
+    };
+    let day = match flags.get("--date") {
+        Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| anyhow!("--date expects YYYY-MM-DD"))?,
+        None => chrono::Local::now().date_naive(),
+    };
+    let period = ReportPeriod::containing(grouping, day);
+    let number = |flag: &str, default: u32| -> Result<u32> {
+        flags.get(flag).map(|n| n.parse::<u32>().map_err(|_| anyhow!("{} expects a number", flag))).unwrap_or(Ok(default))
+    };
+
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let table = match view {
+        "transactions" => {
+            let account = flags.get("--account").ok_or_else(|| anyhow!("missing --account\n\n{}", EXPORT_TABLE_USAGE))?;
+            let id = resolve_account(&client, account).await?;
+            let name = client.list_accounts().await?.into_iter().find(|a| a.id == id).map(|a| a.name).unwrap_or_default();
+            let txns = client.list_transactions(id, Some(-1), None).await?;
+            table::transactions("transactions", &format!("Transactions: {}", name), &txns, &client.list_categories().await?)
+        }
+        "spending" => table::category_spending(&period.label(), &client.get_spending_report(period.start, period.end()).await?),
+        "payees" => table::payees(&period.label(), &client.get_payee_report(period.start, period.end(), None).await?),
+        "comparison" => {
+            let mode = match flags.get("--baseline").map(|b| b.as_str()) {
+                None | Some("previous") => CompareMode::PreviousPeriod,
+                Some("year") => CompareMode::PreviousYear,
+                Some(b) => bail!("unknown --baseline '{}'; use previous or year", b),
+            };
+            let base = mode.baseline(&period);
+            let rows = client.get_category_comparison((period.start, period.end()), (base.start, base.end())).await?;
+            table::comparison("comparison", &format!("Spending {} vs {}", period.label(), base.label()), &rows)
+        }
+        "cashflow" => table::cash_flow(&client.get_cash_flow(number("--months", 6)?.clamp(1, 24)).await?),
+        "goals" => table::goals(&client.list_goals().await?),
+        _ => {
+            let days = number("--days", 30)?.clamp(1, 366);
+            table::forecast(days, &client.get_forecast(days).await?)
+        }
+    };
+
+    let text = table.render(format)?;
+    match flags.get("--output") {
+        Some(path) => {
+            std::fs::write(path, text)?;
+            eprintln!("Wrote {} rows to {}", table.rows.len(), path);
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+async fn backup(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_flags(args)?;
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let doc = client.backup().await?;
+    let json = serde_json::to_string_pretty(&doc)?;
+    match flags.get("--output") {
+        Some(path) => {
+            std::fs::write(path, json)?;
+            eprintln!(
+                "Backed up {} accounts and {} transactions (schema {}) to {}",
+                doc.accounts.len(), doc.transactions.len(), doc.schema_version, path
+            );
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+async fn restore(args: &[String]) -> Result<()> {
+    let (positional, _) = parse_flags(args)?;
+    let Some(path) = positional.first() else {
+        bail!("missing file\n\n{}", BACKUP_USAGE);
+    };
+    let text = std::fs::read_to_string(path)?;
+    let doc = backup::parse(&text, migrate::latest_version())?;
+
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let n = client.restore(&doc).await?;
+    println!(
+        "Restored {} accounts, {} categories, {} transactions and {} other rows from schema {}",
+        n.accounts, n.categories, n.transactions, n.other, doc.schema_version
+    );
+    Ok(())
+}
+
+async fn statement(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_flags(args)?;
+    let month = match flags.get("--month") {
+        Some(m) => export::statement::parse_month(m).ok_or_else(|| anyhow!("--month expects YYYY-MM\n\n{}", STATEMENT_USAGE))?,
+        None => chrono::Local::now().date_naive(),
+    };
+
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let doc = client.get_statement(month).await?;
+    let html = export::statement::render(&doc, &chrono::Local::now().format("%Y-%m-%d %H:%M").to_string());
+    match flags.get("--output") {
+        Some(path) => {
+            std::fs::write(path, html)?;
+            let txns: usize = doc.accounts.iter().map(|a| a.transactions.len()).sum();
+            eprintln!("Wrote the {} statement ({} accounts, {} transactions) to {}", doc.month, doc.accounts.len(), txns, path);
+        }
+        None => print!("{}", html),
+    }
+    Ok(())
+}
+
+async fn list_rules() -> Result<()> {
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let rules = client.list_rules().await?;
+    if rules.is_empty() {
+        println!("No rules");
+        return Ok(());
+    }
+    let categories: HashMap<i64, String> = client.list_categories().await?.into_iter().map(|c| (c.id, c.name)).collect();
+    let accounts: HashMap<i64, String> = client.list_accounts().await?.into_iter().map(|a| (a.id, a.name)).collect();
+    for (i, r) in rules.iter().enumerate() {
+        let mut when = vec![format!("{} {} '{}'", r.field.as_str(), r.kind.as_str(), r.pattern)];
+        if let Some(a) = r.account_id {
+            when.push(format!("account {}", accounts.get(&a).map(|s| s.as_str()).unwrap_or("?")));
+        }
+        match (r.min_amount, r.max_amount) {
+            (Some(lo), Some(hi)) => when.push(format!("amount {}..{}", lo, hi)),
+            (Some(lo), None) => when.push(format!("amount >= {}", lo)),
+            (None, Some(hi)) => when.push(format!("amount <= {}", hi)),
+            (None, None) => {}
+        }
+        let mut then = Vec::new();
+        if let Some(c) = r.set_category_id {
+            then.push(format!("category {}", categories.get(&c).map(|s| s.as_str()).unwrap_or("?")));
+        }
+        if let Some(p) = &r.set_payee {
+            then.push(format!("payee '{}'", p));
+        }
+        if let Some(m) = &r.set_memo {
+            then.push(format!("memo '{}'", m));
+        }
+        if !r.add_tags.is_empty() {
+            then.push(format!("tags {}", r.add_tags.join(", ")));
+        }
+        println!(
+            "{:>2}. {}{}: {} => {}",
+            i + 1, r.name, if r.enabled { "" } else { " (disabled)" }, when.join(", "), then.join(", ")
+        );
+    }
+    Ok(())
+}
+
+async fn apply_rules(args: &[String]) -> Result<()> {
+    let (_, flags) = parse_flags(args)?;
+    let client = init_client().await?.with_origin(Origin::Cli).with_lock_override(flags.contains_key("--allow-locked"));
+    let account_id = match flags.get("--account") {
+        Some(a) => Some(resolve_account(&client, a).await?),
+        None => None,
+    };
+
+    let changes = client.preview_rules(account_id).await?;
+    let categories: HashMap<i64, String> = client.list_categories().await?.into_iter().map(|c| (c.id, c.name)).collect();
+    for c in &changes {
+        let mut what = Vec::new();
+        if let Some(id) = c.category_id {
+            what.push(format!("category -> {}", categories.get(&id).map(|s| s.as_str()).unwrap_or("?")));
+        }
+        if let Some(p) = &c.payee {
+            what.push(format!("payee -> '{}'", p));
+        }
+        if let Some(m) = &c.memo {
+            what.push(format!("memo -> '{}'", m));
+        }
+        if !c.tags.is_empty() {
+            what.push(format!("+tags {}", c.tags.join(", ")));
+        }
+        println!(
+            "#{} {} {:>12} {}: {} [{}]",
+            c.txn.id, c.txn.txn_date, c.txn.amount.0, c.txn.payee.as_deref().unwrap_or(""), what.join(", "), c.rules.join(", ")
+        );
+    }
+
+    if flags.contains_key("--dry-run") {
+        println!("{} transactions would change (dry run, nothing written)", changes.len());
+        return Ok(());
+    }
+    let n = client.apply_rule_changes(&changes).await?;
+    println!("Updated {} transactions", n);
+    Ok(())
+}
+
+async fn check(args: &[String]) -> Result<()> {
+    let (positional, flags) = parse_flags(args)?;
+    if !positional.is_empty() {
+        bail!("unexpected argument '{}'\n\n{}", positional[0], CHECK_USAGE);
+    }
+    let client = init_client().await?.with_origin(Origin::Cli).with_lock_override(flags.contains_key("--allow-locked"));
+    let report = client.check_ledger().await?;
+    for i in &report.issues {
+        match &i.repair {
+            Some(r) => println!("{:<7} {}\n        fix: {}", i.severity.as_str(), i.message, r.describe()),
+            None => println!("{:<7} {}", i.severity.as_str(), i.message),
+        }
+    }
+    println!("Checked {}", report.summary());
+
+    if flags.contains_key("--fix") && report.repairable() > 0 {
+        let n = client.repair_ledger(&report.issues).await?;
+        let after = client.check_ledger().await?;
+        println!("Repaired {} issues; now {}", n, after.summary());
+    } else if report.repairable() > 0 {
+        println!("Run with --fix to make the repairs");
+    }
+    Ok(())
+}
+
+async fn list_assertions() -> Result<()> {
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let assertions = client.list_balance_assertions().await?;
+    if assertions.is_empty() {
+        println!("No balance assertions");
+        return Ok(());
+    }
+    let accounts: HashMap<i64, String> = client.list_accounts().await?.into_iter().map(|a| (a.id, a.name)).collect();
+    for a in &assertions {
+        let actual = client.balance_as_of(a.account_id, a.date).await?;
+        let status = if actual == a.balance { "ok".to_string() } else { format!("FAILS: transactions give {}", actual) };
+        println!(
+            "#{:<3} {} {} = {}  {}",
+            a.id, a.date, accounts.get(&a.account_id).map(|s| s.as_str()).unwrap_or("?"), a.balance, status
+        );
+    }
+    Ok(())
+}
+
+async fn add_assertion(args: &[String]) -> Result<()> {
+    let [account, date, balance] = args else {
+        bail!("expected <account> <YYYY-MM-DD> <balance>\n\n{}", ASSERT_USAGE);
+    };
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| anyhow!("date expects YYYY-MM-DD\n\n{}", ASSERT_USAGE))?;
+    let balance: rust_decimal::Decimal = balance.trim().parse().map_err(|_| anyhow!("'{}' is not an amount", balance))?;
+
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let account_id = resolve_account(&client, account).await?;
+    let id = client.add_balance_assertion(account_id, date, balance).await?;
+    let actual = client.balance_as_of(account_id, date).await?;
+    if actual == balance {
+        println!("Added assertion #{}: holds", id);
+    } else {
+        println!("Added assertion #{}: transactions give {} (off by {})", id, actual, actual - balance);
+    }
+    Ok(())
+}
+
+async fn delete_assertion(args: &[String]) -> Result<()> {
+    let id: i64 = args.first().and_then(|a| a.parse().ok())
+        .ok_or_else(|| anyhow!("expected an assertion id\n\n{}", ASSERT_USAGE))?;
+    let client = init_client().await?.with_origin(Origin::Cli);
+    if !client.delete_balance_assertion(id).await? {
+        bail!("no balance assertion #{}", id);
+    }
+    println!("Deleted assertion #{}", id);
+    Ok(())
+}
+
+async fn list_locks() -> Result<()> {
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let locks = client.list_period_locks().await?;
+    let closes = client.list_year_end_closes().await?;
+    let accounts: HashMap<i64, String> = client.list_accounts().await?.into_iter().map(|a| (a.id, a.name)).collect();
+    let name = |id: i64| accounts.get(&id).map(|s| s.as_str()).unwrap_or("?");
+
+    if locks.is_empty() {
+        println!("No lock dates");
+    }
+    for l in &locks {
+        match l.account_id {
+            Some(a) => println!("{:<20} locked until {}", name(a), l.date),
+            None => println!("{:<20} locked until {}", "All accounts", l.date),
+        }
+    }
+    for c in &closes {
+        let end = NaiveDate::from_ymd_opt(c.year, 12, 31).unwrap_or_default();
+        let now = client.balance_as_of(c.account_id, end).await?;
+        let status = if now == c.balance { "ok".to_string() } else { format!("CHANGED: transactions now give {}", now) };
+        println!("{} close {:<20} {:>12}  {}", c.year, name(c.account_id), c.balance, status);
+    }
+    Ok(())
+}
+
+async fn set_lock(args: &[String]) -> Result<()> {
+    let (positional, flags) = parse_flags(args)?;
+    let [date] = positional.as_slice() else {
+        bail!("expected <YYYY-MM-DD>\n\n{}", LOCK_USAGE);
+    };
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| anyhow!("date expects YYYY-MM-DD\n\n{}", LOCK_USAGE))?;
+
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let account_id = match flags.get("--account") {
+        Some(a) => Some(resolve_account(&client, a).await?),
+        None => None,
+    };
+    client.set_period_lock(account_id, Some(date)).await?;
+    match flags.get("--account") {
+        Some(a) => println!("Locked {} until {}", a, date),
+        None => println!("Locked every account until {}", date),
+    }
+    Ok(())
+}
+
+async fn clear_lock(args: &[String]) -> Result<()> {
+    let (positional, flags) = parse_flags(args)?;
+    if !positional.is_empty() {
+        bail!("unexpected argument '{}'\n\n{}", positional[0], LOCK_USAGE);
+    }
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let account_id = match flags.get("--account") {
+        Some(a) => Some(resolve_account(&client, a).await?),
+        None => None,
+    };
+    client.set_period_lock(account_id, None).await?;
+    match flags.get("--account") {
+        Some(a) => println!("Removed the lock of {}", a),
+        None => println!("Removed the lock of all accounts (locks of single accounts stay)"),
+    }
+    Ok(())
+}
+
+async fn close_year(args: &[String]) -> Result<()> {
+    let year: i32 = args.first().and_then(|y| y.parse().ok())
+        .ok_or_else(|| anyhow!("expected a year\n\n{}", LOCK_USAGE))?;
+    let client = init_client().await?.with_origin(Origin::Cli);
+    let closes = client.close_year(year).await?;
+    let accounts: HashMap<i64, String> = client.list_accounts().await?.into_iter().map(|a| (a.id, a.name)).collect();
+    for c in &closes {
+        println!("{:<20} {:>12}", accounts.get(&c.account_id).map(|s| s.as_str()).unwrap_or("?"), c.balance);
+    }
+    println!("Closed {} for {} accounts; every account is locked until at least {}-12-31", year, closes.len(), year);
+    Ok(())
+}
+
+// Report parse errors, then either print the rows (--dry-run) or import them and check the
+// statement balances. Returns false for a dry run.
+async fn write_import(client: &Client, account_id: i64, flags: &HashMap<String, String>, parsed: &ParsedImport) -> Result<bool> {
+    let category_id = match flags.get("--category") {
+        Some(c) => Some(c.parse::<i64>().map_err(|_| anyhow!("--category expects a category id"))?),
+        None => None,
+    };
+
+    for e in &parsed.errors {
+        eprintln!("skipped {}", e);
+    }
+
+    if flags.contains_key("--dry-run") {
+        for t in &parsed.txns {
+            println!("{}  {:>12}  {}  {}", t.date, t.amount, t.payee.as_deref().unwrap_or(""), t.memo.as_deref().unwrap_or(""));
+        }
+        println!("{} rows parsed, {} skipped (dry run, nothing written)", parsed.txns.len(), parsed.errors.len());
+        if let Some(check) = parsed.lines_check() {
+            println!("Check: {}", check);
+        }
+        return Ok(false);
+    }
+
+    // Row numbers (1-based, in statement order) of held rows to import anyway
+    let accepted: Vec<usize> = match flags.get("--accept") {
+        Some(list) => list.split(',').map(|n| {
+            n.trim().parse::<usize>().ok()
+                .filter(|n| (1..=parsed.txns.len()).contains(n))
+                .ok_or_else(|| anyhow!("--accept expects row numbers between 1 and {}, got '{}'", parsed.txns.len(), n.trim()))
+        }).collect::<Result<_>>()?,
+        None => Vec::new(),
+    };
+
+    // Hold back rows that look already recorded, unless told otherwise
+    let mut txns = parsed.txns.clone();
+    let mut held = 0;
+    if !flags.contains_key("--allow-duplicates") {
+        let matches = client.find_import_duplicates(account_id, &parsed.txns).await?;
+        let mut keep = Vec::with_capacity(txns.len());
+        for (n, (t, m)) in txns.into_iter().zip(matches).enumerate() {
+            match m {
+                Some(m) if !accepted.contains(&(n + 1)) => {
+                    held += 1;
+                    eprintln!(
+                        "held row {}: {} {} {}: looks like #{} on {} {} ({:.0}%)",
+                        n + 1, t.date, t.amount, t.payee.as_deref().unwrap_or(""),
+                        m.existing.id, m.existing.txn_date, m.existing.payee.as_deref().unwrap_or(""), m.score * 100.0
+                    );
+                }
+                _ => keep.push(t),
+            }
+        }
+        txns = keep;
+    }
+
+    let summary = client.import_transactions(account_id, category_id, &txns).await?;
+    println!(
+        "Imported {} transactions ({} matched rules), {} already imported, {} held as duplicates, {} rows skipped",
+        summary.imported, summary.ruled, summary.duplicates, held, parsed.errors.len()
+    );
+    if held > 0 {
+        println!("Import held rows anyway by running again with --accept and their row numbers");
+    }
+    if summary.locked > 0 {
+        println!("Left out {} rows dated in a locked period (--allow-locked imports them)", summary.locked);
+    }
+
+    if let Some(check) = parsed.lines_check() {
+        println!("Check: {}", check);
+    }
+    if let Some(open) = parsed.opening_balance {
+        let ledger = client.balance_as_of(account_id, open.date).await?;
+        println!("Check opening: {}", open.describe(ledger));
+    }
+    if let Some(stmt) = parsed.statement_balance {
+        let ledger = client.balance_as_of(account_id, stmt.date).await?;
+        println!("Check: {}", stmt.describe(ledger));
+    }
+    Ok(true)
+}
+
+// Accept an account id or its (case-insensitive) name
+async fn resolve_account(client: &Client, spec: &str) -> Result<i64> {
+    let accounts = client.list_accounts().await?;
+    accounts.iter()
+        .find(|a| a.id.to_string() == spec || a.name.eq_ignore_ascii_case(spec))
+        .map(|a| a.id)
+        .ok_or_else(|| anyhow!("no account '{}'", spec))
+}
+
+fn single_char(s: &str, flag: &str) -> Result<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => bail!("{} expects a single character", flag),
+    }
+}
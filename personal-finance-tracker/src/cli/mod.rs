@@ -5,16 +5,18 @@
 use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode};
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::{backend::CrosstermBackend, Terminal};
 
 use crate::database::db::{migrate, queries};
 
 pub mod api;
+pub mod commands;
 pub mod state;
 pub mod input;
 pub mod util;
+pub mod undo;
 pub mod ui;
 
 
@@ -48,13 +50,10 @@ pub async fn run() -> Result<()> {
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
 
-        if event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Char('q') {
-                    break;
-                }
-                app.handle_key(key).await?;
-            }
+        if event::poll(timeout)?
+            && let Event::Key(key) = event::read()? {
+            // handle_key sets app.quit on q, unless q is being typed into a field
+            app.handle_key(key).await?;
         }
 
         if last_tick.elapsed() >= tick_rate {
@@ -74,6 +73,16 @@ pub async fn run() -> Result<()> {
 }
 
 pub async fn init_app() -> Result<state::App> {
+    let client = init_client().await?;
+
+    // Create app state
+    let app = state::App::new(client);
+
+    Ok(app)
+}
+
+// Open the local database, migrated and seeded (shared by the TUI and the subcommands)
+pub async fn init_client() -> Result<api::Client> {
     // Load database URL
     let db_url = std::env::var("DATABASE_URL")
         .unwrap_or_else(|_| "sqlite://./finance_tracker.db".to_string());
@@ -87,10 +96,21 @@ pub async fn init_app() -> Result<state::App> {
 
     // Seed categories
     queries::seed_fixed_categories(pool).await?;
-    
 
-    // Create app state
-    let app = state::App::new(client);
+    // Purge what has been in the trash longer than the retention period
+    let days = trash_retention_days();
+    if days > 0 {
+        client.purge_trash_before(chrono::Utc::now() - chrono::Duration::days(days)).await?;
+    }
 
-    Ok(app)
+    Ok(client)
+}
+
+// Days a deleted account or transaction stays in the trash (TRASH_RETENTION_DAYS,
+// default 30); 0 keeps it until purged by hand
+pub fn trash_retention_days() -> i64 {
+    std::env::var("TRASH_RETENTION_DAYS").ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|d: &i64| *d >= 0)
+        .unwrap_or(30)
 }
\ No newline at end of file
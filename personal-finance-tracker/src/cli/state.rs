@@ -1,16 +1,26 @@
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
 use ratatui::widgets::{ListState, TableState};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use crate::cli::api::Client;
 use std::str::FromStr;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+use crate::import::csv::{self as csv_import, AmountColumns, CsvMapping, CsvSheet};
+use crate::export::table::{self, TableExport, TableFormat};
+use crate::import::{camt, duplicates::DuplicateMatch, mt940, ofx, qif, ImportedTxn, ParsedImport};
+use crate::rules::{self, suggest::{CategoryModel, Suggestion}, MatchKind, Rule, RuleChange};
+use crate::audit::{AuditEntry, AuditFilter};
+use crate::check::Report;
+use crate::lock::{self, PeriodLock};
+use crate::search::{self, SearchHit, SearchQuery};
+use crate::cli::undo::{Edit, History, Rows, Target};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum AccountType {
     Checking,
     Credit,
     Savings,
+    #[default]
     Cash,
     Other,
 }
@@ -33,11 +43,6 @@ pub struct GoalForm {
     pub editing: Option<GoalField>,
 }
 
-impl Default for AccountType {
-    fn default() -> Self {
-        AccountType::Cash
-    }
-}
 impl AccountType {
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -66,12 +71,16 @@ pub struct AccountDto {
     pub currency: String,
     pub opening_balance: Money,
     pub created_at: String,
+    pub credit_limit: Option<Money>,
+    #[serde(default)]
+    pub deleted_at: Option<String>,     // in the trash since (RFC 3339); None for live accounts
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CategoryType {
     Income,
     Expense,
+    Transfer,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,10 +101,39 @@ pub struct TransactionDto {
     pub payee: Option<String>,      //added payee
     pub currency: String,
     pub txn_date: NaiveDate,
+    #[serde(default)]
     pub cleared: bool,
+    #[serde(default)]
     pub reconciled: bool,
 }
 
+// Where a row sits in an account's ledger: rows sort newest first by the stored
+// `transacted_at` text, then by id. Pages start after a cursor (keyset pagination).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxnCursor {
+    pub transacted_at: String,
+    pub id: i64,
+}
+
+// A row of an account's ledger with the balance once it is counted
+#[derive(Debug, Clone)]
+pub struct LedgerRow {
+    pub txn: TransactionDto,
+    pub balance: Decimal,
+    pub cursor: TxnCursor,
+}
+
+// Where `Client::ledger_page` starts; pages are always newest first
+#[derive(Debug, Clone, Copy)]
+pub enum PageFrom<'a> {
+    Newest,
+    Oldest,
+    Date(NaiveDate),            // the newest rows dated on or before the day
+    At(&'a TxnCursor),          // that row (or the next older one if it is gone) and below
+    Below(&'a LedgerRow),       // the older rows that follow it
+    Above(&'a LedgerRow),       // the newer rows that precede it
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SavingGoalDto {
     pub id: i64,
@@ -111,12 +149,247 @@ pub struct CategorySpendingDto {
     pub total_amount: Money,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashFlowDto {
+    pub month: String,            // YYYY-MM
+    pub period_start: NaiveDate,
+    pub income: Money,
+    pub expenses: Money,
+    pub net: Money,
+    pub savings_rate: Option<Decimal>, // percent of income kept, None when there is no income
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryComparisonDto {
+    pub category: String,
+    pub previous_amount: Money,
+    pub current_amount: Money,
+    pub change: Money,
+    pub change_pct: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayeeSpendingDto {
+    pub payee: String,
+    pub total_amount: Money,
+    pub txn_count: i64,
+    pub average_amount: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportProfileDto {
+    pub id: i64,
+    pub name: String,               // usually the bank
+    pub mapping: CsvMapping,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalancePointDto {
+    pub date: NaiveDate,
+    pub balance: Money,     // end-of-day balance
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForecastAlertDto {
+    pub date: NaiveDate,
+    pub balance: Money,
+    pub reason: String,     // "goes negative" / "exceeds credit limit"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountForecastDto {
+    pub account_id: i64,
+    pub name: String,
+    pub credit_limit: Option<Money>,
+    pub history: Vec<BalancePointDto>,      // actual balances, oldest first, ends today
+    pub projection: Vec<BalancePointDto>,   // projected balances, starts today
+    pub alert: Option<ForecastAlertDto>,    // first projected problem, if any
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodSpendingDto {
+    pub period: String,           // label, e.g. 2025-03, 2025-Q1, 2025-W12
+    pub period_start: NaiveDate,
+    pub categories: Vec<CategorySpendingDto>,
+    pub total_amount: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountStatementDto {
+    pub account_id: i64,
+    pub name: String,
+    pub currency: String,
+    pub opening_balance: Money,     // end of the day before the month
+    pub closing_balance: Money,     // end of the month's last day
+    pub money_in: Money,
+    pub money_out: Money,
+    pub transactions: Vec<TransactionDto>,  // oldest first
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetStatusDto {
+    pub account: String,
+    pub category: Option<String>,   // None = everything spent from the account
+    pub period: String,             // 'weekly' / 'monthly' as stored
+    pub limit: Money,               // scaled to the whole month
+    pub spent: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementDto {
+    pub month: String,              // YYYY-MM
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub accounts: Vec<AccountStatementDto>,
+    pub income: Money,              // transfers and opening balances excluded
+    pub spending: Vec<CategorySpendingDto>,
+    pub budgets: Vec<BudgetStatusDto>,
+    pub goals: Vec<SavingGoalDto>,
+    pub categories: Vec<CategoryDto>,   // names for the transaction lists
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrashKind {
+    Account,
+    Transaction,
+}
+
+// An account in the trash, or a transaction deleted on its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItemDto {
+    pub kind: TrashKind,
+    pub id: i64,
+    pub account: String,                    // the account, or the transaction's account
+    pub transaction: Option<TransactionDto>,
+    pub transactions: i64,                  // deleted together with the account
+    pub deleted_at: String,                 // RFC 3339, UTC
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportGrouping {
+    Day,
+    Week,
+    #[default]
+    Month,
+    Quarter,
+    Year,
+}
+
+impl ReportGrouping {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Quarter => "quarter",
+            Self::Year => "year",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "day" | "daily" => Some(Self::Day),
+            "week" | "weekly" => Some(Self::Week),
+            "month" | "monthly" => Some(Self::Month),
+            "quarter" | "quarterly" => Some(Self::Quarter),
+            "year" | "yearly" => Some(Self::Year),
+            _ => None,
+        }
+    }
+
+    pub fn cycle(&self) -> Self {
+        match self {
+            Self::Day => Self::Week,
+            Self::Week => Self::Month,
+            Self::Month => Self::Quarter,
+            Self::Quarter => Self::Year,
+            Self::Year => Self::Day,
+        }
+    }
+
+    // First day of the period containing `d` (weeks start on Monday)
+    pub fn period_start(&self, d: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => d,
+            Self::Week => d - chrono::Duration::days(d.weekday().num_days_from_monday() as i64),
+            Self::Month => NaiveDate::from_ymd_opt(d.year(), d.month(), 1).unwrap(),
+            Self::Quarter => NaiveDate::from_ymd_opt(d.year(), (d.month0() / 3) * 3 + 1, 1).unwrap(),
+            Self::Year => NaiveDate::from_ymd_opt(d.year(), 1, 1).unwrap(),
+        }
+    }
+
+    // Shift a period start by `n` periods (negative goes back)
+    pub fn shift(&self, start: NaiveDate, n: i32) -> NaiveDate {
+        let add_months = |months: i32| {
+            let total = start.year() * 12 + start.month0() as i32 + months;
+            NaiveDate::from_ymd_opt(total.div_euclid(12), total.rem_euclid(12) as u32 + 1, 1).unwrap()
+        };
+        match self {
+            Self::Day => start + chrono::Duration::days(n as i64),
+            Self::Week => start + chrono::Duration::weeks(n as i64),
+            Self::Month => add_months(n),
+            Self::Quarter => add_months(n * 3),
+            Self::Year => add_months(n * 12),
+        }
+    }
+
+    pub fn label(&self, start: NaiveDate) -> String {
+        match self {
+            Self::Day => start.format("%Y-%m-%d").to_string(),
+            Self::Week => format!("{}-W{:02}", start.iso_week().year(), start.iso_week().week()),
+            Self::Month => start.format("%Y-%m").to_string(),
+            Self::Quarter => format!("{}-Q{}", start.year(), start.month0() / 3 + 1),
+            Self::Year => start.year().to_string(),
+        }
+    }
+}
+
+// A single report period, browsed on the Dashboard with [ and ]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportPeriod {
+    pub grouping: ReportGrouping,
+    pub start: NaiveDate,
+}
+
+impl Default for ReportPeriod {
+    fn default() -> Self {
+        Self::containing(ReportGrouping::Month, chrono::Utc::now().date_naive())
+    }
+}
+
+impl ReportPeriod {
+    pub fn containing(grouping: ReportGrouping, d: NaiveDate) -> Self {
+        Self { grouping, start: grouping.period_start(d) }
+    }
+    pub fn next(&self) -> Self {
+        Self { grouping: self.grouping, start: self.grouping.shift(self.start, 1) }
+    }
+    pub fn prev(&self) -> Self {
+        Self { grouping: self.grouping, start: self.grouping.shift(self.start, -1) }
+    }
+    // Last day of the period (inclusive)
+    pub fn end(&self) -> NaiveDate {
+        self.grouping.shift(self.start, 1) - chrono::Duration::days(1)
+    }
+    // The same period one year earlier (e.g. March 2025 -> March 2024)
+    pub fn year_ago(&self) -> Self {
+        let d = self.start.with_year(self.start.year() - 1)
+            .unwrap_or_else(|| self.start - chrono::Duration::days(365));
+        Self::containing(self.grouping, d)
+    }
+    pub fn label(&self) -> String {
+        self.grouping.label(self.start)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateAccountReq {
     pub name: String,
     pub r#type: AccountType,
     pub currency: String,
     pub opening_balance: Money,
+    pub credit_limit: Option<Money>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -145,6 +418,8 @@ pub enum Tab {
     Transactions,
     AddTxn,
     Dashboard,
+    Rules,
+    Trash,
     Help,
 }
 
@@ -154,6 +429,7 @@ pub struct AccountForm {
     pub name: String,
     pub currency: String,
     pub opening: String,
+    pub limit: String,
     pub r#type: AccountType,
     pub error: Option<String>,
     pub editing: Option<AccField>,
@@ -167,6 +443,188 @@ pub struct AccountsPage {
     pub form: AccountForm,
     pub editing_id: Option<i64>,   
     pub show_delete_confirm: bool,
+    pub import: Option<ImportWizard>,   // CSV import modal ('i')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportField {
+    #[default]
+    Path,
+    Profile,
+    Delimiter,
+    Header,
+    Skip,
+    DateCol,
+    DateFormat,
+    AmountMode,
+    AmountCol,      // the signed amount, or the debit column
+    CreditCol,
+    PayeeCol,
+    MemoCol,
+    Decimal,
+}
+
+impl ImportField {
+    pub const ALL: [ImportField; 13] = [
+        Self::Path, Self::Profile, Self::Delimiter, Self::Header, Self::Skip, Self::DateCol, Self::DateFormat,
+        Self::AmountMode, Self::AmountCol, Self::CreditCol, Self::PayeeCol, Self::MemoCol, Self::Decimal,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportFormat {
+    #[default]
+    Csv,
+    Ofx,    // detected from the content; needs no mapping
+    Qif,    // likewise
+    Camt,   // ISO 20022 camt.053
+    Mt940,
+}
+
+// Statement import wizard: pick a file, map columns (CSV), check the preview, Ctrl+s to import
+#[derive(Default)]
+pub struct ImportWizard {
+    pub account_id: i64,
+    pub format: ImportFormat,
+    pub path: String,
+    pub profile: String,        // saved on import when not empty
+    pub mapping: CsvMapping,
+    pub field: ImportField,
+    pub content: Option<String>,
+    pub sheet: CsvSheet,
+    pub preview: ParsedImport,
+    pub error: Option<String>,
+    pub message: Option<String>,
+    pub held: Vec<HeldDuplicate>,   // non-empty while reviewing likely duplicates
+    pub held_sel: usize,
+}
+
+// A statement row held back because it looks already recorded
+#[derive(Clone)]
+pub struct HeldDuplicate {
+    pub index: usize,           // into preview.txns
+    pub matched: DuplicateMatch,
+    pub import: bool,           // skipped unless the user says otherwise
+}
+
+impl ImportWizard {
+    // Fields that apply to the current amount layout
+    pub fn fields(&self) -> Vec<ImportField> {
+        if self.format != ImportFormat::Csv {
+            return vec![ImportField::Path];
+        }
+        ImportField::ALL.iter().copied()
+            .filter(|f| *f != ImportField::CreditCol || matches!(self.mapping.amount, AmountColumns::DebitCredit { .. }))
+            .collect()
+    }
+
+    fn step_field(&mut self, dir: i32) {
+        let fields = self.fields();
+        let i = fields.iter().position(|f| *f == self.field).unwrap_or(0) as i32;
+        let n = fields.len() as i32;
+        self.field = fields[((i + dir).rem_euclid(n)) as usize];
+    }
+
+    // Re-split and re-parse the loaded file with the current mapping
+    pub fn reparse(&mut self) {
+        let Some(content) = &self.content else { return };
+        if self.format != ImportFormat::Csv {
+            let parse = match self.format {
+                ImportFormat::Ofx => ofx::parse,
+                ImportFormat::Camt => camt::parse,
+                ImportFormat::Mt940 => mt940::parse,
+                _ => qif::parse,
+            };
+            match parse(content) {
+                Ok(parsed) => {
+                    self.preview = parsed;
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+            return;
+        }
+        match csv_import::read_sheet(content, &self.mapping) {
+            Ok(sheet) => {
+                self.preview = csv_import::parse_rows(&sheet, &self.mapping);
+                self.sheet = sheet;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    fn load_file(&mut self) {
+        let path = self.path.trim();
+        match std::fs::read(path) {
+            Ok(bytes) => {
+                let content = String::from_utf8_lossy(&bytes).into_owned();
+                self.format = if ofx::is_ofx(&content) {
+                    ImportFormat::Ofx
+                } else if camt::is_camt(&content) {
+                    ImportFormat::Camt
+                } else if mt940::is_mt940(&content) {
+                    ImportFormat::Mt940
+                } else if qif::is_qif(&content) {
+                    ImportFormat::Qif
+                } else {
+                    ImportFormat::Csv
+                };
+                self.field = ImportField::Path;
+                self.content = Some(content);
+                self.reparse();
+                self.message = Some(match self.format {
+                    ImportFormat::Ofx => format!("Loaded OFX statement with {} transactions", self.preview.txns.len()),
+                    ImportFormat::Qif => format!("Loaded QIF file with {} transactions (splits counted separately)", self.preview.txns.len()),
+                    ImportFormat::Camt => format!("Loaded camt.053 statement with {} booked entries", self.preview.txns.len()),
+                    ImportFormat::Mt940 => format!("Loaded MT940 statement with {} transactions", self.preview.txns.len()),
+                    ImportFormat::Csv => format!("Loaded {} rows", self.sheet.rows.len()),
+                });
+            }
+            Err(e) => self.error = Some(format!("Cannot read {}: {}", path, e)),
+        }
+    }
+
+    // ←/→ on a field: step columns, toggle switches
+    fn adjust(&mut self, dir: i32) {
+        let width = self.sheet.width().max(1) as i32;
+        let step = |c: usize| ((c as i32 + dir).rem_euclid(width)) as usize;
+        // Optional columns cycle through "none" as well
+        let step_opt = |c: Option<usize>| {
+            let cur = c.map(|c| c as i32).unwrap_or(-1) + 1;
+            let next = (cur + dir).rem_euclid(width + 1);
+            if next == 0 { None } else { Some((next - 1) as usize) }
+        };
+        let m = &mut self.mapping;
+        match self.field {
+            ImportField::Header => m.has_header = !m.has_header,
+            ImportField::Skip => m.skip_rows = (m.skip_rows as i32 + dir).max(0) as usize,
+            ImportField::DateCol => m.date_col = step(m.date_col),
+            ImportField::AmountMode => {
+                m.amount = match m.amount {
+                    AmountColumns::Single { column } => AmountColumns::DebitCredit { debit: column, credit: step(column) },
+                    AmountColumns::DebitCredit { debit, .. } => AmountColumns::Single { column: debit },
+                }
+            }
+            ImportField::AmountCol => match &mut m.amount {
+                AmountColumns::Single { column } => *column = step(*column),
+                AmountColumns::DebitCredit { debit, .. } => *debit = step(*debit),
+            },
+            ImportField::CreditCol => {
+                if let AmountColumns::DebitCredit { credit, .. } = &mut m.amount {
+                    *credit = step(*credit);
+                }
+            }
+            ImportField::PayeeCol => m.payee_col = step_opt(m.payee_col),
+            ImportField::MemoCol => m.memo_col = step_opt(m.memo_col),
+            ImportField::Decimal => m.decimal_separator = if m.decimal_separator == '.' { ',' } else { '.' },
+            ImportField::Delimiter => {
+                m.delimiter = match m.delimiter { ',' => ';', ';' => '\t', '\t' => '|', _ => ',' };
+            }
+            ImportField::Path | ImportField::Profile | ImportField::DateFormat => return,
+        }
+        self.reparse();
+    }
 }
 
 // Transactions 
@@ -176,6 +634,67 @@ pub struct TxnPage {
     pub table: Vec<TransactionDto>,
     pub tsel: TableState,
     pub loading: bool,
+    pub payee: Option<String>,  // drill-down from the Dashboard payee report
+    pub reconcile: Option<Reconcile>,
+    pub unlock: Option<(i64, char)>,    // reconciled row warned about; the same key again goes ahead
+    pub search: Option<TxnSearch>,      // results of a '/' search instead of one account
+    pub ledger: Option<Ledger>,         // set while the table is an account's ledger
+    pub offset: usize,                  // first row on screen
+    pub height: usize,                  // rows on screen, from the last draw
+    pub jump: Option<String>,           // date being typed after 'g'
+}
+
+// Rows of an account's ledger loaded at a time, and most kept
+pub const LEDGER_PAGE: usize = 100;
+const LEDGER_WINDOW: usize = 300;
+// The next page loads when the selection comes this close to either end of the window
+const LEDGER_MARGIN: usize = 20;
+
+// The loaded part of an account's ledger; `table` holds the same transactions. Pages load
+// as the selection nears an end and the far end is dropped past LEDGER_WINDOW rows.
+pub struct Ledger {
+    pub account_id: i64,
+    pub rows: Vec<LedgerRow>,
+    pub at_newest: bool,    // nothing newer to load
+    pub at_oldest: bool,    // nothing older to load
+}
+
+// Transactions matching a search, across every account
+pub struct TxnSearch {
+    pub query: SearchQuery,
+    pub hits: Vec<SearchHit>,   // in the table's order, with the account, category and tag names
+}
+
+// Most matches a search shows
+pub const SEARCH_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReconcileField {
+    #[default]
+    Date,
+    Balance,
+}
+
+// Reconcile mode ('R' on the Transactions tab): enter the statement's ending date and balance,
+// clear rows with Space until the difference is zero, then Ctrl+s locks them as reconciled
+#[derive(Default)]
+pub struct Reconcile {
+    pub account_id: i64,
+    pub end_date: String,
+    pub balance: String,
+    pub field: ReconcileField,
+    pub statement: Option<(NaiveDate, Decimal)>,    // set once the prompt is accepted
+    pub reconciled: Decimal,    // rows locked by earlier reconciliations, up to the date
+    pub error: Option<String>,
+}
+
+impl Reconcile {
+    // Statement balance minus the cleared balance; zero when the books agree
+    pub fn difference(&self, rows: &[TransactionDto]) -> Option<Decimal> {
+        let (_, statement) = self.statement?;
+        let cleared: Decimal = rows.iter().filter(|t| t.cleared).map(|t| t.amount.0).sum();
+        Some(statement - self.reconciled - cleared)
+    }
 }
 
 #[derive(Default)]
@@ -187,6 +706,57 @@ pub struct DashboardPage {
     pub editing_id: Option<i64>,
     pub form: GoalForm,
     pub selected_index: usize,
+    pub period: ReportPeriod,
+    pub view: ReportView,
+    pub cashflow: Vec<CashFlowDto>,
+    pub cashflow_months: u32,
+    pub comparison: Vec<CategoryComparisonDto>,
+    pub compare_mode: CompareMode,
+    pub payees: Vec<PayeeSpendingDto>,
+    pub payee_sel: usize,
+    pub forecast: Vec<AccountForecastDto>,
+    pub forecast_days: u32,     // 30, 60 or 90 ('h' cycles)
+    pub forecast_sel: usize,    // account shown in the chart (←/→)
+}
+
+// Which report the right half of the Dashboard shows ('v' cycles)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportView {
+    #[default]
+    Spending,
+    CashFlow,
+    Comparison,
+    Payees,
+    Forecast,
+}
+
+impl ReportView {
+    pub fn cycle(&self) -> Self {
+        match self {
+            Self::Spending => Self::CashFlow,
+            Self::CashFlow => Self::Comparison,
+            Self::Comparison => Self::Payees,
+            Self::Payees => Self::Forecast,
+            Self::Forecast => Self::Spending,
+        }
+    }
+}
+
+// What the Comparison view compares the selected period against ('y' toggles)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompareMode {
+    #[default]
+    PreviousPeriod,
+    PreviousYear,
+}
+
+impl CompareMode {
+    pub fn baseline(&self, period: &ReportPeriod) -> ReportPeriod {
+        match self {
+            Self::PreviousPeriod => period.prev(),
+            Self::PreviousYear => period.year_ago(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -195,6 +765,7 @@ pub enum AccField {
     Currency,
     Opening,
     Type,
+    Limit,
 }
 
 
@@ -223,6 +794,10 @@ pub struct AddTxnForm {
     pub just_entered: bool,
     pub editing: Option<EditField>,
     pub editing_txn_id: Option<i64>,
+    pub duplicate_of: Option<DuplicateMatch>,   // warned once; the next Ctrl+s saves anyway
+    pub override_lock: bool,                    // saving goes ahead in a locked period
+    pub model: CategoryModel,                   // trained from history when the form opens
+    pub suggestion: Option<Suggestion>,         // → moves the category cursor to it
 }
 
 // App
@@ -237,13 +812,166 @@ pub struct App {
     pub txn: TxnPage,
     pub dashboard: DashboardPage,
     pub add: AddTxnForm,
+    pub rules: RulesPage,
+    pub trash: TrashPage,
+    pub locks: Vec<PeriodLock>,         // loaded with the accounts
+    pub export: Option<ExportPrompt>,   // open while asking where to write a view ('w')
+    pub search: Option<SearchPrompt>,   // open while typing a search ('/')
+    pub check: Option<CheckView>,       // ledger check report ('C' on the Accounts tab)
+    pub history: Option<HistoryView>,   // edit history of one transaction ('h' on the Transactions tab)
+    pub undo: History,                  // 'u' / Ctrl+r
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RuleField {
+    #[default]
+    Name,
+    Field,
+    Kind,
+    Pattern,
+    Min,
+    Max,
+    Account,
+    Category,
+    Payee,
+    Memo,
+    Tags,
+}
+
+impl RuleField {
+    pub const ALL: [RuleField; 11] = [
+        Self::Name, Self::Field, Self::Kind, Self::Pattern, Self::Min, Self::Max,
+        Self::Account, Self::Category, Self::Payee, Self::Memo, Self::Tags,
+    ];
+}
+
+// Rule editor ('n' / 'e' on the Rules tab); empty text fields mean no bound / leave as is
+#[derive(Default, Clone)]
+pub struct RuleForm {
+    pub rule: Rule,             // id 0 until saved
+    pub min: String,
+    pub max: String,
+    pub payee: String,
+    pub memo: String,
+    pub tags: String,           // comma separated
+    pub field: RuleField,
+    pub error: Option<String>,
+}
+
+impl RuleForm {
+    fn new(rule: Rule) -> Self {
+        let text = |d: Option<Decimal>| d.map(|d| d.to_string()).unwrap_or_default();
+        Self {
+            min: text(rule.min_amount),
+            max: text(rule.max_amount),
+            payee: rule.set_payee.clone().unwrap_or_default(),
+            memo: rule.set_memo.clone().unwrap_or_default(),
+            tags: rule.add_tags.join(", "),
+            rule,
+            ..Default::default()
+        }
+    }
+
+    fn step_field(&mut self, dir: i32) {
+        let i = RuleField::ALL.iter().position(|f| *f == self.field).unwrap_or(0) as i32;
+        self.field = RuleField::ALL[(i + dir).rem_euclid(RuleField::ALL.len() as i32) as usize];
+    }
+
+    fn text_mut(&mut self) -> Option<&mut String> {
+        match self.field {
+            RuleField::Name => Some(&mut self.rule.name),
+            RuleField::Pattern => Some(&mut self.rule.pattern),
+            RuleField::Min => Some(&mut self.min),
+            RuleField::Max => Some(&mut self.max),
+            RuleField::Payee => Some(&mut self.payee),
+            RuleField::Memo => Some(&mut self.memo),
+            RuleField::Tags => Some(&mut self.tags),
+            RuleField::Field | RuleField::Kind | RuleField::Account | RuleField::Category => None,
+        }
+    }
+
+    // The rule as it would be saved, or what is wrong with the form
+    fn to_rule(&self) -> Result<Rule, String> {
+        let amount = |s: &str, what: &str| match s.trim() {
+            "" => Ok(None),
+            t => Decimal::from_str_exact(t).map(Some).map_err(|_| format!("Invalid {} amount", what)),
+        };
+        let text = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+        let mut r = self.rule.clone();
+        r.name = r.name.trim().to_string();
+        r.min_amount = amount(&self.min, "minimum")?;
+        r.max_amount = amount(&self.max, "maximum")?;
+        r.set_payee = text(&self.payee);
+        r.set_memo = text(&self.memo);
+        r.add_tags = rules::split_tags(&self.tags);
+
+        if r.name.is_empty() {
+            return Err("Name cannot be empty".into());
+        }
+        if r.min_amount.zip(r.max_amount).is_some_and(|(lo, hi)| lo > hi) {
+            return Err("Minimum is above maximum".into());
+        }
+        if r.kind == MatchKind::Regex
+            && let Err(e) = rules::compile(&r.pattern) {
+            return Err(format!("Invalid regex: {}", e));
+        }
+        if r.set_category_id.is_none() && r.set_payee.is_none() && r.set_memo.is_none() && r.add_tags.is_empty() {
+            return Err("Set a category, payee, memo or tags".into());
+        }
+        Ok(r)
+    }
+}
+
+#[derive(Default)]
+pub struct RulesPage {
+    pub list: Vec<Rule>,
+    pub sel: ListState,
+    pub form: Option<RuleForm>,
+    pub preview: Option<Vec<RuleChange>>,   // dry run over recorded transactions ('p')
+    pub preview_sel: usize,
+}
+
+#[derive(Default)]
+pub struct TrashPage {
+    pub list: Vec<TrashItemDto>,
+    pub sel: ListState,
+    pub purge_armed: Option<usize>,     // 'p' pressed once on this row; the second press purges
+}
+
+// Issues found by the ledger check, errors first
+pub struct CheckView {
+    pub report: Report,
+    pub sel: usize,
+    pub unlock: bool,   // 'f' was refused by a lock; the next 'f' overrides it
+}
+
+// Audit log entries of one row, newest first
+pub struct HistoryView {
+    pub title: String,
+    pub entries: Vec<AuditEntry>,
+    pub sel: usize,
+}
+
+// The view being written to a file; the extension picks CSV or Markdown
+pub struct ExportPrompt {
+    pub table: TableExport,
+    pub path: String,
+    pub error: Option<String>,
+}
+
+// The '/' prompt; Enter runs the query over every account
+pub struct SearchPrompt {
+    pub text: String,
+    pub error: Option<String>,
 }
 
 impl App {
     pub fn new(api: Client) -> Self {
         let today = chrono::Utc::now().date_naive();
-        let mut add = AddTxnForm::default();
-        add.date = today.format("%Y-%m-%d").to_string();
+        let add = AddTxnForm {
+            date: today.format("%Y-%m-%d").to_string(),
+            ..Default::default()
+        };
 
         Self {
             api,
@@ -252,8 +980,16 @@ impl App {
             quit: false,
             accounts: AccountsPage::default(),
             txn: TxnPage::default(),
-            dashboard: DashboardPage::default(),
+            dashboard: DashboardPage { cashflow_months: 6, forecast_days: 30, ..Default::default() },
             add,
+            rules: RulesPage::default(),
+            trash: TrashPage::default(),
+            locks: Vec::new(),
+            export: None,
+            search: None,
+            check: None,
+            history: None,
+            undo: History::default(),
         }
     }
     
@@ -273,8 +1009,47 @@ impl App {
         Ok(())
     }
 
+    pub async fn refresh_cash_flow(&mut self) -> anyhow::Result<()> {
+        let cashflow = self.api.get_cash_flow(self.dashboard.cashflow_months).await.unwrap_or_default();
+        self.dashboard.cashflow = cashflow;
+        Ok(())
+    }
+
+    pub async fn refresh_comparison(&mut self) -> anyhow::Result<()> {
+        let period = self.dashboard.period;
+        let baseline = self.dashboard.compare_mode.baseline(&period);
+        let comparison = self.api
+            .get_category_comparison((period.start, period.end()), (baseline.start, baseline.end()))
+            .await
+            .unwrap_or_default();
+        self.dashboard.comparison = comparison;
+        Ok(())
+    }
+
+    pub async fn refresh_forecast(&mut self) -> anyhow::Result<()> {
+        let forecast = self.api.get_forecast(self.dashboard.forecast_days).await.unwrap_or_default();
+        self.dashboard.forecast = forecast;
+        if self.dashboard.forecast_sel >= self.dashboard.forecast.len() {
+            self.dashboard.forecast_sel = 0;
+        }
+        Ok(())
+    }
+
+    pub async fn refresh_payees(&mut self) -> anyhow::Result<()> {
+        let period = self.dashboard.period;
+        let payees = self.api.get_payee_report(period.start, period.end(), None).await.unwrap_or_default();
+        self.dashboard.payees = payees;
+        if self.dashboard.payee_sel >= self.dashboard.payees.len() {
+            self.dashboard.payee_sel = self.dashboard.payees.len().saturating_sub(1);
+        }
+        Ok(())
+    }
+
     pub async fn refresh_monthly_report(&mut self) -> anyhow::Result<()> {
-        let report = self.api.get_monthly_report().await.unwrap_or_default();
+        self.refresh_comparison().await.ok();
+        self.refresh_payees().await.ok();
+        let period = self.dashboard.period;
+        let report = self.api.get_spending_report(period.start, period.end()).await.unwrap_or_default();
         self.dashboard.report = report;
         Ok(())
     }
@@ -285,7 +1060,9 @@ impl App {
       
         let goals = self.api.list_goals().await.unwrap_or_default();
       
-        let report = self.api.get_monthly_report().await.unwrap_or_default();
+        let period = self.dashboard.period;
+        let report = self.api.get_spending_report(period.start, period.end()).await.unwrap_or_default();
+        let cashflow = self.api.get_cash_flow(self.dashboard.cashflow_months).await.unwrap_or_default();
 
       
         if report.is_empty() {
@@ -301,6 +1078,10 @@ impl App {
 
         self.dashboard.goals = goals;
         self.dashboard.report = report;
+        self.dashboard.cashflow = cashflow;
+        self.refresh_comparison().await.ok();
+        self.refresh_payees().await.ok();
+        self.refresh_forecast().await.ok();
         self.dashboard.loading = false;
         
         Ok(())
@@ -316,6 +1097,7 @@ impl App {
     pub async fn refresh_accounts(&mut self) -> anyhow::Result<()> {
         let data = self.api.list_accounts().await?;
         self.accounts.list = data;
+        self.locks = self.api.list_period_locks().await?;
         if self.accounts.sel.selected().is_none() && !self.accounts.list.is_empty() {
             self.accounts.sel.select(Some(0));
         }
@@ -323,117 +1105,1039 @@ impl App {
     }
 
     pub async fn refresh_txns(&mut self) -> anyhow::Result<()> {
-        if let Some(aid) = self.current_account_id() {
+        if let Some(query) = self.txn.search.as_ref().map(|s| s.query.clone()) {
             self.txn.loading = true;
             self.load_categories().await;
 
-            let rows = self.api.list_transactions(aid, Some(200), Some(0)).await?;
+            let hits = self.api.search_transactions(&query, SEARCH_LIMIT).await?;
+            self.txn.ledger = None;
+            self.txn.table = hits.iter().map(|h| h.txn.clone()).collect();
+            self.txn.search = Some(TxnSearch { query, hits });
+            let len = self.txn.table.len();
+            match self.txn.tsel.selected() {
+                _ if len == 0 => self.txn.tsel.select(None),
+                Some(i) if i >= len => self.txn.tsel.select(Some(len - 1)),
+                None => self.txn.tsel.select(Some(0)),
+                _ => {}
+            }
+            self.txn.loading = false;
+        } else if let Some(payee) = self.txn.payee.clone() {
+            self.txn.loading = true;
+            self.load_categories().await;
 
-            self.txn.table = rows;
+            let period = self.dashboard.period;
+            self.txn.ledger = None;
+            self.txn.table = self.api.list_payee_transactions(&payee, period.start, period.end()).await?;
             if self.txn.tsel.selected().is_none() && !self.txn.table.is_empty() {
                 self.txn.tsel.select(Some(0));
             }
             self.txn.loading = false;
+        } else if let Some(aid) = self.current_account_id() {
+            self.txn.loading = true;
+            self.load_categories().await;
+
+            // Reload the same stretch of the ledger: from its first row unless that is the newest
+            let (from, limit) = match &self.txn.ledger {
+                Some(l) if l.account_id == aid && !l.at_newest => (l.rows.first().map(|r| r.cursor.clone()), l.rows.len()),
+                Some(l) if l.account_id == aid => (None, l.rows.len()),
+                _ => (None, 0),
+            };
+            let limit = limit.max(LEDGER_PAGE);
+            let rows = self.api.ledger_page(aid, from.as_ref().map_or(PageFrom::Newest, PageFrom::At), limit).await?;
+            let at_oldest = rows.len() < limit;
+            self.set_ledger(Ledger { account_id: aid, rows, at_newest: from.is_none(), at_oldest });
+            let len = self.txn.table.len();
+            self.txn.tsel.select(match self.txn.tsel.selected() {
+                _ if len == 0 => None,
+                Some(i) => Some(i.min(len - 1)),
+                None => Some(0),
+            });
+            self.scroll_ledger(0).await?;
+            self.txn.loading = false;
+        }
+        Ok(())
+        }
+
+    fn set_ledger(&mut self, ledger: Ledger) {
+        self.txn.table = ledger.rows.iter().map(|r| r.txn.clone()).collect();
+        self.txn.ledger = Some(ledger);
+    }
+
+    // Load the next older page under the window; returns how many rows were dropped from its top
+    async fn ledger_below(&mut self) -> anyhow::Result<usize> {
+        let Some(l) = &self.txn.ledger else { return Ok(0) };
+        let page = match l.rows.last() {
+            Some(last) => self.api.ledger_page(l.account_id, PageFrom::Below(last), LEDGER_PAGE).await?,
+            None => Vec::new(),
+        };
+        let Some(l) = self.txn.ledger.as_mut() else { return Ok(0) };
+        l.at_oldest = page.len() < LEDGER_PAGE;
+        self.txn.table.extend(page.iter().map(|r| r.txn.clone()));
+        l.rows.extend(page);
+
+        let extra = l.rows.len().saturating_sub(LEDGER_WINDOW);
+        if extra > 0 {
+            l.rows.drain(..extra);
+            self.txn.table.drain(..extra);
+            l.at_newest = false;
+            self.txn.tsel.select(self.txn.tsel.selected().map(|i| i.saturating_sub(extra)));
+            self.txn.offset = self.txn.offset.saturating_sub(extra);
+        }
+        Ok(extra)
+    }
+
+    // Load the next newer page over the window; returns how many rows were added at its top
+    async fn ledger_above(&mut self) -> anyhow::Result<usize> {
+        let Some(l) = &self.txn.ledger else { return Ok(0) };
+        let page = match l.rows.first() {
+            Some(first) => self.api.ledger_page(l.account_id, PageFrom::Above(first), LEDGER_PAGE).await?,
+            None => Vec::new(),
+        };
+        let Some(l) = self.txn.ledger.as_mut() else { return Ok(0) };
+        l.at_newest = page.len() < LEDGER_PAGE;
+        let added = page.len();
+        self.txn.table.splice(0..0, page.iter().map(|r| r.txn.clone()));
+        l.rows.splice(0..0, page);
+        self.txn.tsel.select(self.txn.tsel.selected().map(|i| i + added));
+        self.txn.offset += added;
+
+        if l.rows.len() > LEDGER_WINDOW {
+            l.rows.truncate(LEDGER_WINDOW);
+            self.txn.table.truncate(LEDGER_WINDOW);
+            l.at_oldest = false;
+        }
+        Ok(added)
+    }
+
+    // Move the selection through an account's ledger by `delta` rows (without wrapping),
+    // loading pages on the way
+    async fn scroll_ledger(&mut self, delta: isize) -> anyhow::Result<()> {
+        let mut target = self.txn.tsel.selected().unwrap_or(0) as isize + delta;
+        while let Some(l) = &self.txn.ledger && !l.at_oldest && target + LEDGER_MARGIN as isize >= l.rows.len() as isize {
+            target -= self.ledger_below().await? as isize;
+        }
+        while let Some(l) = &self.txn.ledger && !l.at_newest && target < LEDGER_MARGIN as isize {
+            // ledger_above shifts the selection, so keep the target in step with it
+            target += self.ledger_above().await? as isize;
+        }
+        let len = self.txn.table.len() as isize;
+        self.txn.tsel.select((len > 0).then(|| target.clamp(0, len - 1) as usize));
+        Ok(())
+    }
+
+    // Show the ledger from one end, or from the newest row on or before a day
+    async fn open_ledger_at(&mut self, from: PageFrom<'_>) -> anyhow::Result<bool> {
+        let Some(aid) = self.txn.ledger.as_ref().map(|l| l.account_id) else { return Ok(false) };
+        let rows = self.api.ledger_page(aid, from, LEDGER_PAGE).await?;
+        if rows.is_empty() {
+            return Ok(false);
+        }
+        let short = rows.len() < LEDGER_PAGE;
+        let (at_newest, at_oldest) = match from {
+            PageFrom::Oldest => (short, true),
+            PageFrom::Newest => (true, short),
+            _ => (false, short),
+        };
+        let selected = if matches!(from, PageFrom::Oldest) { rows.len() - 1 } else { 0 };
+        self.set_ledger(Ledger { account_id: aid, rows, at_newest, at_oldest });
+        self.txn.tsel.select(Some(selected));
+        self.txn.offset = selected;
+        self.scroll_ledger(0).await?;
+        Ok(true)
+    }
+
+    // PageUp / PageDown / Home / End on the Transactions tab
+    async fn page_txns(&mut self, code: KeyCode) -> anyhow::Result<()> {
+        let page = self.txn.height.saturating_sub(1).max(1) as isize;
+        if self.txn.ledger.is_some() {
+            return match code {
+                KeyCode::PageUp => self.scroll_ledger(-page).await,
+                KeyCode::PageDown => self.scroll_ledger(page).await,
+                KeyCode::Home => self.open_ledger_at(PageFrom::Newest).await.map(|_| ()),
+                KeyCode::End => self.open_ledger_at(PageFrom::Oldest).await.map(|_| ()),
+                _ => Ok(()),
+            };
+        }
+        let n = self.txn.table.len() as isize;
+        if n == 0 {
+            return Ok(());
+        }
+        let cur = self.txn.tsel.selected().unwrap_or(0) as isize;
+        let next = match code {
+            KeyCode::PageUp => cur - page,
+            KeyCode::PageDown => cur + page,
+            KeyCode::Home => 0,
+            _ => n - 1,
+        };
+        self.txn.tsel.select(Some(next.clamp(0, n - 1) as usize));
+        Ok(())
+    }
+
+    // The 'g' prompt: go to the newest transaction on or before a date
+    async fn handle_jump_key(&mut self, k: KeyEvent) -> anyhow::Result<()> {
+        let Some(text) = self.txn.jump.as_mut() else { return Ok(()) };
+        match k.code {
+            KeyCode::Esc => self.txn.jump = None,
+            KeyCode::Backspace => { text.pop(); }
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Enter => {
+                let Ok(date) = NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d") else {
+                    self.status = "Date must be YYYY-MM-DD".into();
+                    return Ok(());
+                };
+                self.txn.jump = None;
+                self.status = if self.open_ledger_at(PageFrom::Date(date)).await? {
+                    format!("Went to {}.", date)
+                } else {
+                    format!("No transactions on or before {}.", date)
+                };
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub async fn refresh_trash(&mut self) -> anyhow::Result<()> {
+        self.trash.list = self.api.list_trash().await?;
+        let len = self.trash.list.len();
+        match self.trash.sel.selected() {
+            _ if len == 0 => self.trash.sel.select(None),
+            Some(i) if i >= len => self.trash.sel.select(Some(len - 1)),
+            None => self.trash.sel.select(Some(0)),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub async fn refresh_rules(&mut self) -> anyhow::Result<()> {
+        self.rules.list = self.api.list_rules().await?;
+        let len = self.rules.list.len();
+        match self.rules.sel.selected() {
+            _ if len == 0 => self.rules.sel.select(None),
+            Some(i) if i >= len => self.rules.sel.select(Some(len - 1)),
+            None => self.rules.sel.select(Some(0)),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub async fn load_categories(&mut self) {
+        if let Ok(list) = self.api.list_categories().await {
+            self.add.categories = list;
+        }
+    }
+
+
+    pub fn ensure_cat_selected(&mut self) {
+        // Initialize only once when you first enter AddTxn
+        if !self.add.just_entered {
+            return;
+        }
+
+        if self.add.categories.is_empty() {
+            // If there is no category, do not select any items
+            self.add.cat_sel.select(None);
+            self.add.is_expense = false;
+        } else {
+            if self.add.cat_sel.selected().is_none() {
+                self.add.cat_sel.select(Some(0));
+            }
+            if let Some(i) = self.add.cat_sel.selected()
+                .and_then(|idx| self.add.categories.get(idx))
+                && i.r#type != CategoryType::Transfer
+            {
+                self.add.is_expense = matches!(i.r#type, CategoryType::Expense);
+            }
+        }
+
+        self.add.just_entered = false;
+    }
+
+    pub fn clamp_cat_selection(&mut self) {
+        let len = self.add.categories.len();
+        match (len, self.add.cat_sel.selected()) {
+            (0, _) => self.add.cat_sel.select(None),
+            (n, Some(i)) if i >= n => self.add.cat_sel.select(Some(n - 1)),
+            _ => {}
+        }
+    }
+
+    pub fn move_cat(&mut self, delta: i32) {
+        let len = self.add.categories.len();
+        if len == 0 {
+            self.add.cat_sel.select(None);
+            return;
+        }
+        let cur = self.add.cat_sel.selected().unwrap_or(0) as i32;
+        let new = (cur + delta).rem_euclid(len as i32) as usize;
+        self.add.cat_sel.select(Some(new));
+        if let Some(cat) = self.add.categories.get(new) {
+            // Transfers can go either way, keep the current direction
+            if cat.r#type != CategoryType::Transfer {
+                self.add.is_expense = matches!(cat.r#type, CategoryType::Expense);
+            }
+        }
+    }
+
+    // Ask the category model about the payee / memo typed so far; nothing is suggested
+    // when the cursor is already on its pick
+    pub fn update_suggestion(&mut self) {
+        let current = self.add.cat_sel.selected().and_then(|i| self.add.categories.get(i)).map(|c| c.id);
+        self.add.suggestion = self.add.model.suggest(&self.add.payee, &self.add.memo)
+            .filter(|s| Some(s.category_id) != current && self.add.categories.iter().any(|c| c.id == s.category_id));
+    }
+
+    fn accept_suggestion(&mut self) {
+        let Some(s) = self.add.suggestion.take() else { return };
+        let Some(pos) = self.add.categories.iter().position(|c| c.id == s.category_id) else { return };
+        let cur = self.add.cat_sel.selected().unwrap_or(0) as i32;
+        self.move_cat(pos as i32 - cur);
+    }
+
+    pub fn current_account(&self) -> Option<&AccountDto> {
+        let idx = self.accounts.sel.selected()?;
+        self.accounts.list.get(idx)
+    }
+    pub fn current_account_id(&self) -> Option<i64> {
+        self.current_account().map(|a| a.id)
+    }
+
+    // The last locked day of an account, if any
+    pub fn lock_date(&self, account_id: i64) -> Option<NaiveDate> {
+        lock::lock_date_for(&self.locks, account_id)
+    }
+
+    fn current_txn_index(&self) -> Option<usize> {
+        self.txn.tsel.selected()
+    }
+    fn current_txn_id(&self) -> Option<i64> {
+        let idx = self.current_txn_index()?;
+        self.txn.table.get(idx).map(|t| t.id)
+    }
+    fn move_txn(&mut self, delta: isize) {
+        let n = self.txn.table.len();
+        if n == 0 { 
+            self.txn.tsel.select(None);
+            return; 
+        }
+        let cur = self.txn.tsel.selected().unwrap_or(0) as isize;
+        let next = (cur + delta).rem_euclid(n as isize) as usize;
+        self.txn.tsel.select(Some(next));
+    }
+
+
+
+    // fn next_field(&self, f: EditField) -> EditField {
+    //     use EditField::*;
+    //     match f { 
+    //         Date     => Payee,
+    //         Payee    => Memo,
+    //         Memo     => Category,
+    //         Category => Amount,
+    //         Amount   => Date
+    //     }
+    // }
+
+    async fn handle_import_key(&mut self, k: KeyEvent) -> anyhow::Result<()> {
+        let Some(w) = self.accounts.import.as_mut() else { return Ok(()) };
+        let ctrl = k.modifiers.contains(crossterm::event::KeyModifiers::CONTROL);
+
+        // Reviewing held duplicates: only Ctrl+s goes through to the import below
+        if !w.held.is_empty() && (!ctrl || k.code != KeyCode::Char('s')) {
+            match k.code {
+                KeyCode::Esc => {
+                    w.held.clear();
+                    w.message = Some("Back to the mapping; nothing imported".into());
+                }
+                KeyCode::Up => w.held_sel = w.held_sel.saturating_sub(1),
+                KeyCode::Down => w.held_sel = (w.held_sel + 1).min(w.held.len() - 1),
+                KeyCode::Char(' ') => {
+                    if let Some(h) = w.held.get_mut(w.held_sel) {
+                        h.import = !h.import;
+                    }
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match k.code {
+            KeyCode::Esc => {
+                self.accounts.import = None;
+                self.status = "Import cancelled.".into();
+            }
+            KeyCode::Char('s') if ctrl => {
+                if w.content.is_none() {
+                    w.error = Some("Load a file first (type the path, Enter)".into());
+                    return Ok(());
+                }
+                if w.preview.txns.is_empty() {
+                    w.error = Some("Nothing to import with this mapping".into());
+                    return Ok(());
+                }
+                let (account_id, skipped) = (w.account_id, w.preview.errors.len());
+
+                // First Ctrl+s looks for rows that seem already recorded and holds them for review
+                if w.held.is_empty() {
+                    match self.api.find_import_duplicates(account_id, &w.preview.txns).await {
+                        Ok(found) => {
+                            w.held = found.into_iter().enumerate()
+                                .filter_map(|(index, m)| m.map(|matched| HeldDuplicate { index, matched, import: false }))
+                                .collect();
+                            w.held_sel = 0;
+                            if !w.held.is_empty() {
+                                w.message = Some(format!(
+                                    "{} rows look already recorded: Space imports one anyway, Ctrl+s imports, Esc goes back",
+                                    w.held.len()
+                                ));
+                                return Ok(());
+                            }
+                        }
+                        Err(e) => {
+                            w.error = Some(format!("Duplicate check failed: {}", e));
+                            return Ok(());
+                        }
+                    }
+                }
+                let held: Vec<usize> = w.held.iter().filter(|h| !h.import).map(|h| h.index).collect();
+                let txns: Vec<ImportedTxn> = w.preview.txns.iter().enumerate()
+                    .filter(|(i, _)| !held.contains(i))
+                    .map(|(_, t)| t.clone())
+                    .collect();
+
+                match self.api.import_transactions(account_id, None, &txns).await {
+                    Ok(n) => {
+                        self.status = format!(
+                            "Imported {} transactions ({} matched rules, {} already imported, {} held as duplicates, {} rows skipped).",
+                            n.imported, n.ruled, n.duplicates, held.len(), skipped
+                        );
+                        if n.locked > 0 {
+                            self.status = format!("{} Left out {} rows dated in a locked period.", self.status, n.locked);
+                        }
+                        if let Some(open) = w.preview.opening_balance
+                            && let Ok(ledger) = self.api.balance_as_of(account_id, open.date).await {
+                            self.status = format!("{} Opening: {}.", self.status, open.describe(ledger));
+                        }
+                        if let Some(stmt) = w.preview.statement_balance
+                            && let Ok(ledger) = self.api.balance_as_of(account_id, stmt.date).await {
+                            self.status = format!("{} Check: {}", self.status, stmt.describe(ledger));
+                        }
+                        let profile = w.profile.trim().to_string();
+                        if w.format == ImportFormat::Csv && !profile.is_empty()
+                            && let Err(e) = self.api.save_import_profile(&profile, &w.mapping).await {
+                            self.status = format!("{} Saving profile failed: {}", self.status, e);
+                        }
+                        self.accounts.import = None;
+                        self.refresh_accounts().await.ok();
+                    }
+                    Err(e) => w.error = Some(format!("Import failed: {}", e)),
+                }
+            }
+            KeyCode::Tab | KeyCode::Down => w.step_field(1),
+            KeyCode::BackTab | KeyCode::Up => w.step_field(-1),
+            KeyCode::Left => w.adjust(-1),
+            KeyCode::Right => w.adjust(1),
+            KeyCode::Enter => match w.field {
+                ImportField::Path => w.load_file(),
+                ImportField::Profile => {
+                    let name = w.profile.trim().to_string();
+                    match self.api.get_import_profile(&name).await {
+                        Ok(Some(mapping)) => {
+                            w.mapping = mapping;
+                            w.reparse();
+                            w.message = Some(format!("Loaded profile '{}'", name));
+                        }
+                        Ok(None) => w.message = Some(format!("New profile '{}' will be saved on import", name)),
+                        Err(e) => w.error = Some(e.to_string()),
+                    }
+                }
+                _ => w.step_field(1),
+            },
+            KeyCode::Char(' ') if matches!(w.field, ImportField::Header | ImportField::AmountMode | ImportField::Decimal) => w.adjust(1),
+            KeyCode::Char(c) => {
+                match w.field {
+                    ImportField::Path => w.path.push(c),
+                    ImportField::Profile => w.profile.push(c),
+                    ImportField::DateFormat => w.mapping.date_format.push(c),
+                    ImportField::Delimiter => w.mapping.delimiter = c,
+                    ImportField::Decimal if c == '.' || c == ',' => w.mapping.decimal_separator = c,
+                    ImportField::Skip => {
+                        if let Some(d) = c.to_digit(10) {
+                            w.mapping.skip_rows = w.mapping.skip_rows * 10 + d as usize;
+                        }
+                    }
+                    _ => return Ok(()),
+                }
+                if !matches!(w.field, ImportField::Path | ImportField::Profile) {
+                    w.reparse();
+                }
+            }
+            KeyCode::Backspace => {
+                match w.field {
+                    ImportField::Path => { w.path.pop(); }
+                    ImportField::Profile => { w.profile.pop(); }
+                    ImportField::DateFormat => { w.mapping.date_format.pop(); }
+                    ImportField::Skip => w.mapping.skip_rows /= 10,
+                    _ => return Ok(()),
+                }
+                if !matches!(w.field, ImportField::Path | ImportField::Profile) {
+                    w.reparse();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_reconcile_key(&mut self, k: KeyEvent) -> anyhow::Result<()> {
+        let ctrl = k.modifiers.contains(crossterm::event::KeyModifiers::CONTROL);
+        let Some(r) = self.txn.reconcile.as_mut() else { return Ok(()) };
+
+        // Statement prompt
+        if r.statement.is_none() {
+            match k.code {
+                KeyCode::Esc => self.txn.reconcile = None,
+                KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                    r.field = match r.field { ReconcileField::Date => ReconcileField::Balance, ReconcileField::Balance => ReconcileField::Date };
+                }
+                KeyCode::Char(c) => match r.field {
+                    ReconcileField::Date => r.end_date.push(c),
+                    ReconcileField::Balance => r.balance.push(c),
+                },
+                KeyCode::Backspace => {
+                    match r.field {
+                        ReconcileField::Date => r.end_date.pop(),
+                        ReconcileField::Balance => r.balance.pop(),
+                    };
+                }
+                KeyCode::Enter => {
+                    let Ok(end) = NaiveDate::parse_from_str(r.end_date.trim(), "%Y-%m-%d") else {
+                        r.error = Some("Date must be YYYY-MM-DD".into());
+                        return Ok(());
+                    };
+                    let Ok(balance) = Decimal::from_str_exact(r.balance.trim()) else {
+                        r.error = Some("Enter the statement's ending balance".into());
+                        return Ok(());
+                    };
+                    let account_id = r.account_id;
+                    r.reconciled = self.api.reconciled_balance(account_id, end).await?;
+                    r.statement = Some((end, balance));
+                    r.error = None;
+                    self.txn.ledger = None;
+                    self.txn.table = self.api.list_unreconciled(account_id, end).await?;
+                    self.txn.tsel.select(if self.txn.table.is_empty() { None } else { Some(0) });
+                    self.status = "Space: cleared on the statement | Ctrl+s: finish | Esc: leave (cleared marks are kept)".into();
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match k.code {
+            KeyCode::Up => self.move_txn(-1),
+            KeyCode::Down => self.move_txn(1),
+            KeyCode::Char(' ') => {
+                if let Some(i) = self.current_txn_index()
+                    && let Some(t) = self.txn.table.get(i).cloned() {
+                    let before = self.api.snapshot(Target::Transaction(t.id)).await?;
+                    match self.api.set_cleared(t.id, !t.cleared).await {
+                        Err(e) if lock::is_locked(&e) => {
+                            if let Some(r) = self.txn.reconcile.as_mut() {
+                                r.error = Some(e.to_string());
+                            }
+                            return Ok(());
+                        }
+                        res => res?,
+                    }
+                    self.remember(if t.cleared { "un-clear transaction" } else { "clear transaction" }, Target::Transaction(t.id), before).await?;
+                    self.txn.table[i].cleared = !t.cleared;
+                    self.move_txn(1);
+                }
+            }
+            KeyCode::Char('s') if ctrl => {
+                let (Some((end, _)), Some(diff)) = (r.statement, r.difference(&self.txn.table)) else { return Ok(()) };
+                if !diff.is_zero() {
+                    r.error = Some(format!("Difference is {}; clear more rows or check the statement", diff.round_dp(2)));
+                    return Ok(());
+                }
+                let n = self.api.finish_reconciliation(r.account_id, end).await?;
+                self.txn.reconcile = None;
+                self.refresh_txns().await.ok();
+                self.status = format!("Reconciled to {}: {} transactions locked.", end, n);
+            }
+            KeyCode::Esc => {
+                self.txn.reconcile = None;
+                self.refresh_txns().await.ok();
+                self.status = "Left reconcile mode; cleared marks are kept.".into();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // So do rows dated in a locked period; the second press overrides the lock
+    fn confirm_locked(&mut self, key: char, armed: Option<(i64, char)>, action: &str) -> bool {
+        let Some(t) = self.current_txn_index().and_then(|i| self.txn.table.get(i)) else { return true };
+        let Some(lock) = self.lock_date(t.account_id).filter(|d| t.txn_date <= *d) else { return true };
+        if armed == Some((t.id, key)) {
+            return true;
+        }
+        self.txn.unlock = Some((t.id, key));
+        self.status = format!("{} {} is in the period locked until {}. Press {} again to {} it anyway.", t.txn_date, fmt_amount(t.amount.0), lock, key, action);
+        false
+    }
+
+    fn clear_action(&self) -> &'static str {
+        match self.current_txn_index().and_then(|i| self.txn.table.get(i)) {
+            Some(t) if t.cleared => "un-clear",
+            _ => "clear",
+        }
+    }
+
+    // Reconciled rows change only when the key is pressed twice in a row
+    fn confirm_reconciled(&mut self, key: char, armed: Option<(i64, char)>, action: &str) -> bool {
+        let Some(t) = self.current_txn_index().and_then(|i| self.txn.table.get(i)) else { return true };
+        if !t.reconciled || armed == Some((t.id, key)) {
+            return true;
+        }
+        self.txn.unlock = Some((t.id, key));
+        self.status = format!("{} {} is reconciled. Press {} again to {} it anyway.", t.txn_date, fmt_amount(t.amount.0), key, action);
+        false
+    }
+
+    async fn run_check(&mut self) {
+        match self.api.check_ledger().await {
+            Ok(report) => {
+                self.status = format!("Checked {}", report.summary());
+                self.check = Some(CheckView { report, sel: 0, unlock: false });
+            }
+            Err(e) => self.status = format!("Check failed: {}", e),
+        }
+    }
+
+    async fn handle_check_key(&mut self, k: KeyEvent) -> anyhow::Result<()> {
+        let Some(view) = self.check.as_mut() else { return Ok(()) };
+        let len = view.report.issues.len();
+        let unlock = std::mem::take(&mut view.unlock);
+        match k.code {
+            KeyCode::Up => view.sel = view.sel.saturating_sub(1),
+            KeyCode::Down => view.sel = (view.sel + 1).min(len.saturating_sub(1)),
+            KeyCode::Char('f') if view.report.repairable() == 0 => {
+                self.status = "Nothing here can be repaired automatically.".into();
+            }
+            KeyCode::Char('f') => {
+                let api = self.api.clone().with_lock_override(unlock);
+                match api.repair_ledger(&view.report.issues).await {
+                    Ok(n) => {
+                        self.run_check().await;
+                        self.status = format!("Repaired {} issues. {}", n, self.status);
+                        self.refresh_accounts().await.ok();
+                        self.refresh_txns().await.ok();
+                    }
+                    Err(e) if lock::is_locked(&e) => {
+                        view.unlock = true;
+                        self.status = format!("Repair refused: {}. Press f again to repair anyway.", e);
+                    }
+                    Err(e) => self.status = format!("Repair failed: {}", e),
+                }
+            }
+            KeyCode::Char('r') => self.run_check().await,
+            KeyCode::Esc | KeyCode::Char('q') => self.check = None,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Keep a finished edit for undo; `before` was taken just before it was made
+    async fn remember(&mut self, label: &str, target: Target, before: Rows) -> anyhow::Result<()> {
+        let after = self.api.snapshot(target).await?;
+        let override_lock = before.transactions.iter().chain(&after.transactions)
+            .any(|r| self.lock_date(r.txn.account_id).is_some_and(|d| r.txn.txn_date <= d));
+        self.undo.push(Edit { label: label.into(), target, before, after, override_lock });
+        Ok(())
+    }
+
+    async fn undo(&mut self) -> anyhow::Result<()> {
+        let Some(edit) = self.undo.take_undo() else {
+            self.status = "Nothing to undo.".into();
+            return Ok(());
+        };
+        let api = self.api.clone().with_lock_override(edit.override_lock);
+        let status = match api.revert(edit.target, &edit.after, &edit.before).await {
+            Ok(()) => {
+                let status = format!("Undone: {}. Ctrl+r redoes it.", edit.label);
+                self.undo.undone(edit);
+                status
+            }
+            Err(e) => format!("Can't undo {}: {}", edit.label, e),
+        };
+        self.refresh_after_undo().await;
+        self.status = status;
+        Ok(())
+    }
+
+    async fn redo(&mut self) -> anyhow::Result<()> {
+        let Some(edit) = self.undo.take_redo() else {
+            self.status = "Nothing to redo.".into();
+            return Ok(());
+        };
+        let api = self.api.clone().with_lock_override(edit.override_lock);
+        let status = match api.revert(edit.target, &edit.before, &edit.after).await {
+            Ok(()) => {
+                let status = format!("Redone: {}.", edit.label);
+                self.undo.redone(edit);
+                status
+            }
+            Err(e) => format!("Can't redo {}: {}", edit.label, e),
+        };
+        self.refresh_after_undo().await;
+        self.status = status;
+        Ok(())
+    }
+
+    async fn refresh_after_undo(&mut self) {
+        self.refresh_accounts().await.ok();
+        self.refresh_txns().await.ok();
+        self.refresh_goals().await.ok();
+        self.refresh_dashboard().await.ok();
+        self.refresh_trash().await.ok();
+    }
+
+    async fn open_history(&mut self) {
+        let Some(t) = self.current_txn_index().and_then(|i| self.txn.table.get(i)) else { return };
+        let title = format!("#{} {} {}", t.id, t.txn_date, fmt_amount(t.amount.0));
+        let filter = AuditFilter { table: Some("transactions".into()), row_id: Some(t.id), ..Default::default() };
+        match self.api.list_audit(&filter).await {
+            Ok(entries) if entries.is_empty() => self.status = "No recorded changes for this transaction.".into(),
+            Ok(entries) => self.history = Some(HistoryView { title, entries, sel: 0 }),
+            Err(e) => self.status = format!("History failed: {}", e),
+        }
+    }
+
+    fn handle_history_key(&mut self, k: KeyEvent) {
+        let Some(view) = self.history.as_mut() else { return };
+        match k.code {
+            KeyCode::Up => view.sel = view.sel.saturating_sub(1),
+            KeyCode::Down => view.sel = (view.sel + 1).min(view.entries.len().saturating_sub(1)),
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('h') => self.history = None,
+            _ => {}
+        }
+    }
+
+    async fn handle_rules_key(&mut self, k: KeyEvent) -> anyhow::Result<()> {
+        let ctrl = k.modifiers.contains(crossterm::event::KeyModifiers::CONTROL);
+
+        if self.rules.form.is_some() {
+            return self.handle_rule_form_key(k).await;
+        }
+
+        // Dry run on screen: Ctrl+s writes it, Esc drops it
+        if let Some(changes) = &self.rules.preview {
+            match k.code {
+                KeyCode::Up => self.rules.preview_sel = self.rules.preview_sel.saturating_sub(1),
+                KeyCode::Down => self.rules.preview_sel = (self.rules.preview_sel + 1).min(changes.len().saturating_sub(1)),
+                KeyCode::Char('s') if ctrl => {
+                    match self.api.apply_rule_changes(changes).await {
+                        Ok(n) => self.status = format!("Rules applied: {} transactions updated.", n),
+                        Err(e) => self.status = format!("Apply failed: {}", e),
+                    }
+                    self.rules.preview = None;
+                    self.refresh_accounts().await.ok();
+                }
+                KeyCode::Esc => {
+                    self.rules.preview = None;
+                    self.status = "Nothing changed.".into();
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        let selected = self.rules.sel.selected().filter(|i| *i < self.rules.list.len());
+        match k.code {
+            KeyCode::Up | KeyCode::Down if !self.rules.list.is_empty() => {
+                let n = self.rules.list.len() as isize;
+                let delta = if k.code == KeyCode::Up { -1 } else { 1 };
+                let next = (selected.unwrap_or(0) as isize + delta).rem_euclid(n) as usize;
+                self.rules.sel.select(Some(next));
+            }
+            KeyCode::Char('n') => {
+                self.load_categories().await;
+                self.rules.form = Some(RuleForm::new(Rule { enabled: true, ..Default::default() }));
+            }
+            KeyCode::Char('e') | KeyCode::Enter => {
+                if let Some(r) = selected.and_then(|i| self.rules.list.get(i)) {
+                    let form = RuleForm::new(r.clone());
+                    self.load_categories().await;
+                    self.rules.form = Some(form);
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(r) = selected.and_then(|i| self.rules.list.get(i)) {
+                    let name = r.name.clone();
+                    self.api.delete_rule(r.id).await?;
+                    self.refresh_rules().await?;
+                    self.status = format!("Rule '{}' deleted.", name);
+                }
+            }
+            KeyCode::Char(' ') => {
+                if let Some(r) = selected.and_then(|i| self.rules.list.get(i)) {
+                    let rule = Rule { enabled: !r.enabled, ..r.clone() };
+                    self.api.save_rule(&rule).await?;
+                    self.status = format!("Rule '{}' {}.", rule.name, if rule.enabled { "enabled" } else { "disabled" });
+                    self.refresh_rules().await?;
+                }
+            }
+            // Rules run top to bottom
+            KeyCode::Char('[') | KeyCode::Char(']') => {
+                let Some(i) = selected else { return Ok(()) };
+                let j = if k.code == KeyCode::Char('[') { i.checked_sub(1) } else { Some(i + 1).filter(|j| *j < self.rules.list.len()) };
+                if let Some(j) = j {
+                    self.rules.list.swap(i, j);
+                    let ids: Vec<i64> = self.rules.list.iter().map(|r| r.id).collect();
+                    self.api.reorder_rules(&ids).await?;
+                    self.rules.sel.select(Some(j));
+                    self.refresh_rules().await?;
+                }
+            }
+            KeyCode::Char('p') => {
+                match self.api.preview_rules(None).await {
+                    Ok(changes) if changes.is_empty() => self.status = "Rules would change no transactions.".into(),
+                    Ok(changes) => {
+                        self.status = format!("Dry run: {} transactions would change. Ctrl+s applies, Esc cancels.", changes.len());
+                        self.load_categories().await;
+                        self.rules.preview = Some(changes);
+                        self.rules.preview_sel = 0;
+                    }
+                    Err(e) => self.status = format!("Preview failed: {}", e),
+                }
+            }
+            KeyCode::Char('r') => { self.refresh_rules().await.ok(); }
+            KeyCode::Char('?') => self.tab = Tab::Help,
+            KeyCode::Esc => self.tab = Tab::Accounts,
+            _ => {}
         }
         Ok(())
-        }
+    }
 
-    pub async fn load_categories(&mut self) {
-        if let Ok(list) = self.api.list_categories().await {
-            self.add.categories = list;
+    async fn handle_trash_key(&mut self, k: KeyEvent) -> anyhow::Result<()> {
+        let armed = self.trash.purge_armed.take();
+        let selected = self.trash.sel.selected().filter(|i| *i < self.trash.list.len());
+        match k.code {
+            KeyCode::Up | KeyCode::Down if !self.trash.list.is_empty() => {
+                let n = self.trash.list.len() as isize;
+                let delta = if k.code == KeyCode::Up { -1 } else { 1 };
+                let next = (selected.unwrap_or(0) as isize + delta).rem_euclid(n) as usize;
+                self.trash.sel.select(Some(next));
+            }
+            KeyCode::Char('r') | KeyCode::Enter => {
+                let Some(item) = selected.and_then(|i| self.trash.list.get(i)).cloned() else { return Ok(()) };
+                let target = match item.kind {
+                    TrashKind::Account => Target::Account(item.id),
+                    TrashKind::Transaction => Target::Transaction(item.id),
+                };
+                let before = self.api.snapshot(target).await?;
+                match self.api.restore_from_trash(item.kind, item.id).await {
+                    Ok(()) => {
+                        let label = if item.kind == TrashKind::Account { "restore account" } else { "restore transaction" };
+                        self.remember(label, target, before).await?;
+                        self.refresh_trash().await?;
+                        self.refresh_accounts().await.ok();
+                        self.status = format!("Restored {}.", trash_label(&item));
+                    }
+                    Err(e) => self.status = format!("Restore failed: {}", e),
+                }
+            }
+            KeyCode::Char('p') | KeyCode::Delete => {
+                let Some(i) = selected else { return Ok(()) };
+                let item = self.trash.list[i].clone();
+                if armed != Some(i) {
+                    self.trash.purge_armed = Some(i);
+                    self.status = format!("Press p again to delete {} for good.", trash_label(&item));
+                    return Ok(());
+                }
+                match self.api.purge_from_trash(item.kind, item.id).await {
+                    Ok(()) => {
+                        self.refresh_trash().await?;
+                        self.status = format!("Purged {}.", trash_label(&item));
+                    }
+                    Err(e) => self.status = format!("Purge failed: {}", e),
+                }
+            }
+            KeyCode::Char('?') => self.tab = Tab::Help,
+            KeyCode::Esc => self.tab = Tab::Accounts,
+            _ => {}
         }
+        Ok(())
     }
 
+    async fn handle_rule_form_key(&mut self, k: KeyEvent) -> anyhow::Result<()> {
+        let accounts: Vec<i64> = self.accounts.list.iter().map(|a| a.id).collect();
+        let categories: Vec<i64> = self.add.categories.iter().map(|c| c.id).collect();
+        let Some(form) = self.rules.form.as_mut() else { return Ok(()) };
 
-    pub fn ensure_cat_selected(&mut self) {
-        // Initialize only once when you first enter AddTxn
-        if !self.add.just_entered {
-            return;
-        }
-
-        if self.add.categories.is_empty() {
-            // If there is no category, do not select any items
-            self.add.cat_sel.select(None);
-            self.add.is_expense = false;
-        } else {
-            if self.add.cat_sel.selected().is_none() {
-                self.add.cat_sel.select(Some(0));
+        match k.code {
+            KeyCode::Esc => {
+                self.rules.form = None;
+                self.status = "Rule not saved.".into();
             }
-            if let Some(i) = self.add.cat_sel.selected()
-                .and_then(|idx| self.add.categories.get(idx))
-            {
-                self.add.is_expense = matches!(i.r#type, crate::cli::state::CategoryType::Expense);
+            KeyCode::Tab | KeyCode::Down => form.step_field(1),
+            KeyCode::BackTab | KeyCode::Up => form.step_field(-1),
+            KeyCode::Left | KeyCode::Right => {
+                let dir = if k.code == KeyCode::Left { -1 } else { 1 };
+                match form.field {
+                    RuleField::Field => form.rule.field = if dir > 0 { form.rule.field.cycle() } else { form.rule.field.cycle().cycle() },
+                    RuleField::Kind => {
+                        form.rule.kind = match form.rule.kind { MatchKind::Contains => MatchKind::Regex, MatchKind::Regex => MatchKind::Contains };
+                    }
+                    RuleField::Account => form.rule.account_id = cycle_id(form.rule.account_id, &accounts, dir),
+                    RuleField::Category => form.rule.set_category_id = cycle_id(form.rule.set_category_id, &categories, dir),
+                    _ => {}
+                }
+            }
+            KeyCode::Char(c) => {
+                if let Some(text) = form.text_mut() {
+                    text.push(c);
+                    form.error = None;
+                }
+            }
+            KeyCode::Backspace => {
+                if let Some(text) = form.text_mut() {
+                    text.pop();
+                }
+            }
+            KeyCode::Enter => {
+                let rule = match form.to_rule() {
+                    Ok(r) => r,
+                    Err(e) => {
+                        form.error = Some(e);
+                        return Ok(());
+                    }
+                };
+                match self.api.save_rule(&rule).await {
+                    Ok(id) => {
+                        self.rules.form = None;
+                        self.refresh_rules().await?;
+                        if let Some(i) = self.rules.list.iter().position(|r| r.id == id) {
+                            self.rules.sel.select(Some(i));
+                        }
+                        self.status = format!("Rule '{}' saved. p previews it on recorded transactions.", rule.name);
+                    }
+                    Err(e) => form.error = Some(e.to_string()),
+                }
             }
+            _ => {}
         }
+        Ok(())
+    }
 
-        self.add.just_entered = false;
+    fn open_export(&mut self, table: TableExport) {
+        let path = table.default_path(TableFormat::Csv);
+        self.export = Some(ExportPrompt { table, path, error: None });
     }
 
-    pub fn clamp_cat_selection(&mut self) {
-        let len = self.add.categories.len();
-        match (len, self.add.cat_sel.selected()) {
-            (0, _) => self.add.cat_sel.select(None),
-            (n, Some(i)) if i >= n => self.add.cat_sel.select(Some(n - 1)),
-            _ => {}
+    // The report the Dashboard is showing, as a table
+    fn report_table(&self) -> TableExport {
+        let d = &self.dashboard;
+        let label = d.period.label();
+        match d.view {
+            ReportView::Spending => table::category_spending(&label, &d.report),
+            ReportView::CashFlow => table::cash_flow(&d.cashflow),
+            ReportView::Comparison => {
+                let base = d.compare_mode.baseline(&d.period).label();
+                table::comparison(&format!("comparison-{}-vs-{}", label, base), &format!("Spending {} vs {}", label, base), &d.comparison)
+            }
+            ReportView::Payees => table::payees(&label, &d.payees),
+            ReportView::Forecast => table::forecast(d.forecast_days, &d.forecast),
         }
     }
 
-    pub fn move_cat(&mut self, delta: i32) {
-        let len = self.add.categories.len();
-        if len == 0 {
-            self.add.cat_sel.select(None);
-            return;
-        }
-        let cur = self.add.cat_sel.selected().unwrap_or(0) as i32;
-        let new = (cur + delta).rem_euclid(len as i32) as usize;
-        self.add.cat_sel.select(Some(new));
-        if let Some(cat) = self.add.categories.get(new) {
-            self.add.is_expense = matches!(cat.r#type, crate::cli::state::CategoryType::Expense);
+    fn handle_export_key(&mut self, k: KeyEvent) {
+        let Some(e) = self.export.as_mut() else { return };
+        match k.code {
+            KeyCode::Esc => {
+                self.export = None;
+                self.status = "Export cancelled.".into();
+            }
+            KeyCode::Enter => {
+                let path = e.path.trim().to_string();
+                let format = TableFormat::from_path(&path);
+                match e.table.render(format).and_then(|text| Ok(std::fs::write(&path, text)?)) {
+                    Ok(()) => {
+                        self.status = format!("Wrote {} rows to {}", e.table.rows.len(), path);
+                        self.export = None;
+                    }
+                    Err(err) => e.error = Some(err.to_string()),
+                }
+            }
+            // Switch the extension between .csv and .md
+            KeyCode::Tab => {
+                let (from, to) = match TableFormat::from_path(&e.path) {
+                    TableFormat::Csv => (TableFormat::Csv, TableFormat::Markdown),
+                    TableFormat::Markdown => (TableFormat::Markdown, TableFormat::Csv),
+                };
+                let stem = e.path.strip_suffix(&format!(".{}", from.extension())).unwrap_or(&e.path).to_string();
+                e.path = format!("{}.{}", stem, to.extension());
+            }
+            KeyCode::Backspace => { e.path.pop(); }
+            KeyCode::Char(c) => e.path.push(c),
+            _ => {}
         }
     }
 
-    pub fn current_account(&self) -> Option<&AccountDto> {
-        let idx = self.accounts.sel.selected()?;
-        self.accounts.list.get(idx)
-    }
-    pub fn current_account_id(&self) -> Option<i64> {
-        self.current_account().map(|a| a.id)
+    fn open_search(&mut self) {
+        let text = self.txn.search.as_ref().map(|s| s.query.text.clone()).unwrap_or_default();
+        self.search = Some(SearchPrompt { text, error: None });
     }
 
-    fn current_txn_index(&self) -> Option<usize> {
-        self.txn.tsel.selected()
-    }
-    fn current_txn_id(&self) -> Option<i64> {
-        let idx = self.current_txn_index()?;
-        self.txn.table.get(idx).map(|t| t.id)
-    }
-    fn move_txn(&mut self, delta: isize) {
-        let n = self.txn.table.len();
-        if n == 0 { 
-            self.txn.tsel.select(None);
-            return; 
+    async fn handle_search_key(&mut self, k: KeyEvent) -> anyhow::Result<()> {
+        let Some(p) = self.search.as_mut() else { return Ok(()) };
+        match k.code {
+            KeyCode::Esc => self.search = None,
+            KeyCode::Enter => {
+                let query = match search::parse(&p.text) {
+                    Ok(q) => q,
+                    Err(e) => {
+                        p.error = Some(e.to_string());
+                        return Ok(());
+                    }
+                };
+                match self.api.search_transactions(&query, SEARCH_LIMIT).await {
+                    Ok(hits) => {
+                        self.search = None;
+                        self.txn.payee = None;
+                        self.txn.reconcile = None;
+                        self.txn.ledger = None;
+                        self.txn.table = hits.iter().map(|h| h.txn.clone()).collect();
+                        self.txn.tsel.select((!hits.is_empty()).then_some(0));
+                        self.status = match hits.len() {
+                            0 => format!("Nothing matches '{}'.", query.text),
+                            SEARCH_LIMIT => format!("Showing the newest {} matches; narrow the search to see the rest.", SEARCH_LIMIT),
+                            1 => "1 match. Esc leaves the search.".into(),
+                            n => format!("{} matches. Esc leaves the search.", n),
+                        };
+                        self.txn.search = Some(TxnSearch { query, hits });
+                        self.tab = Tab::Transactions;
+                    }
+                    Err(e) => p.error = Some(e.to_string()),
+                }
+            }
+            KeyCode::Backspace => {
+                p.text.pop();
+                p.error = None;
+            }
+            KeyCode::Char(c) => {
+                p.text.push(c);
+                p.error = None;
+            }
+            _ => {}
         }
-        let cur = self.txn.tsel.selected().unwrap_or(0) as isize;
-        let next = (cur + delta).rem_euclid(n as isize) as usize;
-        self.txn.tsel.select(Some(next));
+        Ok(())
     }
 
-
-
-    // fn next_field(&self, f: EditField) -> EditField {
-    //     use EditField::*;
-    //     match f { 
-    //         Date     => Payee,
-    //         Payee    => Memo,
-    //         Memo     => Category,
-    //         Category => Amount,
-    //         Amount   => Date
-    //     }
-    // }
-
     fn acc_next_field(&self, f: AccField) -> AccField {
         use AccField::*;
-        match f { Name => Type, Type => Currency, Currency => Opening, Opening => Name }
+        match f { Name => Type, Type => Currency, Currency => Opening, Opening => Limit, Limit => Name }
     }
 
     fn cycle_account_type(&self, cur: AccountType, forward: bool) -> AccountType {
@@ -450,41 +2154,68 @@ impl App {
         if k.kind != KeyEventKind::Press {
             return Ok(());
         }
+        if self.export.is_some() {
+            self.handle_export_key(k);
+            return Ok(());
+        }
+        if self.search.is_some() {
+            return self.handle_search_key(k).await;
+        }
+        if self.check.is_some() {
+            return self.handle_check_key(k).await;
+        }
+        if self.history.is_some() {
+            self.handle_history_key(k);
+            return Ok(());
+        }
 
         let is_typing = (self.tab == Tab::AddTxn && self.add.editing.is_some()) 
-             || (self.tab == Tab::Accounts && self.accounts.creating);
+             || (self.tab == Tab::Accounts && (self.accounts.creating || self.accounts.import.is_some()))
+             || (self.tab == Tab::Rules && self.rules.form.is_some())
+             || (self.tab == Tab::Transactions && self.txn.reconcile.as_ref().is_some_and(|r| r.statement.is_none()))
+             || (self.tab == Tab::Transactions && self.txn.jump.is_some())
+             || (self.tab == Tab::Dashboard && self.dashboard.creating);
 
         // Pressing q to exit is only allowed when it is not in typing mode
-        if !is_typing {
+        if !is_typing && k.code == KeyCode::Char('q') {
+            self.quit = true;
+            return Ok(());
+        }
+        if !is_typing && !self.dashboard.creating {
+            let ctrl = k.modifiers.contains(crossterm::event::KeyModifiers::CONTROL);
             match k.code {
-                KeyCode::Char('q') => {
-                    self.quit = true;
-                    return Ok(());
-                }
+                KeyCode::Char('u') if !ctrl => return self.undo().await,
+                KeyCode::Char('r') if ctrl => return self.redo().await,
                 _ => {}
             }
         }
         match self.tab {
            Tab::Accounts => {
+            if self.accounts.import.is_some() {
+                return self.handle_import_key(k).await;
+            }
+
             if self.accounts.show_delete_confirm {
                 match k.code {
                     KeyCode::Char('y') | KeyCode::Enter => {
-                        if let Some(idx) = self.accounts.sel.selected() {
-                            if let Some(acc) = self.accounts.list.get(idx) {
-                                if let Err(e) = self.api.delete_account(acc.id).await {
-                                    self.status = format!("Delete failed: {}", e);
+                        if let Some(idx) = self.accounts.sel.selected()
+                            && let Some(acc) = self.accounts.list.get(idx) {
+                            let id = acc.id;
+                            let before = self.api.snapshot(Target::Account(id)).await?;
+                            if let Err(e) = self.api.delete_account(id).await {
+                                self.status = format!("Delete failed: {}", e);
+                            } else {
+                                self.remember("delete account", Target::Account(id), before).await?;
+                                self.status = "Account moved to the Trash. Press u to undo.".to_string();
+                                self.refresh_accounts().await.ok();
+                                self.txn.table.clear(); 
+                                self.txn.tsel.select(None);
+
+                                let len = self.accounts.list.len(); 
+                                if len > 0 {
+                                    self.accounts.sel.select(Some(len - 1));
                                 } else {
-                                    self.status = "Account deleted.".to_string();
-                                    self.refresh_accounts().await.ok();
-                                    self.txn.table.clear(); 
-                                    self.txn.tsel.select(None);
-
-                                    let len = self.accounts.list.len(); 
-                                    if len > 0 {
-                                        self.accounts.sel.select(Some(len - 1));
-                                    } else {
-                                        self.accounts.sel.select(None);
-                                    }
+                                    self.accounts.sel.select(None);
                                 }
                             }
                         }
@@ -513,6 +2244,7 @@ impl App {
                                         self.accounts.form.opening.push(c)
                                     }
                                 },
+                                AccField::Limit    => { if c.is_ascii_digit() || c == '.' { self.accounts.form.limit.push(c); } }
                                 AccField::Type     => {} 
                             }
                         }
@@ -527,6 +2259,7 @@ impl App {
                                         self.accounts.form.opening.pop(); 
                                     }
                                 }
+                                AccField::Limit    => { self.accounts.form.limit.pop(); }
                                 AccField::Type     => {}
                             }
                         }
@@ -558,14 +2291,30 @@ impl App {
                             return Ok(());
                         }
 
+                        let limit_str = self.accounts.form.limit.trim();
+                        let credit_limit = if limit_str.is_empty() {
+                            None
+                        } else {
+                            match Decimal::from_str_exact(limit_str) {
+                                Ok(d) => Some(Money(d.abs())),
+                                Err(_) => {
+                                    self.accounts.form.error = Some("Invalid credit limit".into());
+                                    return Ok(());
+                                }
+                            }
+                        };
+
                         if let Some(edit_id) = self.accounts.editing_id {
+                            let before = self.api.snapshot(Target::Account(edit_id)).await?;
                             match self.api.update_account(
                                 edit_id, 
                                 name, 
                                 self.accounts.form.r#type.as_str(), 
-                                &self.accounts.form.currency
+                                &self.accounts.form.currency,
+                                credit_limit,
                             ).await {
                                 Ok(_) => {
+                                    self.remember("edit account", Target::Account(edit_id), before).await?;
                                     self.status = "Account updated.".to_string();
                                     self.accounts.creating = false;
                                     self.accounts.editing_id = None;
@@ -586,9 +2335,11 @@ impl App {
                                 r#type: self.accounts.form.r#type,
                                 currency: if self.accounts.form.currency.trim().is_empty() { "CAD".into() } else { self.accounts.form.currency.trim().to_uppercase() },
                                 opening_balance: Money(opening),
+                                credit_limit,
                             };
                             match self.api.create_account(&req).await {
-                                Ok(_) => {
+                                Ok(acc) => {
+                                    self.remember("create account", Target::Account(acc.id), Rows::default()).await?;
                                     self.accounts.creating = false;
                                     self.accounts.form.editing = None;
                                     self.refresh_accounts().await.ok();
@@ -612,8 +2363,12 @@ impl App {
             match k.code {
                 KeyCode::Up => self.move_account(-1),
                 KeyCode::Down => self.move_account(1),
+                KeyCode::Char('/') => self.open_search(),
                 KeyCode::Enter => {
                     self.tab = Tab::Transactions;
+                    self.txn.payee = None;
+                    self.txn.search = None;
+                    self.txn.ledger = None;
                     self.txn.account_id = self.current_account_id();
                     self.refresh_txns().await.ok();
                 }
@@ -624,6 +2379,7 @@ impl App {
                         name: String::new(),
                         currency: "CAD".into(),
                         opening: "0".into(),
+                        limit: String::new(),
                         r#type: AccountType::Cash,
                         error: None,
                         editing: Some(AccField::Name),
@@ -631,26 +2387,30 @@ impl App {
                 }
                 
                 KeyCode::Char('e') => {
-                    if let Some(idx) = self.accounts.sel.selected() {
-                        if let Some(acc) = self.accounts.list.get(idx) {
-                            self.accounts.creating = true;
-                            self.accounts.editing_id = Some(acc.id);
-                        
-                            self.accounts.form = AccountForm {
-                                name: acc.name.clone(),
-                                currency: acc.currency.clone(),
-                                opening: acc.opening_balance.0.to_string(),
-                                r#type: acc.r#type,
-                                error: None,
-                                editing: Some(AccField::Name),
-                            };
-                        }
+                    if let Some(idx) = self.accounts.sel.selected()
+                        && let Some(acc) = self.accounts.list.get(idx) {
+                        self.accounts.creating = true;
+                        self.accounts.editing_id = Some(acc.id);
+                    
+                        self.accounts.form = AccountForm {
+                            name: acc.name.clone(),
+                            currency: acc.currency.clone(),
+                            opening: acc.opening_balance.0.to_string(),
+                            limit: acc.credit_limit.map(|l| l.0.to_string()).unwrap_or_default(),
+                            r#type: acc.r#type,
+                            error: None,
+                            editing: Some(AccField::Name),
+                        };
                     }
                 }
               
-                KeyCode::Char('d') | KeyCode::Delete => {
-                    if self.accounts.sel.selected().is_some() {
-                        self.accounts.show_delete_confirm = true;
+                KeyCode::Char('d') | KeyCode::Delete if self.accounts.sel.selected().is_some() => {
+                    self.accounts.show_delete_confirm = true;
+                }
+
+                KeyCode::Char('i') => {
+                    if let Some(account_id) = self.current_account_id() {
+                        self.accounts.import = Some(ImportWizard { account_id, ..Default::default() });
                     }
                 }
 
@@ -658,15 +2418,51 @@ impl App {
                          self.tab = Tab::Dashboard;
                          self.refresh_dashboard().await.ok();
                      }
+                KeyCode::Char('R') => {
+                    self.tab = Tab::Rules;
+                    self.refresh_rules().await.ok();
+                    self.load_categories().await;
+                }
+                KeyCode::Char('T') => {
+                    self.tab = Tab::Trash;
+                    self.refresh_trash().await.ok();
+                }
+                KeyCode::Char('C') => self.run_check().await,
                 KeyCode::Char('r') => { self.refresh_accounts().await.ok(); }
                 KeyCode::Char('?') => { self.tab = Tab::Help; }
                 KeyCode::Esc => { /* no-op */ }
                 _ => {}
             }
         }
-        Tab::Transactions => match k.code {
+        Tab::Transactions if self.txn.reconcile.is_some() => return self.handle_reconcile_key(k).await,
+        Tab::Transactions if self.txn.jump.is_some() => return self.handle_jump_key(k).await,
+        Tab::Transactions => {
+            let armed = self.txn.unlock.take();
+            match k.code {
+            KeyCode::Up | KeyCode::Down if self.txn.ledger.is_some() => {
+                self.scroll_ledger(if k.code == KeyCode::Up { -1 } else { 1 }).await?;
+            }
             KeyCode::Up   => self.move_txn(-1),
             KeyCode::Down => self.move_txn(1),
+            KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End => self.page_txns(k.code).await?,
+            KeyCode::Char('g') if self.txn.ledger.is_some() => {
+                let date = self.current_txn_index().and_then(|i| self.txn.table.get(i)).map(|t| t.txn_date)
+                    .unwrap_or_else(|| chrono::Local::now().date_naive());
+                self.txn.jump = Some(date.format("%Y-%m-%d").to_string());
+            }
+            KeyCode::Char('/') => self.open_search(),
+            KeyCode::Char('w') => {
+                let (name, title) = match (&self.txn.search, &self.txn.payee, self.current_account()) {
+                    (Some(s), _, _) => ("transactions-search".into(), format!("Search: {}", s.query.text)),
+                    (None, Some(payee), _) => (
+                        format!("transactions-{}-{}", slug(payee), self.dashboard.period.label()),
+                        format!("Transactions: {}, {}", payee, self.dashboard.period.label()),
+                    ),
+                    (None, None, Some(a)) => (format!("transactions-{}", slug(&a.name)), format!("Transactions: {}", a.name)),
+                    (None, None, None) => ("transactions".into(), "Transactions".into()),
+                };
+                self.open_export(table::transactions(&name, &title, &self.txn.table, &self.add.categories));
+            }
             KeyCode::Char('a') => {
                 // use crate::cli::state::EditField;
                 self.tab = Tab::AddTxn;
@@ -675,36 +2471,44 @@ impl App {
                 self.add.cat_sel.select(None);
                 self.add.just_entered = true;
                 self.add.editing_txn_id = None; 
+                self.add.duplicate_of = None;
+                self.add.override_lock = false;
                 self.add.amount.clear();
                 self.add.memo.clear();
                 self.add.payee.clear();
                 self.add.date = chrono::Utc::now().format("%Y-%m-%d").to_string();
                 self.load_categories().await;
                 self.ensure_cat_selected();
+                self.add.model = self.api.train_category_model().await.unwrap_or_default();
+                self.update_suggestion();
             }
 
+            KeyCode::Char('e') if !self.confirm_locked('e', armed, "edit") => {}
+            KeyCode::Char('e') if !self.confirm_reconciled('e', armed, "edit") => {}
             KeyCode::Char('e') => {
-                if let Some(idx) = self.txn.tsel.selected() {
-                    if let Some(txn) = self.txn.table.get(idx).cloned() {
-                        self.tab = Tab::AddTxn;
-                        self.load_categories().await; 
-                        self.add.account_id = Some(txn.account_id);
-                        self.add.editing_txn_id = Some(txn.id); 
-                        self.add.date = txn.txn_date.format("%Y-%m-%d").to_string();
-                        self.add.memo = txn.memo.unwrap_or_default();
-                        self.add.payee = txn.payee.unwrap_or_default();
-                        self.add.amount = txn.amount.0.abs().to_string(); 
-                        self.add.is_expense = txn.amount.0.is_sign_negative();
-                        
-                        if let Some(cat_id) = txn.category_id {
-                            if let Some(pos) = self.add.categories.iter().position(|c| c.id == cat_id) {
-                                self.add.cat_sel.select(Some(pos));
-                            }
-                        }
-                        
-                        self.add.just_entered = false;
-                        self.add.editing = None;
+                if let Some(idx) = self.txn.tsel.selected()
+                    && let Some(txn) = self.txn.table.get(idx).cloned() {
+                    self.tab = Tab::AddTxn;
+                    self.load_categories().await; 
+                    self.add.account_id = Some(txn.account_id);
+                    self.add.editing_txn_id = Some(txn.id); 
+                    self.add.duplicate_of = None;
+                    self.add.override_lock = armed == Some((txn.id, 'e'));
+                    self.add.date = txn.txn_date.format("%Y-%m-%d").to_string();
+                    self.add.memo = txn.memo.unwrap_or_default();
+                    self.add.payee = txn.payee.unwrap_or_default();
+                    self.add.amount = txn.amount.0.abs().to_string(); 
+                    self.add.is_expense = txn.amount.0.is_sign_negative();
+                    
+                    if let Some(cat_id) = txn.category_id
+                        && let Some(pos) = self.add.categories.iter().position(|c| c.id == cat_id) {
+                        self.add.cat_sel.select(Some(pos));
                     }
+                    
+                    self.add.just_entered = false;
+                    self.add.editing = None;
+                    self.add.model = self.api.train_category_model().await.unwrap_or_default();
+                    self.update_suggestion();
                 }
             }
 
@@ -712,25 +2516,72 @@ impl App {
             KeyCode::Char('r') => {
                 self.refresh_txns().await.ok();
             }
+            KeyCode::Char('h') => self.open_history().await,
+            KeyCode::Char('c') if !self.confirm_locked('c', armed, self.clear_action()) => {}
+            KeyCode::Char('c') if !self.confirm_reconciled('c', armed, "un-clear") => {}
+            KeyCode::Char('c') => {
+                if let Some(i) = self.current_txn_index()
+                    && let Some(t) = self.txn.table.get(i).cloned() {
+                    let before = self.api.snapshot(Target::Transaction(t.id)).await?;
+                    let api = self.api.clone().with_lock_override(armed == Some((t.id, 'c')));
+                    if let Err(e) = api.set_cleared(t.id, !t.cleared).await {
+                        self.status = format!("{} failed: {e}", if t.cleared { "Un-clear" } else { "Clear" });
+                        return Ok(());
+                    }
+                    self.remember(if t.cleared { "un-clear transaction" } else { "clear transaction" }, Target::Transaction(t.id), before).await?;
+                    self.status = if t.cleared { "Un-cleared." } else { "Cleared." }.into();
+                    let t = &mut self.txn.table[i];
+                    t.reconciled &= !t.cleared;
+                    t.cleared = !t.cleared;
+                }
+            }
+            KeyCode::Char('R') if self.txn.payee.is_none() && self.txn.search.is_none() => {
+                if let Some(account_id) = self.current_account_id() {
+                    self.txn.reconcile = Some(Reconcile {
+                        account_id,
+                        end_date: chrono::Local::now().date_naive().format("%Y-%m-%d").to_string(),
+                        ..Default::default()
+                    });
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete if !self.confirm_locked('d', armed, "delete") => {}
+            KeyCode::Char('d') | KeyCode::Delete if !self.confirm_reconciled('d', armed, "delete") => {}
             KeyCode::Char('d') | KeyCode::Delete => {
                 if let Some(id) = self.current_txn_id() {
-                    if let Err(e) = self.api.delete_transaction(id).await {
+                    let before = self.api.snapshot(Target::Transaction(id)).await?;
+                    let api = self.api.clone().with_lock_override(armed == Some((id, 'd')));
+                    if let Err(e) = api.delete_transaction(id).await {
                         self.status = format!("Delete failed: {e}");
                     } else {
+                        self.remember("delete transaction", Target::Transaction(id), before).await?;
                         let old_idx = self.txn.tsel.selected().unwrap_or(0);
                         self.refresh_txns().await.ok();
                         self.refresh_accounts().await.ok();
                         let n = self.txn.table.len();
                         if n == 0 { self.txn.tsel.select(None); } 
                         else { self.txn.tsel.select(Some(old_idx.min(n - 1))); }
-                        self.status = "Deleted.".into();
+                        self.status = "Moved to the Trash. Press u to undo.".into();
                     }
                 }
             }
-            KeyCode::Esc => self.tab = Tab::Accounts,
+            KeyCode::Esc => {
+                if self.txn.search.take().is_some() {
+                    self.txn.table.clear();
+                    self.txn.tsel.select(None);
+                    self.tab = Tab::Accounts;
+                } else if self.txn.payee.take().is_some() {
+                    // Leaving a payee drill-down goes back to the report it came from
+                    self.txn.table.clear();
+                    self.txn.tsel.select(None);
+                    self.tab = Tab::Dashboard;
+                } else {
+                    self.tab = Tab::Accounts;
+                }
+            }
             KeyCode::Char('?') => self.tab = Tab::Help,
             _ => {}
-        },
+            }
+        }
 
         Tab::AddTxn => {
             if k.kind != KeyEventKind::Press { return Ok(()); }
@@ -741,6 +2592,9 @@ impl App {
                 self.submit_txn().await.ok();
                 return Ok(());
             }
+            // Any other key means the form changed: warn about duplicates and locks again
+            self.add.duplicate_of = None;
+            self.add.override_lock = false;
 
             if k.code == KeyCode::Esc {
                 self.tab = Tab::Transactions;
@@ -822,8 +2676,10 @@ impl App {
 
                 KeyCode::Up => self.move_cat(-1),
                 KeyCode::Down => self.move_cat(1),
+                KeyCode::Right => self.accept_suggestion(),
                 _ => {}
             }
+            self.update_suggestion();
             return Ok(());
         }
         
@@ -867,8 +2723,83 @@ impl App {
                         KeyCode::Esc => self.tab = Tab::Accounts,
                         KeyCode::Char('r') => { self.refresh_dashboard().await.ok(); }
                         KeyCode::Char('?') => self.tab = Tab::Help,
+
+                        KeyCode::Char('[') => {
+                            self.dashboard.period = self.dashboard.period.prev();
+                            self.refresh_monthly_report().await.ok();
+                        }
+                        KeyCode::Char(']') => {
+                            self.dashboard.period = self.dashboard.period.next();
+                            self.refresh_monthly_report().await.ok();
+                        }
+                        KeyCode::Char('p') => {
+                            let period = self.dashboard.period;
+                            self.dashboard.period = ReportPeriod::containing(period.grouping.cycle(), period.start);
+                            self.refresh_monthly_report().await.ok();
+                        }
+                        KeyCode::Char('v') => {
+                            self.dashboard.view = self.dashboard.view.cycle();
+                        }
+                        KeyCode::Char('w') => self.open_export(self.report_table()),
+                        KeyCode::Char('W') => self.open_export(table::goals(&self.dashboard.goals)),
+                        KeyCode::Char('y') if self.dashboard.view == ReportView::Comparison => {
+                            self.dashboard.compare_mode = match self.dashboard.compare_mode {
+                                CompareMode::PreviousPeriod => CompareMode::PreviousYear,
+                                CompareMode::PreviousYear => CompareMode::PreviousPeriod,
+                            };
+                            self.refresh_comparison().await.ok();
+                        }
+                        KeyCode::Char('+') | KeyCode::Char('=') if self.dashboard.view == ReportView::CashFlow => {
+                            self.dashboard.cashflow_months = (self.dashboard.cashflow_months + 1).min(24);
+                            self.refresh_cash_flow().await.ok();
+                        }
+                        KeyCode::Char('-') if self.dashboard.view == ReportView::CashFlow => {
+                            self.dashboard.cashflow_months = self.dashboard.cashflow_months.saturating_sub(1).max(1);
+                            self.refresh_cash_flow().await.ok();
+                        }
                         
                        
+                        KeyCode::Down if self.dashboard.view == ReportView::Payees => {
+                            let len = self.dashboard.payees.len();
+                            if len > 0 {
+                                self.dashboard.payee_sel = (self.dashboard.payee_sel + 1) % len;
+                            }
+                        }
+                        KeyCode::Up if self.dashboard.view == ReportView::Payees => {
+                            let len = self.dashboard.payees.len();
+                            if len > 0 {
+                                self.dashboard.payee_sel = (self.dashboard.payee_sel + len - 1) % len;
+                            }
+                        }
+                        KeyCode::Char('h') if self.dashboard.view == ReportView::Forecast => {
+                            self.dashboard.forecast_days = match self.dashboard.forecast_days {
+                                30 => 60,
+                                60 => 90,
+                                _ => 30,
+                            };
+                            self.refresh_forecast().await.ok();
+                        }
+                        KeyCode::Right if self.dashboard.view == ReportView::Forecast => {
+                            let len = self.dashboard.forecast.len();
+                            if len > 0 {
+                                self.dashboard.forecast_sel = (self.dashboard.forecast_sel + 1) % len;
+                            }
+                        }
+                        KeyCode::Left if self.dashboard.view == ReportView::Forecast => {
+                            let len = self.dashboard.forecast.len();
+                            if len > 0 {
+                                self.dashboard.forecast_sel = (self.dashboard.forecast_sel + len - 1) % len;
+                            }
+                        }
+                        KeyCode::Enter if self.dashboard.view == ReportView::Payees => {
+                            if let Some(p) = self.dashboard.payees.get(self.dashboard.payee_sel) {
+                                self.txn.payee = Some(p.payee.clone());
+                                self.txn.search = None;
+                                self.txn.tsel.select(None);
+                                self.tab = Tab::Transactions;
+                                self.refresh_txns().await.ok();
+                            }
+                        }
                         KeyCode::Down => {
                             let len = self.dashboard.goals.len();
                             if len > 0 {
@@ -921,7 +2852,11 @@ impl App {
                
                         KeyCode::Char('d') | KeyCode::Delete => {
                             if let Some(goal) = self.dashboard.goals.get(self.dashboard.selected_index) {
-                                self.api.delete_goal(goal.id).await.ok();
+                                let id = goal.id;
+                                let before = self.api.snapshot(Target::Goal(id)).await?;
+                                if self.api.delete_goal(id).await.is_ok() {
+                                    self.remember("delete goal", Target::Goal(id), before).await?;
+                                }
                                 self.refresh_dashboard().await.ok();
                                 if self.dashboard.selected_index >= self.dashboard.goals.len() && !self.dashboard.goals.is_empty() {
                                     self.dashboard.selected_index = self.dashboard.goals.len() - 1;
@@ -933,10 +2868,12 @@ impl App {
                 }
             },
             
-            Tab::Help => match k.code {
-                KeyCode::Esc => self.tab = Tab::Accounts, 
-                _ => {}
-            },
+            Tab::Rules => return self.handle_rules_key(k).await,
+            Tab::Trash => return self.handle_trash_key(k).await,
+
+            Tab::Help => {
+                if k.code == KeyCode::Esc { self.tab = Tab::Accounts }
+            }
         }
         Ok(())
     }
@@ -1019,12 +2956,31 @@ impl App {
             
             transacted_at: date.and_hms_opt(0, 0, 0).unwrap(), 
         };
+
+        // Warn once about something that looks already recorded; Ctrl+s again saves anyway
+        if self.add.duplicate_of.take().is_none() {
+            let found = self.api
+                .find_duplicates(acc, date, decimal_amt, req.payee.as_deref(), self.add.editing_txn_id)
+                .await?;
+            if let Some(m) = found.into_iter().next() {
+                self.add.error = None;
+                self.add.success = None;
+                self.add.duplicate_of = Some(m);
+                return Ok(());
+            }
+        }
+        let api = self.api.clone().with_lock_override(self.add.override_lock);
         let res = if let Some(edit_id) = self.add.editing_txn_id {
-           
-            self.api.update_transaction(edit_id, &req).await
+            let before = self.api.snapshot(Target::Transaction(edit_id)).await?;
+            match api.update_transaction(edit_id, &req).await {
+                Ok(()) => self.remember("edit transaction", Target::Transaction(edit_id), before).await,
+                Err(e) => Err(e),
+            }
         } else {
-            
-            self.api.create_transaction(&req).await.map(|_| ()) 
+            match api.create_transaction(&req).await {
+                Ok(t) => self.remember("add transaction", Target::Transaction(t.id), Rows::default()).await,
+                Err(e) => Err(e),
+            }
         };
 
        match res {
@@ -1042,6 +2998,12 @@ impl App {
                 self.tab = Tab::Transactions; 
                 self.status = "Transaction saved.".into();
             }
+            // Refused by a lock date: warn once, Ctrl+s again saves anyway
+            Err(e) if lock::is_locked(&e) => {
+                self.add.override_lock = true;
+                self.add.success = None;
+                self.add.error = Some(format!("{}. Press Ctrl+s again to save anyway.", e));
+            }
             Err(e) => {
                 eprintln!("DEBUG: API Error: {:?}", e);
                 self.add.error = Some(format!("Error: {}", e));
@@ -1123,9 +3085,16 @@ impl App {
 
     
         let result = if let Some(id) = self.dashboard.editing_id {
-            self.api.update_goal(id, &req).await
+            let before = self.api.snapshot(Target::Goal(id)).await?;
+            match self.api.update_goal(id, &req).await {
+                Ok(()) => self.remember("edit goal", Target::Goal(id), before).await,
+                Err(e) => Err(e),
+            }
         } else {
-            self.api.create_goal(&req).await
+            match self.api.create_goal(&req).await {
+                Ok(id) => self.remember("create goal", Target::Goal(id), Rows::default()).await,
+                Err(e) => Err(e),
+            }
         };
 
         match result {
@@ -1144,4 +3113,186 @@ impl App {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn fmt_amount(d: Decimal) -> String {
+    d.round_dp(2).to_string()
+}
+
+// Step through "none" then each id, wrapping around
+fn cycle_id(cur: Option<i64>, ids: &[i64], dir: i32) -> Option<i64> {
+    let pos = cur.and_then(|c| ids.iter().position(|i| *i == c)).map(|p| p as i32 + 1).unwrap_or(0);
+    match (pos + dir).rem_euclid(ids.len() as i32 + 1) {
+        0 => None,
+        n => ids.get(n as usize - 1).copied(),
+    }
+}
+
+// File-name friendly version of a name: "RBC Chequing" -> "rbc-chequing"
+fn slug(s: &str) -> String {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// "account 'Visa'" / "2025-01-03 -12.00"
+fn trash_label(item: &TrashItemDto) -> String {
+    match &item.transaction {
+        Some(t) => format!("{} {}", t.txn_date, fmt_amount(t.amount.0)),
+        None => format!("account '{}'", item.account),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn periods_start_on_their_first_day() {
+        let d = date(2025, 8, 14); // a Thursday
+        assert_eq!(ReportGrouping::Day.period_start(d), d);
+        assert_eq!(ReportGrouping::Week.period_start(d), date(2025, 8, 11));
+        assert_eq!(ReportGrouping::Month.period_start(d), date(2025, 8, 1));
+        assert_eq!(ReportGrouping::Quarter.period_start(d), date(2025, 7, 1));
+        assert_eq!(ReportGrouping::Year.period_start(d), date(2025, 1, 1));
+        assert_eq!(ReportGrouping::Week.period_start(date(2025, 8, 11)), date(2025, 8, 11));
+    }
+
+    #[test]
+    fn shifting_crosses_year_ends() {
+        assert_eq!(ReportGrouping::Month.shift(date(2025, 1, 1), -1), date(2024, 12, 1));
+        assert_eq!(ReportGrouping::Month.shift(date(2024, 12, 1), 1), date(2025, 1, 1));
+        assert_eq!(ReportGrouping::Quarter.shift(date(2025, 1, 1), -1), date(2024, 10, 1));
+        assert_eq!(ReportGrouping::Quarter.shift(date(2025, 10, 1), 2), date(2026, 4, 1));
+        assert_eq!(ReportGrouping::Year.shift(date(2025, 1, 1), -3), date(2022, 1, 1));
+        assert_eq!(ReportGrouping::Week.shift(date(2024, 12, 30), 1), date(2025, 1, 6));
+        assert_eq!(ReportGrouping::Day.shift(date(2025, 3, 1), -1), date(2025, 2, 28));
+    }
+
+    #[test]
+    fn periods_end_on_their_last_day() {
+        let end = |g, d| ReportPeriod::containing(g, d).end();
+        assert_eq!(end(ReportGrouping::Month, date(2024, 2, 10)), date(2024, 2, 29));
+        assert_eq!(end(ReportGrouping::Month, date(2025, 2, 10)), date(2025, 2, 28));
+        assert_eq!(end(ReportGrouping::Quarter, date(2025, 11, 5)), date(2025, 12, 31));
+        assert_eq!(end(ReportGrouping::Week, date(2025, 8, 14)), date(2025, 8, 17));
+        assert_eq!(end(ReportGrouping::Day, date(2025, 8, 14)), date(2025, 8, 14));
+        let p = ReportPeriod::containing(ReportGrouping::Month, date(2025, 1, 20));
+        assert_eq!((p.prev().start, p.next().start), (date(2024, 12, 1), date(2025, 2, 1)));
+    }
+
+    async fn reconciled(app: &App) -> Vec<i64> {
+        sqlx::query_scalar("SELECT transaction_id FROM transactions WHERE reconciled = 1 ORDER BY transaction_id")
+            .fetch_all(app.api.pool()).await.unwrap()
+    }
+
+    async fn app_with_account() -> (App, i64) {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        crate::database::db::migrate::run_migrations(&pool).await.unwrap();
+        sqlx::query("INSERT INTO categories (category_id, category_name, category_type, icon) VALUES (1, 'Groceries', 'EXPENSE', '')")
+            .execute(&pool).await.unwrap();
+        let api = Client::from_pool(pool);
+        let req = CreateAccountReq {
+            name: "Chequing".into(),
+            r#type: AccountType::Checking,
+            currency: "CAD".into(),
+            opening_balance: Money(Decimal::ZERO),
+            credit_limit: None,
+        };
+        let id = api.create_account(&req).await.unwrap().id;
+        (App::new(api), id)
+    }
+
+    async fn add(app: &App, account_id: i64, amount: &str, day: NaiveDate) -> i64 {
+        let amount: Decimal = amount.parse().unwrap();
+        let req = CreateTxnReq {
+            account_id,
+            category_id: 1,
+            amount: Money(amount),
+            base_amount: Money(amount.abs()),
+            is_expense: amount.is_sign_negative(),
+            description: None,
+            payee: Some("Payee".into()),
+            currency: "CAD".into(),
+            transacted_at: day.and_hms_opt(12, 0, 0).unwrap(),
+        };
+        app.api.create_transaction(&req).await.unwrap().id
+    }
+
+    async fn press(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+        app.handle_reconcile_key(KeyEvent::new(code, modifiers)).await.unwrap();
+    }
+
+    // Opens reconcile mode and accepts a statement ending on `end` with `balance`
+    async fn start_reconcile(app: &mut App, account_id: i64, end: &str, balance: &str) {
+        app.txn.reconcile = Some(Reconcile { account_id, ..Default::default() });
+        for c in end.chars() {
+            press(app, KeyCode::Char(c), KeyModifiers::NONE).await;
+        }
+        press(app, KeyCode::Tab, KeyModifiers::NONE).await;
+        for c in balance.chars() {
+            press(app, KeyCode::Char(c), KeyModifiers::NONE).await;
+        }
+        press(app, KeyCode::Enter, KeyModifiers::NONE).await;
+    }
+
+    fn difference(app: &App) -> Decimal {
+        app.txn.reconcile.as_ref().unwrap().difference(&app.txn.table).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reconciling_locks_the_cleared_rows_up_to_the_statement() {
+        let (mut app, acc) = app_with_account().await;
+        let pay = add(&app, acc, "1000", date(2025, 3, 2)).await;
+        let rent = add(&app, acc, "-200", date(2025, 3, 10)).await;
+        let late = add(&app, acc, "-50", date(2025, 3, 15)).await;
+        let april = add(&app, acc, "-30", date(2025, 4, 2)).await;
+        // Cleared already, but after the first statement ends
+        app.api.set_cleared(april, true).await.unwrap();
+
+        start_reconcile(&mut app, acc, "2025-03-31", "800").await;
+        assert_eq!(app.txn.table.iter().map(|t| t.id).collect::<Vec<_>>(), [pay, rent, late]);
+        assert_eq!(difference(&app), Decimal::from(800));
+
+        // Clearing the pay cheque alone overshoots; finishing is refused
+        press(&mut app, KeyCode::Char(' '), KeyModifiers::NONE).await;
+        assert_eq!(difference(&app), Decimal::from(-200));
+        press(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL).await;
+        assert!(app.txn.reconcile.as_ref().unwrap().error.is_some());
+        assert!(reconciled(&app).await.is_empty());
+
+        press(&mut app, KeyCode::Char(' '), KeyModifiers::NONE).await;
+        assert!(difference(&app).is_zero());
+        press(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL).await;
+        assert!(app.txn.reconcile.is_none());
+        assert_eq!(reconciled(&app).await, [pay, rent]);
+
+        // The next statement starts from what the first one reconciled
+        start_reconcile(&mut app, acc, "2025-04-30", "720").await;
+        assert_eq!(app.txn.reconcile.as_ref().unwrap().reconciled, Decimal::from(800));
+        assert_eq!(app.txn.table.iter().map(|t| t.id).collect::<Vec<_>>(), [late, april]);
+        assert_eq!(difference(&app), Decimal::from(-50));
+        press(&mut app, KeyCode::Char(' '), KeyModifiers::NONE).await;
+        assert!(difference(&app).is_zero());
+        press(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL).await;
+        assert_eq!(reconciled(&app).await, [pay, rent, late, april]);
+        assert_eq!(app.api.reconciled_balance(acc, date(2025, 4, 30)).await.unwrap(), Decimal::from(720));
+    }
+
+    #[test]
+    fn labels_use_the_iso_week_year() {
+        assert_eq!(ReportGrouping::Week.label(date(2024, 12, 30)), "2025-W01");
+        assert_eq!(ReportGrouping::Week.label(date(2021, 1, 4)), "2021-W01");
+        assert_eq!(ReportGrouping::Quarter.label(date(2025, 10, 1)), "2025-Q4");
+        assert_eq!(ReportGrouping::Month.label(date(2025, 3, 1)), "2025-03");
+        assert_eq!(ReportGrouping::Day.label(date(2025, 3, 1)), "2025-03-01");
+        assert_eq!(ReportGrouping::Year.label(date(2025, 1, 1)), "2025");
+    }
+}
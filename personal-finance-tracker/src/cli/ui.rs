@@ -1,13 +1,18 @@
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols,
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Row, Table, Tabs, Cell, Wrap,Gauge, BarChart},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Row, Table, TableState, Tabs, Cell, Wrap,Gauge, BarChart, Bar, BarGroup, Chart, Dataset, Axis, GraphType},
     Frame,
 };
 
 use ratatui::prelude::Alignment;
-use crate::cli::state::{self, App, AccField, EditField}; 
+use crate::cli::state::{self, App, AccField, CheckView, EditField, ExportPrompt, HistoryView, SearchPrompt, ImportField, ImportFormat, ImportWizard, Reconcile, ReconcileField, RuleField, RuleForm}; 
+use crate::check::Severity;
+use crate::export::table::TableFormat;
+use crate::search;
+use crate::import::csv::AmountColumns;
 use rust_decimal::Decimal;
 use crate::cli::state::Tab;
 
@@ -24,7 +29,7 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         .split(size);
 
     // Tabs
-    let titles = ["Accounts", "Transactions", "AddTxn", "Goals", "Help"] 
+    let titles = ["Accounts", "Transactions", "AddTxn", "Goals", "Rules", "Trash", "Help"] 
         .iter()
         .map(|t| Line::from(Span::raw(*t)))
         .collect::<Vec<_>>();
@@ -35,7 +40,9 @@ pub fn draw(f: &mut Frame, app: &mut App) {
             state::Tab::Transactions => 1, 
             state::Tab::AddTxn => 2, 
             Tab::Dashboard => 3,
-            state::Tab::Help => 4 
+            Tab::Rules => 4,
+            Tab::Trash => 5,
+            state::Tab::Help => 6 
         })
         .block(Block::default().borders(Borders::ALL).title(" Finance Tracker "))
         .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)); 
@@ -47,12 +54,14 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         state::Tab::Transactions => draw_txns(f, root[1], app),
         state::Tab::AddTxn => draw_add_txn(f, root[1], app),
         Tab::Dashboard => { ui_dashboard(f, &app.dashboard, root[1]); }
+        Tab::Rules => draw_rules(f, root[1], app),
+        Tab::Trash => draw_trash(f, root[1], app),
         state::Tab::Help => draw_help(f, root[1]),
     }
 
     let status_text = match app.tab {
-        state::Tab::AddTxn => "",
-        _ => " Press ? for help | q to quit ", 
+        state::Tab::AddTxn => String::new(),
+        _ => format!(" {} ", app.status),
     };
     
     let status = Paragraph::new(status_text)
@@ -61,11 +70,17 @@ pub fn draw(f: &mut Frame, app: &mut App) {
 
   
     if app.accounts.creating {
-        let area = center_rect(root[1], 60, 14); 
+        let area = center_rect(root[1], 60, 15); 
         f.render_widget(Clear, area);
         draw_new_account_modal(f, area, app);
     }
 
+    if app.tab == Tab::Accounts && app.accounts.import.is_some() {
+        let area = center_rect(root[1], 100, 32);
+        f.render_widget(Clear, area);
+        draw_import_wizard(f, area, app);
+    }
+
     if app.accounts.show_delete_confirm {
         let area = center_rect(root[1], 40, 10);
         
@@ -83,7 +98,7 @@ pub fn draw(f: &mut Frame, app: &mut App) {
             Line::from(Span::styled("Are you sure you want to", Style::default().fg(Color::White))),
             Line::from(Span::styled("DELETE this account?", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
             Line::from(""),
-            Line::from(Span::raw("All transactions will be lost!")),
+            Line::from(Span::raw("It goes to the Trash with all its transactions.")),
             Line::from(""),
             Line::from(""),
             Line::from(vec![
@@ -109,6 +124,164 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         f.render_widget(Clear, area); 
         draw_new_goal_modal(f, area, app);
     }
+
+    if app.tab == Tab::Transactions && let Some(r) = app.txn.reconcile.as_ref().filter(|r| r.statement.is_none()) {
+        let area = center_rect(root[1], 60, 8);
+        f.render_widget(Clear, area);
+        draw_reconcile_prompt(f, area, r);
+    }
+
+    if app.tab == Tab::Transactions && let Some(date) = &app.txn.jump {
+        let area = center_rect(root[1], 50, 5);
+        f.render_widget(Clear, area);
+        let lines = vec![
+            Line::from(vec![
+                Span::styled(" Date: ", Style::default().fg(Color::Yellow)),
+                Span::raw(format!("{}_", date)),
+            ]),
+            Line::from(Span::styled(" The newest transaction on or before it", Style::default().add_modifier(Modifier::DIM))),
+            Line::from(" Enter: Go | Esc: Cancel"),
+        ];
+        let block = Block::default().borders(Borders::ALL).title(" Go to Date ");
+        f.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    if app.tab == Tab::Rules && let Some(form) = &app.rules.form {
+        let area = center_rect(root[1], 72, 17);
+        f.render_widget(Clear, area);
+        draw_rule_form(f, area, app, form);
+    }
+
+    if let Some(c) = &app.check {
+        let area = center_rect(root[1], root[1].width.saturating_sub(4), root[1].height.saturating_sub(2));
+        f.render_widget(Clear, area);
+        draw_check(f, area, c);
+    }
+
+    if let Some(h) = &app.history {
+        let area = center_rect(root[1], root[1].width.saturating_sub(4), root[1].height.saturating_sub(2));
+        f.render_widget(Clear, area);
+        draw_history(f, area, h);
+    }
+
+    if let Some(e) = &app.export {
+        let area = center_rect(root[1], 80, 7);
+        f.render_widget(Clear, area);
+        draw_export_prompt(f, area, e);
+    }
+
+    if let Some(p) = &app.search {
+        let area = center_rect(root[1], 80, 7);
+        f.render_widget(Clear, area);
+        draw_search_prompt(f, area, p);
+    }
+}
+
+fn draw_search_prompt(f: &mut Frame, area: Rect, p: &SearchPrompt) {
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(" Find: ", Style::default().fg(Color::Yellow)),
+            Span::raw(format!("{}_", p.text)),
+        ]),
+        Line::from(Span::styled(format!(" Words, or {} as field:value", search::FIELDS), Style::default().add_modifier(Modifier::DIM))),
+        Line::from(" Enter: Search all accounts | Esc: Cancel"),
+    ];
+    if let Some(err) = &p.error {
+        lines.push(Line::from(Span::styled(format!(" Error: {}", err), Style::default().fg(Color::Red))));
+    }
+    let block = Block::default().borders(Borders::ALL).title(" Search Transactions ");
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+// `text` with the words that matched the search marked
+fn marked(text: &str, terms: &[String]) -> Line<'static> {
+    Line::from(search::highlight(text, terms).into_iter().map(|(run, hit)| if hit {
+        Span::styled(run.to_string(), Style::default().fg(Color::Black).bg(Color::Yellow))
+    } else {
+        Span::raw(run.to_string())
+    }).collect::<Vec<_>>())
+}
+
+fn draw_export_prompt(f: &mut Frame, area: Rect, e: &ExportPrompt) {
+    let format = match TableFormat::from_path(&e.path) {
+        TableFormat::Csv => "CSV",
+        TableFormat::Markdown => "Markdown table",
+    };
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(" File: ", Style::default().fg(Color::Yellow)),
+            Span::raw(format!("{}_", e.path)),
+        ]),
+        Line::from(Span::styled(format!(" {} rows as {}", e.table.rows.len(), format), Style::default().add_modifier(Modifier::DIM))),
+        Line::from(" Enter: Write | Tab: CSV / Markdown | Esc: Cancel"),
+    ];
+    if let Some(err) = &e.error {
+        lines.push(Line::from(Span::styled(format!(" Error: {}", err), Style::default().fg(Color::Red))));
+    }
+    let block = Block::default().borders(Borders::ALL).title(format!(" Export: {} ", e.table.title));
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_check(f: &mut Frame, area: Rect, c: &CheckView) {
+    let block = Block::default().borders(Borders::ALL).title(" Ledger Check (↑/↓ f:Repair All r:Re-run Esc:Close) ");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)])
+        .split(inner);
+
+    let summary = if c.report.issues.is_empty() { Color::Green } else { Color::Yellow };
+    f.render_widget(Paragraph::new(Span::styled(format!(" Checked {}", c.report.summary()), Style::default().fg(summary))), rows[0]);
+
+    let items: Vec<ListItem> = c.report.issues.iter().map(|i| {
+        let color = if i.severity == Severity::Error { Color::Red } else { Color::Yellow };
+        let mut lines = vec![Line::from(vec![
+            Span::styled(format!(" {:<8}", i.severity.as_str()), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+            Span::raw(i.message.clone()),
+        ])];
+        lines.push(match &i.repair {
+            Some(r) => Line::from(Span::styled(format!("          fix: {}", r.describe()), Style::default().fg(Color::Green))),
+            None => Line::from(Span::styled("          needs a look by hand", Style::default().add_modifier(Modifier::DIM))),
+        });
+        ListItem::new(lines)
+    }).collect();
+    let mut sel = ratatui::widgets::ListState::default();
+    sel.select((!items.is_empty()).then_some(c.sel));
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    f.render_stateful_widget(list, rows[1], &mut sel);
+}
+
+fn draw_history(f: &mut Frame, area: Rect, h: &HistoryView) {
+    let block = Block::default().borders(Borders::ALL).title(format!(" History of {} (↑/↓ Esc:Close) ", h.title));
+    let items: Vec<ListItem> = h.entries.iter().map(|e| {
+        let color = match e.action.as_str() {
+            "insert" => Color::Green,
+            "delete" => Color::Red,
+            _ => Color::Yellow,
+        };
+        let mut lines = vec![Line::from(vec![
+            Span::raw(format!(" {}  ", e.at.replace('T', " ").trim_end_matches('Z'))),
+            Span::styled(format!("{:<7}", e.action), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+            Span::styled(format!(" via {}", e.origin), Style::default().fg(Color::Cyan)),
+        ])];
+        for c in e.changes() {
+            let text = match (&c.before, &c.after) {
+                (Some(b), Some(a)) => format!("{} → {}", b, a),
+                (Some(v), None) | (None, Some(v)) => v.clone(),
+                (None, None) => String::new(),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("     {:<15}", c.field), Style::default().add_modifier(Modifier::DIM)),
+                Span::raw(text),
+            ]));
+        }
+        ListItem::new(lines)
+    }).collect();
+    let mut sel = ratatui::widgets::ListState::default();
+    sel.select(Some(h.sel));
+    let list = List::new(items).block(block).highlight_style(Style::default().bg(Color::DarkGray));
+    f.render_stateful_widget(list, area, &mut sel);
 }
 
 // Accounts Page
@@ -144,7 +317,7 @@ fn draw_accounts(f: &mut Frame, area: Rect, app: &mut App) {
     }).collect();
 
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title(" Accounts (n:New e:Edit d:Del g: Goal Enter:Txns) "))
+        .block(Block::default().borders(Borders::ALL).title(" Accounts (n:New e:Edit d:Del i:Import g: Goal R:Rules C:Check Enter:Txns) "))
         .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
 
     f.render_stateful_widget(list, cols[0], &mut app.accounts.sel);
@@ -161,6 +334,11 @@ fn draw_accounts(f: &mut Frame, area: Rect, app: &mut App) {
             Line::from(vec![Span::raw("Type:     "), Span::styled(format!("{:?}", acc.r#type), Style::default().fg(Color::Cyan))]),
             Line::from(vec![Span::raw("Currency: "), Span::raw(&acc.currency)]),
             Line::from(vec![Span::raw("Balance:  "), Span::styled(fmt_money(balance_val), Style::default().fg(b_color))]),
+            Line::from(vec![Span::raw("Limit:    "), Span::raw(acc.credit_limit.map(|l| fmt_money(l.0)).unwrap_or_else(|| "-".into()))]),
+            Line::from(vec![Span::raw("Locked:   "), match app.lock_date(acc.id) {
+                Some(d) => Span::styled(format!("until {}", d), Style::default().fg(Color::Yellow)),
+                None => Span::raw("-"),
+            }]),
             Line::from(""),
             Line::from(vec![Span::raw("Created:  "), Span::raw(&acc.created_at)]),
         ];
@@ -235,6 +413,10 @@ fn draw_new_account_modal(f: &mut Frame, area: Rect, app: &mut App) {
             "Initial Balance (Positive=Asset, Negative=Debt)", 
             "[Enter] Save/Create   [Esc] Cancel" 
         ),
+        Some(AccField::Limit) => (
+            "Credit limit, used by the forecast (blank = none)", 
+            "[Enter] Save/Create   [Esc] Cancel" 
+        ),
         None => ("", "[Esc] Cancel"),
     };
 
@@ -271,6 +453,7 @@ fn draw_new_account_modal(f: &mut Frame, area: Rect, app: &mut App) {
         style_line(AccField::Type, "Type", &type_str),
         style_line(AccField::Currency, "Currency", &form.currency),
         style_line(AccField::Opening, "Opening", &form.opening),
+        style_line(AccField::Limit, "Limit", &form.limit),
         
         Line::from(""),
 
@@ -294,13 +477,205 @@ fn draw_new_account_modal(f: &mut Frame, area: Rect, app: &mut App) {
 
     f.render_widget(p, area);
 }
+fn draw_import_wizard(f: &mut Frame, area: Rect, app: &mut App) {
+    let Some(w) = &app.accounts.import else { return };
+    let m = &w.mapping;
+
+    let kind = match w.format {
+        ImportFormat::Csv => "CSV",
+        ImportFormat::Ofx => "OFX",
+        ImportFormat::Qif => "QIF",
+        ImportFormat::Camt => "camt.053",
+        ImportFormat::Mt940 => "MT940",
+    };
+    let block = Block::default().borders(Borders::ALL).title(format!(" Import {} (↑/↓:Field ←/→:Change Enter:Load Ctrl+s:Import Esc:Cancel) ", kind));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let fields = w.fields();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(fields.len() as u16 + 1), Constraint::Length(2), Constraint::Min(3)])
+        .split(inner);
+
+    let col = |c: usize| w.sheet.column_name(c);
+    let opt_col = |c: Option<usize>| c.map(col).unwrap_or_else(|| "-".into());
+    let delimiter = match m.delimiter { '\t' => "tab".to_string(), c => c.to_string() };
+
+    let lines: Vec<Line> = fields.iter().map(|field| {
+        let (label, value) = match field {
+            ImportField::Path => ("File", w.path.clone()),
+            ImportField::Profile => ("Profile", if w.profile.is_empty() { "(none, Enter loads)".into() } else { w.profile.clone() }),
+            ImportField::Delimiter => ("Delimiter", delimiter.clone()),
+            ImportField::Header => ("Header row", if m.has_header { "yes".into() } else { "no".into() }),
+            ImportField::Skip => ("Skip lines", m.skip_rows.to_string()),
+            ImportField::DateCol => ("Date", col(m.date_col)),
+            ImportField::DateFormat => ("Date format", m.date_format.clone()),
+            ImportField::AmountMode => ("Amount as", match m.amount {
+                AmountColumns::Single { .. } => "one signed column".into(),
+                AmountColumns::DebitCredit { .. } => "debit / credit columns".into(),
+            }),
+            ImportField::AmountCol => match m.amount {
+                AmountColumns::Single { column } => ("Amount", col(column)),
+                AmountColumns::DebitCredit { debit, .. } => ("Debit", col(debit)),
+            },
+            ImportField::CreditCol => ("Credit", match m.amount {
+                AmountColumns::DebitCredit { credit, .. } => col(credit),
+                AmountColumns::Single { .. } => "-".into(),
+            }),
+            ImportField::PayeeCol => ("Payee", opt_col(m.payee_col)),
+            ImportField::MemoCol => ("Memo", opt_col(m.memo_col)),
+            ImportField::Decimal => ("Decimal sep", m.decimal_separator.to_string()),
+        };
+        if *field == w.field {
+            Line::from(vec![
+                Span::styled(" > ", Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{:<12}: {}", label, value), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            ])
+        } else {
+            Line::from(format!("   {:<12}: {}", label, value))
+        }
+    }).collect();
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let summary = if let Some(err) = &w.error {
+        Line::from(Span::styled(format!(" Error: {}", err), Style::default().fg(Color::Red)))
+    } else if w.content.is_some() {
+        let first_err = w.preview.errors.first().map(|e| format!(" | {}", e)).unwrap_or_default();
+        let color = if w.preview.errors.is_empty() { Color::Green } else { Color::Yellow };
+        Line::from(Span::styled(
+            format!(" {} rows ready, {} skipped{}", w.preview.txns.len(), w.preview.errors.len(), first_err),
+            Style::default().fg(color),
+        ))
+    } else {
+        Line::from(Span::styled(" Type the file path and press Enter", Style::default().fg(Color::Cyan)))
+    };
+    let balance = match (w.preview.lines_check(), w.preview.statement_balance) {
+        (Some(check), _) => Some(format!(" | {}", check)),
+        (None, Some(b)) => Some(format!(" | statement balance {} on {}", fmt_money(b.amount), b.date)),
+        _ => None,
+    };
+    let message = w.message.as_ref().map(|m| format!("{}{}", m, balance.unwrap_or_default()));
+    let message = message.as_deref().map(|m| Line::from(Span::styled(format!(" {}", m), Style::default().add_modifier(Modifier::DIM))));
+    f.render_widget(Paragraph::new(std::iter::once(summary).chain(message).collect::<Vec<_>>()), chunks[1]);
+
+    if !w.held.is_empty() {
+        draw_held_duplicates(f, chunks[2], w);
+        return;
+    }
+
+    let header = Row::new(vec!["Date", "Amount", "Payee", "Category", "Memo"])
+        .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+    let rows: Vec<Row> = w.preview.txns.iter().take(chunks[2].height.saturating_sub(3) as usize).map(|t| {
+        let color = if t.amount.is_sign_negative() { Color::Red } else { Color::Green };
+        Row::new(vec![
+            Cell::from(t.date.to_string()),
+            Cell::from(Span::styled(fmt_money(t.amount), Style::default().fg(color))),
+            Cell::from(t.payee.clone().unwrap_or_default()),
+            Cell::from(t.category.clone().unwrap_or_else(|| "-".into())),
+            Cell::from(t.memo.clone().unwrap_or_default()),
+        ])
+    }).collect();
+    let table = Table::new(rows, [Constraint::Length(12), Constraint::Length(12), Constraint::Percentage(30), Constraint::Percentage(25), Constraint::Percentage(30)])
+        .header(header)
+        .block(Block::default().borders(Borders::TOP).title(" Preview "));
+    f.render_widget(table, chunks[2]);
+}
+
+// Statement rows held as likely duplicates, next to the transaction they match
+fn draw_held_duplicates(f: &mut Frame, area: Rect, w: &ImportWizard) {
+    let header = Row::new(vec!["", "Date", "Amount", "Payee", "Recorded", "Recorded Payee", "Match"])
+        .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+    let rows: Vec<Row> = w.held.iter().map(|h| {
+        let t = &w.preview.txns[h.index];
+        let e = &h.matched.existing;
+        let action = if h.import {
+            Span::styled("import", Style::default().fg(Color::Green))
+        } else {
+            Span::styled("skip", Style::default().fg(Color::DarkGray))
+        };
+        Row::new(vec![
+            Cell::from(action),
+            Cell::from(t.date.to_string()),
+            Cell::from(fmt_money(t.amount)),
+            Cell::from(t.payee.clone().unwrap_or_default()),
+            Cell::from(e.txn_date.to_string()),
+            Cell::from(e.payee.clone().unwrap_or_default()),
+            Cell::from(format!("{:.0}%", h.matched.score * 100.0)),
+        ])
+    }).collect();
+    let mut sel = TableState::default();
+    sel.select(Some(w.held_sel));
+    let table = Table::new(rows, [
+        Constraint::Length(7), Constraint::Length(11), Constraint::Length(11), Constraint::Percentage(25),
+        Constraint::Length(11), Constraint::Percentage(25), Constraint::Length(6),
+    ])
+        .header(header)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .block(Block::default().borders(Borders::TOP).title(" Likely Duplicates (↑/↓:Select Space:Import/Skip Ctrl+s:Import Esc:Back) "));
+    f.render_stateful_widget(table, area, &mut sel);
+}
+
 // Transactions Page
 fn draw_txns(f: &mut Frame, area: Rect, app: &mut App) {
-    let header = Row::new(vec!["Date", "Category", "Memo", "Amount"])
+    if app.txn.search.is_some() {
+        return draw_search_results(f, area, app);
+    }
+    // Reconcile mode: the statement against the cleared balance above the table
+    let area = match app.txn.reconcile.as_ref().and_then(|r| r.statement.map(|s| (r, s))) {
+        Some((r, (end, statement))) => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(4), Constraint::Min(5)])
+                .split(area);
+            let diff = r.difference(&app.txn.table).unwrap_or_default();
+            let diff_style = if diff.is_zero() { Style::default().fg(Color::Green).add_modifier(Modifier::BOLD) } else { Style::default().fg(Color::Yellow) };
+            let lines = vec![
+                Line::from(vec![
+                    Span::raw(format!(" Statement {}: {}   Cleared: {}   ", end, fmt_money(statement), fmt_money(statement - diff))),
+                    Span::raw("Difference: "),
+                    Span::styled(fmt_money(diff), diff_style),
+                    Span::styled(if diff.is_zero() { "  Ctrl+s to finish" } else { "" }, Style::default().fg(Color::Green)),
+                ]),
+                match &r.error {
+                    Some(e) => Line::from(Span::styled(format!(" {}", e), Style::default().fg(Color::Red))),
+                    None => Line::from(Span::styled(" Space marks a row as on the statement", Style::default().add_modifier(Modifier::DIM))),
+                },
+            ];
+            f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Reconcile ")), chunks[0]);
+            chunks[1]
+        }
+        None => area,
+    };
+
+    // Only the rows on screen are built: an account's ledger is loaded a window at a time
+    let height = area.height.saturating_sub(3) as usize;    // borders and header
+    let len = app.txn.table.len();
+    let sel = app.txn.tsel.selected().filter(|&i| i < len);
+    let mut offset = app.txn.offset.min(len.saturating_sub(height));
+    match sel {
+        Some(i) if i < offset => offset = i,
+        Some(i) if height > 0 && i >= offset + height => offset = i + 1 - height,
+        _ => {}
+    }
+    app.txn.offset = offset;
+    app.txn.height = height;
+    let visible = offset..len.min(offset + height);
+
+    // The running balance, while the table is an account's ledger
+    let balances: Option<&[state::LedgerRow]> = app.txn.ledger.as_ref().map(|l| &l.rows[visible.clone()]);
+
+    let mut columns = vec!["", "Date", "Category", "Memo", "Amount"];
+    if balances.is_some() {
+        columns.push("Balance");
+    }
+    let header = Row::new(columns)
         .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)) 
         .height(1);
 
-    let body: Vec<Row> = app.txn.table.iter().map(|t| {
+    let body: Vec<Row> = app.txn.table[visible].iter().enumerate().map(|(i, t)| {
+        // Rows in a locked period are dimmed
+        let locked = app.lock_date(t.account_id).is_some_and(|d| t.txn_date <= d);
         let amt_style = if t.amount.0.is_sign_negative() { 
             Style::default().fg(Color::Red) 
         } else { 
@@ -311,33 +686,152 @@ fn draw_txns(f: &mut Frame, area: Rect, app: &mut App) {
             .map(|c| format!("{} {}", c.icon, c.name)) 
             .unwrap_or_else(|| t.category_id.map(|id| format!("#{}", id)).unwrap_or("-".into())); 
 
-        Row::new(vec![
+        // R = reconciled, c = cleared
+        let status = if t.reconciled {
+            Span::styled("R", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+        } else if t.cleared {
+            Span::styled("c", Style::default().fg(Color::Green))
+        } else {
+            Span::raw("")
+        };
+        let mut cells = vec![
+            Cell::from(status),
             Cell::from(t.txn_date.to_string()),
             Cell::from(cat_str),
             Cell::from(t.memo.clone().unwrap_or_default()),
             Cell::from(Span::styled(fmt_money(t.amount.0), amt_style)),
-        ])
+        ];
+        if let Some(row) = balances.and_then(|b| b.get(i)) {
+            cells.push(Cell::from(fmt_money(row.balance)));
+        }
+        Row::new(cells)
+        .style(if locked { Style::default().add_modifier(Modifier::DIM) } else { Style::default() })
     }).collect();
 
-    let widths = [
+    let mut widths = vec![
+        Constraint::Length(2),
         Constraint::Length(15),
         Constraint::Length(20),
         Constraint::Percentage(50),
         Constraint::Length(15),
     ];
+    if balances.is_some() {
+        widths.push(Constraint::Length(15));
+    }
 
-    let mut tsel = app.txn.tsel.clone();
+    let title = if app.txn.loading {
+        " Transactions (Loading...) ".to_string()
+    } else if let Some(payee) = &app.txn.payee {
+        format!(" Payee: {} in {} (e:Edit d:Del Esc:Back to Report) ", payee, app.dashboard.period.label())
+    } else if app.txn.reconcile.is_some() {
+        " Unreconciled Transactions (Space:Cleared Ctrl+s:Finish Esc:Leave) ".to_string()
+    } else if let Some(lock) = app.current_account_id().and_then(|id| app.lock_date(id)) {
+        format!(" Transactions, locked until {} (a:Add e:Edit d:Del c:Cleared h:History R:Reconcile g:Go to Date Esc:Back) ", lock)
+    } else {
+        " Transactions (a:Add e:Edit d:Del c:Cleared h:History R:Reconcile g:Go to Date Esc:Back) ".to_string()
+    };
+
+    let mut tsel = TableState::default().with_selected(sel.map(|i| i - offset));
     let table = Table::new(body, widths)
         .header(header)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(if app.txn.loading { " Transactions (Loading...) " } else { " Transactions (a:Add e:Edit d:Del Esc:Back) " }),
+                .title(title),
         )
         .highlight_style(Style::default().bg(Color::DarkGray));
 
     f.render_stateful_widget(table, area, &mut tsel);
-    app.txn.tsel = tsel; 
+}
+
+// Matches of a '/' search from every account, the matched words marked
+fn draw_search_results(f: &mut Frame, area: Rect, app: &mut App) {
+    let Some(s) = &app.txn.search else { return };
+    let terms = s.query.highlight_terms();
+
+    let header = Row::new(vec!["", "Date", "Account", "Payee", "Category", "Memo / Tags", "Amount"])
+        .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .height(1);
+
+    // The table holds the rows as edited since, the hits the names
+    let body: Vec<Row> = app.txn.table.iter().zip(&s.hits).map(|(t, h)| {
+        let locked = app.lock_date(t.account_id).is_some_and(|d| t.txn_date <= d);
+        let amt_style = if t.amount.0.is_sign_negative() { Style::default().fg(Color::Red) } else { Style::default().fg(Color::Green) };
+        let status = if t.reconciled {
+            Span::styled("R", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+        } else if t.cleared {
+            Span::styled("c", Style::default().fg(Color::Green))
+        } else {
+            Span::raw("")
+        };
+        let mut notes = marked(t.memo.as_deref().unwrap_or(""), &terms);
+        for tag in &h.tags {
+            notes.spans.push(Span::styled(" #", Style::default().fg(Color::Cyan)));
+            notes.spans.extend(marked(tag, &terms).spans);
+        }
+        Row::new(vec![
+            Cell::from(status),
+            Cell::from(t.txn_date.to_string()),
+            Cell::from(h.account.clone()),
+            Cell::from(marked(t.payee.as_deref().unwrap_or(""), &terms)),
+            Cell::from(marked(h.category.as_deref().unwrap_or("-"), &terms)),
+            Cell::from(notes),
+            Cell::from(Span::styled(fmt_money(t.amount.0), amt_style)),
+        ])
+        .style(if locked { Style::default().add_modifier(Modifier::DIM) } else { Style::default() })
+    }).collect();
+
+    let widths = [
+        Constraint::Length(2),
+        Constraint::Length(11),
+        Constraint::Length(14),
+        Constraint::Percentage(25),
+        Constraint::Length(18),
+        Constraint::Percentage(35),
+        Constraint::Length(13),
+    ];
+
+    let title = if app.txn.loading {
+        " Search (Loading...) ".to_string()
+    } else {
+        let count = match s.hits.len() {
+            1 => "1 match".to_string(),
+            n if n == state::SEARCH_LIMIT => format!("newest {} matches", n),
+            n => format!("{} matches", n),
+        };
+        format!(" Search: {}, {} (/:Search e:Edit d:Del c:Cleared h:History Esc:Back) ", s.query.text, count)
+    };
+
+    let mut tsel = app.txn.tsel.clone();
+    let table = Table::new(body, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().bg(Color::DarkGray));
+    f.render_stateful_widget(table, area, &mut tsel);
+    app.txn.tsel = tsel;
+}
+
+fn draw_reconcile_prompt(f: &mut Frame, area: Rect, r: &Reconcile) {
+    let field = |label: &str, value: &str, active: bool| if active {
+        Line::from(vec![
+            Span::styled(" > ", Style::default().fg(Color::Yellow)),
+            Span::styled(format!("{:<16}: {}_", label, value), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        ])
+    } else {
+        Line::from(format!("   {:<16}: {}", label, value))
+    };
+    let lines = vec![
+        Line::from(""),
+        field("Statement date", &r.end_date, r.field == ReconcileField::Date),
+        field("Ending balance", &r.balance, r.field == ReconcileField::Balance),
+        Line::from(""),
+        match &r.error {
+            Some(e) => Line::from(Span::styled(format!(" Error: {}", e), Style::default().fg(Color::Red))),
+            None => Line::from(Span::styled(" From the bank statement; negative for money owed", Style::default().add_modifier(Modifier::DIM))),
+        },
+    ];
+    let block = Block::default().borders(Borders::ALL).title(" Reconcile (Tab:Field Enter:Start Esc:Cancel) ");
+    f.render_widget(Paragraph::new(lines).block(block), area);
 }
 
 // Add Transaction Page
@@ -364,7 +858,11 @@ fn draw_add_txn(f: &mut Frame, area: Rect, app: &mut App) {
     }
     
     let items: Vec<ListItem> = app.add.categories.iter().map(|c| {
-        let style = if c.r#type == state::CategoryType::Income { Style::default().fg(Color::Green) } else { Style::default().fg(Color::Red) };
+        let style = match c.r#type {
+            state::CategoryType::Income => Style::default().fg(Color::Green),
+            state::CategoryType::Expense => Style::default().fg(Color::Red),
+            state::CategoryType::Transfer => Style::default().fg(Color::Yellow),
+        };
         ListItem::new(Line::from(vec![
             Span::raw(format!("{:<15}", c.name)),
             Span::styled(format!("{:?}", c.r#type), style),
@@ -438,6 +936,14 @@ fn draw_add_txn(f: &mut Frame, area: Rect, app: &mut App) {
         Line::from(vec![
             Span::raw("   Category: "), Span::styled(selected_cat_name, Style::default().fg(Color::Cyan))
         ]),
+        match app.add.suggestion.and_then(|s| app.add.categories.iter().find(|c| c.id == s.category_id).map(|c| (c, s))) {
+            Some((c, s)) => Line::from(vec![
+                Span::raw("   Suggest : "),
+                Span::styled(format!("{} ({:.0}%)", c.name, s.confidence * 100.0), Style::default().fg(Color::Magenta)),
+                Span::styled("  → to use", Style::default().add_modifier(Modifier::DIM)),
+            ]),
+            None => Line::from(""),
+        },
     ];
 
    f.render_widget(
@@ -453,10 +959,19 @@ fn draw_add_txn(f: &mut Frame, area: Rect, app: &mut App) {
     let help_lines = vec![
         Line::from(vec![Span::styled(" Controls: ", Style::default().fg(Color::Yellow))]),
         Line::from("  TAB: Switch Field | ENTER: Edit Mode"),
-        Line::from("  t: Toggle Income/Expense"),
+        Line::from("  t: Toggle Income/Expense | →: Use Suggested Category"),
         Line::from("  Ctrl+s: Save | ESC: Back"),
         Line::from(""),
-        if let Some(err) = &app.add.error {
+        if let Some(dup) = &app.add.duplicate_of {
+            let e = &dup.existing;
+            Line::from(Span::styled(
+                format!(
+                    " Looks already recorded: {} {} {} ({:.0}% alike). Ctrl+s again to save anyway.",
+                    e.txn_date, e.payee.as_deref().unwrap_or("-"), fmt_money(e.amount.0), dup.score * 100.0
+                ),
+                Style::default().fg(Color::Yellow),
+            ))
+        } else if let Some(err) = &app.add.error {
             Line::from(Span::styled(format!(" Error: {}", err), Style::default().fg(Color::Red)))
         } else if let Some(succ) = &app.add.success {
             Line::from(Span::styled(format!(" Success: {}", succ), Style::default().fg(Color::Green)))
@@ -471,23 +986,248 @@ fn draw_add_txn(f: &mut Frame, area: Rect, app: &mut App) {
     );
 }
 
+// Rules Page
+fn draw_rules(f: &mut Frame, area: Rect, app: &mut App) {
+    if app.rules.preview.is_some() {
+        draw_rule_preview(f, area, app);
+        return;
+    }
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(area);
+
+    let items: Vec<ListItem> = app.rules.list.iter().enumerate().map(|(i, r)| {
+        let style = if r.enabled { Style::default() } else { Style::default().fg(Color::DarkGray) };
+        ListItem::new(Line::from(vec![
+            Span::styled(format!("{:>2}. ", i + 1), Style::default().fg(Color::DarkGray)),
+            Span::styled(format!("{:<22}", r.name), style.add_modifier(Modifier::BOLD)),
+            Span::styled(format!("{} {} '{}'", r.field.as_str(), r.kind.as_str(), r.pattern), style.fg(if r.enabled { Color::Cyan } else { Color::DarkGray })),
+            Span::styled(if r.enabled { "" } else { "  (off)" }, style),
+        ]))
+    }).collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(" Rules (n:New e:Edit d:Del Space:On/Off [ ]:Move p:Dry Run) "))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+    f.render_stateful_widget(list, cols[0], &mut app.rules.sel);
+
+    let account = |id: Option<i64>| match id {
+        Some(id) => app.accounts.list.iter().find(|a| a.id == id).map(|a| a.name.clone()).unwrap_or_else(|| format!("#{}", id)),
+        None => "any".into(),
+    };
+    let category = |id: Option<i64>| match id {
+        Some(id) => app.add.categories.iter().find(|c| c.id == id).map(|c| c.name.clone()).unwrap_or_else(|| format!("#{}", id)),
+        None => "-".into(),
+    };
+    let right = match app.rules.sel.selected().and_then(|i| app.rules.list.get(i)) {
+        Some(r) => {
+            let amount = match (r.min_amount, r.max_amount) {
+                (None, None) => "any".to_string(),
+                (lo, hi) => format!("{} to {}", lo.map(fmt_money).unwrap_or_else(|| "..".into()), hi.map(fmt_money).unwrap_or_else(|| "..".into())),
+            };
+            let or_dash = |s: &Option<String>| s.clone().unwrap_or_else(|| "-".into());
+            Paragraph::new(vec![
+                Line::from(Span::styled("When", Style::default().fg(Color::Yellow))),
+                Line::from(format!("  {} {} '{}'", r.field.as_str(), r.kind.as_str(), r.pattern)),
+                Line::from(format!("  Amount:   {}", amount)),
+                Line::from(format!("  Account:  {}", account(r.account_id))),
+                Line::from(""),
+                Line::from(Span::styled("Then", Style::default().fg(Color::Yellow))),
+                Line::from(format!("  Category: {}", category(r.set_category_id))),
+                Line::from(format!("  Payee:    {}", or_dash(&r.set_payee))),
+                Line::from(format!("  Memo:     {}", or_dash(&r.set_memo))),
+                Line::from(format!("  Tags:     {}", if r.add_tags.is_empty() { "-".into() } else { r.add_tags.join(", ") })),
+                Line::from(""),
+                Line::from(Span::styled("Rules run top to bottom on every import; the first one that sets a field wins.", Style::default().add_modifier(Modifier::DIM))),
+            ]).wrap(Wrap { trim: false })
+        }
+        None => Paragraph::new("No rules yet. Press n to add one."),
+    }.block(Block::default().borders(Borders::ALL).title(" Details "));
+    f.render_widget(right, cols[1]);
+}
+
+fn draw_trash(f: &mut Frame, area: Rect, app: &mut App) {
+    let days = crate::cli::trash_retention_days();
+    let kept = if days > 0 { format!("purged after {} days", days) } else { "kept until purged".into() };
+    let rows: Vec<Row> = app.trash.list.iter().map(|item| {
+        let deleted: String = item.deleted_at.replace('T', " ").chars().take(19).collect();
+        match &item.transaction {
+            Some(t) => {
+                let what = t.payee.clone().filter(|p| !p.trim().is_empty()).or_else(|| t.memo.clone()).unwrap_or_default();
+                let color = if t.amount.0.is_sign_negative() { Color::Red } else { Color::Green };
+                Row::new(vec![
+                    Cell::from(deleted),
+                    Cell::from(t.txn_date.to_string()),
+                    Cell::from(what),
+                    Cell::from(item.account.clone()),
+                    Cell::from(Span::styled(format!("{:>12}", fmt_money(t.amount.0)), Style::default().fg(color))),
+                ])
+            }
+            None => Row::new(vec![
+                Cell::from(deleted),
+                Cell::from(Span::styled("account", Style::default().fg(Color::Yellow))),
+                Cell::from(format!("{} transactions", item.transactions)),
+                Cell::from(Span::styled(item.account.clone(), Style::default().add_modifier(Modifier::BOLD))),
+                Cell::from(""),
+            ]),
+        }
+    }).collect();
+    let empty = rows.is_empty();
+    let table = Table::new(rows, [
+            Constraint::Length(20),
+            Constraint::Length(11),
+            Constraint::Min(20),
+            Constraint::Length(18),
+            Constraint::Length(13),
+        ])
+        .header(Row::new(vec!["Deleted (UTC)", "Date", "Payee / Memo", "Account", "Amount"]).style(Style::default().fg(Color::Yellow)))
+        .block(Block::default().borders(Borders::ALL).title(format!(" Trash, {} (r/Enter:Restore p:Purge Esc:Back) ", kept)))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+    if empty {
+        f.render_widget(Paragraph::new("The trash is empty.").block(Block::default().borders(Borders::ALL).title(format!(" Trash, {} ", kept))), area);
+        return;
+    }
+    let mut sel = TableState::default();
+    sel.select(app.trash.sel.selected());
+    f.render_stateful_widget(table, area, &mut sel);
+}
+
+fn draw_rule_form(f: &mut Frame, area: Rect, app: &App, form: &RuleForm) {
+    let r = &form.rule;
+    let title = if r.id == 0 { " New Rule " } else { " Edit Rule " };
+    let block = Block::default().borders(Borders::ALL).title(format!("{}(↑/↓:Field ←/→:Change Enter:Save Esc:Cancel) ", title));
+    let account = match r.account_id {
+        Some(id) => app.accounts.list.iter().find(|a| a.id == id).map(|a| a.name.clone()).unwrap_or_default(),
+        None => "any".into(),
+    };
+    let category = match r.set_category_id {
+        Some(id) => app.add.categories.iter().find(|c| c.id == id).map(|c| c.name.clone()).unwrap_or_default(),
+        None => "(leave as is)".into(),
+    };
+
+    let mut lines: Vec<Line> = RuleField::ALL.iter().map(|field| {
+        let (label, value, text) = match field {
+            RuleField::Name => ("Name", r.name.clone(), true),
+            RuleField::Field => ("Match on", r.field.as_str().to_string(), false),
+            RuleField::Kind => ("Match kind", r.kind.as_str().to_string(), false),
+            RuleField::Pattern => ("Pattern", r.pattern.clone(), true),
+            RuleField::Min => ("Min amount", form.min.clone(), true),
+            RuleField::Max => ("Max amount", form.max.clone(), true),
+            RuleField::Account => ("Account", account.clone(), false),
+            RuleField::Category => ("Set category", category.clone(), false),
+            RuleField::Payee => ("Set payee", form.payee.clone(), true),
+            RuleField::Memo => ("Set memo", form.memo.clone(), true),
+            RuleField::Tags => ("Add tags", form.tags.clone(), true),
+        };
+        if *field == form.field {
+            let value = if text { format!("{}_", value) } else { format!("< {} >", value) };
+            Line::from(vec![
+                Span::styled(" > ", Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{:<13}: {}", label, value), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            ])
+        } else {
+            Line::from(format!("   {:<13}: {}", label, value))
+        }
+    }).collect();
+    lines.push(Line::from(""));
+    lines.push(match &form.error {
+        Some(e) => Line::from(Span::styled(format!(" Error: {}", e), Style::default().fg(Color::Red))),
+        None => Line::from(Span::styled(" Amounts are signed (-50 = spent 50); empty fields are ignored", Style::default().add_modifier(Modifier::DIM))),
+    });
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+// Dry run: what the rules would change on recorded transactions
+fn draw_rule_preview(f: &mut Frame, area: Rect, app: &App) {
+    let Some(changes) = &app.rules.preview else { return };
+    let category = |id: i64| app.add.categories.iter().find(|c| c.id == id).map(|c| c.name.clone()).unwrap_or_else(|| format!("#{}", id));
+    let arrow = |old: String, new: String| Cell::from(Line::from(vec![
+        Span::styled(old, Style::default().fg(Color::DarkGray)),
+        Span::raw(" → "),
+        Span::styled(new, Style::default().fg(Color::Green)),
+    ]));
+
+    let header = Row::new(vec!["Date", "Amount", "Payee", "Category", "Memo", "Tags", "Rules"])
+        .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+    let rows: Vec<Row> = changes.iter().map(|c| {
+        let t = &c.txn;
+        let payee = t.payee.clone().unwrap_or_default();
+        Row::new(vec![
+            Cell::from(t.txn_date.to_string()),
+            Cell::from(fmt_money(t.amount.0)),
+            match &c.payee { Some(p) => arrow(payee, p.clone()), None => Cell::from(payee) },
+            match c.category_id {
+                Some(id) => arrow(t.category_id.map(category).unwrap_or_default(), category(id)),
+                None => Cell::from(t.category_id.map(category).unwrap_or_default()),
+            },
+            match &c.memo { Some(m) => arrow(t.memo.clone().unwrap_or_default(), m.clone()), None => Cell::from(t.memo.clone().unwrap_or_default()) },
+            Cell::from(Span::styled(c.tags.iter().map(|t| format!("+{}", t)).collect::<Vec<_>>().join(" "), Style::default().fg(Color::Green))),
+            Cell::from(Span::styled(c.rules.join(", "), Style::default().add_modifier(Modifier::DIM))),
+        ])
+    }).collect();
+    let mut sel = TableState::default();
+    sel.select(Some(app.rules.preview_sel));
+    let table = Table::new(rows, [
+        Constraint::Length(11), Constraint::Length(10), Constraint::Percentage(22), Constraint::Percentage(20),
+        Constraint::Percentage(20), Constraint::Percentage(12), Constraint::Percentage(14),
+    ])
+        .header(header)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .block(Block::default().borders(Borders::ALL).title(format!(" Dry Run: {} transactions would change (Ctrl+s:Apply Esc:Cancel) ", changes.len())));
+    f.render_stateful_widget(table, area, &mut sel);
+}
+
 fn draw_help(f: &mut Frame, area: Rect) {
    let help_text = vec![
         "Global Keys:",
         "  q        : Quit App",
         "  ?        : Toggle this Help",
         "  Tab      : Switch Tabs",
+        "  u        : Undo the Last Add / Edit / Delete of a Transaction, Account or Goal",
+        "  Ctrl+r   : Redo What was Undone",
         "",
         "Accounts Tab:",
         "  n        : Create New Account",
         "  e        : Edit Selected Account",
-        "  d        : Delete Selected Account",
+        "  d        : Move Selected Account to the Trash",
+        "  i        : Import CSV / OFX / QIF / camt.053 / MT940 File into Selected Account",
+        "             (likely duplicates are held: Space imports one anyway)",
         "  Enter    : View Transactions",
+        "  R        : Import Rules",
+        "  T        : Trash (deleted accounts and transactions)",
+        "  /        : Search Transactions of Every Account",
+        "  C        : Check the Ledger (balances, broken rows, balance assertions; f repairs)",
         "  r        : Refresh",
         "",
+        "Rules Tab:",
+        "  n / e    : New / Edit Rule (←/→ change choices, Enter saves)",
+        "  d        : Delete Rule",
+        "  Space    : Enable / Disable Rule",
+        "  [ / ]    : Move Rule Up / Down (rules run top to bottom)",
+        "  p        : Dry Run over Recorded Transactions (Ctrl+s applies, Esc cancels)",
+        "  Esc      : Back to Accounts",
+        "",
+        "Trash Tab:",
+        "  r/Enter  : Restore Selected Item (an account brings back its transactions)",
+        "  p/Del    : Delete Selected Item for Good (press twice)",
+        "  Esc      : Back to Accounts",
+        "             (items are purged TRASH_RETENTION_DAYS after deletion, 30 by default; 0 keeps them)",
+        "",
         "Transactions Tab:",
+        "  PgUp/PgDn: Scroll a Page (older rows load as you go; Balance is the running balance)",
+        "  Home/End : Newest / Oldest Transaction",
+        "  g        : Go to the Newest Transaction on or before a Date",
         "  a        : Add Transaction",
-        "  x/Del    : Delete Transaction",
+        "  w        : Write the Table to a CSV / Markdown File",
+        "  x/Del    : Move Transaction to the Trash",
+        "  c        : Mark Cleared / Not Cleared",
+        "  h        : Show Edit History of the Selected Transaction",
+        "  R        : Reconcile against a Statement (Space clears, Ctrl+s finishes)",
+        "             (reconciled rows ask for a second e / d / c before changing)",
+        "             (dimmed rows are on or before the lock date and ask for a second e / d;",
+        "              lock dates are set with the `lock` and `close` subcommands)",
+        "  /        : Search Every Account (payee:, memo:, cat:, tag:, account:,",
+        "             amount:>50, date:2025-01..2025-03; Esc leaves the results)",
         "  Esc      : Back to Accounts",
         "",
         
@@ -496,12 +1236,22 @@ fn draw_help(f: &mut Frame, area: Rect) {
         "  e        : Edit Selected Goal (Update Amount)",
         "  d        : Delete Selected Goal",
         "  ↑ / ↓    : Select Goal",
+        "  [ / ]    : Previous / Next Report Period",
+        "  p        : Cycle Period (day/week/month/quarter/year)",
+        "  v        : Switch Report (Spending / Cash Flow / Comparison / Payees / Forecast)",
+        "  y        : Compare with Previous Period / Same Period Last Year",
+        "  w / W    : Write the Current Report / Goal Progress to a CSV / Markdown File",
+        "  Enter    : Show Selected Payee's Transactions (Payees report)",
+        "  + / -    : More / Fewer Cash Flow Months",
+        "  h        : Forecast Horizon (30 / 60 / 90 days)",
+        "  ← / →    : Previous / Next Account (Forecast)",
         "  Esc      : Back to Accounts",
         "",
     
         "Add Transaction Tab:",
-        "  Ctrl+s   : Save",
+        "  Ctrl+s   : Save (in a locked period it warns first; Ctrl+s again saves anyway)",
         "  t        : Toggle Expense/Income",
+        "  →        : Use the Suggested Category (learned from your history)",
         "  Enter    : Toggle Edit Mode",
     ].join("\n");
 
@@ -522,7 +1272,7 @@ fn center_rect(rect: Rect, w: u16, h: u16) -> Rect {
     Rect { x, y, width: w.min(rect.width), height: h.min(rect.height) }
 }
 
-use crate::cli::state::DashboardPage;
+use crate::cli::state::{DashboardPage, ReportView};
 use rust_decimal::prelude::ToPrimitive; 
 
 pub fn ui_dashboard(f: &mut Frame, page: &DashboardPage, area: Rect) { 
@@ -586,12 +1336,23 @@ pub fn ui_dashboard(f: &mut Frame, page: &DashboardPage, area: Rect) {
         }
     }
 
-    let right_block = Block::default().title(" Monthly Spending ").borders(Borders::ALL);
-    let right_area = right_block.inner(chunks[1]);
-    f.render_widget(right_block, chunks[1]);
+    match page.view {
+        ReportView::Spending => draw_spending(f, page, chunks[1]),
+        ReportView::CashFlow => draw_cash_flow(f, page, chunks[1]),
+        ReportView::Comparison => draw_comparison(f, page, chunks[1]),
+        ReportView::Payees => draw_payees(f, page, chunks[1]),
+        ReportView::Forecast => draw_forecast(f, page, chunks[1]),
+    }
+}
+
+fn draw_spending(f: &mut Frame, page: &DashboardPage, area: Rect) {
+    let right_title = format!(" Spending {} ([/]:Period p:{} v:View) ", page.period.label(), page.period.grouping.as_str());
+    let right_block = Block::default().title(right_title).borders(Borders::ALL);
+    let right_area = right_block.inner(area);
+    f.render_widget(right_block, area);
 
     if page.report.is_empty() {
-        let msg = "No spending data for this month.\n\nGo to 'Transactions' tab\nand add an 'Expense'.";
+        let msg = "No spending data for this period.\n\nGo to 'Transactions' tab\nand add an 'Expense'.";
         let p = Paragraph::new(msg)
             .style(Style::default().fg(Color::DarkGray))
             .alignment(Alignment::Center);
@@ -618,4 +1379,267 @@ pub fn ui_dashboard(f: &mut Frame, page: &DashboardPage, area: Rect) {
         
         f.render_widget(barchart, right_area);
     }
-}
\ No newline at end of file
+}
+
+fn draw_forecast(f: &mut Frame, page: &DashboardPage, area: Rect) {
+    let Some(acc) = page.forecast.get(page.forecast_sel) else {
+        let block = Block::default().title(" Forecast (v:View) ").borders(Borders::ALL);
+        let p = Paragraph::new("No accounts to forecast.")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
+            .block(block);
+        f.render_widget(p, area);
+        return;
+    };
+
+    let alert_lines = page.forecast.len().min(4) as u16 + 3;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(8), Constraint::Length(alert_lines)])
+        .split(area);
+
+    // x = days relative to today, so history is negative and the projection positive
+    let days = page.forecast_days as f64;
+    let to_points = |pts: &[state::BalancePointDto], offset: f64| -> Vec<(f64, f64)> {
+        pts.iter().enumerate()
+            .map(|(i, p)| (i as f64 + offset, p.balance.0.to_f64().unwrap_or(0.0)))
+            .collect()
+    };
+    let history = to_points(&acc.history, -(acc.history.len() as f64 - 1.0));
+    let projection = to_points(&acc.projection, 0.0);
+
+    // Red line at the level that triggers an alert (zero, or the credit limit)
+    let floor = acc.credit_limit.map(|l| -l.0.to_f64().unwrap_or(0.0)).unwrap_or(0.0);
+    let floor_line = vec![(-days, floor), (days, floor)];
+
+    let (mut y_min, mut y_max) = history.iter().chain(projection.iter())
+        .fold((floor, floor), |(lo, hi), &(_, y)| (lo.min(y), hi.max(y)));
+    let pad = ((y_max - y_min) * 0.05).max(1.0);
+    y_min -= pad;
+    y_max += pad;
+
+    let datasets = vec![
+        Dataset::default()
+            .name("Actual")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&history),
+        Dataset::default()
+            .name("Projected")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Yellow))
+            .data(&projection),
+        Dataset::default()
+            .name(if acc.credit_limit.is_some() { "Limit" } else { "Zero" })
+            .marker(symbols::Marker::Dot)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Red))
+            .data(&floor_line),
+    ];
+
+    let first = acc.history.first().map(|p| p.date.to_string()).unwrap_or_default();
+    let last = acc.projection.last().map(|p| p.date.to_string()).unwrap_or_default();
+    let title = format!(" Forecast: {} next {} days (←/→:Account h:Horizon v:View) ", acc.name, page.forecast_days);
+
+    let chart = Chart::new(datasets)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .x_axis(Axis::default()
+            .bounds([-days, days])
+            .style(Style::default().fg(Color::DarkGray))
+            .labels(vec![Span::raw(first), Span::raw("today"), Span::raw(last)]))
+        .y_axis(Axis::default()
+            .bounds([y_min, y_max])
+            .style(Style::default().fg(Color::DarkGray))
+            .labels(vec![
+                Span::raw(format!("{:.0}", y_min)),
+                Span::raw(format!("{:.0}", (y_min + y_max) / 2.0)),
+                Span::raw(format!("{:.0}", y_max)),
+            ]));
+    f.render_widget(chart, chunks[0]);
+
+    // Earliest alert across all accounts first, then one line per account
+    let mut lines = Vec::new();
+    let earliest = page.forecast.iter()
+        .filter_map(|a| a.alert.as_ref().map(|al| (a, al)))
+        .min_by_key(|(_, al)| al.date);
+    lines.push(match earliest {
+        Some((a, al)) => Line::from(Span::styled(
+            format!("⚠ {} {} on {} ({})", a.name, al.reason, al.date, fmt_money(al.balance.0)),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        None => Line::from(Span::styled("All accounts stay within limits", Style::default().fg(Color::Green))),
+    });
+    for (i, a) in page.forecast.iter().enumerate().take(4) {
+        let marker = if i == page.forecast_sel { "> " } else { "  " };
+        let end = a.projection.last().map(|p| fmt_money(p.balance.0)).unwrap_or_default();
+        let (text, color) = match &a.alert {
+            Some(al) => (format!("{}{}: {} on {} → {}", marker, a.name, al.reason, al.date, end), Color::Red),
+            None => (format!("{}{}: ok → {}", marker, a.name, end), Color::White),
+        };
+        lines.push(Line::from(Span::styled(text, Style::default().fg(color))));
+    }
+    let alerts = Paragraph::new(lines).block(Block::default().title(" Alerts ").borders(Borders::ALL));
+    f.render_widget(alerts, chunks[1]);
+}
+
+fn draw_payees(f: &mut Frame, page: &DashboardPage, area: Rect) {
+    let title = format!(" Top Payees {} (↑/↓ Enter:Txns [/]:Period v:View) ", page.period.label());
+    let block = Block::default().title(title).borders(Borders::ALL);
+
+    if page.payees.is_empty() {
+        let p = Paragraph::new("No payees recorded in this period.")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
+            .block(block);
+        f.render_widget(p, area);
+        return;
+    }
+
+    let header = Row::new(vec!["#", "Payee", "Total", "Count", "Average"])
+        .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+
+    let body: Vec<Row> = page.payees.iter().enumerate().map(|(i, p)| {
+        Row::new(vec![
+            Cell::from((i + 1).to_string()),
+            Cell::from(p.payee.clone()),
+            Cell::from(Span::styled(fmt_money(p.total_amount.0), Style::default().fg(Color::Red))),
+            Cell::from(p.txn_count.to_string()),
+            Cell::from(fmt_money(p.average_amount.0)),
+        ])
+    }).collect();
+
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Min(16),
+        Constraint::Length(11),
+        Constraint::Length(6),
+        Constraint::Length(11),
+    ];
+
+    let mut sel = TableState::default();
+    sel.select(Some(page.payee_sel));
+    let table = Table::new(body, widths)
+        .header(header)
+        .block(block)
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+    f.render_stateful_widget(table, area, &mut sel);
+}
+
+fn draw_comparison(f: &mut Frame, page: &DashboardPage, area: Rect) {
+    let baseline = page.compare_mode.baseline(&page.period);
+    let title = format!(" {} vs {} ([/]:Period y:MoM/YoY v:View) ", page.period.label(), baseline.label());
+    let block = Block::default().title(title).borders(Borders::ALL);
+
+    if page.comparison.is_empty() {
+        let p = Paragraph::new("No spending in either period.")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
+            .block(block);
+        f.render_widget(p, area);
+        return;
+    }
+
+    let header = Row::new(vec![
+        Cell::from("Category"),
+        Cell::from(baseline.label()),
+        Cell::from(page.period.label()),
+        Cell::from("Change"),
+        Cell::from("%"),
+    ])
+    .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+
+    // Spending more is bad news: increases red, decreases green
+    let body: Vec<Row> = page.comparison.iter().map(|c| {
+        let delta_style = if c.change.0.is_zero() {
+            Style::default()
+        } else if c.change.0.is_sign_positive() {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Green)
+        };
+        let sign = if c.change.0.is_sign_positive() && !c.change.0.is_zero() { "+" } else { "" };
+        let pct = c.change_pct
+            .map(|p| format!("{}{}%", sign, p))
+            .unwrap_or_else(|| "new".into());
+
+        Row::new(vec![
+            Cell::from(c.category.clone()),
+            Cell::from(fmt_money(c.previous_amount.0)),
+            Cell::from(fmt_money(c.current_amount.0)),
+            Cell::from(Span::styled(format!("{}{}", sign, fmt_money(c.change.0)), delta_style)),
+            Cell::from(Span::styled(pct, delta_style)),
+        ])
+    }).collect();
+
+    let widths = [
+        Constraint::Length(16),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(9),
+    ];
+
+    let table = Table::new(body, widths).header(header).block(block);
+    f.render_widget(table, area);
+}
+
+fn draw_cash_flow(f: &mut Frame, page: &DashboardPage, area: Rect) {
+    let title = format!(" Cash Flow, last {} months (+/-:Months v:View) ", page.cashflow_months);
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if page.cashflow.iter().all(|m| m.income.0.is_zero() && m.expenses.0.is_zero()) {
+        let p = Paragraph::new("No income or expenses in this range.")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center);
+        f.render_widget(p, inner);
+        return;
+    }
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(6), Constraint::Length(page.cashflow.len() as u16 + 1)])
+        .split(inner);
+
+    // One group per month: income (green) next to expenses (red)
+    let groups: Vec<BarGroup> = page.cashflow.iter().map(|m| {
+        BarGroup::default()
+            .label(Line::from(m.month.clone()))
+            .bars(&[
+                Bar::default()
+                    .value(m.income.0.to_u64().unwrap_or(0))
+                    .style(Style::default().fg(Color::Green))
+                    .value_style(Style::default().bg(Color::Green).fg(Color::Black)),
+                Bar::default()
+                    .value(m.expenses.0.to_u64().unwrap_or(0))
+                    .style(Style::default().fg(Color::Red))
+                    .value_style(Style::default().bg(Color::Red).fg(Color::Black)),
+            ])
+    }).collect();
+
+    let mut chart = BarChart::default()
+        .bar_width(3)
+        .bar_gap(1)
+        .group_gap(2);
+    for g in groups {
+        chart = chart.data(g);
+    }
+    f.render_widget(chart, rows[0]);
+
+    let mut lines = vec![Line::from(Span::styled(
+        format!("{:<8} {:>11} {:>11} {:>11} {:>7}", "Month", "Income", "Expenses", "Net", "Saved"),
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+    ))];
+    for m in &page.cashflow {
+        let net_color = if m.net.0.is_sign_negative() { Color::Red } else { Color::Green };
+        let rate = m.savings_rate.map(|r| format!("{}%", r)).unwrap_or_else(|| "-".into());
+        lines.push(Line::from(vec![
+            Span::raw(format!("{:<8} {:>11} {:>11} ", m.month, fmt_money(m.income.0), fmt_money(m.expenses.0))),
+            Span::styled(format!("{:>11} {:>7}", fmt_money(m.net.0), rate), Style::default().fg(net_color)),
+        ]));
+    }
+    f.render_widget(Paragraph::new(lines), rows[1]);
+}
@@ -0,0 +1,95 @@
+//! Undo / redo of the TUI's edits
+//! - Each edit keeps the rows it touched as they were before and after: a transaction with
+//!   its tag links, a saving goal, or an account with everything that belongs to it
+//! - Undo writes the "before" rows back and redo the "after" rows, each in one SQL
+//!   transaction through `Client::revert`, which also recomputes the balances
+//! - An edit whose rows have changed since (e.g. through the server) is dropped, not undone
+
+use serde::Serialize;
+
+use crate::backup::{Backup, TransactionRecord, TransactionTag};
+use crate::check::BalanceAssertion;
+use crate::cli::state::AccountDto;
+use crate::database::models::{Budget, RecurringTransaction, SavingsGoal};
+use crate::rules::Rule;
+
+// Edits kept for undo; the oldest are forgotten first
+const LIMIT: usize = 100;
+
+// The row an edit was made to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Account(i64),
+    Transaction(i64),
+    Goal(i64),
+}
+
+// Rows that belong to accounts, as backup records. Empty for a row that does not exist.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Rows {
+    pub accounts: Vec<AccountDto>,
+    pub transactions: Vec<TransactionRecord>,
+    pub transaction_tags: Vec<TransactionTag>,
+    pub recurring: Vec<RecurringTransaction>,
+    pub budgets: Vec<Budget>,
+    pub savings_goals: Vec<SavingsGoal>,
+    pub rules: Vec<Rule>,
+    pub balance_assertions: Vec<BalanceAssertion>,
+}
+
+impl From<&Backup> for Rows {
+    fn from(b: &Backup) -> Self {
+        Self {
+            accounts: b.accounts.clone(),
+            transactions: b.transactions.clone(),
+            transaction_tags: b.transaction_tags.clone(),
+            recurring: b.recurring.clone(),
+            budgets: b.budgets.clone(),
+            savings_goals: b.savings_goals.clone(),
+            rules: b.rules.clone(),
+            balance_assertions: b.balance_assertions.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub label: String,      // "delete transaction", shown as "Undone: delete transaction"
+    pub target: Target,
+    pub before: Rows,
+    pub after: Rows,
+    pub override_lock: bool,    // made in a locked period (so confirmed); undo and redo go ahead too
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    done: Vec<Edit>,
+    undone: Vec<Edit>,
+}
+
+impl History {
+    // A new edit; whatever was undone can no longer be redone
+    pub fn push(&mut self, edit: Edit) {
+        self.undone.clear();
+        self.done.push(edit);
+        if self.done.len() > LIMIT {
+            self.done.remove(0);
+        }
+    }
+
+    pub fn take_undo(&mut self) -> Option<Edit> {
+        self.done.pop()
+    }
+
+    pub fn take_redo(&mut self) -> Option<Edit> {
+        self.undone.pop()
+    }
+
+    pub fn undone(&mut self, edit: Edit) {
+        self.undone.push(edit);
+    }
+
+    pub fn redone(&mut self, edit: Edit) {
+        self.done.push(edit);
+    }
+}
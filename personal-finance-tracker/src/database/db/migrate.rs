@@ -4,4 +4,9 @@ use sqlx::{Pool, Sqlite};
 pub async fn run_migrations(pool: &Pool<Sqlite>) -> Result<()> {
     sqlx::migrate!("./migrations").run(pool).await?;
     Ok(())
+}
+
+// Version of the newest migration in this build (e.g. 20250115)
+pub fn latest_version() -> i64 {
+    sqlx::migrate!("./migrations").iter().map(|m| m.version).max().unwrap_or(0)
 }
\ No newline at end of file
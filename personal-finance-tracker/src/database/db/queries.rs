@@ -1,11 +1,15 @@
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, SqliteConnection};
 use rust_decimal::Decimal;
+use std::collections::BTreeMap;
 use std::str::FromStr; 
 use sqlx::Row;
 use chrono::NaiveDateTime;
+use crate::audit::{self, Origin};
+use crate::check;
+use crate::lock;
 use crate::database::models::{
         Account, Category, Transaction, RecurringTransaction, 
-        Budget, SavingsGoal, CategorySpending 
+        Budget, SavingsGoal, CategorySpending, CategoryComparison
 };
 
 /*
@@ -16,13 +20,20 @@ and is responsible for interacting with the database.
 
  /*==========Account Queries=========== */
 
+// credit_limit is optional; an unparsable value is treated as "no limit"
+fn parse_credit_limit(text: Option<String>) -> Option<Decimal> {
+    text.and_then(|s| Decimal::from_str(&s).ok())
+}
+
 // Create account
 pub async fn create_account(
     pool: &Pool<Sqlite>, 
     account_name: &str, 
     account_type: &str,
     currency: &str,
+    origin: Origin,
 ) -> Result<i64, sqlx::Error> {
+    let mut tx = audit::begin(pool, origin).await?;
     let acc_id = sqlx::query!(
         r#"
         INSERT INTO accounts (account_name, account_type, balance, currency, account_created_at)
@@ -34,9 +45,10 @@ pub async fn create_account(
         "0", // The initial balance is 0. can be adjusted it by creating an "Income" transaction with the "initial balance".
         currency
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?
     .account_id;
+    audit::commit(tx).await?;
 
     Ok(acc_id)
 }
@@ -52,9 +64,10 @@ pub async fn get_account_by_id(pool: &Pool<Sqlite>, account_id: i64) -> Result<A
             account_type, 
             balance, 
             currency, 
-            account_created_at
+            account_created_at,
+            credit_limit
         FROM accounts
-        WHERE account_id = ?
+        WHERE account_id = ? AND deleted_at IS NULL
         "#
     )
     .bind(account_id) 
@@ -72,6 +85,7 @@ pub async fn get_account_by_id(pool: &Pool<Sqlite>, account_id: i64) -> Result<A
         balance: balance_decimal,
         currency: row.get("currency"),
         account_created_at: row.get("account_created_at"),
+        credit_limit: parse_credit_limit(row.get("credit_limit")),
     })
 }
 
@@ -85,8 +99,10 @@ pub async fn get_all_accounts(pool: &Pool<Sqlite>) -> Result<Vec<Account>, sqlx:
             account_type, 
             balance, 
             currency, 
-            account_created_at
+            account_created_at,
+            credit_limit
         FROM accounts
+        WHERE deleted_at IS NULL
         ORDER BY account_id ASC
         "#
     )
@@ -107,41 +123,42 @@ pub async fn get_all_accounts(pool: &Pool<Sqlite>) -> Result<Vec<Account>, sqlx:
             balance: balance_decimal,
             currency: row.get("currency"),
             account_created_at: row.get("account_created_at"),
+            credit_limit: parse_credit_limit(row.get("credit_limit")),
         })
     })
     .collect::<Result<Vec<Account>, sqlx::Error>>()
 }
 
 
-// Delete account
-pub async fn delete_account(pool: &Pool<Sqlite>, account_id: i64) -> Result<bool, sqlx::Error> {
-    let mut tx = pool.begin().await?;
+// Delete account: it goes to the trash together with its transactions, all marked with
+// the same time so that restoring the account brings back exactly what went with it
+pub async fn delete_account(pool: &Pool<Sqlite>, account_id: i64, origin: Origin) -> Result<bool, sqlx::Error> {
+    let mut tx = audit::begin(pool, origin).await?;
 
-    // First need to delete all transaction records associated with the account
-    sqlx::query!(
+    let acc_result = sqlx::query(
         r#"
-        DELETE FROM transactions
-        WHERE account_id = ?
-        "#,
-        account_id
+        UPDATE accounts
+        SET deleted_at = strftime('%Y-%m-%dT%H:%M:%fZ','now')
+        WHERE account_id = ? AND deleted_at IS NULL
+        "#
     )
-    .execute(&mut *tx) 
+    .bind(account_id)
+    .execute(&mut *tx)
     .await?;
 
-    // Delete all scheduled transaction records associated with the account ('scheduled_transactions' table)
-
-    // delete the account
-    let acc_result = sqlx::query!(
+    sqlx::query(
         r#"
-        DELETE FROM accounts
-        WHERE account_id = ?
-        "#,
-        account_id
+        UPDATE transactions
+        SET deleted_at = (SELECT deleted_at FROM accounts WHERE account_id = ?1)
+        WHERE account_id = ?1 AND deleted_at IS NULL
+        "#
     )
+    .bind(account_id)
     .execute(&mut *tx)
     .await?;
+    recompute_balance(&mut tx, account_id).await?;
 
-    tx.commit().await?;
+    audit::commit(tx).await?;
 
     Ok(acc_result.rows_affected() > 0)
 }
@@ -153,19 +170,22 @@ pub async fn update_account(
     account_id: i64,
     account_name: String,
     account_type: String,
+    origin: Origin,
 ) -> Result<bool, sqlx::Error> {
+    let mut tx = audit::begin(pool, origin).await?;
     let result = sqlx::query!(
         r#"
         UPDATE accounts
         SET account_name = ?, account_type = ?
-        WHERE account_id = ?
+        WHERE account_id = ? AND deleted_at IS NULL
         "#,
         account_name,
         account_type,
         account_id
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
+    audit::commit(tx).await?;
 
     Ok(result.rows_affected() > 0)
 }
@@ -209,6 +229,7 @@ pub async fn get_all_categories(pool: &Pool<Sqlite>) -> Result<Vec<Category>, sq
 It is an atomic operation: it inserts the transaction record and automatically updates the account balance.
 If any step fails, the database will roll back to ensure the safety of funds. */
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_transaction(
 pool: &Pool<Sqlite>,
     account_id: i64,
@@ -219,12 +240,23 @@ pool: &Pool<Sqlite>,
     is_expense: bool,
     description: Option<&str>,
     transacted_at: NaiveDateTime, // scheduled transaction time
+    origin: Origin,
+    allow_locked: bool,           // write even if the date is locked
 ) -> Result<i64, sqlx::Error> {
     // Start database transaction (ACID)
-    let mut tx = pool.begin().await?;
+    let mut tx = audit::begin(pool, origin).await?;
     let amount_str = amount.to_string();
     let base_amount_str = base_amount.to_string();
 
+    // Accounts in the trash take no new transactions (RowNotFound)
+    sqlx::query("SELECT 1 FROM accounts WHERE account_id = ? AND deleted_at IS NULL")
+        .bind(account_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if !allow_locked {
+        lock::check(&mut tx, account_id, transacted_at.date()).await?;
+    }
+
     // insert transaction record
     let trans_id_record = sqlx::query!(
         r#"
@@ -250,27 +282,36 @@ pool: &Pool<Sqlite>,
 
     let trans_id = trans_id_record.transaction_id;
 
-    // change in balance: expense(-amount), income(+amount)
-    let balance_change = if is_expense { -amount } else { amount };
-    let balance_change_str = balance_change.to_string();
-    //update balance
-    sqlx::query!(
-        r#"
-        UPDATE accounts
-        SET balance = balance + ?
-        WHERE account_id = ?
-        "#,
-        balance_change_str,
-        account_id
-    )
-    .execute(&mut *tx)
-    .await?;
+    // Recompute the balance from the rows rather than adding text in SQL, which goes
+    // through floating point
+    recompute_balance(&mut tx, account_id).await?;
 
-    tx.commit().await?;
+    audit::commit(tx).await?;
 
     Ok(trans_id)
 }
 
+// Store the account balance as the exact Decimal sum of its transactions outside the
+// trash. Amounts that don't parse are left out; the ledger checker reports them.
+pub async fn recompute_balance(conn: &mut SqliteConnection, account_id: i64) -> Result<Decimal, sqlx::Error> {
+    let rows = sqlx::query("SELECT CAST(amount AS TEXT) AS amount, is_expense FROM transactions WHERE account_id = ? AND deleted_at IS NULL")
+        .bind(account_id)
+        .fetch_all(&mut *conn)
+        .await?;
+    let mut balance = Decimal::ZERO;
+    for r in rows {
+        let amount: Option<String> = r.try_get("amount")?;
+        let is_expense: bool = r.try_get("is_expense")?;
+        balance += amount.and_then(|a| check::signed_amount(&a, is_expense)).unwrap_or_default();
+    }
+    sqlx::query("UPDATE accounts SET balance = ? WHERE account_id = ?")
+        .bind(balance.to_string())
+        .bind(account_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(balance)
+}
+
 // Get all transactions of a specific account
 pub async fn get_transactions_by_account(
     pool: &Pool<Sqlite>, 
@@ -280,7 +321,7 @@ pub async fn get_transactions_by_account(
         r#"
         SELECT *
         FROM transactions
-        WHERE account_id = ?
+        WHERE account_id = ? AND deleted_at IS NULL
         ORDER BY transacted_at DESC
         "#
     )
@@ -315,6 +356,7 @@ pub async fn get_transactions_by_account(
 }
 
 /* ====================Recurring Queries====================== */
+#[allow(clippy::too_many_arguments)]
 pub async fn create_recurring(
     pool: &Pool<Sqlite>,
     account_id: i64,
@@ -404,8 +446,9 @@ pub async fn update_next_date(
 }
 
 /*====================Budget Queries====================== */ 
-pub async fn create_budget(pool: &Pool<Sqlite>, b: &Budget) -> Result<i64, sqlx::Error> {
+pub async fn create_budget(pool: &Pool<Sqlite>, b: &Budget, origin: Origin) -> Result<i64, sqlx::Error> {
     let amount_str = b.amount.to_string();
+    let mut tx = audit::begin(pool, origin).await?;
     let id = sqlx::query!(
         r#"
         INSERT INTO budgets 
@@ -419,9 +462,10 @@ pub async fn create_budget(pool: &Pool<Sqlite>, b: &Budget) -> Result<i64, sqlx:
         b.currency,
         b.start_date, 
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?
     .last_insert_rowid();
+    audit::commit(tx).await?;
 
     Ok(id)
 }
@@ -455,9 +499,10 @@ pub async fn list_by_account(pool: &Pool<Sqlite>, acc_id: i64) -> Result<Vec<Bud
 }
 
 /*====================Saving Goal Queries====================== */ 
-pub async fn create_saving_goal(pool: &Pool<Sqlite>, g: &SavingsGoal) -> Result<i64, sqlx::Error> {
+pub async fn create_saving_goal(pool: &Pool<Sqlite>, g: &SavingsGoal, origin: Origin) -> Result<i64, sqlx::Error> {
     let target_amount_str = g.target_amount.to_string();
     let current_amount_str = g.current_amount.to_string();
+    let mut tx = audit::begin(pool, origin).await?;
 
     let new_saving_id = sqlx::query!(
         r#"
@@ -472,9 +517,10 @@ pub async fn create_saving_goal(pool: &Pool<Sqlite>, g: &SavingsGoal) -> Result<
         current_amount_str,
         g.deadline,
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?
     .goal_id;
+    audit::commit(tx).await?;
 
     Ok(new_saving_id)
 }
@@ -482,9 +528,11 @@ pub async fn create_saving_goal(pool: &Pool<Sqlite>, g: &SavingsGoal) -> Result<
 pub async fn update_goal_amount(
     pool: &Pool<Sqlite>,
     goal_id: i64,
-    new_amount: Decimal
+    new_amount: Decimal,
+    origin: Origin,
 ) -> Result<(), sqlx::Error> {
     let new_amount_str = new_amount.to_string();
+    let mut tx = audit::begin(pool, origin).await?;
     sqlx::query!(
         r#"
         UPDATE savings_goals
@@ -494,8 +542,9 @@ pub async fn update_goal_amount(
         new_amount_str,
         goal_id
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
+    audit::commit(tx).await?;
 
     Ok(())
 }
@@ -581,17 +630,19 @@ pub async fn insert_rate(
 }
 
 // ====================reports Queries======================
+// Money is summed in Rust as Decimal: SQLite's SUM and ROUND go through floating point
+
+// Total base-currency amount per category in [start, end], by category id
 pub async fn monthly_summary(
     pool: &Pool<Sqlite>,
     start: &NaiveDateTime,
     end: &NaiveDateTime
-) -> Result<Vec<(i64, f64)>, sqlx::Error> {
+) -> Result<Vec<(i64, Decimal)>, sqlx::Error> {
     let rows = sqlx::query(
         r#"
-        SELECT category_id, SUM(base_amount) AS total
+        SELECT category_id, CAST(base_amount AS TEXT) AS base_amount
         FROM transactions
-        WHERE trans_create_at BETWEEN ? AND ?
-        GROUP BY category_id
+        WHERE date(transacted_at) BETWEEN date(?) AND date(?) AND deleted_at IS NULL
         "#
     )
     .bind(start)
@@ -599,29 +650,33 @@ pub async fn monthly_summary(
     .fetch_all(pool)
     .await?;
 
-    Ok(rows
-        .into_iter()
-        .map(|r| {
-            let category_id: i64 = r.get("category_id");
-            let total: f64 = r.get("total");
-            (category_id, total)
-        })
-        .collect())
+    let mut totals: BTreeMap<i64, Decimal> = BTreeMap::new();
+    for r in rows {
+        let amount: Option<String> = r.try_get("base_amount")?;
+        *totals.entry(r.try_get("category_id")?).or_default() += amount.as_deref().and_then(stored_decimal).unwrap_or_default();
+    }
+    Ok(totals.into_iter().collect())
 }
 
-pub async fn net_savings(pool: &Pool<Sqlite>) -> Result<f64, sqlx::Error> {
-    let row = sqlx::query!(
-        r#"
-        SELECT 
-            ROUND(COALESCE(SUM(base_amount), 0.0), 2) AS net
-        FROM transactions
-        "#
-    )
-    .fetch_one(pool)
-    .await?;
-    
-    Ok(row.net.unwrap_or(0.0))
+// Income minus expenses over every transaction, in the base currency
+pub async fn net_savings(pool: &Pool<Sqlite>) -> Result<Decimal, sqlx::Error> {
+    let rows = sqlx::query("SELECT CAST(base_amount AS TEXT) AS base_amount, is_expense FROM transactions WHERE deleted_at IS NULL")
+        .fetch_all(pool)
+        .await?;
+    let mut net = Decimal::ZERO;
+    for r in rows {
+        let amount = r.try_get::<Option<String>, _>("base_amount")?.as_deref().and_then(stored_decimal).unwrap_or_default();
+        if r.try_get("is_expense")? { net -= amount } else { net += amount }
+    }
+    Ok(net)
+}
+
+// base_amount is a REAL column, which SQLite prints as "12.5" or "1.0e+20"
+pub(crate) fn stored_decimal(s: &str) -> Option<Decimal> {
+    let s = s.trim();
+    Decimal::from_str_exact(s).or_else(|_| Decimal::from_scientific(s)).ok()
 }
+
 pub async fn seed_fixed_categories(pool: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
     let categories = vec![
         (1, "Salary", "Income"),
@@ -652,6 +707,33 @@ pub async fn seed_fixed_categories(pool: &Pool<Sqlite>) -> Result<(), sqlx::Erro
         .execute(pool)
         .await?;
     }
+
+    // Transfers between own accounts are neither income nor expense.
+    // Looked up by name because id 13+ may already be taken by user categories.
+    sqlx::query(
+        r#"
+        INSERT INTO categories (category_name, category_type, icon)
+        SELECT 'Transfer', 'Transfer', ''
+        WHERE NOT EXISTS (SELECT 1 FROM categories WHERE category_name = 'Transfer')
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    // Fallbacks for imported rows that have no category yet
+    for (name, cat_type) in [("Uncategorized", "Expense"), ("Other Income", "Income")] {
+        sqlx::query(
+            r#"
+            INSERT INTO categories (category_name, category_type, icon)
+            SELECT ?1, ?2, ''
+            WHERE NOT EXISTS (SELECT 1 FROM categories WHERE category_name = ?1)
+            "#
+        )
+        .bind(name)
+        .bind(cat_type)
+        .execute(pool)
+        .await?;
+    }
     Ok(())
 }
 
@@ -662,16 +744,13 @@ pub async fn get_category_spending_report(
 ) -> Result<Vec<CategorySpending>, sqlx::Error> {
     let rows = sqlx::query(
         r#"
-        SELECT 
-            c.category_name, 
-            SUM(CAST(t.amount AS REAL)) as total
+        SELECT c.category_name, t.amount
         FROM transactions t
         JOIN categories c ON t.category_id = c.category_id
         WHERE t.is_expense = 1 
-          AND t.transacted_at >= ? 
-          AND t.transacted_at <= ?
-        GROUP BY c.category_name
-        ORDER BY total DESC
+          AND t.deleted_at IS NULL
+          AND UPPER(c.category_type) <> 'TRANSFER'
+          AND date(t.transacted_at) BETWEEN date(?) AND date(?)
         "#
     )
     .bind(start_date)
@@ -679,15 +758,96 @@ pub async fn get_category_spending_report(
     .fetch_all(pool)
     .await?;
 
-    let result = rows.into_iter().map(|row| {
-        let total: f64 = row.try_get("total").unwrap_or(0.0);
-        let category: String = row.get("category_name");
+    let mut totals: BTreeMap<String, Decimal> = BTreeMap::new();
+    for row in rows {
+        let amount: String = row.try_get("amount")?;
+        *totals.entry(row.try_get("category_name")?).or_default() += Decimal::from_str_exact(&amount).unwrap_or_default();
+    }
+    let mut result: Vec<CategorySpending> = totals
+        .into_iter()
+        .map(|(category, total_amount)| CategorySpending { category, total_amount })
+        .collect();
+    result.sort_by_key(|c| std::cmp::Reverse(c.total_amount));
 
-        CategorySpending {
-            category,
-            total_amount: Decimal::from_f64_retain(total).unwrap_or(Decimal::ZERO),
-        }
-    }).collect();
+    Ok(result)
+}
+
+// Line up each category's spending in two periods, largest increase first
+pub async fn compare_category_spending(
+    pool: &Pool<Sqlite>,
+    current_start: NaiveDateTime,
+    current_end: NaiveDateTime,
+    previous_start: NaiveDateTime,
+    previous_end: NaiveDateTime,
+) -> Result<Vec<CategoryComparison>, sqlx::Error> {
+    let current = get_category_spending_report(pool, current_start, current_end).await?;
+    let previous = get_category_spending_report(pool, previous_start, previous_end).await?;
+
+    // category -> (previous, current)
+    let mut totals: std::collections::BTreeMap<String, (Decimal, Decimal)> = std::collections::BTreeMap::new();
+    for p in previous {
+        totals.entry(p.category).or_default().0 += p.total_amount.round_dp(2);
+    }
+    for c in current {
+        totals.entry(c.category).or_default().1 += c.total_amount.round_dp(2);
+    }
 
+    let mut result: Vec<CategoryComparison> = totals
+        .into_iter()
+        .map(|(category, (previous_amount, current_amount))| {
+            let change = current_amount - previous_amount;
+            let change_pct = if previous_amount.is_zero() {
+                None
+            } else {
+                Some((change * Decimal::ONE_HUNDRED / previous_amount).round_dp(1))
+            };
+            CategoryComparison { category, previous_amount, current_amount, change, change_pct }
+        })
+        .collect();
+
+    result.sort_by(|a, b| b.change.cmp(&a.change).then_with(|| a.category.cmp(&b.category)));
     Ok(result)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use crate::database::db::migrate;
+
+    // January's expenses stamped both ways, plus one on each side of the month
+    async fn january_pool() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        migrate::run_migrations(&pool).await.unwrap();
+        sqlx::query("INSERT INTO accounts (account_id, account_name, account_type, balance, currency, account_created_at) VALUES (1, 'Chequing', 'CHECKING', '0', 'CAD', '2025-01-01T00:00:00Z')")
+            .execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO categories (category_id, category_name, category_type, icon) VALUES (900, 'Groceries Test', 'EXPENSE', '')")
+            .execute(&pool).await.unwrap();
+        // Imports and opening balances are stamped "…T…Z", which as text sorts after "2025-01-31 23:59:59"
+        for (amount, at) in [("10", "2025-01-31T18:30:00Z"), ("5", "2025-01-31 23:59:59"), ("2", "2025-01-01T00:00:00Z"), ("99", "2025-02-01T00:00:00Z"), ("99", "2024-12-31 23:59:59")] {
+            sqlx::query("INSERT INTO transactions (account_id, category_id, amount, base_amount, is_expense, currency, transacted_at) VALUES (1, 900, ?, ?, 1, 'CAD', ?)")
+                .bind(amount).bind(amount).bind(at)
+                .execute(&pool).await.unwrap();
+        }
+        pool
+    }
+
+    #[tokio::test]
+    async fn spending_report_counts_whole_days_in_either_stamp() {
+        let pool = january_pool().await;
+        let day = |d: u32| NaiveDate::from_ymd_opt(2025, 1, d).unwrap();
+        let report = get_category_spending_report(&pool, day(1).and_hms_opt(0, 0, 0).unwrap(), day(31).and_hms_opt(23, 59, 59).unwrap()).await.unwrap();
+        let total = report.iter().find(|c| c.category == "Groceries Test").unwrap().total_amount;
+        assert_eq!(total, Decimal::from(17));
+    }
+
+    #[tokio::test]
+    async fn monthly_summary_counts_whole_days_in_either_stamp() {
+        let pool = january_pool().await;
+        let day = |d: u32| NaiveDate::from_ymd_opt(2025, 1, d).unwrap();
+        let summary = monthly_summary(&pool, &day(1).and_hms_opt(0, 0, 0).unwrap(), &day(31).and_hms_opt(23, 59, 59).unwrap()).await.unwrap();
+        assert_eq!(summary, [(900, Decimal::from(17))]);
+    }
+}
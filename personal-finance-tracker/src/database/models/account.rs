@@ -11,4 +11,5 @@ pub struct Account {
     pub balance: Decimal,
     pub currency: Option<String>,
     pub account_created_at: NaiveDateTime,
+    pub credit_limit: Option<Decimal>,
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use serde::{Serialize, Deserialize};
 use rust_decimal::Decimal;
 use chrono::NaiveDateTime;
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Budget {
     pub budget_id: i64,
     pub account_id: i64,
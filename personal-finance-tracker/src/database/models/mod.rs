@@ -25,6 +25,16 @@ pub struct CategorySpending {
     pub total_amount: Decimal,
 }
 
+// One category's spending in two periods, side by side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryComparison {
+    pub category: String,
+    pub previous_amount: Decimal,
+    pub current_amount: Decimal,
+    pub change: Decimal,                // current - previous
+    pub change_pct: Option<Decimal>,    // None when nothing was spent in the previous period
+}
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct SavingsGoal {
     pub goal_id: i64,
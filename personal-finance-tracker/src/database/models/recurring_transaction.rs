@@ -2,7 +2,7 @@ use serde::{Serialize, Deserialize};
 use chrono::NaiveDateTime;
 use rust_decimal::Decimal;
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct RecurringTransaction {
     pub recurring_id: i64,
     pub account_id: i64,
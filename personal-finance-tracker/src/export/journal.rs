@@ -0,0 +1,386 @@
+//! Plain-text accounting export: Ledger, hledger and Beancount journals
+//! - Accounts become `Assets:<Name>` (`Liabilities:<Name>` for credit cards)
+//! - Categories become `Expenses:<Name>` / `Income:<Name>`; transfers go to `Equity:Transfers`
+//!   and opening balances to `Equity:Opening-Balances`
+//! - Every transaction is written in its own currency; `currency_rates` become price directives
+//! - Each account ends with a balance assertion at its stored balance (`accounts.balance`),
+//!   so `hledger check`, `ledger balance` and `bean-check` fail when the stored balance has
+//!   drifted from the transactions. The stored balance is one number over every currency, so
+//!   postings in other currencies are taken off it for the account's own currency, and their
+//!   own assertions can only repeat the postings.
+//! - Rows of one split group (`splits`: transaction id -> group) dated the same day are
+//!   written as one entry with a category posting each, the way they were imported
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::cli::state::{AccountDto, AccountType, CategoryDto, CategoryType, TransactionDto};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+impl Flavor {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "ledger" => Some(Flavor::Ledger),
+            "hledger" => Some(Flavor::Hledger),
+            "beancount" | "bean" => Some(Flavor::Beancount),
+            _ => None,
+        }
+    }
+}
+
+// Everything one export reads
+pub struct Book<'a> {
+    pub accounts: &'a [AccountDto],
+    pub categories: &'a [CategoryDto],
+    pub txns: &'a [TransactionDto],
+    pub tags: &'a HashMap<i64, Vec<String>>,
+    pub rates: &'a [(String, Decimal)],   // (currency, rate_to_base)
+    pub splits: &'a HashMap<i64, i64>,
+}
+
+// `today` dates the assertions and prices when there are no later transactions
+pub fn write(flavor: Flavor, book: &Book, today: NaiveDate) -> String {
+    let names = account_names(book.accounts);
+    let cats: HashMap<i64, &CategoryDto> = book.categories.iter().map(|c| (c.id, c)).collect();
+
+    let group = |t: &TransactionDto| (t.txn_date, t.account_id, book.splits.get(&t.id).copied().unwrap_or(t.id));
+    let mut txns: Vec<&TransactionDto> = book.txns.iter().filter(|t| names.contains_key(&t.account_id)).collect();
+    txns.sort_by_key(|t| (group(t), t.id));
+    let first = txns.first().map(|t| t.txn_date).unwrap_or(today);
+    let as_of = txns.last().map(|t| t.txn_date).unwrap_or(today).max(today);
+
+    // Category side of each transaction, and the balances to assert
+    let postings: Vec<(&TransactionDto, String)> = txns.iter().map(|t| (*t, category_account(t, &cats))).collect();
+    let mut balances: BTreeMap<(&str, String), Decimal> = BTreeMap::new();
+    let mut own: HashMap<i64, String> = HashMap::new();
+    for a in book.accounts {
+        balances.insert((names[&a.id].as_str(), commodity(&a.currency)), a.opening_balance.0);
+        own.insert(a.id, commodity(&a.currency));
+    }
+    for t in txns.iter().filter(|t| commodity(&t.currency) != own[&t.account_id]) {
+        let name = names[&t.account_id].as_str();
+        *balances.entry((name, own[&t.account_id].clone())).or_default() -= t.amount.0;
+        *balances.entry((name, commodity(&t.currency))).or_default() += t.amount.0;
+    }
+    let mut opened: Vec<&str> = names.values().map(|s| s.as_str()).collect();
+    opened.extend(postings.iter().map(|(_, c)| c.as_str()));
+    opened.sort();
+    opened.dedup();
+
+    let base = base_currency(book);
+    let mut out = String::new();
+    match flavor {
+        Flavor::Beancount => {
+            let _ = writeln!(out, "option \"title\" \"Personal Finance Tracker\"");
+            let _ = writeln!(out, "option \"operating_currency\" \"{}\"\n", base);
+            for name in &opened {
+                let _ = writeln!(out, "{} open {}", first, name);
+            }
+            out.push('\n');
+            for (cur, rate) in book.rates.iter().filter(|(c, _)| commodity(c) != base) {
+                let _ = writeln!(out, "{} price {} {} {}", as_of, commodity(cur), rate.normalize(), base);
+            }
+            for rows in postings.chunk_by(|a, b| group(a.0) == group(b.0)) {
+                write_beancount_txn(&mut out, rows, &names[&rows[0].0.account_id], book.tags);
+            }
+            out.push('\n');
+            // A balance directive checks the start of its day
+            let next = as_of.succ_opt().unwrap_or(as_of);
+            for ((account, cur), total) in &balances {
+                let _ = writeln!(out, "{} balance {:<40} {} {}", next, account, amount(*total), cur);
+            }
+        }
+        Flavor::Ledger | Flavor::Hledger => {
+            for name in &opened {
+                let _ = writeln!(out, "account {}", name);
+            }
+            out.push('\n');
+            for (cur, rate) in book.rates.iter().filter(|(c, _)| commodity(c) != base) {
+                let _ = writeln!(out, "P {} {} {} {}", as_of, commodity(cur), rate.normalize(), base);
+            }
+            for rows in postings.chunk_by(|a, b| group(a.0) == group(b.0)) {
+                write_ledger_txn(&mut out, flavor, rows, &names[&rows[0].0.account_id], book.tags);
+            }
+            let _ = writeln!(out, "\n{} Balance assertions", as_of);
+            for ((account, cur), total) in &balances {
+                let _ = writeln!(out, "    {:<40}  0 {} = {} {}", account, cur, amount(*total), cur);
+            }
+        }
+    }
+    out
+}
+
+// One entry for the rows of a split group (or a single row): the account posting for
+// their total, then a category posting each. A memo all the rows share is the entry's;
+// the others follow their posting.
+fn write_ledger_txn(out: &mut String, flavor: Flavor, rows: &[(&TransactionDto, String)], account: &str, tags: &HashMap<i64, Vec<String>>) {
+    let t = rows[0].0;
+    let mark = if rows.iter().all(|(r, _)| r.cleared || r.reconciled) { "* " } else { "" };
+    let memo = shared_memo(rows);
+    let description = t.payee.as_deref().map(one_line).filter(|p| !p.is_empty());
+    let _ = writeln!(out, "\n{} {}{}", t.txn_date, mark, description.as_deref().or(memo.as_deref()).unwrap_or(""));
+    if description.is_some() && let Some(m) = &memo {
+        let _ = writeln!(out, "    ; {}", m);
+    }
+    let tags: Vec<String> = entry_tags(rows, tags);
+    if !tags.is_empty() {
+        match flavor {
+            Flavor::Ledger => { let _ = writeln!(out, "    ; :{}:", tags.join(":")); }
+            _ => { let _ = writeln!(out, "    ; {}:", tags.join(":, ")); }
+        }
+    }
+    write_postings(out, "    ", rows, account, memo.as_deref());
+}
+
+fn write_beancount_txn(out: &mut String, rows: &[(&TransactionDto, String)], account: &str, tags: &HashMap<i64, Vec<String>>) {
+    let t = rows[0].0;
+    let flag = if rows.iter().all(|(r, _)| r.cleared || r.reconciled) { '*' } else { '!' };
+    let shared = shared_memo(rows);
+    let memo = quote(shared.as_deref().unwrap_or(""));
+    let _ = write!(out, "\n{} {}", t.txn_date, flag);
+    match t.payee.as_deref().filter(|p| !p.trim().is_empty()) {
+        Some(p) => { let _ = write!(out, " {} {}", quote(p), memo); }
+        None => { let _ = write!(out, " {}", memo); }
+    }
+    for tg in entry_tags(rows, tags) {
+        let _ = write!(out, " #{}", tg);
+    }
+    out.push('\n');
+    write_postings(out, "  ", rows, account, shared.as_deref());
+}
+
+// The account side once per currency, then each row's category side
+fn write_postings(out: &mut String, indent: &str, rows: &[(&TransactionDto, String)], account: &str, shared_memo: Option<&str>) {
+    let mut totals: Vec<(String, Decimal)> = Vec::new();
+    for (r, _) in rows {
+        let cur = commodity(&r.currency);
+        match totals.iter_mut().find(|(c, _)| *c == cur) {
+            Some((_, total)) => *total += r.amount.0,
+            None => totals.push((cur, r.amount.0)),
+        }
+    }
+    for (cur, total) in &totals {
+        let _ = writeln!(out, "{}{:<40}  {:>12} {}", indent, account, amount(*total), cur);
+    }
+    for (r, category) in rows {
+        let _ = write!(out, "{}{:<40}  {:>12} {}", indent, category, amount(-r.amount.0), commodity(&r.currency));
+        if let Some(m) = r.memo.as_deref().map(one_line).filter(|m| !m.is_empty() && Some(m.as_str()) != shared_memo) {
+            let _ = write!(out, "  ; {}", m);
+        }
+        out.push('\n');
+    }
+}
+
+// The memo every row of the entry has, if they agree on one
+fn shared_memo(rows: &[(&TransactionDto, String)]) -> Option<String> {
+    let memo = rows[0].0.memo.as_deref().map(one_line).filter(|m| !m.is_empty())?;
+    rows.iter().all(|(r, _)| r.memo.as_deref().map(one_line).as_ref() == Some(&memo)).then_some(memo)
+}
+
+// Tags of every row of the entry, each once
+fn entry_tags(rows: &[(&TransactionDto, String)], tags: &HashMap<i64, Vec<String>>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for t in rows.iter().filter_map(|(r, _)| tags.get(&r.id)).flatten().map(|t| tag(t)) {
+        if !out.contains(&t) {
+            out.push(t);
+        }
+    }
+    out
+}
+
+// Account id -> journal account name, unique even when two names sanitize alike
+fn account_names(accounts: &[AccountDto]) -> HashMap<i64, String> {
+    let mut out: HashMap<i64, String> = HashMap::new();
+    for a in accounts {
+        let root = if a.r#type == AccountType::Credit { "Liabilities" } else { "Assets" };
+        let mut name = format!("{}:{}", root, component(&a.name));
+        if out.values().any(|n| *n == name) {
+            name = format!("{}-{}", name, a.id);
+        }
+        out.insert(a.id, name);
+    }
+    out
+}
+
+fn category_account(t: &TransactionDto, cats: &HashMap<i64, &CategoryDto>) -> String {
+    match t.category_id.and_then(|id| cats.get(&id)) {
+        Some(c) if c.name.eq_ignore_ascii_case("Initial Balance") => "Equity:Opening-Balances".into(),
+        Some(c) => match c.r#type {
+            CategoryType::Transfer => "Equity:Transfers".into(),
+            CategoryType::Income => format!("Income:{}", component(&c.name)),
+            CategoryType::Expense => format!("Expenses:{}", component(&c.name)),
+        },
+        None if t.amount.0.is_sign_negative() => "Expenses:Uncategorized".into(),
+        None => "Income:Uncategorized".into(),
+    }
+}
+
+// One account-name component that all three tools accept: words capitalized and joined
+// with '-', e.g. "eating out / bars" -> "Eating-Out-Bars"
+fn component(name: &str) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        })
+        .collect();
+    if words.is_empty() { "Unnamed".into() } else { words.join("-") }
+}
+
+// Commodity symbols are upper-case letters and digits
+fn commodity(code: &str) -> String {
+    let c: String = code.trim().to_uppercase().chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    if c.is_empty() { "CAD".into() } else { c }
+}
+
+fn tag(t: &str) -> String {
+    t.trim().chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect()
+}
+
+// The currency with rate 1, else the most common account currency
+fn base_currency(book: &Book) -> String {
+    if let Some((c, _)) = book.rates.iter().find(|(_, r)| *r == Decimal::ONE) {
+        return commodity(c);
+    }
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for a in book.accounts {
+        *counts.entry(commodity(&a.currency)).or_default() += 1;
+    }
+    counts.into_iter().max_by_key(|(_, n)| *n).map(|(c, _)| c).unwrap_or_else(|| "CAD".into())
+}
+
+// Amounts keep their stored precision; zero is written without a sign
+fn amount(d: Decimal) -> String {
+    if d.is_zero() { "0".into() } else { d.to_string() }
+}
+
+fn one_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ").trim().to_string()
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", one_line(s).replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::state::Money;
+
+    fn account(balance: &str) -> AccountDto {
+        AccountDto {
+            id: 1,
+            name: "Chequing".into(),
+            r#type: AccountType::Checking,
+            currency: "CAD".into(),
+            opening_balance: Money(Decimal::from_str_exact(balance).unwrap()),
+            created_at: "2025-01-01T00:00:00Z".into(),
+            credit_limit: None,
+            deleted_at: None,
+        }
+    }
+
+    fn txn(id: i64, amount: &str, currency: &str) -> TransactionDto {
+        TransactionDto {
+            id,
+            account_id: 1,
+            category_id: Some(1),
+            amount: Money(Decimal::from_str_exact(amount).unwrap()),
+            memo: None,
+            payee: Some("Shop".into()),
+            currency: currency.into(),
+            txn_date: NaiveDate::from_ymd_opt(2025, 3, id as u32).unwrap(),
+            cleared: false,
+            reconciled: false,
+        }
+    }
+
+    fn export(flavor: Flavor, accounts: &[AccountDto], txns: &[TransactionDto]) -> String {
+        export_splits(flavor, accounts, txns, &HashMap::new())
+    }
+
+    fn export_splits(flavor: Flavor, accounts: &[AccountDto], txns: &[TransactionDto], splits: &HashMap<i64, i64>) -> String {
+        let categories = [
+            CategoryDto { id: 1, name: "Food".into(), r#type: CategoryType::Expense, icon: String::new() },
+            CategoryDto { id: 2, name: "Household".into(), r#type: CategoryType::Expense, icon: String::new() },
+        ];
+        let tags = HashMap::from([(5, vec!["costco".into()]), (6, vec!["costco".into(), "bulk buy".into()])]);
+        let book = Book { accounts, categories: &categories, txns, tags: &tags, rates: &[], splits };
+        write(flavor, &book, NaiveDate::from_ymd_opt(2025, 3, 31).unwrap())
+    }
+
+    // Three rows on one day, the first two imported as one split
+    fn split_purchase() -> Vec<TransactionDto> {
+        let mut txns = vec![txn(5, "-80.00", "CAD"), txn(6, "-15.50", "CAD"), txn(7, "-4.50", "CAD")];
+        for t in &mut txns {
+            t.txn_date = NaiveDate::from_ymd_opt(2025, 3, 20).unwrap();
+            t.memo = Some("weekly".into());
+        }
+        txns[1].category_id = Some(2);
+        txns[1].memo = Some("paper towels".into());
+        txns
+    }
+
+    // The lines of the entries whose first line starts with `head`
+    fn entries<'a>(out: &'a str, head: &str) -> Vec<&'a str> {
+        out.split("\n\n").map(|e| e.trim_start_matches('\n')).filter(|e| e.starts_with(head)).flat_map(|e| e.lines()).map(str::trim_end).collect()
+    }
+
+    #[test]
+    fn asserts_the_stored_balance_not_the_postings() {
+        // The postings add up to 70, but the stored balance has drifted to 75
+        let txns = [txn(1, "100", "CAD"), txn(2, "-30", "CAD")];
+        let out = export(Flavor::Hledger, &[account("75")], &txns);
+        assert!(out.lines().any(|l| l.split_whitespace().collect::<Vec<_>>() == ["Assets:Chequing", "0", "CAD", "=", "75", "CAD"]), "{}", out);
+
+        let out = export(Flavor::Beancount, &[account("75")], &txns);
+        assert!(out.lines().any(|l| l.split_whitespace().collect::<Vec<_>>() == ["2025-04-01", "balance", "Assets:Chequing", "75", "CAD"]), "{}", out);
+    }
+
+    #[test]
+    fn other_currencies_come_off_the_stored_balance() {
+        let txns = [txn(1, "100", "CAD"), txn(2, "-20", "USD")];
+        let out = export(Flavor::Ledger, &[account("80")], &txns);
+        assert!(out.contains("0 CAD = 100 CAD"), "{}", out);
+        assert!(out.contains("0 USD = -20 USD"), "{}", out);
+    }
+
+    #[test]
+    fn writes_a_split_group_as_one_entry() {
+        let splits = HashMap::from([(5, 5), (6, 5)]);
+        let out = export_splits(Flavor::Hledger, &[account("0")], &split_purchase(), &splits);
+        assert_eq!(entries(&out, "2025-03-20 Shop"), [
+            "2025-03-20 Shop",
+            "    ; costco:, bulk-buy:",
+            "    Assets:Chequing                                 -95.50 CAD",
+            "    Expenses:Food                                    80.00 CAD  ; weekly",
+            "    Expenses:Household                               15.50 CAD  ; paper towels",
+            "2025-03-20 Shop",
+            "    ; weekly",
+            "    Assets:Chequing                                  -4.50 CAD",
+            "    Expenses:Food                                     4.50 CAD",
+        ]);
+
+        let out = export_splits(Flavor::Beancount, &[account("0")], &split_purchase(), &splits);
+        assert_eq!(entries(&out, "2025-03-20 !"), [
+            "2025-03-20 ! \"Shop\" \"\" #costco #bulk-buy",
+            "  Assets:Chequing                                 -95.50 CAD",
+            "  Expenses:Food                                    80.00 CAD  ; weekly",
+            "  Expenses:Household                               15.50 CAD  ; paper towels",
+            "2025-03-20 ! \"Shop\" \"weekly\"",
+            "  Assets:Chequing                                  -4.50 CAD",
+            "  Expenses:Food                                     4.50 CAD",
+        ]);
+    }
+}
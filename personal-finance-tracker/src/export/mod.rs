@@ -0,0 +1,7 @@
+//! File exports for other finance tools
+//! - Each format renders DTOs loaded through `Client` into a `String`
+
+pub mod journal;
+pub mod qif;
+pub mod statement;
+pub mod table;
@@ -0,0 +1,124 @@
+//! QIF export of one account, readable by Quicken, MS Money and GnuCash
+//! - Rows of one split group (`splits`: transaction id -> group) dated the same day are
+//!   written as one record with an `S` / `E` / `$` line each, the way they were imported
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use rust_decimal::Decimal;
+
+use crate::cli::state::{AccountDto, AccountType, CategoryDto, CategoryType, TransactionDto};
+
+pub fn qif_type(t: AccountType) -> &'static str {
+    match t {
+        AccountType::Cash => "Cash",
+        AccountType::Credit => "CCard",
+        _ => "Bank",
+    }
+}
+
+// `txns` in any order; they are written oldest first
+pub fn write_account(account: &AccountDto, txns: &[TransactionDto], categories: &[CategoryDto], splits: &HashMap<i64, i64>) -> String {
+    let cats: HashMap<i64, &CategoryDto> = categories.iter().map(|c| (c.id, c)).collect();
+    let category = |t: &TransactionDto| t.category_id.and_then(|id| cats.get(&id)).map(|c| {
+        // Transfers name the other account in brackets; we only know it was a transfer
+        if c.r#type == CategoryType::Transfer { format!("[{}]", one_line(&c.name)) } else { one_line(&c.name) }
+    });
+    let group = |t: &TransactionDto| (t.txn_date, splits.get(&t.id).copied().unwrap_or(t.id));
+    let mut sorted: Vec<&TransactionDto> = txns.iter().collect();
+    sorted.sort_by_key(|t| (group(t), t.id));
+
+    let mut out = String::new();
+    let _ = writeln!(out, "!Type:{}", qif_type(account.r#type));
+    for rows in sorted.chunk_by(|a, b| group(a) == group(b)) {
+        let t = rows[0];
+        let _ = writeln!(out, "D{}", t.txn_date.format("%m/%d/%Y"));
+        let _ = writeln!(out, "T{}", rows.iter().map(|r| r.amount.0).sum::<Decimal>().round_dp(2));
+        if rows.iter().all(|r| r.reconciled) {
+            out.push_str("CX\n");
+        } else if rows.iter().all(|r| r.cleared) {
+            out.push_str("C*\n");
+        }
+        if let Some(p) = &t.payee {
+            let _ = writeln!(out, "P{}", one_line(p));
+        }
+        // A memo all the splits share is the record's; the others go on their split lines
+        let memo = t.memo.as_ref().filter(|m| rows.iter().all(|r| r.memo.as_ref() == Some(*m)));
+        if let Some(m) = memo {
+            let _ = writeln!(out, "M{}", one_line(m));
+        }
+        if !splits.contains_key(&t.id) {
+            if let Some(c) = category(t) {
+                let _ = writeln!(out, "L{}", c);
+            }
+        } else {
+            for r in rows {
+                let _ = writeln!(out, "S{}", category(r).unwrap_or_default());
+                if let Some(m) = r.memo.as_ref().filter(|m| memo != Some(*m)) {
+                    let _ = writeln!(out, "E{}", one_line(m));
+                }
+                let _ = writeln!(out, "${}", r.amount.0.round_dp(2));
+            }
+        }
+        out.push_str("^\n");
+    }
+    out
+}
+
+fn one_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::cli::state::Money;
+
+    fn txn(id: i64, amount: &str, category_id: i64, memo: Option<&str>, cleared: bool) -> TransactionDto {
+        TransactionDto {
+            id,
+            account_id: 1,
+            category_id: Some(category_id),
+            amount: Money(amount.parse().unwrap()),
+            memo: memo.map(Into::into),
+            payee: Some("Superstore".into()),
+            currency: "CAD".into(),
+            txn_date: NaiveDate::from_ymd_opt(2025, 3, 20).unwrap(),
+            cleared,
+            reconciled: false,
+        }
+    }
+
+    #[test]
+    fn writes_a_split_group_as_one_record() {
+        let account = AccountDto {
+            id: 1,
+            name: "Chequing".into(),
+            r#type: AccountType::Checking,
+            currency: "CAD".into(),
+            opening_balance: Money::zero(),
+            created_at: "2025-01-01T00:00:00Z".into(),
+            credit_limit: None,
+            deleted_at: None,
+        };
+        let categories = [
+            CategoryDto { id: 1, name: "Groceries".into(), r#type: CategoryType::Expense, icon: String::new() },
+            CategoryDto { id: 2, name: "Savings".into(), r#type: CategoryType::Transfer, icon: String::new() },
+        ];
+        let txns = [
+            txn(7, "-15.00", 2, Some("weekly"), true),
+            txn(5, "-80.00", 1, Some("weekly"), true),
+            txn(6, "-4.50", 1, None, false),
+        ];
+        let splits = HashMap::from([(5, 5), (7, 5)]);
+
+        assert_eq!(write_account(&account, &txns, &categories, &splits), [
+            "!Type:Bank",
+            "D03/20/2025", "T-95.00", "C*", "PSuperstore", "Mweekly", "SGroceries", "$-80.00", "S[Savings]", "$-15.00", "^",
+            "D03/20/2025", "T-4.50", "PSuperstore", "LGroceries", "^",
+            "",
+        ].join("\n"));
+    }
+}
@@ -0,0 +1,299 @@
+//! Monthly statement as one self-contained HTML page
+//! - Styles are inline and charts are inline SVG, so the file needs nothing else to open,
+//!   archive or print
+//! - Sections: account summary, spending by category, budgets, goals, then every transaction
+//!   of the month per account
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::cli::state::{CategorySpendingDto, StatementDto, TransactionDto};
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; color: #222; margin: 2em auto; max-width: 60em; padding: 0 1em; }
+h1 { margin-bottom: 0; }
+h2 { border-bottom: 2px solid #ddd; padding-bottom: .2em; margin-top: 2em; }
+.sub { color: #666; margin-top: .2em; }
+table { border-collapse: collapse; width: 100%; margin: .5em 0 1em; font-size: .92em; }
+th, td { padding: .3em .6em; border-bottom: 1px solid #eee; text-align: left; }
+th { background: #f5f5f5; }
+td.num, th.num { text-align: right; font-variant-numeric: tabular-nums; white-space: nowrap; }
+tr.total td { font-weight: bold; border-top: 2px solid #ccc; }
+.neg { color: #b00020; }
+.empty { color: #888; font-style: italic; }
+svg text { font-family: inherit; font-size: 12px; fill: #222; }
+@media print {
+  body { margin: 0; max-width: none; }
+  h2 { break-after: avoid; }
+  section.account { break-inside: avoid-page; }
+}
+"#;
+
+const PALETTE: [&str; 8] = ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#9c755f"];
+
+// First day of a YYYY-MM month
+pub fn parse_month(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", s.trim()), "%Y-%m-%d").ok()
+}
+
+// `generated` is printed in the header, e.g. "2025-04-02 09:30"
+pub fn render(s: &StatementDto, generated: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">");
+    let _ = writeln!(out, "<title>Statement {}</title>\n<style>{}</style>\n</head>\n<body>", esc(&s.month), STYLE);
+    let _ = writeln!(out, "<h1>Monthly statement &middot; {}</h1>", esc(&s.month));
+    let _ = writeln!(out, "<p class=\"sub\">{} to {} &middot; generated {}</p>", s.start, s.end, esc(generated));
+
+    write_summary(&mut out, s);
+    write_spending(&mut out, s);
+    write_budgets(&mut out, s);
+    write_goals(&mut out, s);
+    write_transactions(&mut out, s);
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn write_summary(out: &mut String, s: &StatementDto) {
+    out.push_str("<h2>Accounts</h2>\n<table>\n<tr><th>Account</th><th>Currency</th><th class=\"num\">Opening</th><th class=\"num\">In</th><th class=\"num\">Out</th><th class=\"num\">Closing</th></tr>\n");
+    for a in &s.accounts {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td>{}{}{}{}</tr>",
+            esc(&a.name), esc(&a.currency),
+            num(a.opening_balance.0), num(a.money_in.0), num(a.money_out.0), num(a.closing_balance.0)
+        );
+    }
+    out.push_str("</table>\n");
+    let expenses: Decimal = s.spending.iter().map(|c| c.total_amount.0).sum();
+    let _ = writeln!(
+        out,
+        "<table>\n<tr><td>Income</td>{}</tr>\n<tr><td>Expenses</td>{}</tr>\n<tr class=\"total\"><td>Net</td>{}</tr>\n</table>",
+        num(s.income.0), num(expenses), num(s.income.0 - expenses)
+    );
+}
+
+fn write_spending(out: &mut String, s: &StatementDto) {
+    out.push_str("<h2>Spending by category</h2>\n");
+    if s.spending.is_empty() {
+        out.push_str("<p class=\"empty\">No spending this month.</p>\n");
+        return;
+    }
+    out.push_str(&bar_chart(&s.spending));
+    let total: Decimal = s.spending.iter().map(|c| c.total_amount.0).sum();
+    out.push_str("<table>\n<tr><th>Category</th><th class=\"num\">Amount</th><th class=\"num\">Share</th></tr>\n");
+    for c in &s.spending {
+        let _ = writeln!(out, "<tr><td>{}</td>{}<td class=\"num\">{}</td></tr>", esc(&c.category), num(c.total_amount.0), percent(c.total_amount.0, total));
+    }
+    let _ = writeln!(out, "<tr class=\"total\"><td>Total</td>{}<td class=\"num\">100.0%</td></tr>\n</table>", num(total));
+}
+
+fn write_budgets(out: &mut String, s: &StatementDto) {
+    out.push_str("<h2>Budgets</h2>\n");
+    if s.budgets.is_empty() {
+        out.push_str("<p class=\"empty\">No budgets.</p>\n");
+        return;
+    }
+    out.push_str("<table>\n<tr><th>Budget</th><th>Progress</th><th class=\"num\">Spent</th><th class=\"num\">Limit</th><th class=\"num\">Left</th></tr>\n");
+    for b in &s.budgets {
+        let name = match &b.category {
+            Some(c) => format!("{} &middot; {}", esc(&b.account), esc(c)),
+            None => format!("{} &middot; all spending", esc(&b.account)),
+        };
+        let over = b.spent.0 > b.limit.0;
+        let _ = writeln!(
+            out,
+            "<tr><td>{} <span class=\"sub\">({})</span></td><td>{}</td>{}{}{}</tr>",
+            name, esc(&b.period),
+            progress_bar(b.spent.0, b.limit.0, if over { "#e15759" } else { "#59a14f" }),
+            num(b.spent.0), num(b.limit.0), num(b.limit.0 - b.spent.0)
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn write_goals(out: &mut String, s: &StatementDto) {
+    out.push_str("<h2>Saving goals</h2>\n");
+    if s.goals.is_empty() {
+        out.push_str("<p class=\"empty\">No saving goals.</p>\n");
+        return;
+    }
+    out.push_str("<table>\n<tr><th>Goal</th><th>Progress</th><th class=\"num\">Saved</th><th class=\"num\">Target</th><th>Deadline</th></tr>\n");
+    for g in &s.goals {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td>{}{}<td>{}</td></tr>",
+            esc(&g.name),
+            progress_bar(g.current_amount.0, g.target_amount.0, "#4e79a7"),
+            num(g.current_amount.0), num(g.target_amount.0),
+            esc(g.deadline.as_deref().unwrap_or(""))
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn write_transactions(out: &mut String, s: &StatementDto) {
+    let cats: HashMap<i64, &str> = s.categories.iter().map(|c| (c.id, c.name.as_str())).collect();
+    out.push_str("<h2>Transactions</h2>\n");
+    for a in &s.accounts {
+        let _ = writeln!(out, "<section class=\"account\">\n<h3>{} ({})</h3>", esc(&a.name), esc(&a.currency));
+        out.push_str("<table>\n<tr><th>Date</th><th>Payee</th><th>Category</th><th>Memo</th><th class=\"num\">Amount</th><th class=\"num\">Balance</th></tr>\n");
+        let _ = writeln!(out, "<tr><td>{}</td><td colspan=\"4\">Opening balance</td>{}</tr>", s.start, num(a.opening_balance.0));
+        let mut balance = a.opening_balance.0;
+        for t in &a.transactions {
+            balance += t.amount.0;
+            write_txn_row(out, t, &cats, balance);
+        }
+        let _ = writeln!(out, "<tr class=\"total\"><td>{}</td><td colspan=\"4\">Closing balance</td>{}</tr>\n</table>\n</section>", s.end, num(a.closing_balance.0));
+    }
+}
+
+fn write_txn_row(out: &mut String, t: &TransactionDto, cats: &HashMap<i64, &str>, balance: Decimal) {
+    let category = t.category_id.and_then(|id| cats.get(&id)).copied().unwrap_or("");
+    let _ = writeln!(
+        out,
+        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td>{}{}</tr>",
+        t.txn_date,
+        esc(t.payee.as_deref().unwrap_or("")),
+        esc(category),
+        esc(t.memo.as_deref().unwrap_or("")),
+        num(t.amount.0), num(balance)
+    );
+}
+
+// Horizontal bars, largest category first (the report is already sorted)
+fn bar_chart(rows: &[CategorySpendingDto]) -> String {
+    const WIDTH: u32 = 640;
+    const LABEL: u32 = 150;
+    const VALUE: u32 = 90;
+    const ROW: u32 = 24;
+    let max = rows.iter().map(|r| r.total_amount.0).max().unwrap_or(Decimal::ONE).max(Decimal::ONE);
+    let span = Decimal::from(WIDTH - LABEL - VALUE);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" role=\"img\" aria-label=\"Spending by category\">\n",
+        WIDTH, ROW * rows.len() as u32, WIDTH, ROW * rows.len() as u32
+    );
+    for (i, r) in rows.iter().enumerate() {
+        let y = ROW * i as u32;
+        let w = (r.total_amount.0 / max * span).round().to_u32().unwrap_or(0).max(1);
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>", LABEL - 8, y + 16, esc(&truncate(&r.category, 22)));
+        let _ = writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>", LABEL, y + 4, w, ROW - 8, PALETTE[i % PALETTE.len()]);
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\">{:.2}</text>", LABEL + w + 6, y + 16, r.total_amount.0);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Bar filled to `part / whole`, capped at full
+fn progress_bar(part: Decimal, whole: Decimal, color: &str) -> String {
+    const WIDTH: u32 = 200;
+    let ratio = if whole > Decimal::ZERO { (part / whole).clamp(Decimal::ZERO, Decimal::ONE) } else { Decimal::ZERO };
+    let filled = (ratio * Decimal::from(WIDTH)).round().to_u32().unwrap_or(0);
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"14\" viewBox=\"0 0 {w} 14\"><rect width=\"{w}\" height=\"14\" rx=\"3\" fill=\"#eee\"/><rect width=\"{f}\" height=\"14\" rx=\"3\" fill=\"{c}\"/></svg> {p}",
+        w = WIDTH, f = filled, c = color, p = percent(part, whole)
+    )
+}
+
+fn num(d: Decimal) -> String {
+    let class = if d.is_sign_negative() && !d.is_zero() { "num neg" } else { "num" };
+    format!("<td class=\"{}\">{:.2}</td>", class, d)
+}
+
+fn percent(part: Decimal, whole: Decimal) -> String {
+    if whole.is_zero() { String::new() } else { format!("{:.1}%", part / whole * Decimal::ONE_HUNDRED) }
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max { s.to_string() } else { format!("{}…", s.chars().take(max - 1).collect::<String>()) }
+}
+
+fn esc(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::state::{AccountStatementDto, CategoryDto, CategoryType, Money};
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+
+    fn txn(id: i64, amount: i64, payee: &str) -> TransactionDto {
+        TransactionDto {
+            id,
+            account_id: 1,
+            category_id: Some(1),
+            amount: Money(Decimal::from(amount)),
+            memo: None,
+            payee: Some(payee.into()),
+            currency: "CAD".into(),
+            txn_date: day(id as u32 * 10),
+            cleared: false,
+            reconciled: false,
+        }
+    }
+
+    fn statement() -> StatementDto {
+        let account = |name: &str, opening: i64, txns: Vec<TransactionDto>| {
+            let money_in: Decimal = txns.iter().map(|t| t.amount.0).filter(|a| a.is_sign_positive()).sum();
+            let money_out: Decimal = txns.iter().map(|t| t.amount.0).filter(|a| a.is_sign_negative()).sum();
+            AccountStatementDto {
+                account_id: 1,
+                name: name.into(),
+                currency: "CAD".into(),
+                opening_balance: Money(Decimal::from(opening)),
+                closing_balance: Money(Decimal::from(opening) + money_in + money_out),
+                money_in: Money(money_in),
+                money_out: Money(-money_out),
+                transactions: txns,
+            }
+        };
+        StatementDto {
+            month: "2025-03".into(),
+            start: day(1),
+            end: day(31),
+            accounts: vec![
+                account("Chequing", 100, vec![txn(1, -30, "Ben & Jerry's"), txn(2, 50, "Employer")]),
+                account("Visa", 0, vec![txn(3, -40, "Grocer")]),
+            ],
+            income: Money(Decimal::from(50)),
+            spending: vec![CategorySpendingDto { category: "Food".into(), total_amount: Money(Decimal::from(70)) }],
+            budgets: Vec::new(),
+            goals: Vec::new(),
+            categories: vec![CategoryDto { id: 1, name: "Food".into(), r#type: CategoryType::Expense, icon: String::new() }],
+        }
+    }
+
+    #[test]
+    fn each_account_runs_from_its_opening_to_its_closing_balance() {
+        let html = render(&statement(), "2025-04-02 09:30");
+        assert!(html.contains("<tr><td>Chequing</td><td>CAD</td><td class=\"num\">100.00</td><td class=\"num\">50.00</td><td class=\"num\">30.00</td><td class=\"num\">120.00</td></tr>"), "{}", html);
+
+        let chequing: Vec<&str> = html.lines().skip_while(|l| !l.starts_with("<h3>Chequing")).take_while(|l| *l != "</section>").collect();
+        assert_eq!(chequing[3..], [
+            "<tr><td>2025-03-01</td><td colspan=\"4\">Opening balance</td><td class=\"num\">100.00</td></tr>",
+            "<tr><td>2025-03-10</td><td>Ben &amp; Jerry&#39;s</td><td>Food</td><td></td><td class=\"num neg\">-30.00</td><td class=\"num\">70.00</td></tr>",
+            "<tr><td>2025-03-20</td><td>Employer</td><td>Food</td><td></td><td class=\"num\">50.00</td><td class=\"num\">120.00</td></tr>",
+            "<tr class=\"total\"><td>2025-03-31</td><td colspan=\"4\">Closing balance</td><td class=\"num\">120.00</td></tr>",
+            "</table>",
+        ]);
+
+        // A balance below zero is marked as such
+        assert!(html.contains("<tr class=\"total\"><td>2025-03-31</td><td colspan=\"4\">Closing balance</td><td class=\"num neg\">-40.00</td></tr>"), "{}", html);
+        assert!(html.contains("<tr class=\"total\"><td>Net</td><td class=\"num neg\">-20.00</td></tr>"), "{}", html);
+    }
+
+    #[test]
+    fn months_parse_to_their_first_day() {
+        assert_eq!(parse_month(" 2025-03 "), Some(day(1)));
+        assert_eq!(parse_month("2025-13"), None);
+    }
+}
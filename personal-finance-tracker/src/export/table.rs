@@ -0,0 +1,251 @@
+//! CSV / Markdown tables of the views the TUI shows (transactions, reports, goals)
+//! - Each view is first turned into a `TableExport` of display strings, then rendered
+//! - Markdown right-aligns the columns that hold only numbers
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+
+use crate::cli::state::{
+    AccountForecastDto, CashFlowDto, CategoryComparisonDto, CategoryDto, CategorySpendingDto,
+    PayeeSpendingDto, SavingGoalDto, TransactionDto,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Markdown,
+}
+
+impl TableFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "md" | "markdown" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+    // From the file extension; anything but .md / .markdown is CSV
+    pub fn from_path(path: &str) -> Self {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".md") || lower.ends_with(".markdown") { Self::Markdown } else { Self::Csv }
+    }
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Markdown => "md",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TableExport {
+    pub name: String,       // file name stem, e.g. "spending-2025-03"
+    pub title: String,      // Markdown heading
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl TableExport {
+    fn new(name: String, title: String, headers: &[&str]) -> Self {
+        Self { name, title, headers: headers.iter().map(|h| h.to_string()).collect(), rows: Vec::new() }
+    }
+
+    pub fn default_path(&self, format: TableFormat) -> String {
+        format!("{}.{}", self.name, format.extension())
+    }
+
+    pub fn render(&self, format: TableFormat) -> Result<String> {
+        match format {
+            TableFormat::Csv => self.to_csv(),
+            TableFormat::Markdown => Ok(self.to_markdown()),
+        }
+    }
+
+    fn to_csv(&self) -> Result<String> {
+        let mut w = csv::Writer::from_writer(Vec::new());
+        w.write_record(&self.headers)?;
+        for row in &self.rows {
+            w.write_record(row)?;
+        }
+        Ok(String::from_utf8(w.into_inner()?)?)
+    }
+
+    fn to_markdown(&self) -> String {
+        let cell = |s: &str| s.replace('|', "\\|").replace(['\r', '\n'], " ");
+        let numeric: Vec<bool> = (0..self.headers.len())
+            .map(|c| {
+                let mut cells = self.rows.iter().filter_map(|r| r.get(c)).filter(|s| !s.is_empty()).peekable();
+                cells.peek().is_some() && cells.all(|s| s.trim_end_matches('%').parse::<Decimal>().is_ok())
+            })
+            .collect();
+
+        let mut out = format!("### {}\n\n", self.title);
+        out.push_str(&format!("| {} |\n", self.headers.iter().map(|h| cell(h)).collect::<Vec<_>>().join(" | ")));
+        out.push_str(&format!("|{}|\n", numeric.iter().map(|n| if *n { " ---: " } else { " --- " }).collect::<Vec<_>>().join("|")));
+        for row in &self.rows {
+            out.push_str(&format!("| {} |\n", row.iter().map(|c| cell(c)).collect::<Vec<_>>().join(" | ")));
+        }
+        out
+    }
+}
+
+pub fn transactions(name: &str, title: &str, txns: &[TransactionDto], categories: &[CategoryDto]) -> TableExport {
+    let cats: HashMap<i64, &str> = categories.iter().map(|c| (c.id, c.name.as_str())).collect();
+    let mut t = TableExport::new(name.into(), title.into(), &["Date", "Payee", "Category", "Memo", "Amount", "Currency", "Status"]);
+    for x in txns {
+        let status = if x.reconciled { "reconciled" } else if x.cleared { "cleared" } else { "" };
+        t.rows.push(vec![
+            x.txn_date.to_string(),
+            x.payee.clone().unwrap_or_default(),
+            x.category_id.and_then(|id| cats.get(&id)).map(|s| s.to_string()).unwrap_or_default(),
+            x.memo.clone().unwrap_or_default(),
+            money(x.amount.0),
+            x.currency.clone(),
+            status.into(),
+        ]);
+    }
+    t
+}
+
+// `period` is the report label, e.g. 2025-03
+pub fn category_spending(period: &str, rows: &[CategorySpendingDto]) -> TableExport {
+    let mut t = TableExport::new(format!("spending-{}", period), format!("Spending by category, {}", period), &["Category", "Amount", "Share"]);
+    let total: Decimal = rows.iter().map(|r| r.total_amount.0).sum();
+    for r in rows {
+        t.rows.push(vec![r.category.clone(), money(r.total_amount.0), percent(r.total_amount.0, total)]);
+    }
+    t.rows.push(vec!["Total".into(), money(total), if rows.is_empty() { String::new() } else { "100.0%".into() }]);
+    t
+}
+
+pub fn goals(goals: &[SavingGoalDto]) -> TableExport {
+    let mut t = TableExport::new("goals".into(), "Saving goals".into(), &["Goal", "Saved", "Target", "Progress", "Remaining", "Deadline"]);
+    for g in goals {
+        let remaining = (g.target_amount.0 - g.current_amount.0).max(Decimal::ZERO);
+        t.rows.push(vec![
+            g.name.clone(),
+            money(g.current_amount.0),
+            money(g.target_amount.0),
+            percent(g.current_amount.0, g.target_amount.0),
+            money(remaining),
+            g.deadline.clone().unwrap_or_default(),
+        ]);
+    }
+    t
+}
+
+pub fn cash_flow(rows: &[CashFlowDto]) -> TableExport {
+    let mut t = TableExport::new("cashflow".into(), "Cash flow by month".into(), &["Month", "Income", "Expenses", "Net", "Savings Rate"]);
+    for r in rows {
+        t.rows.push(vec![
+            r.month.clone(),
+            money(r.income.0),
+            money(r.expenses.0),
+            money(r.net.0),
+            r.savings_rate.map(|p| format!("{:.1}%", p)).unwrap_or_default(),
+        ]);
+    }
+    t
+}
+
+// `title` names both periods, e.g. "2025-03 vs 2025-02"
+pub fn comparison(name: &str, title: &str, rows: &[CategoryComparisonDto]) -> TableExport {
+    let mut t = TableExport::new(name.into(), title.into(), &["Category", "Previous", "Current", "Change", "Change %"]);
+    for r in rows {
+        t.rows.push(vec![
+            r.category.clone(),
+            money(r.previous_amount.0),
+            money(r.current_amount.0),
+            money(r.change.0),
+            r.change_pct.map(|p| format!("{:.1}%", p)).unwrap_or_default(),
+        ]);
+    }
+    t
+}
+
+pub fn payees(period: &str, rows: &[PayeeSpendingDto]) -> TableExport {
+    let mut t = TableExport::new(format!("payees-{}", period), format!("Spending by payee, {}", period), &["Payee", "Transactions", "Total", "Average"]);
+    for r in rows {
+        t.rows.push(vec![r.payee.clone(), r.txn_count.to_string(), money(r.total_amount.0), money(r.average_amount.0)]);
+    }
+    t
+}
+
+// Projected balances of every account; the first projected problem is noted on its day
+pub fn forecast(days: u32, accounts: &[AccountForecastDto]) -> TableExport {
+    let mut t = TableExport::new(format!("forecast-{}d", days), format!("Balance forecast, next {} days", days), &["Account", "Date", "Balance", "Alert"]);
+    for a in accounts {
+        for p in &a.projection {
+            let alert = a.alert.as_ref().filter(|al| al.date == p.date).map(|al| al.reason.clone()).unwrap_or_default();
+            t.rows.push(vec![a.name.clone(), p.date.to_string(), money(p.balance.0), alert]);
+        }
+    }
+    t
+}
+
+fn money(d: Decimal) -> String {
+    format!("{:.2}", d)
+}
+
+fn percent(part: Decimal, whole: Decimal) -> String {
+    if whole.is_zero() { String::new() } else { format!("{:.1}%", part / whole * Decimal::ONE_HUNDRED) }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::cli::state::{CategoryType, Money};
+
+    fn export() -> TableExport {
+        let categories = [CategoryDto { id: 1, name: "Food".into(), r#type: CategoryType::Expense, icon: String::new() }];
+        let txn = |id, amount: &str, payee: &str, memo: Option<&str>| TransactionDto {
+            id,
+            account_id: 1,
+            category_id: Some(1),
+            amount: Money(amount.parse().unwrap()),
+            memo: memo.map(Into::into),
+            payee: Some(payee.into()),
+            currency: "CAD".into(),
+            txn_date: NaiveDate::from_ymd_opt(2025, 3, id as u32).unwrap(),
+            cleared: id == 2,
+            reconciled: false,
+        };
+        let txns = [
+            txn(1, "-12.5", "Cafe | Bar", Some("split, \"large\"\nreceipt")),
+            txn(2, "1500", "Employer", None),
+        ];
+        transactions("march", "Chequing, March", &txns, &categories)
+    }
+
+    #[test]
+    fn markdown_escapes_pipes_and_right_aligns_numbers() {
+        assert_eq!(export().render(TableFormat::Markdown).unwrap(), [
+            "### Chequing, March",
+            "",
+            "| Date | Payee | Category | Memo | Amount | Currency | Status |",
+            "| --- | --- | --- | --- | ---: | --- | --- |",
+            "| 2025-03-01 | Cafe \\| Bar | Food | split, \"large\" receipt | -12.50 | CAD |  |",
+            "| 2025-03-02 | Employer | Food |  | 1500.00 | CAD | cleared |",
+            "",
+        ].join("\n"));
+
+        // Percent columns count as numbers; an empty total share does not spoil that
+        let spending = category_spending("2025-03", &[CategorySpendingDto { category: "Rent".into(), total_amount: Money(Decimal::from(900)) }]);
+        assert!(spending.render(TableFormat::Markdown).unwrap().contains("| --- | ---: | ---: |"));
+        assert!(category_spending("2025-03", &[]).render(TableFormat::Markdown).unwrap().contains("| --- | ---: | --- |"));
+    }
+
+    #[test]
+    fn csv_quotes_cells_that_need_it() {
+        assert_eq!(export().render(TableFormat::Csv).unwrap(), [
+            "Date,Payee,Category,Memo,Amount,Currency,Status",
+            "2025-03-01,Cafe | Bar,Food,\"split, \"\"large\"\"\nreceipt\",-12.50,CAD,",
+            "2025-03-02,Employer,Food,,1500.00,CAD,cleared",
+            "",
+        ].join("\n"));
+    }
+}
@@ -0,0 +1,230 @@
+//! ISO 20022 camt.053 bank-to-customer statements (XML)
+//! - One transaction per booked `<Ntry>`; pending entries are skipped
+//! - Booking date, value date, the counterparty (`Cdtr` for money out, `Dbtr` for money in)
+//!   as payee and the remittance info (`Ustrd`, structured `Ref`) as memo
+//! - `OPBD` / `PRCD` and `CLBD` balances become the opening and closing balance checks
+//!
+//! Read with a small tag scanner like the OFX one; namespace prefixes are ignored, so
+//! camt.053.001.02 through .001.08 all read the same.
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use super::ofx::decode_entities;
+use super::{drop_repeated_ids, non_empty, parse_amount, ImportedTxn, ParsedImport, StatementBalance};
+
+// True when the file looks like a camt.053 statement, whatever its extension
+pub fn is_camt(content: &str) -> bool {
+    content.contains("BkToCstmrStmt")
+}
+
+#[derive(Default)]
+struct Balance {
+    code: String,
+    amount: Option<Decimal>,
+    debit: bool,
+    date: Option<NaiveDate>,
+}
+
+#[derive(Default)]
+struct Entry {
+    amount: Option<Decimal>,
+    debit: Option<bool>,
+    status: String,
+    booked: Option<NaiveDate>,
+    value: Option<NaiveDate>,
+    reference: Option<String>,      // AcctSvcrRef, else NtryRef
+    debtor: Option<String>,
+    creditor: Option<String>,
+    remittance: Vec<String>,
+    info: Option<String>,           // AddtlNtryInf, the memo when there is no remittance info
+}
+
+pub fn parse(content: &str) -> Result<ParsedImport> {
+    if !is_camt(content) {
+        bail!("not a camt.053 statement (no <BkToCstmrStmt> element)");
+    }
+
+    let mut out = ParsedImport::default();
+    let mut path: Vec<String> = Vec::new();
+    let mut entry: Option<Entry> = None;
+    let mut balance: Option<Balance> = None;
+    let mut entry_no = 0;
+
+    for piece in content.split('<').skip(1) {
+        let Some((tag, text)) = piece.split_once('>') else { continue };
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+
+        if let Some(closed) = tag.strip_prefix('/') {
+            let name = local_name(closed);
+            match name {
+                "Ntry" => {
+                    if let Some(e) = entry.take() {
+                        finish_entry(&mut out, e, entry_no);
+                    }
+                }
+                "Bal" => {
+                    if let Some(b) = balance.take() {
+                        finish_balance(&mut out, b);
+                    }
+                }
+                _ => {}
+            }
+            if let Some(i) = path.iter().rposition(|p| p == name) {
+                path.truncate(i);
+            }
+            continue;
+        }
+
+        let self_closing = tag.ends_with('/');
+        let name = local_name(tag.trim_end_matches('/')).to_string();
+        match name.as_str() {
+            "Ntry" => {
+                entry_no += 1;
+                entry = Some(Entry::default());
+            }
+            "Bal" => balance = Some(Balance::default()),
+            _ => {}
+        }
+        if self_closing {
+            continue;
+        }
+        path.push(name);
+
+        let text = decode_entities(text.trim());
+        if text.is_empty() {
+            continue;
+        }
+        let parent = |n: usize| path.len().checked_sub(n + 1).map(|i| path[i].as_str()).unwrap_or("");
+        let within = |n: &str| path.iter().any(|p| p == n);
+
+        if let Some(b) = balance.as_mut() {
+            match (parent(0), parent(1)) {
+                ("Cd", "CdOrPrtry") => b.code = text.to_ascii_uppercase(),
+                ("Amt", "Bal") => b.amount = parse_amount(&text, '.'),
+                ("CdtDbtInd", "Bal") => b.debit = text.eq_ignore_ascii_case("DBIT"),
+                ("Dt", "Dt") | ("DtTm", "Dt") => b.date = parse_date(&text),
+                _ => {}
+            }
+        } else if let Some(e) = entry.as_mut() {
+            match (parent(0), parent(1)) {
+                ("Amt", "Ntry") => match parse_amount(&text, '.') {
+                    Some(a) => e.amount = Some(a),
+                    None => out.errors.push(format!("entry {}: invalid amount '{}'", entry_no, text)),
+                },
+                ("CdtDbtInd", "Ntry") => e.debit = Some(text.eq_ignore_ascii_case("DBIT")),
+                ("Sts", "Ntry") | ("Cd", "Sts") => e.status = text.to_ascii_uppercase(),
+                ("Dt", "BookgDt") | ("DtTm", "BookgDt") => e.booked = parse_date(&text),
+                ("Dt", "ValDt") | ("DtTm", "ValDt") => e.value = parse_date(&text),
+                ("AcctSvcrRef", "Ntry") => e.reference = non_empty(&text),
+                ("NtryRef", "Ntry") => e.reference = e.reference.take().or_else(|| non_empty(&text)),
+                ("AddtlNtryInf", "Ntry") => e.info = non_empty(&text),
+                ("Nm", _) if within("RltdPties") && within("Dbtr") => e.debtor = e.debtor.take().or_else(|| non_empty(&text)),
+                ("Nm", _) if within("RltdPties") && within("Cdtr") => e.creditor = e.creditor.take().or_else(|| non_empty(&text)),
+                ("Ustrd", "RmtInf") | ("Ref", "CdtrRefInf") => e.remittance.push(text),
+                _ => {}
+            }
+        }
+    }
+
+    drop_repeated_ids(&mut out.txns);
+    Ok(out)
+}
+
+fn finish_entry(out: &mut ParsedImport, e: Entry, entry_no: usize) {
+    // Older files have no status; anything not booked would not be in the closing balance
+    if !e.status.is_empty() && e.status != "BOOK" {
+        out.errors.push(format!("entry {}: status {}, not booked yet", entry_no, e.status));
+        return;
+    }
+    let (Some(date), Some(amount), Some(debit)) = (e.booked.or(e.value), e.amount, e.debit) else {
+        out.errors.push(format!("entry {}: missing booking date, amount or credit/debit indicator", entry_no));
+        return;
+    };
+    let memo = if e.remittance.is_empty() { e.info } else { Some(e.remittance.join(" ")) };
+    out.txns.push(ImportedTxn {
+        date,
+        value_date: e.value,
+        amount: if debit && !amount.is_zero() { -amount.abs() } else { amount.abs() },
+        payee: if debit { e.creditor.or(e.debtor) } else { e.debtor.or(e.creditor) },
+        memo,
+        external_id: e.reference,
+        category: None,
+        cleared: true,  // booked by the bank
+        reconciled: false,
+        split: None,
+    });
+}
+
+// A statement file may hold several statements: the first opening and the last closing
+// balance bracket them all
+fn finish_balance(out: &mut ParsedImport, b: Balance) {
+    let (Some(amount), Some(date)) = (b.amount, b.date) else { return };
+    let amount = if b.debit { -amount } else { amount };
+    match b.code.as_str() {
+        // OPBD is dated the statement's first day, PRCD the previous statement's last day
+        "OPBD" if out.opening_balance.is_none() => {
+            out.opening_balance = Some(StatementBalance { date: date.pred_opt().unwrap_or(date), amount });
+        }
+        "PRCD" if out.opening_balance.is_none() => out.opening_balance = Some(StatementBalance { date, amount }),
+        "CLBD" => out.statement_balance = Some(StatementBalance { date, amount }),
+        _ => {}
+    }
+}
+
+fn local_name(tag: &str) -> &str {
+    let name = tag.split_whitespace().next().unwrap_or("");
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+// ISO dates, or date-times of which only the date is kept
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s.get(..10)?, "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn reads_booked_entries_and_balances() {
+        let content = include_str!("../../tests/fixtures/import/camt053-march.xml");
+        assert!(is_camt(content));
+        let parsed = parse(content).unwrap();
+
+        let rows: Vec<_> = parsed.txns.iter().map(|t| (t.date, t.amount.to_string(), t.external_id.as_deref())).collect();
+        assert_eq!(rows, [
+            (date(2025, 3, 4), "-95.50".into(), Some("BANK-0001")),
+            (date(2025, 3, 14), "1500.00".into(), Some("N2")),
+        ]);
+        // Money out names the creditor, money in the debtor
+        let t = &parsed.txns[0];
+        assert_eq!(t.value_date, Some(date(2025, 3, 3)));
+        assert_eq!((t.payee.as_deref(), t.memo.as_deref()), (Some("City Power & Light"), Some("Invoice 2025-117")));
+        let t = &parsed.txns[1];
+        assert_eq!((t.payee.as_deref(), t.memo.as_deref()), (Some("Employer GmbH"), Some("RF18539007547034")));
+        assert!(parsed.txns.iter().all(|t| t.cleared && !t.reconciled));
+
+        assert_eq!(parsed.errors, [
+            "entry 3: status PDNG, not booked yet",
+            "entry 4: invalid amount '12,00'",
+            "entry 4: missing booking date, amount or credit/debit indicator",
+        ]);
+        // OPBD is the balance at the start of the 1st, so the check is dated the day before
+        assert_eq!(parsed.opening_balance, Some(StatementBalance { date: date(2025, 2, 28), amount: "1000.00".parse().unwrap() }));
+        assert_eq!(parsed.statement_balance, Some(StatementBalance { date: date(2025, 3, 31), amount: "2404.50".parse().unwrap() }));
+    }
+
+    #[test]
+    fn refuses_a_file_without_a_statement() {
+        assert!(!is_camt("<Document><BkToCstmrDbtCdtNtfctn/></Document>"));
+        assert!(parse("<Document><BkToCstmrDbtCdtNtfctn/></Document>").is_err());
+    }
+}
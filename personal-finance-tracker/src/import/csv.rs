@@ -0,0 +1,227 @@
+//! CSV statements with a user-defined column mapping
+
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use super::{non_empty, parse_amount, ImportedTxn, ParsedImport};
+
+// Where the amount lives in a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AmountColumns {
+    Single { column: usize },               // signed, negative = money out
+    DebitCredit { debit: usize, credit: usize },
+}
+
+// Column mapping for one bank's CSV layout. Columns are 0-based.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CsvMapping {
+    pub delimiter: char,
+    pub has_header: bool,
+    pub skip_rows: usize,           // banner lines before the header
+    pub date_col: usize,
+    pub date_format: String,        // chrono format, e.g. %d/%m/%Y
+    pub amount: AmountColumns,
+    pub payee_col: Option<usize>,
+    pub memo_col: Option<usize>,
+    pub decimal_separator: char,    // '.' or ','
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            has_header: true,
+            skip_rows: 0,
+            date_col: 0,
+            date_format: "%Y-%m-%d".into(),
+            amount: AmountColumns::Single { column: 1 },
+            payee_col: Some(2),
+            memo_col: None,
+            decimal_separator: '.',
+        }
+    }
+}
+
+// Raw cells of a file, split by the mapping's delimiter
+#[derive(Debug, Clone, Default)]
+pub struct CsvSheet {
+    pub headers: Vec<String>,               // empty when the file has no header row
+    pub rows: Vec<(usize, Vec<String>)>,    // (line number, cells)
+}
+
+impl CsvSheet {
+    pub fn width(&self) -> usize {
+        self.rows.iter().map(|(_, r)| r.len()).chain(std::iter::once(self.headers.len())).max().unwrap_or(0)
+    }
+
+    // Column name for display: the header when there is one, otherwise "#3"
+    pub fn column_name(&self, col: usize) -> String {
+        match self.headers.get(col) {
+            Some(h) if !h.trim().is_empty() => h.trim().to_string(),
+            _ => format!("#{}", col + 1),
+        }
+    }
+
+    // Resolve a column given as a 1-based number or a header name
+    pub fn find_column(&self, spec: &str) -> Option<usize> {
+        if let Ok(n) = spec.trim().parse::<usize>() {
+            return n.checked_sub(1);
+        }
+        self.headers.iter().position(|h| h.trim().eq_ignore_ascii_case(spec.trim()))
+    }
+}
+
+pub fn read_sheet(content: &str, mapping: &CsvMapping) -> Result<CsvSheet> {
+    let content = content.trim_start_matches('\u{feff}');
+    let body: String = content.split_inclusive('\n').skip(mapping.skip_rows).collect();
+
+    if !mapping.delimiter.is_ascii() {
+        return Err(anyhow!("delimiter must be a single ASCII character"));
+    }
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(mapping.delimiter as u8)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+
+    let mut sheet = CsvSheet::default();
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = record.position().map_or(i + 1, |p| line_at(&body, p.byte() as usize)) + mapping.skip_rows;
+        let cells: Vec<String> = record.iter().map(|c| c.to_string()).collect();
+        if i == 0 && mapping.has_header {
+            sheet.headers = cells;
+        } else if cells.iter().any(|c| !c.trim().is_empty()) {
+            sheet.rows.push((line, cells));
+        }
+    }
+    Ok(sheet)
+}
+
+// 1-based line of a record starting at `byte`. In "\r\n" files the reader ends a record at
+// the '\r' and starts the next at the '\n', and its own line count is one short for each.
+fn line_at(body: &str, byte: usize) -> usize {
+    let start = byte + body[byte..].starts_with('\n') as usize;
+    body[..start].matches('\n').count() + 1
+}
+
+pub fn parse_rows(sheet: &CsvSheet, mapping: &CsvMapping) -> ParsedImport {
+    let mut out = ParsedImport::default();
+    for (line, cells) in &sheet.rows {
+        match parse_row(cells, mapping) {
+            Ok(txn) => out.txns.push(txn),
+            Err(e) => out.errors.push(format!("line {}: {}", line, e)),
+        }
+    }
+    out
+}
+
+pub fn parse(content: &str, mapping: &CsvMapping) -> Result<ParsedImport> {
+    Ok(parse_rows(&read_sheet(content, mapping)?, mapping))
+}
+
+fn parse_row(cells: &[String], m: &CsvMapping) -> Result<ImportedTxn> {
+    let cell = |col: usize| cells.get(col).map(|s| s.trim()).unwrap_or("");
+
+    let date = parse_date(cell(m.date_col), &m.date_format)?;
+
+    let amount = match m.amount {
+        AmountColumns::Single { column } => parse_amount(cell(column), m.decimal_separator)
+            .ok_or_else(|| anyhow!("invalid amount '{}'", cell(column)))?,
+        AmountColumns::DebitCredit { debit, credit } => {
+            // An empty cell is the other side's row; anything else has to be an amount
+            let side = |col: usize| match cell(col) {
+                "" => Ok(None),
+                c => parse_amount(c, m.decimal_separator).map(|d| Some(d.abs())).ok_or_else(|| anyhow!("invalid amount '{}'", c)),
+            };
+            match (side(debit)?.map(|d| -d), side(credit)?) {
+                (Some(d), None) => d,
+                (None, Some(c)) => c,
+                (Some(d), Some(c)) => d + c,
+                (None, None) => return Err(anyhow!("no debit or credit amount")),
+            }
+        }
+    };
+
+    Ok(ImportedTxn {
+        date,
+        value_date: None,
+        amount,
+        payee: m.payee_col.and_then(|c| non_empty(cell(c))),
+        memo: m.memo_col.and_then(|c| non_empty(cell(c))),
+        external_id: None,
+        category: None,
+        cleared: false,
+        reconciled: false,
+        split: None,
+    })
+}
+
+// Dates may carry a time part; only the date is kept
+fn parse_date(s: &str, format: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(s, format)
+        .or_else(|_| NaiveDateTime::parse_from_str(s, format).map(|dt| dt.date()))
+        .map_err(|_| anyhow!("invalid date '{}' (expected {})", s, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn amounts(parsed: &ParsedImport) -> Vec<(String, Decimal)> {
+        parsed.txns.iter().map(|t| (t.payee.clone().unwrap_or_default(), t.amount)).collect()
+    }
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn reads_debit_and_credit_columns_with_a_decimal_comma() {
+        let mapping = CsvMapping {
+            delimiter: ';',
+            skip_rows: 2,
+            date_format: "%d/%m/%Y".into(),
+            amount: AmountColumns::DebitCredit { debit: 3, credit: 4 },
+            payee_col: Some(1),
+            memo_col: Some(2),
+            decimal_separator: ',',
+            ..Default::default()
+        };
+        let sheet = read_sheet(include_str!("../../tests/fixtures/import/statement-debit-credit.csv"), &mapping).unwrap();
+        assert_eq!(sheet.find_column("paid in"), Some(4));
+        let parsed = parse_rows(&sheet, &mapping);
+
+        assert_eq!(amounts(&parsed), [
+            ("Corner Grocer".into(), dec("-1234.56")),
+            ("Employer Ltd".into(), dec("2500.00")),
+            ("Coffee Cart".into(), dec("-3.80")),
+        ]);
+        assert_eq!(parsed.txns[0].date, NaiveDate::from_ymd_opt(2025, 2, 3).unwrap());
+        assert_eq!(parsed.txns[0].memo.as_deref(), Some("card 4411"));
+        assert_eq!(parsed.txns[2].memo, None);
+        assert_eq!(parsed.errors, [
+            "line 7: invalid date '31/02/2025' (expected %d/%m/%Y)",
+            "line 8: invalid amount '12 3'",
+            "line 9: invalid amount '1e5'",
+            "line 10: no debit or credit amount",
+        ]);
+    }
+
+    #[test]
+    fn reads_signed_amounts_with_symbols_and_brackets() {
+        let mapping = CsvMapping { memo_col: Some(3), ..Default::default() };
+        let parsed = parse(include_str!("../../tests/fixtures/import/statement-signed.csv"), &mapping).unwrap();
+
+        assert_eq!(amounts(&parsed), [
+            ("Hardware Barn".into(), dec("-42.10")),
+            ("Employer Ltd".into(), dec("1250.00")),
+            ("Parking Co".into(), dec("-18.00")),
+            ("Friend".into(), dec("7.5")),
+        ]);
+        assert_eq!(parsed.errors, ["line 6: invalid amount '12.50 CR'"]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Likely-duplicate detection, for imports and for manual entry
+//! - Only rows in the same account with exactly the same amount are candidates
+//! - Candidates are scored on date distance and payee similarity
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::ImportedTxn;
+use crate::cli::state::TransactionDto;
+
+// Rows further apart than this are never duplicates
+pub const DATE_WINDOW_DAYS: i64 = 3;
+// Scores at or above this are reported
+pub const THRESHOLD: f64 = 0.6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateMatch {
+    pub existing: TransactionDto,
+    pub score: f64,     // 0..=1
+}
+
+// Half date (same day = 1, edge of the window ≈ 0), half payee similarity.
+// A missing payee on either side counts as a neutral 0.5.
+pub fn score(date: NaiveDate, amount: Decimal, payee: Option<&str>, existing: &TransactionDto) -> Option<f64> {
+    if existing.amount.0 != amount {
+        return None;
+    }
+    let days = (existing.txn_date - date).num_days().abs();
+    if days > DATE_WINDOW_DAYS {
+        return None;
+    }
+    let date_score = 1.0 - days as f64 / (DATE_WINDOW_DAYS + 1) as f64;
+    let payee_score = match (payee, existing.payee.as_deref()) {
+        (Some(a), Some(b)) => payee_similarity(a, b),
+        _ => 0.5,
+    };
+    Some(0.5 * date_score + 0.5 * payee_score)
+}
+
+// Dice coefficient over character bigrams of the letters and digits, case-insensitive.
+// "AMZN Mktp CA*2K4" vs "Amazon" scores low, "COFFEE & CO #12" vs "Coffee Co" high.
+pub fn payee_similarity(a: &str, b: &str) -> f64 {
+    let norm = |s: &str| -> Vec<char> {
+        s.chars().filter(|c| c.is_alphanumeric()).flat_map(|c| c.to_lowercase()).collect()
+    };
+    let (a, b) = (norm(a), norm(b));
+    if a == b {
+        return 1.0;
+    }
+    if a.len() < 2 || b.len() < 2 {
+        return 0.0;
+    }
+    let mut pairs: Vec<(char, char)> = a.windows(2).map(|w| (w[0], w[1])).collect();
+    let total = pairs.len() + b.len() - 1;
+    let mut shared = 0;
+    for w in b.windows(2) {
+        if let Some(i) = pairs.iter().position(|p| *p == (w[0], w[1])) {
+            pairs.swap_remove(i);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / total as f64
+}
+
+// Best match for one new row, at or above the threshold
+pub fn find(date: NaiveDate, amount: Decimal, payee: Option<&str>, existing: &[TransactionDto]) -> Vec<DuplicateMatch> {
+    let mut out: Vec<DuplicateMatch> = existing.iter()
+        .filter_map(|e| score(date, amount, payee, e).map(|score| DuplicateMatch { existing: e.clone(), score }))
+        .filter(|m| m.score >= THRESHOLD)
+        .collect();
+    out.sort_by(|a, b| b.score.total_cmp(&a.score));
+    out
+}
+
+// Match a whole statement. Each existing row can be claimed by one new row only, so a
+// statement with two identical coffees against one recorded coffee flags just one of them.
+pub fn match_batch(new: &[ImportedTxn], existing: &[TransactionDto]) -> Vec<Option<DuplicateMatch>> {
+    let mut claimed = vec![false; existing.len()];
+    new.iter().map(|t| {
+        let best = existing.iter().enumerate()
+            .filter(|(i, _)| !claimed[*i])
+            .filter_map(|(i, e)| score(t.date, t.amount, t.payee.as_deref(), e).map(|s| (i, s)))
+            .filter(|(_, s)| *s >= THRESHOLD)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        best.map(|(i, score)| {
+            claimed[i] = true;
+            DuplicateMatch { existing: existing[i].clone(), score }
+        })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::state::Money;
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+
+    fn recorded(id: i64, day: u32, amount: &str, payee: Option<&str>) -> TransactionDto {
+        TransactionDto {
+            id,
+            account_id: 1,
+            category_id: Some(1),
+            amount: Money(amount.parse().unwrap()),
+            memo: None,
+            payee: payee.map(String::from),
+            currency: "CAD".into(),
+            txn_date: date(day),
+            cleared: false,
+            reconciled: false,
+        }
+    }
+
+    fn statement_row(day: u32, amount: &str, payee: &str) -> ImportedTxn {
+        ImportedTxn {
+            date: date(day),
+            value_date: None,
+            amount: amount.parse().unwrap(),
+            payee: Some(payee.into()),
+            memo: None,
+            external_id: None,
+            category: None,
+            cleared: true,
+            reconciled: false,
+            split: None,
+        }
+    }
+
+    #[test]
+    fn payees_compare_on_letters_and_digits() {
+        assert_eq!(payee_similarity("COFFEE & CO #12", "Coffee Co"), 0.875);
+        assert!(payee_similarity("AMZN Mktp CA*2K4", "Amazon") < 0.2);
+        assert_eq!(payee_similarity("Hydro-One", "HYDRO ONE"), 1.0);
+        assert_eq!(payee_similarity("A", "B"), 0.0);
+    }
+
+    #[test]
+    fn scores_need_the_same_amount_within_the_window() {
+        let existing = recorded(1, 10, "-4.50", Some("Coffee Co"));
+        let amount: Decimal = "-4.50".parse().unwrap();
+        assert_eq!(score(date(10), amount, Some("Coffee Co"), &existing), Some(1.0));
+        // Two days off with no payee: half of the date score plus a neutral payee
+        assert_eq!(score(date(12), amount, None, &existing), Some(0.5));
+        assert_eq!(score(date(14), amount, Some("Coffee Co"), &existing), None);
+        assert_eq!(score(date(10), "-4.51".parse().unwrap(), Some("Coffee Co"), &existing), None);
+    }
+
+    #[test]
+    fn each_recorded_row_matches_one_statement_row() {
+        let existing = [recorded(1, 2, "-4.50", Some("Coffee Co")), recorded(2, 3, "-4.50", Some("Coffee Co"))];
+        let new = [
+            statement_row(3, "-4.50", "COFFEE CO"),
+            statement_row(3, "-4.50", "COFFEE CO"),
+            statement_row(3, "-4.50", "COFFEE CO"),
+            statement_row(3, "-60.00", "Grocer"),
+        ];
+        let ids: Vec<_> = match_batch(&new, &existing).iter().map(|m| m.as_ref().map(|m| m.existing.id)).collect();
+        // The closest date is claimed first
+        assert_eq!(ids, [Some(2), Some(1), None, None]);
+        assert_eq!(find(date(3), "-4.50".parse().unwrap(), Some("Coffee Co"), &existing).iter().map(|m| m.existing.id).collect::<Vec<_>>(), [2, 1]);
+    }
+}
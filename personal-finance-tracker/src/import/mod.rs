@@ -0,0 +1,239 @@
+//! Bank statement importers
+//! - Each format parses a file into `ImportedTxn`s: CSV, OFX/QFX, QIF, camt.053 and MT940
+//! - `Client::import_transactions` writes them to an account in one DB transaction
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+pub mod camt;
+pub mod csv;
+pub mod duplicates;
+pub mod mt940;
+pub mod ofx;
+pub mod qif;
+
+// One statement line, before it is assigned a category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportedTxn {
+    pub date: NaiveDate,            // booking date
+    #[serde(default)]
+    pub value_date: Option<NaiveDate>,  // when the money counts for interest (camt.053 / MT940)
+    pub amount: Decimal,            // signed, negative = money out
+    pub payee: Option<String>,
+    pub memo: Option<String>,
+    pub external_id: Option<String>,    // bank's id for the row (OFX FITID, bank reference), if any
+    pub category: Option<String>,       // category name from the file (QIF); created if missing
+    pub cleared: bool,
+    pub reconciled: bool,
+    #[serde(default)]
+    pub split: Option<usize>,           // rows from one split record (QIF) share a number
+}
+
+// Balance printed on a statement at the end of `date`, used to check the import
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatementBalance {
+    pub date: NaiveDate,
+    pub amount: Decimal,
+}
+
+impl StatementBalance {
+    // Compare with the account's balance on the statement date
+    pub fn describe(&self, ledger: Decimal) -> String {
+        if ledger == self.amount {
+            format!("balance matches statement: {} on {}", self.amount, self.date)
+        } else {
+            format!(
+                "balance mismatch on {}: statement {}, account {} (off by {})",
+                self.date, self.amount, ledger, ledger - self.amount
+            )
+        }
+    }
+}
+
+// What `Client::import_transactions` did with a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub duplicates: usize,          // rows whose external id was already imported
+    pub ruled: usize,               // imported rows a rule matched
+    pub locked: usize,              // rows dated in a locked period, left out
+}
+
+// Parsed rows plus the lines that could not be read
+#[derive(Debug, Clone, Default)]
+pub struct ParsedImport {
+    pub txns: Vec<ImportedTxn>,
+    pub errors: Vec<String>,        // "line 7: invalid date '31/02/2025'"
+    pub opening_balance: Option<StatementBalance>,  // end of the day before the first line
+    pub statement_balance: Option<StatementBalance>,
+}
+
+impl ParsedImport {
+    // Whether opening balance + lines = closing balance, for files that carry both
+    pub fn lines_check(&self) -> Option<String> {
+        let (open, close) = (self.opening_balance?, self.statement_balance?);
+        let total = open.amount + self.txns.iter().map(|t| t.amount).sum::<Decimal>();
+        Some(if total == close.amount {
+            format!("statement lines add up: {} + lines = {}", open.amount, close.amount)
+        } else {
+            format!("statement lines don't add up: {} + lines = {}, closing balance says {}", open.amount, total, close.amount)
+        })
+    }
+}
+
+// Parse an amount the way banks print them: a currency symbol or ISO code on either side,
+// thousands separators, "(12.50)", "-12.50" and "12.50-". `decimal_sep` is '.' or ','.
+// Anything else is refused rather than skipped, so "12 3" or "1e5" is not read as 123 or 15.
+pub fn parse_amount(raw: &str, decimal_sep: char) -> Option<Decimal> {
+    let mut s = raw.trim();
+    let mut negative = false;
+    if let Some(inner) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        negative = true;
+        s = inner.trim();
+    }
+
+    // One sign and one currency, in front in either order ("-$12", "$-12") or behind
+    let (mut signed, mut currency) = (false, false);
+    loop {
+        if !signed && let Some(rest) = s.strip_prefix(['-', '+']) {
+            negative ^= s.starts_with('-');
+            (signed, s) = (true, rest.trim_start());
+        } else if !currency && let Some(rest) = strip_currency(s, true) {
+            (currency, s) = (true, rest.trim_start());
+        } else if !signed && let Some(rest) = s.strip_suffix('-') {
+            negative = !negative;
+            (signed, s) = (true, rest.trim_end());
+        } else if !currency && let Some(rest) = strip_currency(s, false) {
+            (currency, s) = (true, rest.trim_end());
+        } else {
+            break;
+        }
+    }
+
+    let (int, frac) = s.split_once(decimal_sep).unwrap_or((s, ""));
+    if !frac.chars().all(|c| c.is_ascii_digit()) || (int.is_empty() && frac.is_empty()) {
+        return None;
+    }
+    let int = ungroup(int, decimal_sep)?;
+    let int = if int.is_empty() { "0".into() } else { int };
+    let value = Decimal::from_str_exact(&if frac.is_empty() { int } else { format!("{}.{}", int, frac) }).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+// The integer part without its thousands separators. Groups after the first have three
+// digits, or two before the last ("1,23,456"); a space counts only between such groups.
+fn ungroup(int: &str, decimal_sep: char) -> Option<String> {
+    const SEPARATORS: [char; 7] = [',', '.', ' ', '\u{a0}', '\u{202f}', '\'', '\u{2019}'];
+    let groups: Vec<&str> = int.split(|c| c != decimal_sep && SEPARATORS.contains(&c)).collect();
+    let last = groups.len() - 1;
+    let valid = groups.iter().enumerate().all(|(i, g)| {
+        g.chars().all(|c| c.is_ascii_digit()) && match i {
+            0 => !g.is_empty() && (last == 0 || g.len() <= 3),
+            i if i == last => g.len() == 3,
+            _ => g.len() == 2 || g.len() == 3,
+        }
+    });
+    (valid || int.is_empty()).then(|| groups.concat())
+}
+
+// Take a currency off the front or back of `s`: a symbol, an ISO code ("USD"), a dollar
+// with its country ("US$", "C$") or one of a few local abbreviations
+fn strip_currency(s: &str, front: bool) -> Option<&str> {
+    const SYMBOLS: &str = "$€£¥₹₩₽₺₪₫₱฿¢₴₦";
+    const WORDS: [&str; 4] = ["kr", "Kr", "zł", "Fr."];
+    let boundary = |c: char| c.is_ascii_digit() || c.is_whitespace() || c == '-' || c == '+';
+    let (token, rest) = if front {
+        let end = s.find(boundary).unwrap_or(s.len());
+        (&s[..end], &s[end..])
+    } else {
+        let start = s.rfind(boundary).map_or(0, |i| i + s[i..].chars().next().map_or(1, char::len_utf8));
+        (&s[start..], &s[..start])
+    };
+    let letters = token.strip_suffix('$').unwrap_or(token);
+    let known = (token.chars().count() == 1 && SYMBOLS.contains(token))
+        || (token.len() == 3 && token.chars().all(|c| c.is_ascii_uppercase()))
+        || (letters.len() < token.len() && (1..=2).contains(&letters.len()) && letters.chars().all(|c| c.is_ascii_uppercase()))
+        || WORDS.contains(&token);
+    known.then_some(rest)
+}
+
+// Bank references are not always unique (e.g. one per batch); a repeated one would make
+// the import skip real rows as already imported, so those rows go without
+fn drop_repeated_ids(txns: &mut [ImportedTxn]) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for id in txns.iter().filter_map(|t| t.external_id.clone()) {
+        *counts.entry(id).or_default() += 1;
+    }
+    for t in txns.iter_mut() {
+        if t.external_id.as_ref().is_some_and(|id| counts[id] > 1) {
+            t.external_id = None;
+        }
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    if s.is_empty() { None } else { Some(s.to_string()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(raw: &str, sep: char) -> Option<String> {
+        parse_amount(raw, sep).map(|d| d.to_string())
+    }
+
+    #[test]
+    fn reads_amounts_as_banks_print_them() {
+        for (raw, sep, want) in [
+            ("12.50", '.', "12.50"),
+            ("-12.50", '.', "-12.50"),
+            ("+7", '.', "7"),
+            ("(12.50)", '.', "-12.50"),
+            ("12.50-", '.', "-12.50"),
+            ("$1,234.56", '.', "1234.56"),
+            ("-$1,234.56", '.', "-1234.56"),
+            ("$-5", '.', "-5"),
+            ("USD 1,234,567.89", '.', "1234567.89"),
+            ("US$ 40", '.', "40"),
+            ("1.234,56 €", ',', "1234.56"),
+            ("1 234,56", ',', "1234.56"),
+            ("1\u{a0}234,56", ',', "1234.56"),
+            ("1'234.50 CHF", '.', "1234.50"),
+            ("1,23,456.00", '.', "123456.00"),
+            ("99,95 kr", ',', "99.95"),
+            ("1000,", ',', "1000"),
+            (".5", '.', "0.5"),
+            ("(£3.20)", '.', "-3.20"),
+        ] {
+            assert_eq!(amount(raw, sep).as_deref(), Some(want), "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn refuses_what_it_cannot_read_exactly() {
+        for (raw, sep) in [
+            ("12 3", '.'),         // not a thousands group
+            ("1e5", '.'),
+            ("1,5", '.'),          // a decimal comma in a '.' file
+            ("1.234.567", '.'),
+            ("12.50 CR", '.'),
+            ("12.5.0", ','),
+            ("--5", '.'),
+            ("-5-", '.'),
+            ("$$5", '.'),
+            ("abc", '.'),
+            ("", '.'),
+            ("-", '.'),
+            ("$", '.'),
+            (".", '.'),
+            ("12,34,5", '.'),
+        ] {
+            assert_eq!(amount(raw, sep), None, "{:?}", raw);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! SWIFT MT940 customer statements
+//! - `:61:` lines are the transactions: value date, optional booking date (MMDD),
+//!   C/D/RC/RD mark, amount with a decimal comma, type code and references
+//! - The following `:86:` holds the details. German banks structure it with `?NN` subfields
+//!   (`?20`-`?29` remittance, `?32`/`?33` counterparty), others with `/NAME/` and `/REMI/`;
+//!   anything else goes to the memo as it is.
+//! - `:60F:` / `:60M:` and `:62F:` / `:62M:` are the opening and closing balances
+//!
+//! SWIFT block headers (`{1:...}{4:`) are skipped, so raw bank messages read as well.
+
+use anyhow::{bail, Result};
+use chrono::{Datelike, NaiveDate};
+
+use super::{drop_repeated_ids, non_empty, parse_amount, ImportedTxn, ParsedImport, StatementBalance};
+
+// True when the file looks like MT940, whatever its extension
+pub fn is_mt940(content: &str) -> bool {
+    content.contains(":20:") && (content.contains(":60F:") || content.contains(":60M:"))
+}
+
+pub fn parse(content: &str) -> Result<ParsedImport> {
+    if !is_mt940(content) {
+        bail!("not an MT940 statement (no :20: and :60F: fields)");
+    }
+
+    let mut out = ParsedImport::default();
+    let fields = fields(content);
+    let mut i = 0;
+    while i < fields.len() {
+        let (line, tag, value) = (fields[i].0, fields[i].1.as_str(), fields[i].2.as_str());
+        match tag {
+            "60F" | "60M" if out.opening_balance.is_none() => match parse_balance(value) {
+                Some(b) => out.opening_balance = Some(b),
+                None => out.errors.push(format!("line {}: invalid opening balance '{}'", line, value)),
+            },
+            "62F" | "62M" => match parse_balance(value) {
+                Some(b) => out.statement_balance = Some(b),
+                None => out.errors.push(format!("line {}: invalid closing balance '{}'", line, value)),
+            },
+            "61" => {
+                let details = fields.get(i + 1).filter(|f| f.1 == "86").map(|f| f.2.as_str());
+                match parse_line(value, details) {
+                    Some(t) => out.txns.push(t),
+                    None => out.errors.push(format!("line {}: invalid statement line '{}'", line, value.lines().next().unwrap_or(""))),
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    drop_repeated_ids(&mut out.txns);
+    Ok(out)
+}
+
+// (line number, tag, value) of every field; continuation lines stay in the value
+fn fields(content: &str) -> Vec<(usize, String, String)> {
+    let mut out: Vec<(usize, String, String)> = Vec::new();
+    for (n, raw) in content.lines().enumerate() {
+        let mut line = raw.trim_end();
+        if let Some(i) = line.find("{4:") {
+            line = &line[i + 3..];
+        }
+        if line.is_empty() || line == "-" || line.starts_with("-}") || line.starts_with('{') {
+            continue;
+        }
+        let tag = line.strip_prefix(':').and_then(|rest| rest.split_once(':'))
+            .filter(|(t, _)| (2..=3).contains(&t.len()) && t.chars().take(2).all(|c| c.is_ascii_digit()));
+        match tag {
+            Some((t, v)) => out.push((n + 1, t.to_string(), v.to_string())),
+            None => {
+                if let Some(last) = out.last_mut() {
+                    last.2.push('\n');
+                    last.2.push_str(line);
+                }
+            }
+        }
+    }
+    out
+}
+
+// C|D, YYMMDD, currency, amount: "C250301EUR1234,56"; the balance at the end of that day
+fn parse_balance(v: &str) -> Option<StatementBalance> {
+    let v = v.trim();
+    let debit = match v.get(..1)? {
+        "C" => false,
+        "D" => true,
+        _ => return None,
+    };
+    let date = NaiveDate::parse_from_str(v.get(1..7)?, "%y%m%d").ok()?;
+    let amount = parse_amount(v.get(10..)?, ',')?;
+    Some(StatementBalance { date, amount: if debit { -amount } else { amount } })
+}
+
+// "2503020302DR12,50NTRFNONREF//BANKREF\nsupplementary details"
+fn parse_line(v: &str, details: Option<&str>) -> Option<ImportedTxn> {
+    let (first, supplementary) = v.split_once('\n').unwrap_or((v, ""));
+    let value_date = NaiveDate::parse_from_str(first.get(..6)?, "%y%m%d").ok()?;
+    let mut rest = &first[6..];
+
+    // Booking date MMDD, in the value date's year unless the two straddle New Year
+    let mut booked = value_date;
+    if rest.get(..4).is_some_and(|d| d.chars().all(|c| c.is_ascii_digit())) {
+        let (month, day) = (rest[..2].parse::<u32>().ok()?, rest[2..4].parse::<u32>().ok()?);
+        let year = match (value_date.month(), month) {
+            (12, 1) => value_date.year() + 1,
+            (1, 12) => value_date.year() - 1,
+            _ => value_date.year(),
+        };
+        booked = NaiveDate::from_ymd_opt(year, month, day)?;
+        rest = &rest[4..];
+    }
+
+    // RC (reversed credit) takes money out, RD (reversed debit) brings it back
+    let (debit, rest) = if let Some(r) = rest.strip_prefix("RC") {
+        (true, r)
+    } else if let Some(r) = rest.strip_prefix("RD") {
+        (false, r)
+    } else if let Some(r) = rest.strip_prefix('C') {
+        (false, r)
+    } else {
+        (true, rest.strip_prefix('D')?)
+    };
+    // Optional funds code: the third letter of the currency code
+    let rest = rest.strip_prefix(|c: char| c.is_ascii_alphabetic()).unwrap_or(rest);
+
+    let end = rest.find(|c: char| !(c.is_ascii_digit() || c == ',')).unwrap_or(rest.len());
+    let amount = parse_amount(&rest[..end], ',')?;
+    // Type code (N/F/S + 3 characters), then customer reference [//bank reference]
+    let refs = rest[end..].get(4..).unwrap_or("");
+    let (customer, bank) = refs.split_once("//").unwrap_or((refs, ""));
+    let reference = [bank, customer].into_iter()
+        .map(|r| r.trim())
+        .find(|r| !r.is_empty() && !r.eq_ignore_ascii_case("NONREF"))
+        .map(|r| r.to_string());
+
+    let (payee, memo) = match details {
+        Some(d) => parse_details(d),
+        None => (None, non_empty(supplementary)),
+    };
+    Some(ImportedTxn {
+        date: booked,
+        value_date: Some(value_date),
+        amount: if debit && !amount.is_zero() { -amount } else { amount },
+        payee,
+        memo,
+        external_id: reference,
+        category: None,
+        cleared: true,  // booked by the bank
+        reconciled: false,
+        split: None,
+    })
+}
+
+// (counterparty, remittance info) from a :86: field
+fn parse_details(d: &str) -> (Option<String>, Option<String>) {
+    // Structured fields wrap at a fixed width, so their lines join without a space
+    let text: String = d.lines().map(|l| l.trim_end()).collect();
+
+    // "166?00SEPA-UEBERWEISUNG?20EREF+...?21SVWZ+Invoice 123?32ACME GMBH"
+    if text.get(..3).is_some_and(|c| c.chars().all(|c| c.is_ascii_digit())) && text[3..].starts_with('?') {
+        let mut payee = String::new();
+        let mut remittance = String::new();
+        for sub in text[4..].split('?') {
+            let (Some(code), Some(value)) = (sub.get(..2), sub.get(2..)) else { continue };
+            match code {
+                "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" | "60" | "61" | "62" | "63" => remittance.push_str(value),
+                "32" | "33" => payee.push_str(value),
+                _ => {}
+            }
+        }
+        // SEPA keeps the purpose text after SVWZ+, next to EREF+ / KREF+ / MREF+ references
+        let memo = match remittance.find("SVWZ+") {
+            Some(i) => remittance[i + 5..].to_string(),
+            None => remittance,
+        };
+        return (non_empty(&payee), non_empty(&memo));
+    }
+
+    // "/NAME/ACME GMBH/REMI/Invoice 123/EREF/..."
+    if text.contains("/NAME/") || text.contains("/REMI/") {
+        let parts: Vec<&str> = text.split('/').collect();
+        let value = |key: &str| {
+            let i = parts.iter().position(|p| *p == key)?;
+            let v: Vec<&str> = parts[i + 1..].iter().take_while(|p| !is_code(p)).copied().collect();
+            non_empty(&v.join("/"))
+        };
+        return (value("NAME"), value("REMI").or_else(|| non_empty(&text)));
+    }
+
+    (None, non_empty(&d.lines().map(|l| l.trim()).collect::<Vec<_>>().join(" ")))
+}
+
+// The upper-case keys of the slash-structured :86: format
+fn is_code(s: &str) -> bool {
+    matches!(s, "NAME" | "REMI" | "EREF" | "IBAN" | "BIC" | "ORDP" | "BENM" | "ADDR" | "PURP" | "MREF" | "CRED" | "ACCW" | "TRCD" | "CSID")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn reads_statement_lines_and_balances() {
+        let content = include_str!("../../tests/fixtures/import/mt940-december.sta");
+        assert!(is_mt940(content));
+        let parsed = parse(content).unwrap();
+
+        let rows: Vec<_> = parsed.txns.iter().map(|t| (t.date, t.amount.to_string(), t.external_id.as_deref())).collect();
+        assert_eq!(rows, [
+            (date(2024, 12, 2), "-45.00".into(), Some("BANK1")),
+            (date(2024, 12, 15), "1200.00".into(), Some("REF123")),
+            (date(2024, 12, 18), "45.00".into(), None),
+            (date(2025, 1, 2), "-5.50".into(), Some("FEE9")),
+        ]);
+        let details: Vec<_> = parsed.txns.iter().map(|t| (t.payee.as_deref(), t.memo.as_deref())).collect();
+        assert_eq!(details, [
+            (Some("STADTWERKE"), Some("Strom Dezember")),
+            (Some("ACME LTD"), Some("Salary December")),
+            (None, Some("Returned debit")),
+            (None, Some("Account fee")),
+        ]);
+        // Booked on 2 January for a 31 December value date
+        assert_eq!(parsed.txns[3].value_date, Some(date(2024, 12, 31)));
+
+        assert_eq!(parsed.errors, ["line 13: invalid statement line '24XX20D1,00NMSC'"]);
+        assert_eq!(parsed.opening_balance, Some(StatementBalance { date: date(2024, 12, 1), amount: "500.00".parse().unwrap() }));
+        assert_eq!(parsed.statement_balance, Some(StatementBalance { date: date(2024, 12, 31), amount: "1694.50".parse().unwrap() }));
+    }
+
+    #[test]
+    fn reads_balances_with_a_debit_mark() {
+        assert_eq!(parse_balance("D250301EUR12,5"), Some(StatementBalance { date: date(2025, 3, 1), amount: "-12.5".parse().unwrap() }));
+        assert_eq!(parse_balance("X250301EUR12,5"), None);
+    }
+
+    #[test]
+    fn refuses_a_file_without_statement_fields() {
+        assert!(!is_mt940(":20:REF\n:61:250301D1,00NMSC"));
+        assert!(parse(":20:REF\n:61:250301D1,00NMSC").is_err());
+    }
+}
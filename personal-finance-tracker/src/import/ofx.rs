@@ -0,0 +1,182 @@
+//! OFX / QFX statements
+//! - 1.x is SGML: leaf tags are not closed (`<TRNAMT>-12.50`)
+//! - 2.x is XML: every tag is closed
+//!
+//! Both are read with the same tag scanner; aggregates are closed in either version.
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use super::{non_empty, parse_amount, ImportedTxn, ParsedImport, StatementBalance};
+
+// True when the file looks like OFX, whatever its extension
+pub fn is_ofx(content: &str) -> bool {
+    content.to_ascii_uppercase().contains("<OFX>")
+}
+
+#[derive(Default)]
+struct TxnBuilder {
+    date: Option<NaiveDate>,
+    amount: Option<Decimal>,
+    fitid: Option<String>,
+    name: Option<String>,
+    memo: Option<String>,
+}
+
+pub fn parse(content: &str) -> Result<ParsedImport> {
+    let upper = content.to_ascii_uppercase();
+    let start = upper.find("<OFX>").ok_or_else(|| anyhow!("not an OFX file (no <OFX> element)"))?;
+    let body = &content[start..];
+
+    let mut out = ParsedImport::default();
+    let mut txn: Option<TxnBuilder> = None;
+    let mut txn_no = 0;
+    let mut in_ledger = false;
+    let mut ledger_amount = None;
+    let mut ledger_date = None;
+
+    for piece in body.split('<').skip(1) {
+        let Some((tag, text)) = piece.split_once('>') else { continue };
+        let tag = tag.trim().to_ascii_uppercase();
+        let text = decode_entities(text.trim());
+
+        if let Some(closed) = tag.strip_prefix('/') {
+            match closed {
+                "STMTTRN" => {
+                    if let Some(b) = txn.take() {
+                        match (b.date, b.amount) {
+                            (Some(date), Some(amount)) => out.txns.push(ImportedTxn {
+                                date,
+                                value_date: None,
+                                amount,
+                                payee: b.name,
+                                memo: b.memo,
+                                external_id: b.fitid,
+                                category: None,
+                                cleared: true,  // the bank has already posted it
+                                reconciled: false,
+                                split: None,
+                            }),
+                            _ => out.errors.push(format!("transaction {}: missing DTPOSTED or TRNAMT", txn_no)),
+                        }
+                    }
+                }
+                "LEDGERBAL" => in_ledger = false,
+                _ => {}
+            }
+            continue;
+        }
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+
+        match tag.as_str() {
+            "STMTTRN" => {
+                txn_no += 1;
+                txn = Some(TxnBuilder::default());
+            }
+            "LEDGERBAL" => in_ledger = true,
+            _ => {}
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        if let Some(b) = txn.as_mut() {
+            match tag.as_str() {
+                "DTPOSTED" => match parse_ofx_date(&text) {
+                    Some(d) => b.date = Some(d),
+                    None => out.errors.push(format!("transaction {}: invalid DTPOSTED '{}'", txn_no, text)),
+                },
+                "TRNAMT" => match parse_ofx_amount(&text) {
+                    Some(a) => b.amount = Some(a),
+                    None => out.errors.push(format!("transaction {}: invalid TRNAMT '{}'", txn_no, text)),
+                },
+                "FITID" => b.fitid = non_empty(&text),
+                "NAME" => b.name = non_empty(&text),
+                "MEMO" => b.memo = non_empty(&text),
+                _ => {}
+            }
+        } else if in_ledger {
+            match tag.as_str() {
+                "BALAMT" => ledger_amount = parse_ofx_amount(&text),
+                "DTASOF" => ledger_date = parse_ofx_date(&text),
+                _ => {}
+            }
+        }
+    }
+
+    if let (Some(amount), Some(date)) = (ledger_amount, ledger_date) {
+        out.statement_balance = Some(StatementBalance { date, amount });
+    }
+    Ok(out)
+}
+
+// YYYYMMDD[HHMMSS[.XXX]][[-5:EST]]; only the date part matters here
+fn parse_ofx_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s.get(..8)?, "%Y%m%d").ok()
+}
+
+// The spec allows either '.' or ',' as the decimal mark
+fn parse_ofx_amount(s: &str) -> Option<Decimal> {
+    let sep = if s.contains(',') && !s.contains('.') { ',' } else { '.' };
+    parse_amount(s, sep)
+}
+
+pub(super) fn decode_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn reads_an_sgml_statement() {
+        let content = include_str!("../../tests/fixtures/import/march-sgml.qfx");
+        assert!(is_ofx(content));
+        let parsed = parse(content).unwrap();
+
+        let rows: Vec<_> = parsed.txns.iter().map(|t| (t.date, t.amount.to_string(), t.external_id.as_deref(), t.payee.as_deref())).collect();
+        assert_eq!(rows, [
+            (date(2025, 3, 3), "-42.10".into(), Some("202503030001"), Some("HARDWARE BARN")),
+            (date(2025, 3, 14), "2500.00".into(), Some("202503140002"), Some("EMPLOYER LTD")),
+            (date(2025, 3, 20), "-3.80".into(), Some("202503200003"), Some("COFFEE CART")),
+        ]);
+        assert_eq!(parsed.txns[0].memo.as_deref(), Some("Paint & brushes"));
+        assert!(parsed.txns.iter().all(|t| t.cleared && !t.reconciled));
+        assert_eq!(parsed.errors, [
+            "transaction 4: invalid TRNAMT '12 3'",
+            "transaction 4: missing DTPOSTED or TRNAMT",
+        ]);
+        assert_eq!(parsed.statement_balance, Some(StatementBalance { date: date(2025, 3, 31), amount: "3634.60".parse().unwrap() }));
+    }
+
+    #[test]
+    fn reads_an_xml_statement() {
+        let parsed = parse(include_str!("../../tests/fixtures/import/march-xml.ofx")).unwrap();
+
+        assert_eq!(parsed.txns.len(), 1);
+        let t = &parsed.txns[0];
+        assert_eq!((t.date, t.amount.to_string()), (date(2025, 3, 5), "-18.00".into()));
+        assert_eq!((t.payee.as_deref(), t.memo.as_deref()), (Some("Parking <Centre>"), None));
+        assert_eq!(parsed.errors, ["transaction 2: missing DTPOSTED or TRNAMT"]);
+        assert_eq!(parsed.statement_balance.map(|b| b.date), Some(date(2025, 3, 31)));
+    }
+
+    #[test]
+    fn refuses_a_file_without_an_ofx_element() {
+        assert!(!is_ofx("date,amount\n2025-03-01,5"));
+        assert!(parse("date,amount\n2025-03-01,5").is_err());
+    }
+}
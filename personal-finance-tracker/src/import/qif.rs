@@ -0,0 +1,237 @@
+//! QIF (Quicken Interchange Format)
+//! - `!Type:Bank`, `!Type:Cash` and `!Type:CCard` sections are read; other sections are skipped
+//! - Split lines (`S` / `E` / `$`) become one transaction each, since rows have a single
+//!   category; the rows of one record share a split number, so they stay one record on export
+//! - Dates are month-first unless the file contains a day above 12 in the first position
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use super::{non_empty, parse_amount, ImportedTxn, ParsedImport};
+
+// Account types this importer understands (the part after `!Type:`)
+pub const SUPPORTED_TYPES: [&str; 3] = ["Bank", "Cash", "CCard"];
+
+pub fn is_qif(content: &str) -> bool {
+    content.trim_start_matches('\u{feff}').trim_start().to_ascii_lowercase().starts_with("!type:")
+        || content.to_ascii_lowercase().contains("\n!type:")
+}
+
+#[derive(Default)]
+struct Split {
+    category: Option<String>,
+    memo: Option<String>,
+    amount: Option<String>,
+}
+
+#[derive(Default)]
+struct Record {
+    line: usize,
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+    category: Option<String>,
+    status: Option<char>,
+    splits: Vec<Split>,
+}
+
+pub fn parse(content: &str) -> Result<ParsedImport> {
+    let mut out = ParsedImport::default();
+    let mut records = Vec::new();
+    let mut in_txns = false;
+    let mut cur = Record::default();
+
+    for (i, raw) in content.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line = raw.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('!') {
+            let header = header.trim();
+            if let Some(t) = header.get(..5).filter(|h| h.eq_ignore_ascii_case("type:")).map(|_| header[5..].trim()) {
+                in_txns = SUPPORTED_TYPES.iter().any(|s| s.eq_ignore_ascii_case(t));
+                if !in_txns && !["cat", "class", "memorized"].contains(&t.to_ascii_lowercase().as_str()) {
+                    out.errors.push(format!("line {}: !Type:{} is not supported, section skipped", i + 1, t));
+                }
+            } else if header.eq_ignore_ascii_case("account") {
+                in_txns = false;
+            }
+            // !Option / !Clear lines only toggle Quicken behaviour
+            continue;
+        }
+        if !in_txns {
+            continue;
+        }
+
+        let mut chars = line.chars();
+        let code = chars.next().unwrap_or('^');
+        let value = chars.as_str().trim().to_string();
+        if cur.line == 0 {
+            cur.line = i + 1;
+        }
+        match code {
+            '^' => records.push(std::mem::take(&mut cur)),
+            'D' => cur.date = Some(value),
+            'T' | 'U' => cur.amount = Some(value),
+            'P' => cur.payee = non_empty(&value),
+            'M' => cur.memo = non_empty(&value),
+            'L' => cur.category = non_empty(&value),
+            'C' => cur.status = value.chars().next(),
+            'S' => cur.splits.push(Split { category: non_empty(&value), ..Default::default() }),
+            'E' => if let Some(s) = cur.splits.last_mut() { s.memo = non_empty(&value) },
+            '$' => if let Some(s) = cur.splits.last_mut() { s.amount = Some(value) },
+            _ => {} // N (check number), A (address), ... are not stored
+        }
+    }
+
+    let day_first = records.iter()
+        .filter_map(|r| r.date.as_deref())
+        .any(|d| date_parts(d).is_some_and(|(first, _, _, _)| first.len() < 4 && first.parse::<u32>().unwrap_or(0) > 12));
+
+    for (n, r) in records.into_iter().enumerate() {
+        let Some(date) = r.date.as_deref().and_then(|d| parse_qif_date(d, day_first)) else {
+            out.errors.push(format!("line {}: invalid or missing date '{}'", r.line, r.date.unwrap_or_default()));
+            continue;
+        };
+        let Some(total) = r.amount.as_deref().and_then(|a| parse_amount(a, '.')) else {
+            out.errors.push(format!("line {}: invalid or missing amount '{}'", r.line, r.amount.unwrap_or_default()));
+            continue;
+        };
+        let (cleared, reconciled) = match r.status {
+            Some('*') | Some('c') => (true, false),
+            Some('X') | Some('x') | Some('R') => (true, true),
+            _ => (false, false),
+        };
+        let split = (!r.splits.is_empty()).then_some(n);
+        let txn = |amount: Decimal, category: Option<&str>, memo: Option<String>| ImportedTxn {
+            date,
+            value_date: None,
+            amount,
+            payee: r.payee.clone(),
+            memo,
+            external_id: None,
+            category: category.map(category_name),
+            cleared,
+            reconciled,
+            split,
+        };
+
+        if r.splits.is_empty() {
+            out.txns.push(txn(total, r.category.as_deref(), r.memo.clone()));
+            continue;
+        }
+
+        let mut remaining = total;
+        for s in &r.splits {
+            let Some(amount) = s.amount.as_deref().and_then(|a| parse_amount(a, '.')) else {
+                out.errors.push(format!("line {}: split '{}' has no valid amount", r.line, s.category.as_deref().unwrap_or("")));
+                continue;
+            };
+            remaining -= amount;
+            out.txns.push(txn(amount, s.category.as_deref(), s.memo.clone().or_else(|| r.memo.clone())));
+        }
+        // Keep the account total right when the splits don't add up
+        if !remaining.is_zero() {
+            out.txns.push(txn(remaining, r.category.as_deref(), r.memo.clone()));
+        }
+    }
+    Ok(out)
+}
+
+// "[Savings]" is a transfer to another account; "Food/Vacation" carries a class after '/'
+fn category_name(l: &str) -> String {
+    if l.starts_with('[') && l.ends_with(']') {
+        return "Transfer".into();
+    }
+    l.split('/').next().unwrap_or(l).trim().to_string()
+}
+
+// Splits "3/15'25", "03/15/2025", "15.03.2025" or "2025-03-15" into (first, second, third, y2k)
+fn date_parts(s: &str) -> Option<(&str, &str, &str, bool)> {
+    let y2k = s.contains('\'');
+    let mut parts = s.trim().split(['/', '-', '.', '\'']).map(|p| p.trim());
+    let (a, b, c) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((a, b, c, y2k))
+}
+
+fn parse_qif_date(s: &str, day_first: bool) -> Option<NaiveDate> {
+    let (a, b, c, y2k) = date_parts(s)?;
+    let num = |p: &str| p.parse::<i32>().ok();
+    let (year_s, month, day) = if a.len() == 4 {
+        (a, num(b)?, num(c)?)
+    } else if day_first {
+        (c, num(b)?, num(a)?)
+    } else {
+        (c, num(a)?, num(b)?)
+    };
+    let mut year = num(year_s)?;
+    if year_s.len() <= 2 {
+        year += if y2k || year < 70 { 2000 } else { 1900 };
+    }
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn reads_records_splits_and_status() {
+        let content = include_str!("../../tests/fixtures/import/quicken.qif");
+        assert!(is_qif(content));
+        let parsed = parse(content).unwrap();
+
+        let rows: Vec<_> = parsed.txns.iter()
+            .map(|t| (t.date, t.amount.to_string(), t.category.as_deref(), t.memo.as_deref(), t.split))
+            .collect();
+        assert_eq!(rows, [
+            (date(2025, 3, 1), "-42.10".into(), Some("Home:Repairs"), Some("Paint"), None),
+            (date(2025, 3, 14), "2500.00".into(), Some("Salary"), None, None),
+            (date(2025, 3, 20), "-80.00".into(), Some("Groceries"), Some("Weekly shop"), Some(2)),
+            (date(2025, 3, 20), "-25.00".into(), Some("Household"), Some("Bulbs"), Some(2)),
+            (date(2025, 3, 20), "-15.00".into(), Some("Transfer"), Some("Weekly shop"), Some(2)),
+            // The splits leave 10.00 of the total unassigned; it keeps the record's category
+            (date(2025, 3, 22), "-20.00".into(), Some("Groceries"), None, Some(3)),
+            (date(2025, 3, 22), "-10.00".into(), None, None, Some(3)),
+        ]);
+        assert_eq!(parsed.txns.iter().map(|t| (t.cleared, t.reconciled)).take(3).collect::<Vec<_>>(), [(true, false), (true, true), (false, false)]);
+        assert!(parsed.txns.iter().all(|t| t.payee.is_some()));
+        assert_eq!(parsed.errors, [
+            "line 44: !Type:Invst is not supported, section skipped",
+            "line 37: invalid or missing date '02/30/2025'",
+            "line 41: invalid or missing amount ''",
+        ]);
+    }
+
+    #[test]
+    fn a_day_above_twelve_makes_the_file_day_first() {
+        let parsed = parse(include_str!("../../tests/fixtures/import/quicken-day-first.qif")).unwrap();
+        let dates: Vec<_> = parsed.txns.iter().map(|t| t.date).collect();
+        assert_eq!(dates, [date(2025, 3, 5), date(2025, 3, 25), date(2025, 3, 28)]);
+        assert!(parsed.errors.is_empty());
+    }
+
+    #[test]
+    fn reads_the_date_styles_quicken_writes() {
+        assert_eq!(parse_qif_date("3/14'25", false), Some(date(2025, 3, 14)));
+        assert_eq!(parse_qif_date("03/14/99", false), Some(date(1999, 3, 14)));
+        assert_eq!(parse_qif_date("03/14/05", false), Some(date(2005, 3, 14)));
+        assert_eq!(parse_qif_date("14.03.2025", true), Some(date(2025, 3, 14)));
+        assert_eq!(parse_qif_date("2025-03-14", true), Some(date(2025, 3, 14)));
+        assert_eq!(parse_qif_date("03/04/2025", false), Some(date(2025, 3, 4)));
+        assert_eq!(parse_qif_date("03/04/2025", true), Some(date(2025, 4, 3)));
+        assert_eq!(parse_qif_date("14/03/2025", false), None);
+        assert_eq!(parse_qif_date("03/14", false), None);
+        assert_eq!(parse_qif_date("03/14/2025/1", false), None);
+    }
+}
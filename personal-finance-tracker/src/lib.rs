@@ -1,3 +1,11 @@
 pub mod database;
 pub mod cli; 
-pub mod backend;
\ No newline at end of file
+pub mod backend;
+pub mod import;
+pub mod export;
+pub mod backup;
+pub mod rules;
+pub mod check;
+pub mod audit;
+pub mod lock;
+pub mod search;
@@ -0,0 +1,117 @@
+//! Lock dates and year-end closes
+//! - A lock date freezes the transactions dated on or before it: adding, editing,
+//!   clearing, deleting, rule changes and ledger repairs are refused unless the caller
+//!   overrides the lock (see `Client::with_lock_override`; the server's sync takes
+//!   `override_lock`)
+//! - A lock without an account covers every account; an account's own lock can only
+//!   reach further, the later of the two applies
+//! - Closing a year stores each account's balance at December 31 and locks the year
+//!
+//! Finishing a reconciliation stays allowed: it only marks rows already cleared.
+
+use std::fmt;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::error::{DatabaseError, ErrorKind};
+use sqlx::SqliteConnection;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeriodLock {
+    pub account_id: Option<i64>,    // None: every account
+    pub date: NaiveDate,            // the last locked day
+}
+
+// The balances of a closed year, as they were when it was closed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearEndClose {
+    pub account_id: i64,
+    pub year: i32,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub balance: Decimal,           // at the end of December 31
+    pub closed_at: String,          // RFC 3339, UTC
+}
+
+// The last locked day of an account, if any
+pub fn lock_date_for(locks: &[PeriodLock], account_id: i64) -> Option<NaiveDate> {
+    locks.iter()
+        .filter(|l| l.account_id.is_none_or(|a| a == account_id))
+        .map(|l| l.date)
+        .max()
+}
+
+// A change refused because of a lock. A database error, so the sqlx-based queries can
+// return it as well as the client.
+#[derive(Debug)]
+pub struct PeriodLocked {
+    pub date: NaiveDate,            // of the transaction
+    pub lock_date: NaiveDate,
+    message: String,
+}
+
+impl PeriodLocked {
+    pub fn new(date: NaiveDate, lock_date: NaiveDate) -> Self {
+        let message = format!("period locked: {} is on or before the lock date {}", date, lock_date);
+        Self { date, lock_date, message }
+    }
+}
+
+impl fmt::Display for PeriodLocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for PeriodLocked {}
+
+impl DatabaseError for PeriodLocked {
+    fn message(&self) -> &str {
+        &self.message
+    }
+    fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+    fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+    fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+        self
+    }
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::CheckViolation
+    }
+}
+
+// Whether an error is a refusal because of a lock
+pub fn is_locked(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| {
+        c.is::<PeriodLocked>()
+            || matches!(c.downcast_ref::<sqlx::Error>(), Some(sqlx::Error::Database(d)) if d.try_downcast_ref::<PeriodLocked>().is_some())
+    })
+}
+
+// The last locked day of an account, read inside the caller's transaction
+pub async fn lock_date(conn: &mut SqliteConnection, account_id: i64) -> Result<Option<NaiveDate>, sqlx::Error> {
+    let date: Option<String> = sqlx::query_scalar("SELECT MAX(lock_date) FROM period_locks WHERE account_id IS NULL OR account_id = ?")
+        .bind(account_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    date.map(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").map_err(|e| sqlx::Error::Decode(Box::new(e))))
+        .transpose()
+}
+
+// The refusal for a change to a transaction of the account dated `date`, if that day is locked
+pub async fn find(conn: &mut SqliteConnection, account_id: i64, date: NaiveDate) -> Result<Option<PeriodLocked>, sqlx::Error> {
+    Ok(lock_date(conn, account_id).await?
+        .filter(|lock| date <= *lock)
+        .map(|lock| PeriodLocked::new(date, lock)))
+}
+
+// Refuse a change to a transaction of the account dated `date` if that day is locked
+pub async fn check(conn: &mut SqliteConnection, account_id: i64, date: NaiveDate) -> Result<(), sqlx::Error> {
+    match find(conn, account_id, date).await? {
+        Some(locked) => Err(locked.into()),
+        None => Ok(()),
+    }
+}
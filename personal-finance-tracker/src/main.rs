@@ -14,6 +14,8 @@ async fn main() -> anyhow::Result<()> {
         let pool = database::db::connection::get_db_pool().await?;
         
         backend::run_server(pool).await?;
+    } else if args.len() > 1 && matches!(args[1].as_str(), "import" | "export" | "backup" | "restore" | "statement" | "rules" | "check" | "assert" | "lock" | "close") {
+        cli::commands::run(&args[1..]).await?;
     } else {
         println!("Starting CLI...");
         
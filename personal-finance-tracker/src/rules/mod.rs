@@ -0,0 +1,341 @@
+//! User-defined rules that clean up and categorise transactions
+//! - A rule matches on payee and/or memo (case-insensitive `contains`, or a regex),
+//!   an optional signed amount range and an optional account
+//! - A matching rule can set the category, replace the payee or memo, and add tags
+//! - Rules run in `position` order; the first matching rule that sets a field wins,
+//!   tags from every matching rule are added
+//! - Used by `Client::import_transactions` and by `Client::preview_rules` for existing rows
+//! - `suggest` learns categories from history instead, for the Add Transaction form
+
+pub mod suggest;
+
+use anyhow::{anyhow, Result};
+use regex::{Regex, RegexBuilder};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::state::TransactionDto;
+use crate::import::ImportedTxn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchField {
+    #[default]
+    Payee,
+    Memo,
+    Any,    // payee or memo
+}
+
+impl MatchField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Payee => "payee",
+            Self::Memo => "memo",
+            Self::Any => "any",
+        }
+    }
+    pub fn parse(s: &str) -> Self {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "memo" => Self::Memo,
+            "any" => Self::Any,
+            _ => Self::Payee,
+        }
+    }
+    pub fn cycle(&self) -> Self {
+        match self {
+            Self::Payee => Self::Memo,
+            Self::Memo => Self::Any,
+            Self::Any => Self::Payee,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    #[default]
+    Contains,
+    Regex,
+}
+
+impl MatchKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Contains => "contains",
+            Self::Regex => "regex",
+        }
+    }
+    pub fn parse(s: &str) -> Self {
+        if s.trim().eq_ignore_ascii_case("regex") { Self::Regex } else { Self::Contains }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: i64,
+    pub name: String,
+    pub position: i64,
+    pub enabled: bool,
+    pub field: MatchField,
+    pub kind: MatchKind,
+    pub pattern: String,                // empty matches everything
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub min_amount: Option<Decimal>,    // signed, inclusive
+    #[serde(with = "rust_decimal::serde::str_option")]
+    pub max_amount: Option<Decimal>,
+    pub account_id: Option<i64>,
+    pub set_category_id: Option<i64>,
+    pub set_payee: Option<String>,
+    pub set_memo: Option<String>,
+    pub add_tags: Vec<String>,
+}
+
+// What a rule is matched against
+pub struct Subject<'a> {
+    pub account_id: i64,
+    pub amount: Decimal,                // signed, negative = money out
+    pub payee: Option<&'a str>,
+    pub memo: Option<&'a str>,
+}
+
+// The combined result of every rule that matched
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleEffect {
+    pub category_id: Option<i64>,
+    pub payee: Option<String>,
+    pub memo: Option<String>,
+    pub tags: Vec<String>,
+    pub rules: Vec<String>,             // names of the rules that matched
+}
+
+// What applying the rules would change on a recorded transaction; unchanged fields are None
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleChange {
+    pub txn: TransactionDto,
+    pub category_id: Option<i64>,
+    pub payee: Option<String>,
+    pub memo: Option<String>,
+    pub tags: Vec<String>,              // tags the transaction doesn't have yet
+    pub rules: Vec<String>,
+}
+
+// Enabled rules with their patterns compiled, in the order they run
+pub struct RuleSet {
+    rules: Vec<(Rule, Option<Regex>)>,
+}
+
+impl RuleSet {
+    pub fn new(mut rules: Vec<Rule>) -> Result<Self> {
+        rules.retain(|r| r.enabled);
+        rules.sort_by_key(|r| (r.position, r.id));
+        let rules = rules
+            .into_iter()
+            .map(|r| {
+                let re = match r.kind {
+                    MatchKind::Regex => Some(compile(&r.pattern).map_err(|e| anyhow!("rule '{}': {}", r.name, e))?),
+                    MatchKind::Contains => None,
+                };
+                Ok((r, re))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn apply(&self, s: &Subject) -> Option<RuleEffect> {
+        let mut effect = RuleEffect::default();
+        for (rule, re) in &self.rules {
+            if !matches(rule, re.as_ref(), s) {
+                continue;
+            }
+            effect.rules.push(rule.name.clone());
+            effect.category_id = effect.category_id.or(rule.set_category_id);
+            if effect.payee.is_none() {
+                effect.payee = rule.set_payee.clone();
+            }
+            if effect.memo.is_none() {
+                effect.memo = rule.set_memo.clone();
+            }
+            for t in &rule.add_tags {
+                if !effect.tags.iter().any(|e| e.eq_ignore_ascii_case(t)) {
+                    effect.tags.push(t.clone());
+                }
+            }
+        }
+        if effect.rules.is_empty() { None } else { Some(effect) }
+    }
+
+    // Rewrite the payee / memo of a statement row in place; the caller applies the
+    // category and tags once the row is written
+    pub fn apply_to_import(&self, account_id: i64, t: &mut ImportedTxn) -> Option<RuleEffect> {
+        let effect = self.apply(&Subject { account_id, amount: t.amount, payee: t.payee.as_deref(), memo: t.memo.as_deref() })?;
+        if let Some(p) = &effect.payee {
+            t.payee = Some(p.clone());
+        }
+        if let Some(m) = &effect.memo {
+            t.memo = Some(m.clone());
+        }
+        Some(effect)
+    }
+
+    // None when the rules would leave the transaction as it is
+    pub fn change_for(&self, t: &TransactionDto, tags: &[String]) -> Option<RuleChange> {
+        let effect = self.apply(&Subject { account_id: t.account_id, amount: t.amount.0, payee: t.payee.as_deref(), memo: t.memo.as_deref() })?;
+        let change = RuleChange {
+            txn: t.clone(),
+            category_id: effect.category_id.filter(|c| t.category_id != Some(*c)),
+            payee: effect.payee.filter(|p| t.payee.as_deref() != Some(p.as_str())),
+            memo: effect.memo.filter(|m| t.memo.as_deref() != Some(m.as_str())),
+            tags: effect.tags.into_iter().filter(|n| !tags.iter().any(|o| o.eq_ignore_ascii_case(n))).collect(),
+            rules: effect.rules,
+        };
+        let changed = change.category_id.is_some() || change.payee.is_some() || change.memo.is_some() || !change.tags.is_empty();
+        if changed { Some(change) } else { None }
+    }
+}
+
+// Case-insensitive unless the pattern says otherwise ((?-i)); errors keep only the reason,
+// e.g. "unclosed group"
+pub fn compile(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern).case_insensitive(true).build().map_err(|e| {
+        let text = e.to_string();
+        let reason = text.lines().last().unwrap_or_default();
+        reason.strip_prefix("error: ").unwrap_or(reason).to_string()
+    })
+}
+
+fn matches(rule: &Rule, re: Option<&Regex>, s: &Subject) -> bool {
+    if rule.account_id.is_some_and(|a| a != s.account_id)
+        || rule.min_amount.is_some_and(|m| s.amount < m)
+        || rule.max_amount.is_some_and(|m| s.amount > m)
+    {
+        return false;
+    }
+    let text_matches = |text: Option<&str>| {
+        let text = text.unwrap_or("");
+        match re {
+            Some(re) => re.is_match(text),
+            None => text.to_lowercase().contains(&rule.pattern.trim().to_lowercase()),
+        }
+    };
+    match rule.field {
+        MatchField::Payee => text_matches(s.payee),
+        MatchField::Memo => text_matches(s.memo),
+        MatchField::Any => text_matches(s.payee) || text_matches(s.memo),
+    }
+}
+
+// "a, b ,c" -> ["a", "b", "c"]
+pub fn split_tags(s: &str) -> Vec<String> {
+    s.split(',').map(|t| t.trim()).filter(|t| !t.is_empty()).map(|t| t.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::state::Money;
+    use chrono::NaiveDate;
+
+    fn rule(id: i64, position: i64, pattern: &str) -> Rule {
+        Rule { id, name: format!("r{}", id), position, enabled: true, pattern: pattern.into(), ..Default::default() }
+    }
+
+    fn subject<'a>(amount: &str, payee: &'a str, memo: Option<&'a str>) -> Subject<'a> {
+        Subject { account_id: 1, amount: amount.parse().unwrap(), payee: Some(payee), memo }
+    }
+
+    fn coffee() -> Vec<Rule> {
+        vec![
+            Rule { set_category_id: Some(7), add_tags: vec!["treats".into()], ..rule(2, 20, "coffee") },
+            Rule { set_category_id: Some(3), set_payee: Some("Coffee Co".into()), add_tags: vec!["Treats".into(), "cafe".into()], ..rule(1, 10, "COFFEE CO") },
+            Rule { kind: MatchKind::Regex, field: MatchField::Memo, set_memo: Some("Card".into()), ..rule(3, 30, r"^POS \d+") },
+            Rule { enabled: false, set_category_id: Some(9), ..rule(4, 0, "") },
+        ]
+    }
+
+    #[test]
+    fn the_first_rule_to_set_a_field_wins_and_tags_add_up() {
+        let rules = RuleSet::new(coffee()).unwrap();
+        let effect = rules.apply(&subject("-4.50", "COFFEE CO #12", Some("POS 0412 Main St"))).unwrap();
+        assert_eq!(effect, RuleEffect {
+            category_id: Some(3),
+            payee: Some("Coffee Co".into()),
+            memo: Some("Card".into()),
+            tags: vec!["Treats".into(), "cafe".into()],
+            rules: vec!["r1".into(), "r2".into(), "r3".into()],
+        });
+        assert_eq!(rules.apply(&subject("-4.50", "Coffee Cart", Some("pos 12"))).unwrap().rules, ["r2", "r3"]);
+        assert!(rules.apply(&subject("-4.50", "Grocer", None)).is_none());
+    }
+
+    #[test]
+    fn amount_range_and_account_narrow_a_rule() {
+        let rules = RuleSet::new(vec![Rule {
+            account_id: Some(1),
+            min_amount: Some("-100".parse().unwrap()),
+            max_amount: Some("-10".parse().unwrap()),
+            set_category_id: Some(5),
+            ..rule(1, 0, "")
+        }]).unwrap();
+        assert!(rules.apply(&subject("-10", "Anyone", None)).is_some());
+        assert!(rules.apply(&subject("-100", "Anyone", None)).is_some());
+        assert!(rules.apply(&subject("-9.99", "Anyone", None)).is_none());
+        assert!(rules.apply(&subject("50", "Anyone", None)).is_none());
+        assert!(rules.apply(&Subject { account_id: 2, ..subject("-20", "Anyone", None) }).is_none());
+    }
+
+    #[test]
+    fn an_import_row_gets_the_new_payee_and_memo() {
+        let rules = RuleSet::new(coffee()).unwrap();
+        let mut t = ImportedTxn {
+            date: NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+            value_date: None,
+            amount: "-4.50".parse().unwrap(),
+            payee: Some("COFFEE CO #12".into()),
+            memo: Some("POS 0412".into()),
+            external_id: None,
+            category: None,
+            cleared: true,
+            reconciled: false,
+            split: None,
+        };
+        let effect = rules.apply_to_import(1, &mut t).unwrap();
+        assert_eq!((t.payee.as_deref(), t.memo.as_deref()), (Some("Coffee Co"), Some("Card")));
+        assert_eq!(effect.category_id, Some(3));
+    }
+
+    #[test]
+    fn a_change_lists_only_what_differs() {
+        let rules = RuleSet::new(coffee()).unwrap();
+        let mut t = TransactionDto {
+            id: 1,
+            account_id: 1,
+            category_id: Some(3),
+            amount: Money("-4.50".parse().unwrap()),
+            memo: None,
+            payee: Some("Coffee Co".into()),
+            currency: "CAD".into(),
+            txn_date: NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+            cleared: false,
+            reconciled: false,
+        };
+        let change = rules.change_for(&t, &["TREATS".into()]).unwrap();
+        assert_eq!((change.category_id, change.payee, change.memo), (None, None, None));
+        assert_eq!(change.tags, ["cafe"]);
+        assert!(rules.change_for(&t, &["treats".into(), "cafe".into()]).is_none());
+
+        t.category_id = Some(1);
+        assert_eq!(rules.change_for(&t, &["treats".into(), "cafe".into()]).unwrap().category_id, Some(3));
+    }
+
+    #[test]
+    fn a_bad_pattern_names_the_rule() {
+        assert_eq!(compile("(coffee").unwrap_err(), "unclosed group");
+        let bad = Rule { kind: MatchKind::Regex, ..rule(1, 0, "(coffee") };
+        assert_eq!(RuleSet::new(vec![bad]).err().unwrap().to_string(), "rule 'r1': unclosed group");
+        assert!(compile("(?-i)Coffee").unwrap().is_match("Coffee") && !compile("(?-i)Coffee").unwrap().is_match("COFFEE"));
+    }
+}
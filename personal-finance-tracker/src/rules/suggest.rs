@@ -0,0 +1,149 @@
+//! Category suggestions learned from the user's own categorised history
+//! - Multinomial naive Bayes over the words of payee and memo, with light (Lidstone)
+//!   smoothing so that a payee seen once is already enough for a suggestion
+//! - Payee and memo words are kept apart ("p:coffee" / "m:coffee"): the same word usually
+//!   means more in the payee
+//! - Trained in memory from the local database each time the Add Transaction form opens;
+//!   nothing leaves the machine
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// Below this posterior the best category is not worth suggesting
+const MIN_CONFIDENCE: f64 = 0.5;
+// Pseudo-count of every known word in every category
+const ALPHA: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Suggestion {
+    pub category_id: i64,
+    pub confidence: f64,    // posterior probability, 0..1
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CategoryModel {
+    docs: usize,
+    classes: BTreeMap<i64, Class>,     // ordered, so ties always resolve the same way
+    vocab: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Class {
+    docs: usize,
+    words: usize,
+    counts: HashMap<String, usize>,
+}
+
+impl CategoryModel {
+    // (category id, payee, memo) of every categorised transaction
+    pub fn train<'a>(examples: impl IntoIterator<Item = (i64, Option<&'a str>, Option<&'a str>)>) -> Self {
+        let mut model = Self::default();
+        for (category_id, payee, memo) in examples {
+            model.add(category_id, payee.unwrap_or(""), memo.unwrap_or(""));
+        }
+        model
+    }
+
+    pub fn add(&mut self, category_id: i64, payee: &str, memo: &str) {
+        let words: Vec<String> = tokens(payee, memo).collect();
+        if words.is_empty() {
+            return;
+        }
+        let class = self.classes.entry(category_id).or_default();
+        class.docs += 1;
+        class.words += words.len();
+        for w in words {
+            *class.counts.entry(w.clone()).or_default() += 1;
+            self.vocab.insert(w);
+        }
+        self.docs += 1;
+    }
+
+    // Most likely category for the text typed so far; None until a known word shows up
+    // or when no category is clearly ahead
+    pub fn suggest(&self, payee: &str, memo: &str) -> Option<Suggestion> {
+        let words: Vec<String> = tokens(payee, memo).filter(|w| self.vocab.contains(w)).collect();
+        if words.is_empty() {
+            return None;
+        }
+        let smoothing = self.vocab.len() as f64 * ALPHA;
+        let scores: Vec<(i64, f64)> = self.classes.iter()
+            .map(|(id, c)| {
+                let prior = (c.docs as f64 / self.docs as f64).ln();
+                let likelihood: f64 = words.iter()
+                    .map(|w| ((c.counts.get(w).copied().unwrap_or(0) as f64 + ALPHA) / (c.words as f64 + smoothing)).ln())
+                    .sum();
+                (*id, prior + likelihood)
+            })
+            .collect();
+
+        // Posterior of the best class, computed from log scores without underflow
+        let (best, top) = scores.iter().copied().fold((0, f64::NEG_INFINITY), |acc, s| if s.1 > acc.1 { s } else { acc });
+        let total: f64 = scores.iter().map(|(_, s)| (s - top).exp()).sum();
+        let confidence = 1.0 / total;
+        (confidence >= MIN_CONFIDENCE).then_some(Suggestion { category_id: best, confidence })
+    }
+}
+
+// "AMZN Mktp CA*2X4" -> p:amzn p:mktp p:ca p:2x4; pure numbers (store ids, dates) are dropped
+fn tokens<'a>(payee: &'a str, memo: &'a str) -> impl Iterator<Item = String> + 'a {
+    let words = |prefix: &'static str, text: &'a str| {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.chars().count() > 1 && !w.chars().all(|c| c.is_ascii_digit()))
+            .map(move |w| format!("{}{}", prefix, w.to_lowercase()))
+    };
+    words("p:", payee).chain(words("m:", memo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROCERIES: i64 = 1;
+    const DINING: i64 = 2;
+    const FUEL: i64 = 3;
+
+    fn model() -> CategoryModel {
+        CategoryModel::train([
+            (GROCERIES, Some("FRESHCO #1234"), None),
+            (GROCERIES, Some("Freshco 0987"), Some("weekly shop")),
+            (GROCERIES, Some("Farm Boy"), None),
+            (DINING, Some("Joe's Diner"), Some("lunch")),
+            (DINING, Some("Pizza Nova"), Some("dinner")),
+            (FUEL, Some("Shell 4411"), None),
+            (FUEL, None, None),     // nothing to learn from
+        ])
+    }
+
+    #[test]
+    fn suggests_from_one_known_payee_word() {
+        let m = model();
+        assert_eq!(m.suggest("SHELL C04", "").map(|s| s.category_id), Some(FUEL));
+        let s = m.suggest("freshco 5555", "").unwrap();
+        assert_eq!(s.category_id, GROCERIES);
+        assert!(s.confidence > 0.9 && s.confidence < 1.0);
+    }
+
+    #[test]
+    fn payee_and_memo_words_count_apart() {
+        let m = model();
+        assert_eq!(m.suggest("", "lunch").map(|s| s.category_id), Some(DINING));
+        // "lunch" was only ever a memo word
+        assert_eq!(m.suggest("Lunch Box", ""), None);
+    }
+
+    #[test]
+    fn stays_quiet_without_a_known_word_or_a_clear_winner() {
+        let m = model();
+        assert_eq!(m.suggest("", ""), None);
+        assert_eq!(m.suggest("1234 0987", ""), None);
+        let tie = CategoryModel::train([(GROCERIES, Some("Costco"), None), (FUEL, Some("Costco"), None), (DINING, Some("Costco"), None)]);
+        assert_eq!(tie.suggest("COSTCO WHOLESALE", ""), None);
+        assert_eq!(CategoryModel::default().suggest("Anything", ""), None);
+    }
+
+    #[test]
+    fn splits_text_into_prefixed_words() {
+        let words: Vec<String> = tokens("AMZN Mktp CA*2X4", "Order 12345 x").collect();
+        assert_eq!(words, ["p:amzn", "p:mktp", "p:ca", "p:2x4", "m:order"]);
+    }
+}
@@ -0,0 +1,352 @@
+//! Transaction search
+//! - Words are looked up in the `transaction_search` FTS5 index (payee, memo, category
+//!   name and tags; see the 20250123 migration) as word prefixes, every word must match
+//! - `field:value` narrows a word to one column: `payee:amazon`, `memo:`, `category:`
+//!   (or `cat:`), `tag:`; quotes keep words together: `payee:"whole foods"`
+//! - `account:` matches the start of the account name
+//! - `amount:>50`, `amount:<=20`, `amount:12.50`, `amount:10..50` compare the amount
+//!   without its sign, exactly in `Decimal`
+//! - `date:2025`, `date:2025-03`, `date:2025-03-14` and ranges `date:2025-01..2025-03`
+//!   (either end may be left open) are inclusive
+//!
+//! `parse` only reads the text; `Client::search_transactions` runs it.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::cli::state::TransactionDto;
+
+pub const FIELDS: &str = "payee, memo, category, tag, account, amount, date";
+// Rows read per query when amounts are filtered after reading
+pub const PAGE_ROWS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Any,
+    Payee,
+    Memo,
+    Category,
+    Tags,
+}
+
+impl Column {
+    fn fts_name(&self) -> Option<&'static str> {
+        match self {
+            Self::Any => None,
+            Self::Payee => Some("payee"),
+            Self::Memo => Some("memo"),
+            Self::Category => Some("category"),
+            Self::Tags => Some("tags"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Cmp {
+    pub fn holds(&self, value: Decimal, bound: Decimal) -> bool {
+        match self {
+            Self::Lt => value < bound,
+            Self::Le => value <= bound,
+            Self::Eq => value == bound,
+            Self::Ge => value >= bound,
+            Self::Gt => value > bound,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub text: String,                       // as typed
+    pub words: Vec<(Column, String)>,
+    pub account: Option<String>,
+    pub amounts: Vec<(Cmp, Decimal)>,       // all must hold for the unsigned amount
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,              // inclusive
+}
+
+impl SearchQuery {
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.account.is_none() && self.amounts.is_empty() && self.from.is_none() && self.to.is_none()
+    }
+
+    // The FTS5 MATCH expression for the words, None without any
+    pub fn fts_match(&self) -> Option<String> {
+        if self.words.is_empty() {
+            return None;
+        }
+        let terms: Vec<String> = self.words.iter().map(|(col, w)| {
+            let quoted = format!("\"{}\"*", w.replace('"', "\"\""));
+            match col.fts_name() {
+                Some(name) => format!("{} : {}", name, quoted),
+                None => quoted,
+            }
+        }).collect();
+        Some(terms.join(" AND "))
+    }
+
+    // The LIKE pattern for `account:`, with `%`, `_` and `\` taken literally (ESCAPE '\')
+    pub fn account_like(&self) -> Option<String> {
+        let a = self.account.as_ref()?;
+        let escaped: String = a.chars().flat_map(|c| match c {
+            '%' | '_' | '\\' => vec!['\\', c],
+            c => vec![c],
+        }).collect();
+        Some(format!("{}%", escaped))
+    }
+
+    pub fn amount_matches(&self, amount: Decimal) -> bool {
+        self.amounts.iter().all(|(cmp, bound)| cmp.holds(amount.abs(), *bound))
+    }
+
+    // The lowercase words to highlight in results
+    pub fn highlight_terms(&self) -> Vec<String> {
+        self.words.iter()
+            .flat_map(|(_, w)| w.split_whitespace().map(|s| s.to_lowercase()).collect::<Vec<_>>())
+            .collect()
+    }
+}
+
+// One match, with the names the result list shows
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub txn: TransactionDto,
+    pub account: String,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+pub fn parse(text: &str) -> Result<SearchQuery> {
+    let mut q = SearchQuery { text: text.trim().to_string(), ..Default::default() };
+    for token in tokens(text)? {
+        let Some((field, value)) = token.split_once(':').filter(|(f, _)| !f.contains('"')) else {
+            // The index keeps no punctuation, so a word of it alone would match nothing
+            if token.chars().any(char::is_alphanumeric) {
+                q.words.push((Column::Any, token.replace('"', "")));
+            }
+            continue;
+        };
+        let value = value.trim_matches('"').to_string();
+        if value.is_empty() {
+            bail!("'{}:' needs a value", field);
+        }
+        match field.to_ascii_lowercase().as_str() {
+            "payee" => q.words.push((Column::Payee, value)),
+            "memo" => q.words.push((Column::Memo, value)),
+            "category" | "cat" => q.words.push((Column::Category, value)),
+            "tag" | "tags" => q.words.push((Column::Tags, value)),
+            "account" => q.account = Some(value),
+            "amount" => q.amounts.extend(parse_amount(&value)?),
+            "date" => {
+                let (from, to) = parse_dates(&value)?;
+                q.from = from.max(q.from);
+                q.to = match (q.to, to) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+            f => bail!("unknown field '{}' (use {})", f, FIELDS),
+        }
+    }
+    Ok(q)
+}
+
+// Split on spaces outside double quotes
+fn tokens(text: &str) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                cur.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !cur.is_empty() {
+                    out.push(std::mem::take(&mut cur));
+                }
+            }
+            c => cur.push(c),
+        }
+    }
+    if quoted {
+        bail!("unclosed quote");
+    }
+    if !cur.is_empty() {
+        out.push(cur);
+    }
+    Ok(out)
+}
+
+fn parse_amount(value: &str) -> Result<Vec<(Cmp, Decimal)>> {
+    let number = |s: &str| -> Result<Decimal> {
+        s.trim().parse::<Decimal>().map(|d| d.abs()).map_err(|_| anyhow!("'{}' is not an amount", s))
+    };
+    if let Some((lo, hi)) = value.split_once("..") {
+        let mut out = Vec::new();
+        if !lo.is_empty() {
+            out.push((Cmp::Ge, number(lo)?));
+        }
+        if !hi.is_empty() {
+            out.push((Cmp::Le, number(hi)?));
+        }
+        return Ok(out);
+    }
+    let (cmp, rest) = [(">=", Cmp::Ge), ("<=", Cmp::Le), (">", Cmp::Gt), ("<", Cmp::Lt), ("=", Cmp::Eq)]
+        .into_iter()
+        .find_map(|(op, cmp)| value.strip_prefix(op).map(|rest| (cmp, rest)))
+        .unwrap_or((Cmp::Eq, value));
+    Ok(vec![(cmp, number(rest)?)])
+}
+
+// `a..b` from the first day of `a` to the last day of `b`
+fn parse_dates(value: &str) -> Result<(Option<NaiveDate>, Option<NaiveDate>)> {
+    let (lo, hi) = value.split_once("..").unwrap_or((value, value));
+    let from = if lo.is_empty() { None } else { Some(date_span(lo)?.0) };
+    let to = if hi.is_empty() { None } else { Some(date_span(hi)?.1) };
+    Ok((from, to))
+}
+
+// The first and last day of a year (2025), month (2025-03) or day (2025-03-14)
+fn date_span(s: &str) -> Result<(NaiveDate, NaiveDate)> {
+    let bad = || anyhow!("'{}' is not a date (YYYY, YYYY-MM or YYYY-MM-DD)", s);
+    let parts: Vec<&str> = s.split('-').collect();
+    let num = |p: &str| p.parse::<u32>().map_err(|_| bad());
+    match parts.as_slice() {
+        [y] => {
+            let y = num(y)? as i32;
+            Ok((NaiveDate::from_ymd_opt(y, 1, 1).ok_or_else(bad)?, NaiveDate::from_ymd_opt(y, 12, 31).ok_or_else(bad)?))
+        }
+        [y, m] => {
+            let start = NaiveDate::from_ymd_opt(num(y)? as i32, num(m)?, 1).ok_or_else(bad)?;
+            let next = if start.month() == 12 {
+                NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+            } else {
+                NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+            };
+            Ok((start, next.and_then(|d| d.pred_opt()).ok_or_else(bad)?))
+        }
+        [_, _, _] => {
+            let d = NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| bad())?;
+            Ok((d, d))
+        }
+        _ => Err(bad()),
+    }
+}
+
+// Split `text` into runs, true where a run starts a word with one of the terms (the way
+// the index matches prefixes). Case-insensitive for ASCII.
+pub fn highlight<'a>(text: &'a str, terms: &[String]) -> Vec<(&'a str, bool)> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let lower = text.to_ascii_lowercase();
+    let mut marked = vec![false; text.len()];
+    for term in terms.iter().filter(|t| !t.is_empty()) {
+        let term = term.to_ascii_lowercase();
+        for (i, _) in lower.match_indices(&term) {
+            let word_start = lower[..i].chars().next_back().is_none_or(|c| !c.is_alphanumeric());
+            if word_start {
+                marked[i..i + term.len()].iter_mut().for_each(|m| *m = true);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut start = 0;
+    for (i, _) in text.char_indices().skip(1).chain(std::iter::once((text.len(), ' '))) {
+        if i == text.len() || marked[i] != marked[start] {
+            out.push((&text[start..i], marked[start]));
+            start = i;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn reads_words_fields_and_quotes() {
+        let q = parse(r#"coffee payee:"whole foods" cat:dining tag:trip account:visa -"#).unwrap();
+        assert_eq!(q.words, [
+            (Column::Any, "coffee".to_string()),
+            (Column::Payee, "whole foods".to_string()),
+            (Column::Category, "dining".to_string()),
+            (Column::Tags, "trip".to_string()),
+        ]);
+        assert_eq!(q.account.as_deref(), Some("visa"));
+        assert_eq!(q.fts_match().unwrap(), r#""coffee"* AND payee : "whole foods"* AND category : "dining"* AND tags : "trip"*"#);
+        assert_eq!(q.highlight_terms(), ["coffee", "whole", "foods", "dining", "trip"]);
+    }
+
+    #[test]
+    fn refuses_what_it_cannot_read() {
+        assert_eq!(parse("payee:").unwrap_err().to_string(), "'payee:' needs a value");
+        assert_eq!(parse(r#"payee:"whole foods"#).unwrap_err().to_string(), "unclosed quote");
+        assert_eq!(parse("colour:red").unwrap_err().to_string(), format!("unknown field 'colour' (use {})", FIELDS));
+        assert!(parse("amount:lots").is_err());
+        assert!(parse("date:2025-13").is_err());
+        assert!(parse("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn compares_amounts_without_their_sign() {
+        let q = parse("amount:10..50").unwrap();
+        assert_eq!(q.amounts, [(Cmp::Ge, Decimal::from(10)), (Cmp::Le, Decimal::from(50))]);
+        assert!(q.amount_matches("-50.00".parse().unwrap()) && q.amount_matches(Decimal::from(10)));
+        assert!(!q.amount_matches("50.01".parse().unwrap()));
+
+        let q = parse("amount:>=-20 amount:<25").unwrap();
+        assert_eq!(q.amounts, [(Cmp::Ge, Decimal::from(20)), (Cmp::Lt, Decimal::from(25))]);
+        assert!(parse("amount:12.50").unwrap().amount_matches("-12.5".parse().unwrap()));
+    }
+
+    #[test]
+    fn dates_cover_whole_years_months_and_days() {
+        assert_eq!(date_span("2024").unwrap(), (day(2024, 1, 1), day(2024, 12, 31)));
+        assert_eq!(date_span("2024-02").unwrap(), (day(2024, 2, 1), day(2024, 2, 29)));
+        assert_eq!(date_span("2024-12").unwrap(), (day(2024, 12, 1), day(2024, 12, 31)));
+        assert_eq!(date_span("2025-03-14").unwrap(), (day(2025, 3, 14), day(2025, 3, 14)));
+        assert!(date_span("2025-02-30").is_err() && date_span("March").is_err() && date_span("2025-1-2-3").is_err());
+
+        let q = parse("date:2025-01..2025-03").unwrap();
+        assert_eq!((q.from, q.to), (Some(day(2025, 1, 1)), Some(day(2025, 3, 31))));
+        let q = parse("date:..2024").unwrap();
+        assert_eq!((q.from, q.to), (None, Some(day(2024, 12, 31))));
+        // Two date filters keep the days both allow
+        let q = parse("date:2025 date:2025-06..").unwrap();
+        assert_eq!((q.from, q.to), (Some(day(2025, 6, 1)), Some(day(2025, 12, 31))));
+    }
+
+    #[test]
+    fn account_names_match_literally() {
+        assert_eq!(parse(r"account:100%_\x").unwrap().account_like().as_deref(), Some(r"100\%\_\\x%"));
+        assert_eq!(parse("coffee").unwrap().account_like(), None);
+    }
+
+    #[test]
+    fn highlights_word_starts_only() {
+        let terms = vec!["co".to_string(), "fee".to_string()];
+        assert_eq!(highlight("Coffee Co-op", &terms), [("Co", true), ("ffee ", false), ("Co", true), ("-op", false)]);
+        assert_eq!(highlight("Café COFFEE", &["caf".to_string()]), [("Caf", true), ("é COFFEE", false)]);
+        assert_eq!(highlight("Tea", &terms), [("Tea", false)]);
+        assert!(highlight("", &terms).is_empty());
+    }
+}
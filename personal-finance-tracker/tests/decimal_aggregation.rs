@@ -0,0 +1,165 @@
+//! Property tests: money totals stay exact, whatever amounts go in and in whatever order
+//! - Stored balances after adds, edits, deletes and synced rows equal the Decimal sum
+//! - Category spending and net savings equal the Decimal sums of their rows
+//! - Amounts have up to 6 decimals and 13 significant digits, where sums done as REAL
+//!   (the old SQL path) drift
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use proptest::prelude::*;
+use rust_decimal::Decimal;
+use sqlx::sqlite::SqlitePoolOptions;
+
+use personal_finance_tracker::audit::Origin;
+use personal_finance_tracker::check::Severity;
+use personal_finance_tracker::cli::api::Client;
+use personal_finance_tracker::cli::state::{AccountType, CreateAccountReq, CreateTxnReq, Money};
+use personal_finance_tracker::database::db::{migrate, queries};
+
+#[derive(Debug, Clone)]
+enum Op {
+    Add(Decimal),
+    Sync(Decimal),          // through the backend's insert path
+    Edit(usize, Decimal),   // index into the rows added so far
+    Delete(usize),
+}
+
+fn amount() -> impl Strategy<Value = Decimal> {
+    (-9_999_999_999_999i64..=9_999_999_999_999, 0u32..=6).prop_map(|(m, scale)| Decimal::new(m, scale))
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => amount().prop_map(Op::Add),
+        2 => amount().prop_map(Op::Sync),
+        1 => (any::<usize>(), amount()).prop_map(|(i, a)| Op::Edit(i, a)),
+        1 => any::<usize>().prop_map(Op::Delete),
+    ]
+}
+
+fn block_on<F: Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(f)
+}
+
+// A fresh in-memory database with one empty account; a single connection that never
+// expires keeps the database alive
+async fn ledger() -> (Client, i64) {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    migrate::run_migrations(&pool).await.unwrap();
+    queries::seed_fixed_categories(&pool).await.unwrap();
+    let client = Client::from_pool(pool);
+    let account = client.create_account(&CreateAccountReq {
+        name: "Chequing".into(),
+        r#type: AccountType::Checking,
+        currency: "CAD".into(),
+        opening_balance: Money(Decimal::ZERO),
+        credit_limit: None,
+    }).await.unwrap();
+    (client, account.id)
+}
+
+fn day(n: usize) -> NaiveDateTime {
+    (NaiveDate::from_ymd_opt(2025, 1, 1).unwrap() + chrono::Days::new(n as u64 % 365)).and_hms_opt(0, 0, 0).unwrap()
+}
+
+fn req(account_id: i64, category_id: i64, amount: Decimal, n: usize) -> CreateTxnReq {
+    CreateTxnReq {
+        account_id,
+        category_id,
+        amount: Money(amount),
+        base_amount: Money(amount.abs()),
+        is_expense: amount.is_sign_negative(),
+        description: None,
+        payee: None,
+        currency: "CAD".into(),
+        transacted_at: day(n),
+    }
+}
+
+async fn stored_balance(client: &Client, account_id: i64) -> Decimal {
+    client.list_accounts().await.unwrap().into_iter().find(|a| a.id == account_id).unwrap().opening_balance.0
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn balance_is_the_exact_sum(ops in prop::collection::vec(op(), 1..40)) {
+        let (stored, expected, problems) = block_on(async {
+            let (client, account_id) = ledger().await;
+            let category = 4;   // Food
+            let mut rows: Vec<(i64, Decimal)> = Vec::new();
+            for (n, op) in ops.iter().enumerate() {
+                match op {
+                    Op::Add(a) => {
+                        let t = client.create_transaction(&req(account_id, category, *a, n)).await.unwrap();
+                        rows.push((t.id, *a));
+                    }
+                    Op::Sync(a) => {
+                        let id = queries::create_transaction(
+                            client.pool(), account_id, category, a.abs(), a.abs(), "CAD".into(),
+                            a.is_sign_negative(), None, day(n), Origin::Server, false,
+                        ).await.unwrap();
+                        rows.push((id, *a));
+                    }
+                    Op::Edit(i, a) if !rows.is_empty() => {
+                        let i = i % rows.len();
+                        client.update_transaction(rows[i].0, &req(account_id, category, *a, n)).await.unwrap();
+                        rows[i].1 = *a;
+                    }
+                    Op::Delete(i) if !rows.is_empty() => {
+                        let (id, _) = rows.remove(i % rows.len());
+                        client.delete_transaction(id).await.unwrap();
+                    }
+                    Op::Edit(..) | Op::Delete(_) => {}
+                }
+            }
+            let report = client.check_ledger().await.unwrap();
+            let problems: Vec<String> = report.issues.iter()
+                .filter(|i| i.severity == Severity::Error)
+                .map(|i| i.message.clone())
+                .collect();
+            (stored_balance(&client, account_id).await, rows.iter().map(|(_, a)| *a).sum::<Decimal>(), problems)
+        });
+        prop_assert_eq!(stored, expected);
+        prop_assert!(problems.is_empty(), "ledger check: {:?}", problems);
+    }
+
+    #[test]
+    fn category_totals_and_net_savings_are_exact(
+        rows in prop::collection::vec((1i64..=12, amount()), 1..40),
+    ) {
+        let (report, expected, net, expected_net) = block_on(async {
+            let (client, account_id) = ledger().await;
+            let names: HashMap<i64, String> = client.list_categories().await.unwrap().into_iter().map(|c| (c.id, c.name)).collect();
+            // Categories 1-3 are income, 4-12 expenses
+            let mut expected: BTreeMap<String, Decimal> = BTreeMap::new();
+            let mut expected_net = Decimal::ZERO;
+            for (n, (category, a)) in rows.iter().enumerate() {
+                let signed = if *category <= 3 { a.abs() } else { -a.abs() };
+                client.create_transaction(&req(account_id, *category, signed, n)).await.unwrap();
+                if *category > 3 {
+                    *expected.entry(names[category].clone()).or_default() += a.abs();
+                }
+                expected_net += signed;
+            }
+            let report: BTreeMap<String, Decimal> = queries::get_category_spending_report(client.pool(), day(0), day(364))
+                .await.unwrap()
+                .into_iter()
+                .map(|c| (c.category, c.total_amount))
+                .collect();
+            let net = queries::net_savings(client.pool()).await.unwrap();
+            (report, expected, net, expected_net)
+        });
+        prop_assert_eq!(report, expected);
+        prop_assert_eq!(net, expected_net);
+    }
+}